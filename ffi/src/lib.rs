@@ -0,0 +1,191 @@
+//! Kotlin and Swift bindings for the tic-tac-toe engine, generated with uniffi
+//!
+//! Build this crate, then generate bindings from the compiled library, e.g.
+//!
+//! ```sh
+//! cargo build -p simple-tic-tac-toe-ffi --release
+//! cargo run -p simple-tic-tac-toe-ffi --bin uniffi-bindgen -- generate \
+//!     --library target/release/libsimple_tic_tac_toe_ffi.so --language kotlin --out-dir out
+//! ```
+//!
+//! Nothing exported here does any I/O, and `computer_move` and `best_moves` look their moves up in
+//! the solved positions instead of searching, so every call is quick enough for a UI thread.
+
+use simple_tic_tac_toe::{GameState, GameStatus};
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+
+uniffi::setup_scaffolding!();
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, uniffi::Enum)]
+pub enum Player {
+    X,
+    O,
+}
+
+impl From<simple_tic_tac_toe::Player> for Player {
+    fn from(player: simple_tic_tac_toe::Player) -> Self {
+        match player {
+            simple_tic_tac_toe::Player::X => Player::X,
+            simple_tic_tac_toe::Player::O => Player::O,
+            _ => unreachable!("the bindings cover every player"),
+        }
+    }
+}
+
+impl From<Player> for simple_tic_tac_toe::Player {
+    fn from(player: Player) -> Self {
+        match player {
+            Player::X => simple_tic_tac_toe::Player::X,
+            Player::O => simple_tic_tac_toe::Player::O,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, uniffi::Enum)]
+pub enum Status {
+    InProgress,
+    Won { winner: Player },
+    Tie,
+}
+
+impl From<GameStatus> for Status {
+    fn from(status: GameStatus) -> Self {
+        match status {
+            GameStatus::InProgress => Status::InProgress,
+            GameStatus::Won(winner) => Status::Won {
+                winner: winner.into(),
+            },
+            GameStatus::Tie => Status::Tie,
+            _ => unreachable!("the bindings cover every status"),
+        }
+    }
+}
+
+/// Why a move couldn't be played
+#[derive(Debug, uniffi::Error)]
+pub enum MoveError {
+    /// The square number isn't between 0 and 8
+    OutOfRange,
+    /// There is already a mark on the square
+    Taken,
+    /// The game has already been won or tied
+    GameOver,
+    /// The library turned the move down for a reason the bindings don't have a variant for
+    Illegal { reason: String },
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                MoveError::OutOfRange => "square is not on the board",
+                MoveError::Taken => "square is already taken",
+                MoveError::GameOver => "the game is over",
+                MoveError::Illegal { reason } => reason,
+            }
+        )
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+impl From<simple_tic_tac_toe::error::MoveError> for MoveError {
+    fn from(error: simple_tic_tac_toe::error::MoveError) -> Self {
+        use simple_tic_tac_toe::error::MoveError as Library;
+        match error {
+            Library::OffBoard(_) => MoveError::OutOfRange,
+            Library::Taken(_) => MoveError::Taken,
+            Library::GameOver(_) => MoveError::GameOver,
+            other => MoveError::Illegal {
+                reason: other.to_string(),
+            },
+        }
+    }
+}
+
+/// A game against the computer, with squares numbered 0-8 from the top left
+#[derive(uniffi::Object)]
+pub struct Game {
+    state: Mutex<GameState>,
+}
+
+impl Game {
+    fn state(&self) -> std::sync::MutexGuard<'_, GameState> {
+        // The state is never left half-updated, so a poisoned lock is still safe to use
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[uniffi::export]
+impl Game {
+    /// Start a new game with the computer playing `computer_player`; X always moves first
+    #[uniffi::constructor]
+    pub fn new(computer_player: Player) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(GameState::new(computer_player.into())),
+        })
+    }
+
+    /// The mark on each square, or None if it is empty
+    pub fn board(&self) -> Vec<Option<Player>> {
+        self.state()
+            .board()
+            .iter()
+            .map(|s| s.map(Player::from))
+            .collect()
+    }
+
+    pub fn next_player(&self) -> Player {
+        self.state().next_player().into()
+    }
+
+    pub fn computer_player(&self) -> Player {
+        self.state().computer_player.into()
+    }
+
+    pub fn status(&self) -> Status {
+        self.state().status().into()
+    }
+
+    /// The squares that can still be played
+    pub fn open_squares(&self) -> Vec<u8> {
+        self.state()
+            .open_squares()
+            .iter()
+            .map(|m| m.square as u8)
+            .collect()
+    }
+
+    /// Play the next move on `square`, for whichever side is to move
+    pub fn play_move(&self, square: u8) -> Result<(), MoveError> {
+        Ok(self.state().try_move(usize::from(square))?)
+    }
+
+    /// Let the computer choose and play the next move, returning the square it played
+    pub fn computer_move(&self) -> Result<u8, MoveError> {
+        let mut state = self.state();
+        if state.status() != GameStatus::InProgress {
+            return Err(MoveError::GameOver);
+        }
+        let square = state.get_random_computer_move().square;
+        state.apply_move(square);
+        Ok(square as u8)
+    }
+
+    /// The best squares for the side to move, as a hint
+    pub fn best_moves(&self) -> Vec<u8> {
+        let mut state = self.state().clone();
+        if state.status() != GameStatus::InProgress {
+            return Vec::new();
+        }
+        state.computer_player = state.next_player();
+        state
+            .get_best_computer_moves()
+            .iter()
+            .map(|m| m.square as u8)
+            .collect()
+    }
+}
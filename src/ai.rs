@@ -0,0 +1,527 @@
+use crate::error::Error;
+use crate::game::{GameState, GameStatus, Player, Selection};
+use crate::{solved, tablebase};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tracing::{debug, info, Level};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameResult {
+    Loss = -1,
+    Tie = 0,
+    Win = 1,
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                GameResult::Loss => "loss",
+                GameResult::Tie => "tie",
+                GameResult::Win => "win",
+            }
+        )
+    }
+}
+
+impl GameResult {
+    /// Get the result of a finished game for the given player, or None if it isn't finished
+    pub fn for_player(status: GameStatus, player: Player) -> Option<Self> {
+        match status {
+            GameStatus::Won(winner) if winner == player => Some(GameResult::Win),
+            GameStatus::Won(_) => Some(GameResult::Loss),
+            GameStatus::Tie => Some(GameResult::Tie),
+            GameStatus::InProgress => None,
+        }
+    }
+
+    /// Get the same result from the other side's point of view
+    pub fn reversed(self) -> Self {
+        match self {
+            GameResult::Loss => GameResult::Win,
+            GameResult::Tie => GameResult::Tie,
+            GameResult::Win => GameResult::Loss,
+        }
+    }
+}
+
+/// How strongly the computer plays
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Difficulty {
+    /// Always play a best move
+    #[default]
+    Perfect,
+    /// Blunder some of the time, tuned from past results so the human wins a target share of games
+    Adaptive,
+    /// Blunder half the time, for young or brand new players
+    Easy,
+}
+
+/// How often the computer blunders on easy
+pub const EASY_BLUNDER_CHANCE: f64 = 0.5;
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Difficulty::Perfect => write!(f, "perfect"),
+            Difficulty::Adaptive => write!(f, "adaptive"),
+            Difficulty::Easy => write!(f, "easy"),
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "perfect" => Ok(Difficulty::Perfect),
+            "adaptive" => Ok(Difficulty::Adaptive),
+            "easy" => Ok(Difficulty::Easy),
+            _ => Err(Error::parse(format!(
+                "`{s}` is not a difficulty, expected perfect, adaptive or easy"
+            ))),
+        }
+    }
+}
+
+/// Counters collected while searching, reported through the engine logs, along with the
+/// transposition table that lets the search reuse results across one decision
+#[derive(Default, Debug)]
+pub struct SearchStats {
+    /// Number of positions visited
+    pub nodes: u64,
+    /// Number of positions answered from the transposition table
+    pub transposition_hits: u64,
+    /// Most moves ahead of the searched position the search looked
+    pub max_depth: usize,
+    /// Results already found for the side to move, keyed by `GameState::position_id`
+    transpositions: HashMap<u64, GameResult>,
+    /// Checked at every node so the search can be stopped from another thread
+    cancel: Option<CancelToken>,
+    /// Table shared with other searches, read when the local table misses
+    shared: Option<Arc<TranspositionTable>>,
+}
+
+impl SearchStats {
+    /// Start a search that gives up as soon as `cancel` is cancelled
+    pub fn with_cancel(cancel: CancelToken) -> Self {
+        Self {
+            cancel: Some(cancel),
+            ..Self::default()
+        }
+    }
+
+    /// Start a search that also uses results from, and adds its own to, a shared table
+    pub fn with_shared_table(shared: Arc<TranspositionTable>) -> Self {
+        Self {
+            shared: Some(shared),
+            ..Self::default()
+        }
+    }
+
+    /// Add everything this search found to the shared table, if it has one
+    pub fn publish(&mut self) {
+        if let Some(shared) = &self.shared {
+            if !self.cancelled() {
+                shared.extend(
+                    self.transpositions
+                        .iter()
+                        .map(|(&id, &result)| (id, result)),
+                );
+            }
+        }
+    }
+
+    /// Use a new cancel token, e.g. to carry on with a stopped search and its transposition table
+    pub fn set_cancel(&mut self, cancel: CancelToken) {
+        self.cancel = Some(cancel);
+    }
+
+    /// Whether the search was cancelled, in which case its results can't be trusted
+    pub fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    /// Share of visited positions answered from the transposition table instead of searched,
+    /// which is the only pruning the search does
+    pub fn hit_rate(&self) -> f64 {
+        hit_rate(self.transposition_hits, self.nodes)
+    }
+}
+
+fn hit_rate(hits: u64, nodes: u64) -> f64 {
+    if nodes == 0 {
+        0.0
+    } else {
+        hits as f64 / nodes as f64
+    }
+}
+
+/// Search results that several threads can share, keyed by `GameState::position_id` and stored
+/// for the side to move. Reads take a shared lock, and each search writes once when it publishes
+#[derive(Debug, Default)]
+pub struct TranspositionTable {
+    entries: RwLock<HashMap<u64, GameResult>>,
+}
+
+impl TranspositionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of positions in the table
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, id: u64) -> Option<GameResult> {
+        self.entries.read().unwrap().get(&id).copied()
+    }
+
+    fn extend(&self, results: impl Iterator<Item = (u64, GameResult)>) {
+        self.entries.write().unwrap().extend(results);
+    }
+}
+
+/// A flag for stopping a search early, shared between the search and whoever started it
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask any search using this token to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A move the computer considered, with the result it expects from it
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Candidate {
+    pub square: usize,
+    pub result: GameResult,
+}
+
+/// Everything the computer saw when choosing a move, kept so it can be inspected after the game
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Decision {
+    /// The square the computer played
+    pub chosen: usize,
+    /// Every legal move with its evaluation
+    pub candidates: Vec<Candidate>,
+    /// Number of positions searched to make the decision
+    pub nodes: u64,
+    /// How many of those positions came from the transposition table
+    #[serde(default)]
+    pub transposition_hits: u64,
+    /// Most moves ahead of the position the computer looked, counting its own move
+    #[serde(default)]
+    pub depth: usize,
+    /// Time taken to make the decision, in microseconds
+    #[serde(default)]
+    pub elapsed_us: u64,
+}
+
+impl Decision {
+    /// Share of the positions looked at that came from the transposition table
+    pub fn hit_rate(&self) -> f64 {
+        hit_rate(self.transposition_hits, self.nodes)
+    }
+}
+
+/// Minimax algorithm to choose the best move for the computer
+pub fn minimax(state: &GameState, stats: &mut SearchStats) -> GameResult {
+    // Search on one copy of the position, making and unmaking moves instead of cloning each node
+    minimax_in_place(&mut state.clone(), stats, 0)
+}
+
+fn minimax_in_place(state: &mut GameState, stats: &mut SearchStats, depth: usize) -> GameResult {
+    if stats.cancelled() {
+        // The caller throws the result away, so any value will do
+        return GameResult::Tie;
+    }
+    stats.nodes += 1;
+    stats.max_depth = stats.max_depth.max(depth);
+
+    // Table results are for the side to move, so turn them round when it's the opponent's move
+    let computer_to_move = state.next_player() == state.computer_player;
+    let for_computer = |result: GameResult| {
+        if computer_to_move {
+            result
+        } else {
+            result.reversed()
+        }
+    };
+    if let Some(result) = tablebase::installed().and_then(|tb| tb.probe(state)) {
+        return for_computer(result);
+    }
+    if let Some(&result) = stats.transpositions.get(&state.position_id()) {
+        stats.transposition_hits += 1;
+        return for_computer(result);
+    }
+    if let Some(result) = stats
+        .shared
+        .as_ref()
+        .and_then(|t| t.get(state.position_id()))
+    {
+        stats.transposition_hits += 1;
+        return for_computer(result);
+    }
+    let result = search(state, stats, depth);
+    if !stats.cancelled() {
+        stats
+            .transpositions
+            .insert(state.position_id(), for_computer(result));
+    }
+    result
+}
+
+/// Search a position that neither table has a result for
+fn search(state: &mut GameState, stats: &mut SearchStats, depth: usize) -> GameResult {
+    if let Some(winner) = state.winner() {
+        if winner == state.computer_player {
+            return GameResult::Win;
+        } else {
+            return GameResult::Loss;
+        }
+    }
+
+    // Check tied game state
+    let possible_moves = state.legal_moves();
+    if possible_moves.is_empty() {
+        return GameResult::Tie;
+    }
+
+    let maximizing = state.next_player() == state.computer_player;
+    let mut best = if maximizing {
+        GameResult::Loss
+    } else {
+        GameResult::Win
+    };
+    for square in possible_moves {
+        let undo = state.make_move(square);
+        let result = minimax_in_place(state, stats, depth + 1);
+        state.unmake_move(undo);
+        best = if maximizing {
+            best.max(result)
+        } else {
+            best.min(result)
+        };
+    }
+    best
+}
+
+/// Get the line of play that follows from both sides playing their best moves
+pub fn principal_variation(state: &GameState) -> Vec<usize> {
+    let mut line = Vec::new();
+    let mut state = state.clone();
+    let mut stats = SearchStats::default();
+    while state.winner().is_none() {
+        let scored = state
+            .legal_moves()
+            .map(|square| (minimax(&state.with_move(square), &mut stats), square));
+        let best = if state.next_player() == state.computer_player {
+            scored.max_by_key(|&(result, _)| result)
+        } else {
+            scored.min_by_key(|&(result, _)| result)
+        };
+        let Some((_, square)) = best else {
+            break;
+        };
+        line.push(square);
+        state.apply_move(square);
+    }
+    line
+}
+
+/// Get the winner with best play from both sides, or None if the position is a tie
+pub fn perfect_play_winner(state: &GameState) -> Option<Player> {
+    let mut state = state.clone();
+    state.computer_player = state.next_player();
+    match minimax(&state, &mut SearchStats::default()) {
+        GameResult::Win => Some(state.next_player()),
+        GameResult::Tie => None,
+        GameResult::Loss => Some(state.next_player().opponent()),
+    }
+}
+
+impl GameState {
+    /// Evaluate every legal move for the computer
+    pub fn evaluate_computer_moves(&self, stats: &mut SearchStats) -> Vec<Candidate> {
+        let start = Instant::now();
+        let nodes_before = stats.nodes;
+        let hits_before = stats.transposition_hits;
+
+        let candidates: Vec<Candidate> = self
+            .legal_moves()
+            .map(|square| {
+                let result = minimax(&self.with_move(square), stats);
+                debug!(square, %result, "evaluated candidate");
+                Candidate { square, result }
+            })
+            .collect();
+
+        info!(
+            nodes = stats.nodes - nodes_before,
+            transposition_hits = stats.transposition_hits - hits_before,
+            max_depth = stats.max_depth,
+            elapsed_us = start.elapsed().as_micros() as u64,
+            result = ?candidates.iter().map(|c| c.result).max(),
+            "searched position"
+        );
+
+        candidates
+    }
+
+    /// Evaluate every legal move for the computer from the compile-time solution instead of
+    /// searching, counting one node per move looked up
+    pub fn look_up_computer_moves(&self, stats: &mut SearchStats) -> Vec<Candidate> {
+        let candidates: Vec<Candidate> = self
+            .legal_moves()
+            .map(|square| {
+                let after = self.with_move(square);
+                stats.nodes += 1;
+                // Solved results are for the side to move after the candidate
+                let result = solved::result(&after).expect("legal moves lead to legal positions");
+                let result = if after.next_player() == self.computer_player {
+                    result
+                } else {
+                    result.reversed()
+                };
+                Candidate { square, result }
+            })
+            .collect();
+        candidates
+    }
+
+    /// Get a list of the best moves
+    pub fn get_best_computer_moves(&self) -> Vec<Selection> {
+        let candidates = self.look_up_computer_moves(&mut SearchStats::default());
+        best_candidates(&candidates)
+    }
+
+    /// Choose one of the best moves at random, keeping the evaluation that led to it
+    pub fn decide_computer_move(&self) -> Decision {
+        self.decide_computer_move_blundering(0.0)
+    }
+
+    /// Like `decide_computer_move`, but play a worse move instead with the given probability
+    pub fn decide_computer_move_blundering(&self, blunder_chance: f64) -> Decision {
+        self.decide_computer_move_with_rng(blunder_chance, &mut rand::thread_rng())
+    }
+
+    /// Like `decide_computer_move_blundering`, but making every random choice with `rng`, so a
+    /// seeded generator gives the same moves each time
+    pub fn decide_computer_move_with_rng(
+        &self,
+        blunder_chance: f64,
+        rng: &mut impl Rng,
+    ) -> Decision {
+        let start = Instant::now();
+        let mut stats = SearchStats::default();
+        let candidates = self.look_up_computer_moves(&mut stats);
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        self.choose_move(candidates, &stats, elapsed_us, blunder_chance, rng)
+    }
+
+    /// Like `decide_computer_move`, but with the live search instead of the solved positions.
+    /// Returns None if the search was cancelled through `stats`
+    pub fn search_computer_move(&self, stats: &mut SearchStats) -> Option<Decision> {
+        let start = Instant::now();
+        let candidates = self.evaluate_computer_moves(stats);
+        if stats.cancelled() {
+            return None;
+        }
+        let elapsed_us = start.elapsed().as_micros() as u64;
+        Some(self.choose_move(candidates, stats, elapsed_us, 0.0, &mut rand::thread_rng()))
+    }
+
+    /// Pick from the evaluated candidates, blundering with the given probability
+    pub(crate) fn choose_move(
+        &self,
+        candidates: Vec<Candidate>,
+        stats: &SearchStats,
+        elapsed_us: u64,
+        blunder_chance: f64,
+        rng: &mut impl Rng,
+    ) -> Decision {
+        let best_moves = best_candidates(&candidates);
+        let best = candidates.iter().map(|c| c.result).max();
+        let worse_moves: Vec<Selection> = candidates
+            .iter()
+            .filter(|c| Some(c.result) < best)
+            .map(|c| Selection::new(c.square))
+            .collect();
+
+        let pool = if !worse_moves.is_empty() && rng.gen_bool(blunder_chance.clamp(0.0, 1.0)) {
+            debug!(blunder_chance, "blundering");
+            &worse_moves
+        } else {
+            &best_moves
+        };
+        let chosen = pool[rng.gen_range(0..pool.len())].square;
+        if tracing::enabled!(Level::DEBUG) {
+            let line = principal_variation(&self.with_move(chosen));
+            debug!(square = chosen, ?line, "chose move");
+        }
+        Decision {
+            chosen,
+            candidates,
+            nodes: stats.nodes,
+            transposition_hits: stats.transposition_hits,
+            depth: stats.max_depth + 1,
+            elapsed_us,
+        }
+    }
+
+    /// Whether the computer would accept a draw offer, which it does unless it can force a win
+    pub fn computer_accepts_draw(&self) -> bool {
+        perfect_play_winner(self) != Some(self.computer_player)
+    }
+
+    /// Whether the computer should take over its opponent's side under the pie rule
+    pub fn computer_should_swap(&self) -> bool {
+        let mut stats = SearchStats::default();
+        let mut swapped = self.clone();
+        swapped.swap_sides();
+        minimax(&swapped, &mut stats) > minimax(self, &mut stats)
+    }
+
+    /// Randomly choose one of the best moves to avoid repetitive games
+    pub fn get_random_computer_move(&self) -> Selection {
+        Selection::new(self.decide_computer_move().chosen)
+    }
+}
+
+/// Get the moves sharing the best result
+fn best_candidates(candidates: &[Candidate]) -> Vec<Selection> {
+    let best = candidates.iter().map(|c| c.result).max();
+    candidates
+        .iter()
+        .filter(|c| Some(c.result) == best)
+        .map(|c| Selection::new(c.square))
+        .collect()
+}
@@ -0,0 +1,163 @@
+use crate::ai::perfect_play_winner;
+use crate::game::{GameState, Player, Selection};
+use crate::render::Pieces;
+
+/// The decisive moment in a game the human lost
+#[derive(Copy, Clone, Debug)]
+pub enum LossReason {
+    /// The computer created two threats at once by playing on the given square
+    Fork(usize),
+    /// The human left the computer's threat on the given square unanswered
+    MissedBlock(usize),
+}
+
+/// Replay a finished game from its starting position to work out where the human went wrong
+pub fn analyze_loss(start: &GameState, history: &[usize]) -> Option<LossReason> {
+    let computer_player = start.computer_player;
+    let mut state = start.clone();
+    for &square in history {
+        if state.next_player() == computer_player {
+            state.apply_move(square);
+            if state.threats(computer_player).len() >= 2 {
+                return Some(LossReason::Fork(square));
+            }
+        } else {
+            let threats = state.threats(computer_player);
+            if !threats.is_empty() && !threats.contains(&square) {
+                return Some(LossReason::MissedBlock(threats[0]));
+            }
+            state.apply_move(square);
+        }
+    }
+    None
+}
+
+/// Check whether playing `square` turns a position that isn't lost into a forced loss, and why
+pub fn find_blunder(state: &GameState, square: usize) -> Option<LossReason> {
+    let player = state.next_player();
+    let opponent = player.opponent();
+    let after = state.with_move(square);
+    if perfect_play_winner(state) == Some(opponent) || perfect_play_winner(&after) != Some(opponent)
+    {
+        return None;
+    }
+
+    let threats = state.threats(opponent);
+    if !threats.is_empty() && !threats.contains(&square) {
+        return Some(LossReason::MissedBlock(threats[0]));
+    }
+    forcing_fork(&after, opponent).map(LossReason::Fork)
+}
+
+/// Find the fork `attacker` wins with, following forced blocks if it has to make threats first
+fn forcing_fork(state: &GameState, attacker: Player) -> Option<usize> {
+    let winning = state
+        .legal_moves()
+        .map(|square| (square, state.with_move(square)))
+        .filter(|(_, next)| perfect_play_winner(next) == Some(attacker));
+    let mut forcing = Vec::new();
+    for (square, next) in winning {
+        match next.threats(attacker).as_slice() {
+            [] => {}
+            [block] => forcing.push(next.with_move(*block)),
+            _ => return Some(square),
+        }
+    }
+    forcing.iter().find_map(|next| forcing_fork(next, attacker))
+}
+
+/// Get the game tree below a position as Graphviz, with nodes colored by evaluation
+///
+/// X wins are blue, O wins are pink and ties are gray. With `max_depth`, only that many moves are
+/// expanded and the positions at the cutoff are evaluated by the search.
+pub fn game_tree_dot(state: &GameState, max_depth: Option<usize>) -> String {
+    let mut writer = DotWriter::new(max_depth);
+    writer.write_node(state, 0);
+    writer.finish()
+}
+
+struct DotWriter {
+    out: String,
+    next_id: usize,
+    max_depth: Option<usize>,
+}
+
+impl DotWriter {
+    fn new(max_depth: Option<usize>) -> Self {
+        Self {
+            out: String::from(
+                "digraph game_tree {\n    node [shape=box, style=filled, fontname=monospace];\n",
+            ),
+            next_id: 0,
+            max_depth,
+        }
+    }
+
+    fn color(winner: Option<Player>) -> &'static str {
+        match winner {
+            Some(Player::X) => "lightblue",
+            Some(Player::O) => "lightpink",
+            None => "lightgray",
+        }
+    }
+
+    /// Write the node for `state` and everything below it, returning its id and evaluation
+    fn write_node(&mut self, state: &GameState, depth: usize) -> (usize, Option<Player>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let children: Vec<(usize, usize, Option<Player>)> = if state.winner().is_some()
+            || self.max_depth.is_some_and(|max| depth >= max)
+        {
+            Vec::new()
+        } else {
+            state
+                .open_squares()
+                .iter()
+                .map(|m| {
+                    let (child, winner) = self.write_node(&state.with_move(m.square), depth + 1);
+                    (m.square, child, winner)
+                })
+                .collect()
+        };
+
+        let winner = if children.is_empty() {
+            // Terminal positions and the depth cutoff are evaluated by the search directly
+            perfect_play_winner(state)
+        } else if children
+            .iter()
+            .any(|&(_, _, w)| w == Some(state.next_player()))
+        {
+            Some(state.next_player())
+        } else if children.iter().any(|&(_, _, w)| w.is_none()) {
+            None
+        } else {
+            Some(state.next_player().opponent())
+        };
+
+        let pieces = Pieces::default();
+        let label: Vec<String> = state
+            .board()
+            .chunks(3)
+            .map(|row| row.iter().map(|&square| pieces.get(square)).collect())
+            .collect();
+        self.out.push_str(&format!(
+            "    n{id} [label=\"{}\", fillcolor={}];\n",
+            label.join("\\n"),
+            Self::color(winner)
+        ));
+        for (square, child, _) in children {
+            self.out.push_str(&format!(
+                "    n{id} -> n{child} [label=\"{}\"];\n",
+                Selection::new(square)
+            ));
+        }
+
+        (id, winner)
+    }
+
+    fn finish(mut self) -> String {
+        self.out.push_str("}\n");
+        self.out
+    }
+}
@@ -0,0 +1,361 @@
+//! Dots and boxes, where players take turns drawing a line between two neighbouring dots and
+//! claim every box they close, moving again after each one
+//!
+//! Lines are numbered with the horizontal ones first, row by row from the top, then the vertical
+//! ones. Dots are named like spreadsheet cells, columns by letter and rows by number, so the top
+//! left line is `a1-b1`.
+
+use crate::error::{Error, MoveError};
+use crate::game::{GameStatus, Player};
+use crate::games::Game;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Most boxes on a side, so every line fits in a `u64`
+pub const MAX_SIZE: usize = 5;
+
+/// With this many lines or fewer left, the computer plays the rest out exactly instead of by rule
+/// of thumb
+const ENDGAME_LINES: usize = 14;
+
+/// A game of dots and boxes on a grid of boxes
+#[derive(Clone, Debug)]
+pub struct DotsAndBoxes {
+    width: usize,
+    height: usize,
+    /// One bit a line, set once it's drawn
+    drawn: u64,
+    owners: Vec<Option<Player>>,
+    pub next_player: Player,
+}
+
+impl DotsAndBoxes {
+    /// A board `width` boxes wide and `height` boxes tall
+    pub fn new(width: usize, height: usize) -> crate::Result<Self> {
+        if !(1..=MAX_SIZE).contains(&width) || !(1..=MAX_SIZE).contains(&height) {
+            return Err(Error::Config(format!(
+                "dots and boxes is played on 1x1 to {MAX_SIZE}x{MAX_SIZE} boxes, not {width}x{height}"
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            drawn: 0,
+            owners: vec![None; width * height],
+            next_player: Player::X,
+        })
+    }
+
+    fn lines(&self) -> usize {
+        (self.height + 1) * self.width + self.height * (self.width + 1)
+    }
+
+    fn horizontal_lines(&self) -> usize {
+        (self.height + 1) * self.width
+    }
+
+    pub fn is_drawn(&self, line: usize) -> bool {
+        self.drawn & 1 << line != 0
+    }
+
+    /// The boxes claimed by a player
+    pub fn score(&self, player: Player) -> usize {
+        self.owners.iter().filter(|&&o| o == Some(player)).count()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.owners.iter().any(Option::is_none) {
+            return GameStatus::InProgress;
+        }
+        match self.score(Player::X).cmp(&self.score(Player::O)) {
+            std::cmp::Ordering::Greater => GameStatus::Won(Player::X),
+            std::cmp::Ordering::Less => GameStatus::Won(Player::O),
+            std::cmp::Ordering::Equal => GameStatus::Tie,
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<usize> {
+        (0..self.lines())
+            .filter(|&line| !self.is_drawn(line))
+            .collect()
+    }
+
+    /// The dots at each end of a line, e.g. `a1-b1`
+    pub fn line_name(&self, line: usize) -> String {
+        let dot =
+            |row: usize, column: usize| format!("{}{}", (b'a' + column as u8) as char, row + 1);
+        if line < self.horizontal_lines() {
+            let (row, column) = (line / self.width, line % self.width);
+            format!("{}-{}", dot(row, column), dot(row, column + 1))
+        } else {
+            let line = line - self.horizontal_lines();
+            let (row, column) = (line / (self.width + 1), line % (self.width + 1));
+            format!("{}-{}", dot(row, column), dot(row + 1, column))
+        }
+    }
+
+    /// The four lines around a box: top, bottom, left and right
+    fn sides(&self, square: usize) -> [usize; 4] {
+        let (row, column) = (square / self.width, square % self.width);
+        let left = self.horizontal_lines() + row * (self.width + 1) + column;
+        [
+            row * self.width + column,
+            (row + 1) * self.width + column,
+            left,
+            left + 1,
+        ]
+    }
+
+    /// The one or two boxes a line is a side of
+    fn boxes_beside(&self, line: usize) -> Vec<usize> {
+        let mut boxes = Vec::with_capacity(2);
+        if line < self.horizontal_lines() {
+            let (row, column) = (line / self.width, line % self.width);
+            if row > 0 {
+                boxes.push((row - 1) * self.width + column);
+            }
+            if row < self.height {
+                boxes.push(row * self.width + column);
+            }
+        } else {
+            let line = line - self.horizontal_lines();
+            let (row, column) = (line / (self.width + 1), line % (self.width + 1));
+            if column > 0 {
+                boxes.push(row * self.width + column - 1);
+            }
+            if column < self.width {
+                boxes.push(row * self.width + column);
+            }
+        }
+        boxes
+    }
+
+    fn sides_drawn(&self, square: usize) -> usize {
+        self.sides(square)
+            .iter()
+            .filter(|&&line| self.is_drawn(line))
+            .count()
+    }
+
+    /// Draw a line for the side to move, returning how many boxes it closed. The side to move
+    /// only changes when that's none
+    pub fn play(&mut self, line: usize) -> Result<usize, MoveError> {
+        if line >= self.lines() {
+            return Err(MoveError::NoSuchLine(line));
+        }
+        if self.is_drawn(line) {
+            return Err(MoveError::LineDrawn(line));
+        }
+        Ok(self.draw(line))
+    }
+
+    fn draw(&mut self, line: usize) -> usize {
+        self.drawn |= 1 << line;
+        let mut closed = 0;
+        for square in self.boxes_beside(line) {
+            if self.sides_drawn(square) == 4 {
+                self.owners[square] = Some(self.next_player);
+                closed += 1;
+            }
+        }
+        if closed == 0 {
+            self.next_player = self.next_player.opponent();
+        }
+        closed
+    }
+
+    /// Whether drawing a line would close a box
+    fn closes_box(&self, line: usize) -> bool {
+        self.boxes_beside(line)
+            .iter()
+            .any(|&square| self.sides_drawn(square) == 3)
+    }
+
+    /// Whether drawing a line would leave a box with three sides for the opponent to close
+    fn gives_box(&self, line: usize) -> bool {
+        self.boxes_beside(line)
+            .iter()
+            .any(|&square| self.sides_drawn(square) == 2)
+    }
+
+    /// How many boxes the opponent can close in a row after this line is drawn
+    fn boxes_given(&self, line: usize) -> usize {
+        let mut game = self.clone();
+        game.draw(line);
+        let mut given = 0;
+        while let Some(line) = game
+            .legal_moves()
+            .into_iter()
+            .find(|&line| game.closes_box(line))
+        {
+            given += game.draw(line);
+        }
+        given
+    }
+}
+
+impl Display for DotsAndBoxes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let columns: Vec<String> = (0..=self.width)
+            .map(|column| ((b'a' + column as u8) as char).to_string())
+            .collect();
+        writeln!(f, "  {}", columns.join("   "))?;
+        for row in 0..=self.height {
+            let mut dots = format!("{:<2}+", row + 1);
+            for column in 0..self.width {
+                let drawn = self.is_drawn(row * self.width + column);
+                dots.push_str(if drawn { "---+" } else { "   +" });
+            }
+            writeln!(f, "{}", dots.trim_end())?;
+            if row == self.height {
+                break;
+            }
+            let mut boxes = "  ".to_string();
+            for column in 0..=self.width {
+                let line = self.horizontal_lines() + row * (self.width + 1) + column;
+                boxes.push(if self.is_drawn(line) { '|' } else { ' ' });
+                if column < self.width {
+                    match self.owners[row * self.width + column] {
+                        Some(player) => boxes.push_str(&format!(" {player} ")),
+                        None => boxes.push_str("   "),
+                    }
+                }
+            }
+            writeln!(f, "{}", boxes.trim_end())?;
+        }
+        write!(
+            f,
+            "X {} - O {}",
+            self.score(Player::X),
+            self.score(Player::O)
+        )
+    }
+}
+
+/// The computer player, which plays by rules of thumb until few enough lines are left to search
+/// the rest exactly
+#[derive(Debug, Default)]
+pub struct Search {
+    /// Boxes still to come for the side to move less the opponent's, by the lines drawn
+    table: HashMap<u64, i32>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Choose a line for the side to move
+    ///
+    /// With lines to spare it closes any box it can, then draws a line that gives nothing away.
+    /// Once every line gives a box away, it opens the shortest chain, counting how many boxes in
+    /// a row each line would hand over. The endgame is searched exactly, which finds when to
+    /// leave the last two boxes of a chain to keep control.
+    pub fn decide(&mut self, game: &DotsAndBoxes, rng: &mut impl Rng) -> Option<usize> {
+        let moves = game.legal_moves();
+        if moves.len() <= ENDGAME_LINES {
+            return self.best_endgame_line(game, &moves, rng);
+        }
+        let captures: Vec<usize> = moves
+            .iter()
+            .copied()
+            .filter(|&line| game.closes_box(line))
+            .collect();
+        let safe: Vec<usize> = moves
+            .iter()
+            .copied()
+            .filter(|&line| !game.closes_box(line) && !game.gives_box(line))
+            .collect();
+        if let Some(&line) = captures.choose(rng) {
+            return Some(line);
+        }
+        if let Some(&line) = safe.choose(rng) {
+            return Some(line);
+        }
+        let fewest = moves.iter().map(|&line| game.boxes_given(line)).min()?;
+        let cheapest: Vec<usize> = moves
+            .into_iter()
+            .filter(|&line| game.boxes_given(line) == fewest)
+            .collect();
+        cheapest.choose(rng).copied()
+    }
+
+    fn best_endgame_line(
+        &mut self,
+        game: &DotsAndBoxes,
+        moves: &[usize],
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        let scored: Vec<(usize, i32)> = moves
+            .iter()
+            .map(|&line| (line, self.value_of(game, line)))
+            .collect();
+        let best = scored.iter().map(|&(_, value)| value).max()?;
+        let best_lines: Vec<usize> = scored
+            .into_iter()
+            .filter(|&(_, value)| value == best)
+            .map(|(line, _)| line)
+            .collect();
+        best_lines.choose(rng).copied()
+    }
+
+    /// The boxes the side to move gets from drawing a line onwards, less the opponent's
+    fn value_of(&mut self, game: &DotsAndBoxes, line: usize) -> i32 {
+        let mut after = game.clone();
+        let closed = after.draw(line) as i32;
+        if closed > 0 {
+            closed + self.negamax(&after)
+        } else {
+            -self.negamax(&after)
+        }
+    }
+
+    fn negamax(&mut self, game: &DotsAndBoxes) -> i32 {
+        if game.status() != GameStatus::InProgress {
+            return 0;
+        }
+        if let Some(&value) = self.table.get(&game.drawn) {
+            return value;
+        }
+        let value = game
+            .legal_moves()
+            .into_iter()
+            .map(|line| self.value_of(game, line))
+            .max()
+            .expect("an unfinished game has a line to draw");
+        self.table.insert(game.drawn, value);
+        value
+    }
+}
+
+impl Game for DotsAndBoxes {
+    type Move = usize;
+
+    fn next_player(&self) -> Player {
+        self.next_player
+    }
+
+    fn legal_moves(&self) -> Vec<usize> {
+        DotsAndBoxes::legal_moves(self)
+    }
+
+    fn play(&mut self, line: usize) -> Result<(), MoveError> {
+        DotsAndBoxes::play(self, line).map(|_| ())
+    }
+
+    fn status(&self) -> GameStatus {
+        DotsAndBoxes::status(self)
+    }
+
+    fn describe(&self, line: usize) -> String {
+        self.line_name(line)
+    }
+
+    /// The boxes the side to move is ahead by
+    fn evaluate(&self) -> i32 {
+        let player = self.next_player;
+        self.score(player) as i32 - self.score(player.opponent()) as i32
+    }
+}
@@ -0,0 +1,115 @@
+//! One validated way to set up a game, so the front-ends don't each check their own options
+
+use crate::ai::{Difficulty, EASY_BLUNDER_CHANCE};
+use crate::error::Error;
+use crate::game::{GameState, Player};
+
+/// How the computer plays a game
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct AiOptions {
+    pub difficulty: Difficulty,
+    /// Chance of the computer playing a worse move than its best, only set for adaptive and easy
+    /// games
+    pub blunder_chance: Option<f64>,
+    /// Whether the second player may swap sides after the first move
+    pub pie_rule: bool,
+}
+
+/// A game ready to be played, as built by [`GameBuilder`]
+#[derive(Clone)]
+pub struct GameSetup {
+    pub state: GameState,
+    pub ai: AiOptions,
+}
+
+/// Collects the options for a new game, checking them together in `build`
+#[derive(Clone, Debug, Default)]
+pub struct GameBuilder {
+    computer_player: Option<Player>,
+    board: Option<[Option<Player>; 9]>,
+    opening: Vec<usize>,
+    ai: AiOptions,
+}
+
+impl GameState {
+    /// Start setting up a game, with the computer playing O by default
+    pub fn builder() -> GameBuilder {
+        GameBuilder::default()
+    }
+}
+
+impl GameBuilder {
+    /// Have the human play the given side and the computer the other
+    pub fn human_player(mut self, player: Player) -> Self {
+        self.computer_player = Some(player.opponent());
+        self
+    }
+
+    pub fn computer_player(mut self, player: Player) -> Self {
+        self.computer_player = Some(player);
+        self
+    }
+
+    /// Start from a set-up board instead of an empty one
+    pub fn board(mut self, board: [Option<Player>; 9]) -> Self {
+        self.board = Some(board);
+        self
+    }
+
+    /// Play these squares in order from the starting board before the game starts, e.g. to give the
+    /// weaker side a head start
+    pub fn opening(mut self, moves: &[usize]) -> Self {
+        self.opening = moves.to_vec();
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.ai.difficulty = difficulty;
+        self
+    }
+
+    /// How often an adaptive computer blunders, between 0 and 1
+    pub fn blunder_chance(mut self, chance: f64) -> Self {
+        self.ai.blunder_chance = Some(chance);
+        self
+    }
+
+    pub fn pie_rule(mut self, pie_rule: bool) -> Self {
+        self.ai.pie_rule = pie_rule;
+        self
+    }
+
+    /// Check the options and set up the game
+    pub fn build(self) -> crate::Result<GameSetup> {
+        if let Some(chance) = self.ai.blunder_chance {
+            if self.ai.difficulty != Difficulty::Adaptive {
+                return Err(Error::Config(
+                    "a blunder chance only applies to adaptive games".to_string(),
+                ));
+            }
+            if !(0.0..=1.0).contains(&chance) {
+                return Err(Error::Config(format!(
+                    "the blunder chance must be between 0 and 1, got {chance}"
+                )));
+            }
+        }
+        if self.ai.pie_rule && (self.board.is_some() || !self.opening.is_empty()) {
+            return Err(Error::Config(
+                "the pie rule only applies to games started from an empty board".to_string(),
+            ));
+        }
+        let mut ai = self.ai;
+        if ai.difficulty == Difficulty::Easy {
+            ai.blunder_chance = Some(EASY_BLUNDER_CHANCE);
+        }
+        let computer_player = self.computer_player.unwrap_or(Player::O);
+        let mut state = match self.board {
+            Some(board) => GameState::from_board(computer_player, board)?,
+            None => GameState::new(computer_player),
+        };
+        for &square in &self.opening {
+            state.try_move(square)?;
+        }
+        Ok(GameSetup { state, ai })
+    }
+}
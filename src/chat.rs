@@ -0,0 +1,164 @@
+//! Games played through text commands in a chat, shared by the chat bot front-ends
+//!
+//! Each user has at most one game going, keyed by their name. Commands start with a prefix such
+//! as `!ttt` and squares are named by column and row, `a1` being the top left and `c3` the bottom
+//! right:
+//!
+//! ```text
+//! !ttt start o   start a game as O (X if no side is given)
+//! !ttt b2        play on the center square
+//! !ttt board     show the board again
+//! !ttt resign    give up the current game
+//! !ttt help      list the commands
+//! ```
+
+use crate::game::{GameState, GameStatus, Player};
+use std::collections::HashMap;
+
+/// Every game being played in one chat
+pub struct ChatGames {
+    prefix: String,
+    games: HashMap<String, GameState>,
+}
+
+impl ChatGames {
+    /// Games driven by messages starting with `prefix`, e.g. `!ttt`
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            games: HashMap::new(),
+        }
+    }
+
+    /// Number of games in progress
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// The game `user` is playing, if they have one going
+    pub fn game(&self, user: &str) -> Option<&GameState> {
+        self.games.get(user)
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Handle a message from `user`, returning the lines to reply with, or nothing if the message
+    /// isn't a command
+    pub fn handle(&mut self, user: &str, message: &str) -> Vec<String> {
+        let mut words = message.split_whitespace();
+        if words.next() != Some(self.prefix.as_str()) {
+            return Vec::new();
+        }
+        let command = words.next().unwrap_or("help").to_lowercase();
+        let argument = words.next();
+
+        match command.as_str() {
+            "start" => self.start(user, argument),
+            "board" => match self.games.get(user) {
+                Some(game) => render_for_chat(game),
+                None => vec![format!("{user}: you don't have a game, start one with `{} start`", self.prefix)],
+            },
+            "resign" => match self.games.remove(user) {
+                Some(_) => vec![format!("{user}: you resigned, the computer wins")],
+                None => vec![format!("{user}: you don't have a game to resign")],
+            },
+            "help" => vec![format!(
+                "{user}: `{0} start [x|o]` starts a game, `{0} b2` plays a square (a1 is the top left), `{0} board` shows the board and `{0} resign` gives up",
+                self.prefix
+            )],
+            square => match parse_coordinate(square) {
+                Some(square) => self.play(user, square),
+                None => vec![format!(
+                    "{user}: `{square}` isn't a command or a square, try `{} help`",
+                    self.prefix
+                )],
+            },
+        }
+    }
+
+    fn start(&mut self, user: &str, side: Option<&str>) -> Vec<String> {
+        let human = match side.map(str::parse::<Player>) {
+            None => Player::X,
+            Some(Ok(player)) => player,
+            Some(Err(e)) => return vec![format!("{user}: {e}")],
+        };
+        let mut game = GameState::new(human.opponent());
+        let mut lines = vec![format!("{user}: new game, you play {human}")];
+        if game.next_player() == game.computer_player {
+            let square = game.decide_computer_move().chosen;
+            game.apply_move(square);
+            lines.push(format!("The computer played {}", coordinate(square)));
+        }
+        lines.extend(render_for_chat(&game));
+        self.games.insert(user.to_string(), game);
+        lines
+    }
+
+    fn play(&mut self, user: &str, square: usize) -> Vec<String> {
+        let Some(game) = self.games.get_mut(user) else {
+            return vec![format!(
+                "{user}: you don't have a game, start one with `{} start`",
+                self.prefix
+            )];
+        };
+        if let Err(e) = game.try_move(square) {
+            return vec![format!("{user}: {e}")];
+        }
+        let mut lines = Vec::new();
+        if game.status() == GameStatus::InProgress {
+            let reply = game.decide_computer_move().chosen;
+            game.apply_move(reply);
+            lines.push(format!("{user}: the computer played {}", coordinate(reply)));
+        }
+        lines.extend(render_for_chat(game));
+
+        let result = match game.status() {
+            GameStatus::InProgress => return lines,
+            GameStatus::Won(winner) if winner == game.computer_player => "the computer wins",
+            GameStatus::Won(_) => "you win!",
+            GameStatus::Tie => "it's a tie",
+        };
+        lines.push(format!("{user}: game over, {result}"));
+        self.games.remove(user);
+        lines
+    }
+}
+
+/// Read a square named by column and row, e.g. `b2` or `B2` for the center, or a number 1-9
+pub fn parse_coordinate(s: &str) -> Option<usize> {
+    let chars: Vec<char> = s.chars().collect();
+    match chars[..] {
+        [digit @ '1'..='9'] => Some(digit as usize - '1' as usize),
+        [column @ ('a'..='c' | 'A'..='C'), row @ '1'..='3'] => {
+            let column = column.to_ascii_lowercase() as usize - 'a' as usize;
+            Some((row as usize - '1' as usize) * 3 + column)
+        }
+        _ => None,
+    }
+}
+
+/// Name a square by column and row, e.g. `b2` for the center
+pub fn coordinate(square: usize) -> String {
+    format!("{}{}", (b'a' + (square % 3) as u8) as char, square / 3 + 1)
+}
+
+/// Draw the board as short lines with the column letters and row numbers, for monospaced chat
+pub fn render_for_chat(state: &GameState) -> Vec<String> {
+    let mut lines = vec!["  a b c".to_string()];
+    for row in 0..3 {
+        let squares: Vec<String> = (0..3)
+            .map(|column| match state.board()[row * 3 + column] {
+                Some(player) => player.to_string(),
+                None => ".".to_string(),
+            })
+            .collect();
+        lines.push(format!("{} {}", row + 1, squares.join(" ")));
+    }
+    lines
+}
@@ -0,0 +1,145 @@
+//! Connecting to a game [`server`](crate::server), and the games joined on servers, kept
+//! between runs so they can be picked up again
+
+use crate::error::Error;
+use crate::game::Player;
+use crate::handshake::{Hello, Welcome};
+use crate::server::{self, Event, Request};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How long to wait for the server to answer the hello
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A connection to a game server
+pub struct Connection {
+    stream: TcpStream,
+    address: String,
+    agreed: Welcome,
+}
+
+impl Connection {
+    /// Connect to the server at `address`, e.g. `localhost:4040`, and go through the handshake,
+    /// with what the server sends afterwards arriving on the receiver until the connection closes
+    pub fn connect(address: &str) -> crate::Result<(Self, Receiver<Event>)> {
+        let stream = TcpStream::connect(address).map_err(Error::network("connect to", address))?;
+        let reader = stream
+            .try_clone()
+            .map_err(Error::network("connect to", address))?;
+        let (sender, receiver) = mpsc::channel();
+        let peer = address.to_string();
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else { break };
+                match serde_json::from_str(&line) {
+                    Ok(event) => {
+                        if sender.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => tracing::warn!(server = peer, error = %e, "unreadable event"),
+                }
+            }
+        });
+        let mut connection = Self {
+            stream,
+            address: address.to_string(),
+            agreed: Welcome::assumed(&[]),
+        };
+        connection.send(&Request::Hello(Hello {
+            version: server::SUPPORT.newest,
+            capabilities: server::SUPPORT.capabilities.to_vec(),
+        }))?;
+        let failed =
+            |kind: ErrorKind| Error::network("agree on a protocol with", address)(kind.into());
+        match receiver.recv_timeout(HANDSHAKE_TIMEOUT) {
+            Ok(Event::Welcome(welcome)) => connection.agreed = welcome,
+            // A server from before the handshake, which speaks version 1 and nothing more
+            Ok(Event::Error { message, .. }) if message.starts_with("invalid request") => {}
+            Ok(Event::Error { message, .. }) => {
+                return Err(Error::network("agree on a protocol with", address)(
+                    std::io::Error::other(message),
+                ))
+            }
+            Ok(_) => return Err(failed(ErrorKind::InvalidData)),
+            Err(RecvTimeoutError::Timeout) => return Err(failed(ErrorKind::TimedOut)),
+            Err(RecvTimeoutError::Disconnected) => return Err(failed(ErrorKind::UnexpectedEof)),
+        }
+        Ok((connection, receiver))
+    }
+
+    /// The version and capabilities agreed with the server
+    pub fn agreed(&self) -> &Welcome {
+        &self.agreed
+    }
+
+    pub fn send(&mut self, request: &Request) -> crate::Result<()> {
+        let line = serde_json::to_string(request)? + "\n";
+        self.stream
+            .write_all(line.as_bytes())
+            .map_err(Error::network("send to", &self.address))
+    }
+}
+
+/// A seat taken in a game on a server
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Seat {
+    pub server: String,
+    pub code: String,
+    pub token: String,
+    pub player: Player,
+    /// The square committed to in a quantum game and not revealed yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committed: Option<Committed>,
+}
+
+/// A square committed to in a quantum game, kept until it's revealed since the commitment can't
+/// be revealed without it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Committed {
+    pub square: usize,
+    pub nonce: String,
+}
+
+/// The seats taken in games still being played, stored as a JSON list
+pub struct SeatBook {
+    path: PathBuf,
+}
+
+impl SeatBook {
+    /// The location, e.g. `~/.local/share/simple-tic-tac-toe/games.json` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("simple-tic-tac-toe").join("games.json"))
+    }
+
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    pub fn seats(&self) -> crate::Result<Vec<Seat>> {
+        let json = match std::fs::read_to_string(&self.path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io("read", &self.path)(e)),
+        };
+        serde_json::from_str(&json).map_err(|e| {
+            Error::parse_with(format!("{} is not a list of games", self.path.display()), e)
+        })
+    }
+
+    /// Replace the stored seats
+    pub fn save(&self, seats: &[Seat]) -> crate::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+        }
+        let json = serde_json::to_string_pretty(seats)?;
+        std::fs::write(&self.path, json).map_err(Error::io("write", &self.path))
+    }
+}
@@ -0,0 +1,302 @@
+use crate::ai::Difficulty;
+use crate::error::Error;
+use crate::journal::SyncPolicy;
+use crate::mnk::Rules;
+use crate::think_time::ThinkTime;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(feature = "storage")]
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Settings read from the TOML config file, with every value optional
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub adaptive: AdaptiveConfig,
+    pub think_time: ThinkTimeConfig,
+    pub saves: SavesConfig,
+    pub journal: JournalConfig,
+    pub server: ServerConfig,
+    /// Bundles of settings by name, each from a `[profiles.<name>]` table, picked with
+    /// `--profile`. One with the name of a built in profile replaces it
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Rule variants by name, each from a `[variants.<name>]` table
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub variants: BTreeMap<String, VariantConfig>,
+}
+
+/// Settings for the adaptive difficulty
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveConfig {
+    /// Share of games the human should win, between 0 and 1
+    pub target_win_rate: f64,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            target_win_rate: 0.5,
+        }
+    }
+}
+
+/// Settings for the computer's pause before each move
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThinkTimeConfig {
+    /// Pause before every computer move, as if with `--think-time`
+    pub enabled: bool,
+    /// The pause for the most obvious moves, in milliseconds
+    pub fastest_ms: u64,
+    /// The pause for the hardest moves, in milliseconds
+    pub slowest_ms: u64,
+}
+
+impl Default for ThinkTimeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fastest_ms: 300,
+            slowest_ms: 2000,
+        }
+    }
+}
+
+impl ThinkTimeConfig {
+    pub fn think_time(&self) -> ThinkTime {
+        ThinkTime {
+            fastest: Duration::from_millis(self.fastest_ms),
+            slowest: Duration::from_millis(self.slowest_ms),
+        }
+    }
+}
+
+/// Settings for the save directory
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SavesConfig {
+    /// How many of the last games played to keep as autosaves, or 0 for none
+    pub autosave: usize,
+}
+
+impl Default for SavesConfig {
+    fn default() -> Self {
+        Self { autosave: 5 }
+    }
+}
+
+/// Settings for the journal kept of the game being played, for restoring it after a crash
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JournalConfig {
+    pub enabled: bool,
+    /// When the journal is flushed to disk, `always` or `never`
+    pub sync: SyncPolicy,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sync: SyncPolicy::Always,
+        }
+    }
+}
+
+/// Limits on what clients of the game server can ask of it, so one can't take it over
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Games kept at once, across everyone
+    pub max_games: usize,
+    /// Games each connection can hold a seat in at once
+    pub games_per_connection: usize,
+    /// Connections open at once from each IP address
+    pub connections_per_ip: usize,
+    /// Requests read from each connection a second, after a burst of as many
+    pub requests_per_second: u32,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_games: 10_000,
+            games_per_connection: 32,
+            connections_per_ip: 8,
+            requests_per_second: 10,
+        }
+    }
+}
+
+/// A bundle of settings picked with `--profile`, for players who need the game to work
+/// differently
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Describe the board in words instead of drawing it
+    pub screen_reader: bool,
+    /// Pick from menus by typing an option's number instead of with the arrow keys
+    pub number_keys: bool,
+    /// Draw prompts without color
+    pub no_color: bool,
+    /// Leave out spinners and other lines that redraw themselves
+    pub no_animations: bool,
+    /// Say back every move in full as it's made
+    pub verbose: bool,
+    /// Play at this difficulty whatever `--difficulty` says
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+    /// Draw the pieces as emoji
+    pub emoji: bool,
+    /// Draw the board with bigger squares
+    pub big_board: bool,
+    /// Set off fireworks when the human wins
+    pub celebrations: bool,
+    /// Ask and tell things in shorter, simpler words
+    pub simple_prompts: bool,
+    /// Offer another game after each one, starting it on Enter
+    pub rematch: bool,
+    /// Keep a stream overlay in the default place, as if with `--overlay`
+    pub overlay: bool,
+    /// Also serve the overlay over HTTP on this local port
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay_port: Option<u16>,
+}
+
+impl Profile {
+    /// The names of the profiles that come with the game
+    pub const BUILT_IN: [&'static str; 3] = ["accessible", "kids", "streamer"];
+
+    /// A profile that comes with the game, by name
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "accessible" => Some(Self {
+                screen_reader: true,
+                number_keys: true,
+                no_color: true,
+                no_animations: true,
+                verbose: true,
+                ..Self::default()
+            }),
+            "kids" => Some(Self {
+                difficulty: Some(Difficulty::Easy),
+                emoji: true,
+                big_board: true,
+                celebrations: true,
+                simple_prompts: true,
+                rematch: true,
+                ..Self::default()
+            }),
+            "streamer" => Some(Self {
+                overlay: true,
+                rematch: true,
+                ..Self::default()
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A variant made by mixing rule toggles, played with the `variant` subcommand
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VariantConfig {
+    pub width: usize,
+    pub height: usize,
+    /// How many marks in a row end the game
+    pub win_length: usize,
+    /// Completing a line loses instead of wins
+    pub misere: bool,
+    /// Marks fall to the bottom of the chosen column
+    pub gravity: bool,
+    /// Lines carry on from one edge of the board to the opposite one
+    pub wrap: bool,
+    /// The second player may swap sides after the first move
+    pub pie_rule: bool,
+}
+
+impl Default for VariantConfig {
+    fn default() -> Self {
+        Self {
+            width: 3,
+            height: 3,
+            win_length: 3,
+            misere: false,
+            gravity: false,
+            wrap: false,
+            pie_rule: false,
+        }
+    }
+}
+
+impl VariantConfig {
+    /// The board rules the variant plays by. The pie rule is up to whoever plays the game
+    pub fn rules(&self) -> crate::Result<Rules> {
+        Ok(Rules::new(self.width, self.height, self.win_length)?
+            .with_misere(self.misere)
+            .with_gravity(self.gravity)
+            .with_wrap(self.wrap))
+    }
+}
+
+impl Config {
+    /// A profile by name, from the config file or else built in
+    pub fn profile(&self, name: &str) -> crate::Result<Profile> {
+        if let Some(profile) = self.profiles.get(name) {
+            return Ok(profile.clone());
+        }
+        Profile::built_in(name).ok_or_else(|| {
+            let mut names: Vec<&str> = Profile::BUILT_IN.to_vec();
+            names.extend(self.profiles.keys().map(String::as_str));
+            Error::Config(format!(
+                "there is no profile named `{name}`, expected one of {}",
+                names.join(", ")
+            ))
+        })
+    }
+
+    /// The config file location, e.g. `~/.config/simple-tic-tac-toe/config.toml` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("simple-tic-tac-toe").join("config.toml"))
+    }
+
+    /// Read the config file, using the defaults if it doesn't exist
+    #[cfg(feature = "storage")]
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(Error::io("read", path)(e)),
+        };
+        let config: Self = toml::from_str(&text)
+            .map_err(|e| Error::parse_with(format!("invalid config in {}", path.display()), e))?;
+        let target = config.adaptive.target_win_rate;
+        if !(0.0..=1.0).contains(&target) {
+            return Err(Error::Config(format!(
+                "adaptive.target_win_rate must be between 0 and 1, got {target}"
+            )));
+        }
+        let think_time = &config.think_time;
+        if think_time.fastest_ms > think_time.slowest_ms {
+            return Err(Error::Config(format!(
+                "think_time.fastest_ms can't be more than think_time.slowest_ms, got {} and {}",
+                think_time.fastest_ms, think_time.slowest_ms
+            )));
+        }
+        if config.server.requests_per_second == 0 {
+            return Err(Error::Config(
+                "server.requests_per_second must be at least 1".to_string(),
+            ));
+        }
+        for (name, variant) in &config.variants {
+            if let Err(e) = variant.rules() {
+                return Err(Error::Config(format!("variants.{name}: {e}")));
+            }
+        }
+        Ok(config)
+    }
+}
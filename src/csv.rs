@@ -0,0 +1,264 @@
+//! The stats store as CSV for spreadsheets: one row per game, or one row per move for the full
+//! history
+//!
+//! The columns are fixed and only ever added to at the end, so sheets built on an export keep
+//! working with later ones. Times are in UTC.
+
+use crate::ai::Difficulty;
+use crate::error::Error;
+use crate::game::{Player, Selection};
+use crate::record::Ending;
+use crate::stats::StatsEntry;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The columns of `stats_csv`, in order
+pub const STATS_COLUMNS: [&str; 11] = [
+    "game",
+    "finished_at",
+    "finished",
+    "difficulty",
+    "blunder_chance",
+    "human_player",
+    "result",
+    "moves",
+    "swapped",
+    "ending",
+    "set_up",
+];
+
+/// The columns of `history_csv`, in order. Squares are numbered 1-9 from the top left, as on the
+/// number keys, and the evaluation is the result for the player with best play after the move.
+/// The last four are empty for moves saved without them
+pub const HISTORY_COLUMNS: [&str; 13] = [
+    "game",
+    "finished_at",
+    "finished",
+    "difficulty",
+    "move",
+    "player",
+    "by",
+    "square",
+    "square_name",
+    "think_time_ms",
+    "evaluation",
+    "annotation",
+    "glyph",
+];
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A calendar day, written like `2024-05-31`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Day {
+    /// Days since 1970-01-01
+    days: i64,
+}
+
+impl Day {
+    /// The day a time in seconds since the Unix epoch falls on
+    pub fn of(seconds: u64) -> Self {
+        Self {
+            days: (seconds / SECONDS_PER_DAY) as i64,
+        }
+    }
+
+    /// The year, month and day of the month
+    fn date(self) -> (i64, u32, u32) {
+        // Howard Hinnant's civil_from_days, with years running from March
+        let z = self.days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+
+    fn from_date(year: i64, month: u32, day: u32) -> Self {
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let mp = i64::from(if month > 2 { month - 3 } else { month + 9 });
+        let day_of_year = (153 * mp + 2) / 5 + i64::from(day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Self {
+            days: era * 146_097 + day_of_era - 719_468,
+        }
+    }
+}
+
+impl Display for Day {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = self.date();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl FromStr for Day {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || Error::parse(format!("`{s}` is not a date, expected one like 2024-05-31"));
+        let mut parts = s.splitn(3, '-');
+        let mut part = || parts.next().ok_or_else(bad);
+        let (year, month, day) = (part()?, part()?, part()?);
+        let year: i64 = year.parse().map_err(|_| bad())?;
+        let month: u32 = month.parse().map_err(|_| bad())?;
+        let day: u32 = day.parse().map_err(|_| bad())?;
+        let parsed = Self::from_date(year, month, day);
+        // Catches days past the end of the month, which come out as another date
+        if parsed.date() != (year, month, day) {
+            return Err(bad());
+        }
+        Ok(parsed)
+    }
+}
+
+/// Which games to export; everything left as None lets all games through
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    /// The first day to include
+    pub since: Option<Day>,
+    /// The last day to include
+    pub until: Option<Day>,
+    pub difficulty: Option<Difficulty>,
+}
+
+impl Filter {
+    pub fn matches(&self, entry: &StatsEntry) -> bool {
+        let day = Day::of(entry.finished_at);
+        self.since.is_none_or(|since| day >= since)
+            && self.until.is_none_or(|until| day <= until)
+            && self
+                .difficulty
+                .is_none_or(|difficulty| entry.difficulty == difficulty)
+    }
+}
+
+/// One row for each game the filter lets through, under `STATS_COLUMNS`. Games are numbered from
+/// 1 in the order they were recorded, before filtering, so the numbers match `history_csv`
+pub fn stats_csv(entries: &[StatsEntry], filter: &Filter) -> String {
+    let mut csv = row(STATS_COLUMNS);
+    for (i, entry) in numbered(entries, filter) {
+        csv.push_str(&row([
+            i.to_string(),
+            entry.finished_at.to_string(),
+            timestamp(entry.finished_at),
+            entry.difficulty.to_string(),
+            entry
+                .blunder_chance
+                .map_or(String::new(), |chance| chance.to_string()),
+            entry.human_player.to_string(),
+            entry.result.to_string(),
+            entry.game.moves.len().to_string(),
+            entry.game.swapped.to_string(),
+            ending(entry).unwrap_or_default(),
+            entry.game.start.is_some().to_string(),
+        ]));
+    }
+    csv
+}
+
+/// One row for each move of each game the filter lets through, under `HISTORY_COLUMNS`
+pub fn history_csv(entries: &[StatsEntry], filter: &Filter) -> crate::Result<String> {
+    let mut csv = row(HISTORY_COLUMNS);
+    for (i, entry) in numbered(entries, filter) {
+        let positions = entry.game.positions()?;
+        for (m, (played, before)) in entry.game.moves.iter().zip(&positions).enumerate() {
+            let by = mover(entry, m, before.next_player());
+            csv.push_str(&row([
+                i.to_string(),
+                entry.finished_at.to_string(),
+                timestamp(entry.finished_at),
+                entry.difficulty.to_string(),
+                (m + 1).to_string(),
+                before.next_player().to_string(),
+                by.to_string(),
+                (played.square + 1).to_string(),
+                Selection::new(played.square).to_string(),
+                played.think_time.map_or(String::new(), |ms| ms.to_string()),
+                played
+                    .evaluation
+                    .map_or(String::new(), |result| result.to_string()),
+                played.annotation.clone().unwrap_or_default(),
+                played
+                    .glyph
+                    .map_or(String::new(), |glyph| glyph.to_string()),
+            ]));
+        }
+    }
+    Ok(csv)
+}
+
+/// Each game the filter lets through, numbered from 1 in the order they were recorded
+pub(crate) fn numbered<'a>(
+    entries: &'a [StatsEntry],
+    filter: &'a Filter,
+) -> impl Iterator<Item = (usize, &'a StatsEntry)> {
+    (1..)
+        .zip(entries)
+        .filter(|(_, entry)| filter.matches(entry))
+}
+
+/// How a game ended other than on the board, if it did
+pub(crate) fn ending(entry: &StatsEntry) -> Option<String> {
+    match entry.game.ending {
+        Some(Ending::Resigned(player)) => Some(format!("{player} resigned")),
+        Some(Ending::DrawAgreed) => Some("draw agreed".to_string()),
+        None => None,
+    }
+}
+
+/// Who made move `m` of a game, counting from 0, when it was `player`'s turn: `human` or
+/// `computer`
+pub(crate) fn mover(entry: &StatsEntry, m: usize, player: Player) -> &'static str {
+    // Under the pie rule the first move was made by whoever ended up on the other side
+    if (player == entry.human_player) != (m == 0 && entry.game.swapped) {
+        "human"
+    } else {
+        "computer"
+    }
+}
+
+/// A time as ISO 8601 in UTC, e.g. `2024-05-31T18:04:09Z`
+pub(crate) fn timestamp(seconds: u64) -> String {
+    let time = seconds % SECONDS_PER_DAY;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        Day::of(seconds),
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// A line of CSV, quoting the fields that need it
+fn row<T: AsRef<str>>(fields: impl IntoIterator<Item = T>) -> String {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|field| quote(field.as_ref()))
+        .collect();
+    fields.join(",") + "\n"
+}
+
+/// A CSV field, in quotes if it has anything in it that would break the row
+///
+/// ```
+/// use simple_tic_tac_toe::csv::quote;
+///
+/// assert_eq!(quote("b2"), "b2");
+/// assert_eq!(quote("a \"good\" move, really"), "\"a \"\"good\"\" move, really\"");
+/// assert_eq!(quote("x\r\n"), "\"x\r\n\"");
+/// ```
+pub fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,78 @@
+//! The squares that differ between two positions, as a patch that turns one into the other, for
+//! sending updates smaller than the whole board and checking exactly what a move changed
+
+use crate::error::{Error, MoveError};
+use crate::game::{GameState, Player};
+use serde::{Deserialize, Serialize};
+
+/// One square that differs, with what it holds in each position
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SquareChange {
+    pub square: usize,
+    pub before: Option<Player>,
+    pub after: Option<Player>,
+}
+
+impl GameState {
+    /// The squares that differ from `self` to `other`, from the top left. The side to move isn't
+    /// included since it follows from the marks
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::diff::SquareChange;
+    /// use simple_tic_tac_toe::{GameState, Player};
+    ///
+    /// let before = GameState::from_moves(Player::O, &[4]).unwrap();
+    /// let mut after = before.with_move(0);
+    /// let changes = before.diff(&after);
+    /// assert_eq!(
+    ///     changes,
+    ///     [SquareChange { square: 0, before: None, after: Some(Player::O) }]
+    /// );
+    ///
+    /// let mut patched = before.clone();
+    /// patched.apply_patch(&changes).unwrap();
+    /// assert_eq!(patched.board(), after.board());
+    ///
+    /// // The patch no longer fits once it's been applied
+    /// assert!(after.apply_patch(&changes).is_err());
+    /// ```
+    pub fn diff(&self, other: &GameState) -> Vec<SquareChange> {
+        self.board()
+            .iter()
+            .zip(other.board())
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(square, (&before, &after))| SquareChange {
+                square,
+                before,
+                after,
+            })
+            .collect()
+    }
+
+    /// Apply changes made by [`GameState::diff`], checking that each square holds what the change
+    /// expects and that the result could come up in a game. The position is left as it was if not
+    pub fn apply_patch(&mut self, changes: &[SquareChange]) -> crate::Result<()> {
+        let mut board = *self.board();
+        for change in changes {
+            let Some(&current) = board.get(change.square) else {
+                return Err(MoveError::OffBoard(change.square).into());
+            };
+            if current != change.before {
+                return Err(Error::Position(format!(
+                    "the patch expects {} on square {}, but it has {}",
+                    describe(change.before),
+                    change.square,
+                    describe(current)
+                )));
+            }
+            board[change.square] = change.after;
+        }
+        *self = GameState::from_board(self.computer_player, board)?;
+        Ok(())
+    }
+}
+
+fn describe(mark: Option<Player>) -> String {
+    mark.map_or("nothing".to_string(), |player| player.to_string())
+}
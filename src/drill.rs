@@ -0,0 +1,156 @@
+//! Drills made from the human's own mistakes: positions from their recorded games where they
+//! played a move that lost by force, to be solved again until the right move sticks
+
+use crate::analysis::find_blunder;
+use crate::error::Error;
+use crate::game::GameState;
+use crate::position::parse_position;
+use crate::stats::StatsEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A position where the human went wrong
+#[derive(Clone, Debug)]
+pub struct Drill {
+    /// The position before the mistake, in the notation of [`crate::position`]
+    pub position: String,
+    /// The losing move that was played
+    pub blunder: usize,
+}
+
+impl Drill {
+    /// The position to solve, with the computer set to the side to move like analysis does
+    pub fn state(&self) -> crate::Result<GameState> {
+        parse_position(&self.position)
+    }
+}
+
+/// Find every position in the recorded games where the human turned a game that wasn't lost into
+/// a forced loss, once per position, most recent games first
+pub fn find_drills(entries: &[StatsEntry]) -> crate::Result<Vec<Drill>> {
+    let mut seen = HashSet::new();
+    let mut drills = Vec::new();
+    for entry in entries.iter().rev() {
+        let game = &entry.game;
+        for (n, square) in game.squares().into_iter().enumerate() {
+            let state = game.position(n)?;
+            if state.next_player() == state.computer_player
+                || find_blunder(&state, square).is_none()
+            {
+                continue;
+            }
+            let position = state.to_position_string();
+            if seen.insert(position.clone()) {
+                drills.push(Drill {
+                    position,
+                    blunder: square,
+                });
+            }
+        }
+    }
+    Ok(drills)
+}
+
+/// One drill solved, as kept in the drill log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DrillAttempt {
+    /// When the drill was solved, in seconds since the Unix epoch
+    pub finished_at: u64,
+    pub position: String,
+    /// How many moves it took to find a good one, 1 if the first try was right
+    pub tries: u32,
+}
+
+impl DrillAttempt {
+    pub fn new(position: &str, tries: u32) -> Self {
+        let finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            finished_at,
+            position: position.to_string(),
+            tries,
+        }
+    }
+}
+
+/// Every drill attempt, kept as one JSON entry per line next to the stats store
+pub struct DrillLog {
+    path: PathBuf,
+}
+
+impl DrillLog {
+    /// The drill log location, e.g. `~/.local/share/simple-tic-tac-toe/drills.jsonl` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("simple-tic-tac-toe").join("drills.jsonl"))
+    }
+
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Read every attempt, oldest first
+    pub fn attempts(&self) -> crate::Result<Vec<DrillAttempt>> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io("read", &self.path)(e)),
+        };
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    let message = format!("bad entry on line {} of {}", i + 1, self.path.display());
+                    Error::parse_with(message, e)
+                })
+            })
+            .collect()
+    }
+
+    /// Add an attempt to the end of the log, creating it if needed
+    pub fn append(&self, attempt: &DrillAttempt) -> crate::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(Error::io("open", &self.path))?;
+        writeln!(file, "{}", serde_json::to_string(attempt)?)
+            .map_err(Error::io("write", &self.path))
+    }
+}
+
+/// Put the drills most in need of practice first: ones never practiced, then ones whose last
+/// attempt took more than one try, then the rest, each least recently practiced first
+pub fn schedule(drills: &mut [Drill], attempts: &[DrillAttempt]) {
+    drills.sort_by_key(|drill| {
+        let last = attempts
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, attempt)| attempt.position == drill.position);
+        match last {
+            None => (0, 0),
+            Some((index, attempt)) if attempt.tries > 1 => (1, index),
+            Some((index, _)) => (2, index),
+        }
+    });
+}
+
+/// The share of attempts solved on the first try, or None with no attempts
+pub fn first_try_rate(attempts: &[DrillAttempt]) -> Option<f64> {
+    if attempts.is_empty() {
+        return None;
+    }
+    let first_try = attempts.iter().filter(|a| a.tries == 1).count();
+    Some(first_try as f64 / attempts.len() as f64)
+}
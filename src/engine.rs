@@ -0,0 +1,121 @@
+//! An engine that several games can share, for front-ends that run many games at once
+//!
+//! An [`Engine`] owns the resources that are worth sharing between games: a transposition table,
+//! the opening book and a random number generator. It is `Send` and `Sync`, so it can be put in an
+//! `Arc` and used from any number of threads. Each game is an [`EngineSession`] that borrows the
+//! engine and owns its own position, so sessions never see each other's moves.
+//!
+//! Concurrency guarantees:
+//! - The transposition table sits behind a read-write lock. Searches read it freely, and each
+//!   search only takes the write lock once, at the end, to add what it found. Entries are exact
+//!   results, so it doesn't matter which session's search wrote them first.
+//! - The random number generator sits behind a mutex that is only held while picking among the
+//!   already evaluated moves, never during a search.
+//! - Results never depend on what other sessions are doing. Sharing the table only saves work.
+
+use crate::ai::{Decision, SearchStats, TranspositionTable};
+use crate::error::MoveError;
+use crate::game::{GameState, Player};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Resources shared by every game played through it
+#[derive(Debug)]
+pub struct Engine {
+    transpositions: Arc<TranspositionTable>,
+    rng: Mutex<StdRng>,
+    /// Look moves up in the positions solved at compile time instead of searching
+    use_book: bool,
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Engine>();
+};
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::from_rng(StdRng::from_entropy())
+    }
+
+    /// Create an engine whose move choices are reproducible for the same games
+    pub fn with_seed(seed: u64) -> Self {
+        Self::from_rng(StdRng::seed_from_u64(seed))
+    }
+
+    fn from_rng(rng: StdRng) -> Self {
+        Self {
+            transpositions: Arc::new(TranspositionTable::new()),
+            rng: Mutex::new(rng),
+            use_book: true,
+        }
+    }
+
+    /// Search every position instead of using the opening book, sharing results through the
+    /// transposition table
+    pub fn without_book(mut self) -> Self {
+        self.use_book = false;
+        self
+    }
+
+    /// The transposition table shared by this engine's searches
+    pub fn transpositions(&self) -> &TranspositionTable {
+        &self.transpositions
+    }
+
+    /// Start a new game against the computer playing the given side
+    pub fn session(&self, computer_player: Player) -> EngineSession<'_> {
+        EngineSession {
+            engine: self,
+            game: GameState::new(computer_player),
+        }
+    }
+}
+
+/// One game played through a shared [`Engine`]
+#[derive(Clone)]
+pub struct EngineSession<'a> {
+    engine: &'a Engine,
+    game: GameState,
+}
+
+impl EngineSession<'_> {
+    /// The current position
+    pub fn state(&self) -> &GameState {
+        &self.game
+    }
+
+    /// Play a move for whichever side is next, checking it's legal
+    pub fn play_move(&mut self, square: usize) -> Result<(), MoveError> {
+        self.game.try_move(square)
+    }
+
+    /// Decide the computer's move in the current position and play it
+    pub fn computer_move(&mut self) -> Decision {
+        let start = Instant::now();
+        let mut stats = SearchStats::with_shared_table(self.engine.transpositions.clone());
+        let candidates = if self.engine.use_book {
+            self.game.look_up_computer_moves(&mut stats)
+        } else {
+            self.game.evaluate_computer_moves(&mut stats)
+        };
+        stats.publish();
+        let elapsed_us = start.elapsed().as_micros() as u64;
+
+        let mut rng = self.engine.rng.lock().unwrap();
+        let decision = self
+            .game
+            .choose_move(candidates, &stats, elapsed_us, 0.0, &mut *rng);
+        drop(rng);
+        self.game.apply_move(decision.chosen);
+        decision
+    }
+}
@@ -0,0 +1,175 @@
+//! The errors the library can return, so callers can match on what went wrong
+
+use crate::game::Selection;
+use crate::handshake::Capability;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Any underlying error kept as the source of a [`Error::Parse`], [`Error::Matrix`] or [`Error::Image`]
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// A move couldn't be played
+    #[error(transparent)]
+    Move(#[from] MoveError),
+    /// Text or a file couldn't be read as the value it should hold
+    #[error("{message}")]
+    Parse {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+    /// Reading or writing a file failed
+    #[error("failed to {action} {}", path.display())]
+    Io {
+        /// What was being done, e.g. "read" or "create"
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Talking to another machine over the network failed
+    #[error("failed to {action} {address}")]
+    Network {
+        /// What was being done, e.g. "connect to"
+        action: &'static str,
+        address: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// A request over the JSON protocol couldn't be carried out
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    /// A board was set up that can't come up in a game
+    #[error("{0}")]
+    Position(String),
+    /// A config value is outside what it allows
+    #[error("{0}")]
+    Config(String),
+    /// A recorded game doesn't have as many moves as asked for
+    #[error("the game only has {moves} moves")]
+    NoSuchPosition { requested: usize, moves: usize },
+    /// The game is still being played but a result was needed
+    #[error("the game isn't finished")]
+    GameNotFinished,
+    /// Serializing a value to JSON failed
+    #[error("failed to serialize")]
+    Serialize(#[from] serde_json::Error),
+    /// A request to a Matrix homeserver failed
+    #[error("failed to {action}")]
+    Matrix {
+        /// What was being done, e.g. "log in to the homeserver"
+        action: &'static str,
+        #[source]
+        source: BoxError,
+    },
+    /// An image couldn't be rendered or encoded
+    #[error("failed to render the image")]
+    Image(#[source] BoxError),
+}
+
+impl Error {
+    /// Wrap a network error with what was being done with which address
+    #[cfg(feature = "net")]
+    pub(crate) fn network(
+        action: &'static str,
+        address: &str,
+    ) -> impl FnOnce(std::io::Error) -> Self {
+        let address = address.to_string();
+        move |source| Self::Network {
+            action,
+            address,
+            source,
+        }
+    }
+
+    /// A parse error with nothing underneath it
+    pub(crate) fn parse(message: impl Into<String>) -> Self {
+        Self::Parse {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// A parse error caused by another error
+    pub(crate) fn parse_with(message: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        Self::Parse {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Wrap an I/O error with what was being done to which file
+    pub(crate) fn io(
+        action: &'static str,
+        path: impl Into<PathBuf>,
+    ) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.into();
+        move |source| Self::Io {
+            action,
+            path,
+            source,
+        }
+    }
+}
+
+/// Why a move isn't legal, with the square it was tried on
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, thiserror::Error)]
+#[non_exhaustive]
+pub enum MoveError {
+    #[error("{0} is not a square on the board")]
+    OffBoard(usize),
+    #[error("{} is already taken", Selection::new(*.0))]
+    Taken(usize),
+    #[error("the game is already over before the move at {}", Selection::new(*.0))]
+    GameOver(usize),
+    /// On boards other than 3x3, where squares don't have names
+    #[error("row {}, column {} is already taken", .row + 1, .column + 1)]
+    Occupied { row: usize, column: usize },
+    /// Under gravity rules, a square with an empty square below it
+    #[error("row {}, column {} has nothing under it", .row + 1, .column + 1)]
+    Floating { row: usize, column: usize },
+    #[error("there is no column {}", .0 + 1)]
+    NoColumn(usize),
+    #[error("column {} is full", .0 + 1)]
+    ColumnFull(usize),
+    #[error("the game is already over")]
+    Finished,
+    /// In numerical tic-tac-toe, a digit that isn't the player's or was already played
+    #[error("{0} isn't one of the digits left to play")]
+    NoSuchDigit(u8),
+    /// In dots and boxes, a line between dots that aren't next to each other
+    #[error("there is no line {0} on the board")]
+    NoSuchLine(usize),
+    #[error("line {0} is already drawn")]
+    LineDrawn(usize),
+    #[error("there is no heap {}", .0 + 1)]
+    NoSuchHeap(usize),
+    #[error("heap {} doesn't have {count} counters to take", .heap + 1)]
+    NotInHeap { heap: usize, count: u32 },
+}
+
+/// Why a protocol request was turned down
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ProtocolError {
+    #[error("invalid request: {0}")]
+    InvalidRequest(serde_json::Error),
+    #[error("no game in progress, start a new game")]
+    NoGame,
+    #[error("the game is over, start a new game")]
+    GameOver,
+    #[error("version {version} of the protocol isn't supported, only {oldest} to {newest}")]
+    UnsupportedVersion {
+        version: u32,
+        oldest: u32,
+        newest: u32,
+    },
+    /// The request needs a capability that wasn't agreed on in the handshake
+    #[error("`{0}` wasn't agreed on in the handshake")]
+    NotAgreed(Capability),
+}
@@ -0,0 +1,107 @@
+//! An opening explorer: how often each opening line was played and how those games ended, like
+//! the opening explorers for chess
+
+use crate::game::{GameStatus, Player};
+use crate::record::GameRecord;
+use rand::Rng;
+use std::collections::BTreeMap;
+
+/// How the games through one position ended
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Results {
+    pub x_wins: u32,
+    pub o_wins: u32,
+    pub ties: u32,
+}
+
+impl Results {
+    pub fn games(&self) -> u32 {
+        self.x_wins + self.o_wins + self.ties
+    }
+
+    pub(crate) fn add(&mut self, status: GameStatus) {
+        match status {
+            GameStatus::Won(Player::X) => self.x_wins += 1,
+            GameStatus::Won(Player::O) => self.o_wins += 1,
+            GameStatus::Tie | GameStatus::InProgress => self.ties += 1,
+        }
+    }
+
+    /// Percentages of X wins, ties and O wins, e.g. `X 40% / tie 50% / O 10%`
+    pub fn summary(&self) -> String {
+        let percent = |n: u32| (100.0 * n as f64 / self.games().max(1) as f64).round();
+        format!(
+            "X {}% / tie {}% / O {}%",
+            percent(self.x_wins),
+            percent(self.ties),
+            percent(self.o_wins)
+        )
+    }
+}
+
+/// A position in the opening tree, with the moves played from it
+#[derive(Clone, Debug, Default)]
+pub struct OpeningNode {
+    pub results: Results,
+    /// The next move of the games through this position, by square
+    pub children: BTreeMap<usize, OpeningNode>,
+}
+
+impl OpeningNode {
+    /// The moves played from here, most played first
+    pub fn moves_by_popularity(&self) -> Vec<(usize, &OpeningNode)> {
+        let mut moves: Vec<(usize, &OpeningNode)> = self
+            .children
+            .iter()
+            .map(|(&square, node)| (square, node))
+            .collect();
+        moves.sort_by_key(|(_, node)| std::cmp::Reverse(node.results.games()));
+        moves
+    }
+
+    /// The node reached by playing `moves` from here, if any game went that way
+    pub fn get(&self, moves: &[usize]) -> Option<&OpeningNode> {
+        moves
+            .iter()
+            .try_fold(self, |node, square| node.children.get(square))
+    }
+}
+
+/// Every finished game's first moves, merged into a tree from the empty board
+#[derive(Clone, Debug)]
+pub struct OpeningTree {
+    pub root: OpeningNode,
+    /// How many moves into each game the tree goes
+    pub depth: usize,
+}
+
+impl OpeningTree {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            root: OpeningNode::default(),
+            depth,
+        }
+    }
+
+    /// Add the first moves of a finished game. Games started from a set-up board or not finished
+    /// are skipped, since they don't say anything about the opening. Returns whether it was added
+    pub fn add(&mut self, game: &GameRecord) -> crate::Result<bool> {
+        let status = game.status()?;
+        if game.start.is_some() || status == GameStatus::InProgress {
+            return Ok(false);
+        }
+        let mut node = &mut self.root;
+        node.results.add(status);
+        for square in game.squares().into_iter().take(self.depth) {
+            node = node.children.entry(square).or_default();
+            node.results.add(status);
+        }
+        Ok(true)
+    }
+}
+
+/// Play a game of the computer against itself, each side blundering with the given chance, so
+/// the explorer has more varied games to show than perfect play's ties
+pub fn self_play_game(blunder_chance: f64, rng: &mut impl Rng) -> GameRecord {
+    crate::tournament::play_game(blunder_chance, blunder_chance, rng)
+}
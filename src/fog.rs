@@ -0,0 +1,262 @@
+//! Fog of war tic-tac-toe, where each player only sees the squares next to their own marks
+//!
+//! Both players see their own marks and every square touching one of them, diagonals included.
+//! A move onto a square that looks free but is secretly taken is wasted: the turn passes and the
+//! square is revealed to the player who tried it. Since a wasted turn breaks the usual rule that
+//! the side to move follows from the marks, the fog keeps its own board and side to move rather
+//! than a [`GameState`](crate::GameState).
+
+use crate::error::MoveError;
+use crate::game::{GameStatus, Player, Selection, LINES};
+use crate::games::Game;
+use crate::render::{render_grid, Pieces};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// How many guesses at the hidden marks the computer averages over for each decision
+const SAMPLES: usize = 64;
+
+/// A square as one player sees it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Seen {
+    Empty,
+    Mark(Player),
+    Hidden,
+}
+
+/// What happened to an attempted move
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Attempt {
+    Placed,
+    /// The square was secretly taken, so the turn was wasted
+    Blocked,
+}
+
+/// A game in the fog, with the true board and what each player has found out
+#[derive(Clone, Debug)]
+pub struct FogGame {
+    board: [Option<Player>; 9],
+    pub next_player: Player,
+    pub computer_player: Player,
+    /// Squares each player found taken by running into them, X first
+    revealed: [[bool; 9]; 2],
+}
+
+impl FogGame {
+    pub fn new(computer_player: Player) -> Self {
+        Self {
+            board: [None; 9],
+            next_player: Player::X,
+            computer_player,
+            revealed: [[false; 9]; 2],
+        }
+    }
+
+    /// The true board, for showing once the game is over
+    pub fn board(&self) -> &[Option<Player>; 9] {
+        &self.board
+    }
+
+    pub fn status(&self) -> GameStatus {
+        match winner(&self.board) {
+            Some(player) => GameStatus::Won(player),
+            None if self.board.iter().all(Option::is_some) => GameStatus::Tie,
+            None => GameStatus::InProgress,
+        }
+    }
+
+    /// The board as `player` sees it
+    pub fn view(&self, player: Player) -> [Seen; 9] {
+        let near_own = near_marks(&self.board, player);
+        std::array::from_fn(|square| {
+            if near_own[square] || self.revealed[index(player)][square] {
+                self.board[square].map_or(Seen::Empty, Seen::Mark)
+            } else {
+                Seen::Hidden
+            }
+        })
+    }
+
+    /// Try to play on a square for the side to move. Squares the player can see are taken are
+    /// rejected, while hidden ones that turn out to be taken waste the turn
+    pub fn attempt(&mut self, square: usize) -> Result<Attempt, MoveError> {
+        if square >= 9 {
+            return Err(MoveError::OffBoard(square));
+        }
+        if self.status() != GameStatus::InProgress {
+            return Err(MoveError::GameOver(square));
+        }
+        let player = self.next_player;
+        if matches!(self.view(player)[square], Seen::Mark(_)) {
+            return Err(MoveError::Taken(square));
+        }
+        self.next_player = player.opponent();
+        if self.board[square].is_some() {
+            self.revealed[index(player)][square] = true;
+            return Ok(Attempt::Blocked);
+        }
+        self.board[square] = Some(player);
+        Ok(Attempt::Placed)
+    }
+
+    /// Squares the side to move may try, i.e. every square it doesn't see taken
+    pub fn candidate_squares(&self) -> Vec<usize> {
+        let view = self.view(self.next_player);
+        (0..9)
+            .filter(|&s| !matches!(view[s], Seen::Mark(_)))
+            .collect()
+    }
+
+    /// Choose a move for the side to move from what it can see
+    ///
+    /// The computer guesses where the opponent's hidden marks are many times over, consistent with
+    /// how many the opponent has, and plays the square that does best on average when each guessed
+    /// board is played out with perfect play. It's a heuristic: the playouts ignore the fog.
+    pub fn decide_computer_move(&self, rng: &mut impl Rng) -> usize {
+        let player = self.next_player;
+        let view = self.view(player);
+        let hidden: Vec<usize> = (0..9).filter(|&s| view[s] == Seen::Hidden).collect();
+        let visible_opponent = view
+            .iter()
+            .filter(|&&s| s == Seen::Mark(player.opponent()))
+            .count();
+        let hidden_opponent = self
+            .board
+            .iter()
+            .filter(|&&s| s == Some(player.opponent()))
+            .count()
+            - visible_opponent;
+
+        let candidates = self.candidate_squares();
+        let mut totals = vec![0i32; candidates.len()];
+        let mut memo = HashMap::new();
+        for _ in 0..SAMPLES {
+            let mut guess = [None; 9];
+            for square in 0..9 {
+                if let Seen::Mark(mark) = view[square] {
+                    guess[square] = Some(mark);
+                }
+            }
+            for &square in hidden.choose_multiple(rng, hidden_opponent) {
+                guess[square] = Some(player.opponent());
+            }
+            for (total, &square) in totals.iter_mut().zip(&candidates) {
+                *total += if guess[square].is_some() {
+                    // A wasted turn: the opponent moves next on the same board
+                    -solve(&guess, player.opponent(), &mut memo)
+                } else {
+                    let mut after = guess;
+                    after[square] = Some(player);
+                    -solve(&after, player.opponent(), &mut memo)
+                };
+            }
+        }
+
+        let best = totals.iter().copied().max().unwrap_or(0);
+        let best_squares: Vec<usize> = candidates
+            .iter()
+            .zip(&totals)
+            .filter(|&(_, &total)| total == best)
+            .map(|(&square, _)| square)
+            .collect();
+        *best_squares
+            .choose(rng)
+            .expect("a game in progress has a square to try")
+    }
+}
+
+/// Draw a player's view of the board, with `?` on the squares they can't see
+pub fn render_view(view: &[Seen; 9], pieces: &Pieces) -> String {
+    let cells: Vec<&str> = view
+        .iter()
+        .map(|&seen| match seen {
+            Seen::Empty => pieces.get(None),
+            Seen::Mark(player) => pieces.get(Some(player)),
+            Seen::Hidden => "?",
+        })
+        .collect();
+    render_grid(&cells, 3, pieces.cell_width())
+}
+
+fn index(player: Player) -> usize {
+    match player {
+        Player::X => 0,
+        Player::O => 1,
+    }
+}
+
+/// Squares holding the player's marks or touching one, diagonals included
+fn near_marks(board: &[Option<Player>; 9], player: Player) -> [bool; 9] {
+    let own: Vec<usize> = (0..9).filter(|&s| board[s] == Some(player)).collect();
+    std::array::from_fn(|square| {
+        own.iter().any(|&mark| {
+            (square / 3).abs_diff(mark / 3) <= 1 && (square % 3).abs_diff(mark % 3) <= 1
+        })
+    })
+}
+
+fn winner(board: &[Option<Player>; 9]) -> Option<Player> {
+    LINES.iter().find_map(|&[a, b, c]| {
+        board[a].filter(|&player| board[b] == Some(player) && board[c] == Some(player))
+    })
+}
+
+/// The result with perfect play and a clear board for the side to move: 1 for a win, 0 for a tie
+/// and -1 for a loss
+fn solve(
+    board: &[Option<Player>; 9],
+    to_move: Player,
+    memo: &mut HashMap<([Option<Player>; 9], Player), i32>,
+) -> i32 {
+    if let Some(player) = winner(board) {
+        return if player == to_move { 1 } else { -1 };
+    }
+    if board.iter().all(Option::is_some) {
+        return 0;
+    }
+    if let Some(&result) = memo.get(&(*board, to_move)) {
+        return result;
+    }
+    let result = (0..9)
+        .filter(|&square| board[square].is_none())
+        .map(|square| {
+            let mut after = *board;
+            after[square] = Some(to_move);
+            -solve(&after, to_move.opponent(), memo)
+        })
+        .max()
+        .expect("a board that isn't full has an empty square");
+    memo.insert((*board, to_move), result);
+    result
+}
+
+/// The rules without the fog: a search through this sees the whole board, unlike
+/// [`FogGame::decide_computer_move`]
+impl Game for FogGame {
+    type Move = usize;
+
+    fn next_player(&self) -> Player {
+        self.next_player
+    }
+
+    fn legal_moves(&self) -> Vec<usize> {
+        if self.status() == GameStatus::InProgress {
+            self.candidate_squares()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn play(&mut self, square: usize) -> Result<(), MoveError> {
+        self.attempt(square).map(|_| ())
+    }
+
+    fn status(&self) -> GameStatus {
+        FogGame::status(self)
+    }
+
+    fn describe(&self, square: usize) -> String {
+        Selection::new(square).to_string()
+    }
+}
@@ -0,0 +1,604 @@
+use crate::error::{Error, MoveError};
+use crate::games::Game;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Player {
+    X,
+    O,
+}
+
+impl Player {
+    /// Get the opponent for the given player
+    pub fn opponent(self) -> Self {
+        match self {
+            Player::X => Player::O,
+            Player::O => Player::X,
+        }
+    }
+}
+
+impl Display for Player {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Player::X => 'X',
+                Player::O => 'O',
+            }
+        )
+    }
+}
+
+impl FromStr for Player {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "X" | "x" => Ok(Player::X),
+            "O" | "o" => Ok(Player::O),
+            _ => Err(Error::parse(format!(
+                "`{s}` is not a player, expected X or O"
+            ))),
+        }
+    }
+}
+
+/// Whether a game is still being played, and how it ended if not
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(tag = "status", content = "winner", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum GameStatus {
+    InProgress,
+    Won(Player),
+    Tie,
+}
+
+// Hack to have inquire display a nice name for the board index representing the square on the board
+#[derive(Copy, Clone, Debug)]
+pub struct Selection {
+    pub square: usize,
+}
+
+impl Selection {
+    pub const SQUARES: [&'static str; 9] = [
+        "Top Left",
+        "Top Middle",
+        "Top Right",
+        "Middle Left",
+        "Middle",
+        "Middle Right",
+        "Bottom Left",
+        "Bottom Middle",
+        "Bottom Right",
+    ];
+
+    pub fn new(square: usize) -> Self {
+        Self { square }
+    }
+}
+
+impl Display for Selection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Self::SQUARES[self.square])
+    }
+}
+
+/// Return the winner for a given line or None if there is no winner
+fn get_line_winner(a: Option<Player>, b: Option<Player>, c: Option<Player>) -> Option<Player> {
+    if a.is_some() && a == b && b == c {
+        a
+    } else {
+        None
+    }
+}
+
+/// Every row, column and diagonal on the board
+pub const LINES: [[usize; 3]; 8] = [
+    [0, 1, 2],
+    [3, 4, 5],
+    [6, 7, 8],
+    [0, 3, 6],
+    [1, 4, 7],
+    [2, 5, 8],
+    [0, 4, 8],
+    [2, 4, 6],
+];
+
+/// Indices into `LINES` of the lines through each square, so a move only needs its own lines
+/// checked
+const LINES_THROUGH: [&[usize]; 9] = [
+    &[0, 3, 6],
+    &[0, 4],
+    &[0, 5, 7],
+    &[1, 3],
+    &[1, 4, 6, 7],
+    &[1, 5],
+    &[2, 3, 7],
+    &[2, 4],
+    &[2, 5, 6],
+];
+
+/// Zobrist keys for each player on each square, then for O being the side to move. They come
+/// from a fixed seed so position ids stay the same between runs and releases
+const ZOBRIST_KEYS: [u64; 19] = zobrist_keys();
+
+const fn zobrist_keys() -> [u64; 19] {
+    // splitmix64
+    let mut keys = [0; 19];
+    let mut seed: u64 = 0x7469_6374_6163_746f;
+    let mut i = 0;
+    while i < keys.len() {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+const O_TO_MOVE_KEY: u64 = ZOBRIST_KEYS[18];
+
+fn piece_key(square: usize, player: Player) -> u64 {
+    match player {
+        Player::X => ZOBRIST_KEYS[square],
+        Player::O => ZOBRIST_KEYS[9 + square],
+    }
+}
+
+/// Iterator over the open squares of a position, in order from the top left
+#[derive(Copy, Clone, Debug)]
+pub struct LegalMoves {
+    /// Bit `i` is set if square `i` is open and not yet returned
+    empty: u16,
+}
+
+impl LegalMoves {
+    /// Whether there are no more open squares
+    pub fn is_empty(&self) -> bool {
+        self.empty == 0
+    }
+}
+
+impl Iterator for LegalMoves {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.empty == 0 {
+            return None;
+        }
+        let square = self.empty.trailing_zeros() as usize;
+        self.empty &= self.empty - 1;
+        Some(square)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.empty.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for LegalMoves {}
+
+/// Every position that can come up in a game, see [`GameState::reachable_positions`]
+#[derive(Clone)]
+pub struct ReachablePositions {
+    /// The positions with the current number of marks still to return
+    layer: std::vec::IntoIter<GameState>,
+    /// The positions with one more mark found so far
+    next: Vec<GameState>,
+    /// Board codes of the positions in `next`, which can be reached by more than one move order
+    seen: HashSet<u32>,
+}
+
+impl Iterator for ReachablePositions {
+    type Item = GameState;
+
+    fn next(&mut self) -> Option<GameState> {
+        loop {
+            if let Some(state) = self.layer.next() {
+                if state.winner.is_none() {
+                    for square in state.legal_moves() {
+                        let after = state.with_move(square);
+                        if self.seen.insert(after.board_code()) {
+                            self.next.push(after);
+                        }
+                    }
+                }
+                return Some(state);
+            }
+            if self.next.is_empty() {
+                return None;
+            }
+            // Positions in different layers have different numbers of marks, so they never match
+            self.seen.clear();
+            self.layer = std::mem::take(&mut self.next).into_iter();
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Undo {
+    square: usize,
+    winner: Option<Player>,
+}
+
+/// A game of tic-tac-toe. The board, the side to move and the winner only change through moves,
+/// which keeps the position's hash up to date with them
+#[derive(Clone)]
+pub struct GameState {
+    board: [Option<Player>; 9],
+    next_player: Player,
+    winner: Option<Player>,
+    pub computer_player: Player,
+    /// Zobrist hash of the board and side to move, kept up to date by each move
+    hash: u64,
+}
+
+impl GameState {
+    pub fn new(computer_player: Player) -> Self {
+        Self {
+            board: [None; 9],
+            next_player: Player::X,
+            winner: None,
+            computer_player,
+            hash: 0,
+        }
+    }
+
+    /// Set up a position by playing the given squares in order from an empty board
+    pub fn from_moves(computer_player: Player, moves: &[usize]) -> crate::Result<Self> {
+        let mut state = Self::new(computer_player);
+        for &square in moves {
+            state.try_move(square).map_err(Error::Move)?;
+        }
+        Ok(state)
+    }
+
+    /// Set up a position from a board, checking that it could come up in a game. X always moves
+    /// first, so the side to move follows from how many marks each side has
+    pub fn from_board(computer_player: Player, board: [Option<Player>; 9]) -> crate::Result<Self> {
+        let count = |player| board.iter().filter(|&&s| s == Some(player)).count();
+        let (x, o) = (count(Player::X), count(Player::O));
+        let next_player = if x == o {
+            Player::X
+        } else if x == o + 1 {
+            Player::O
+        } else {
+            return Err(Error::Position(format!(
+                "X has {x} marks and O has {o}, but X should have as many as O or one more"
+            )));
+        };
+
+        let has_line = |player| {
+            LINES
+                .iter()
+                .any(|line| line.iter().all(|&i| board[i] == Some(player)))
+        };
+        let winner = match (has_line(Player::X), has_line(Player::O)) {
+            (true, true) => return Err(Error::Position("both sides have a line".to_string())),
+            (true, false) => Some(Player::X),
+            (false, true) => Some(Player::O),
+            (false, false) => None,
+        };
+        if winner == Some(next_player) {
+            return Err(Error::Position(format!(
+                "{next_player} has a line, but the other side moved after it"
+            )));
+        }
+
+        let mut state = Self {
+            board,
+            next_player,
+            winner,
+            computer_player,
+            hash: 0,
+        };
+        state.hash = state.full_hash();
+        Ok(state)
+    }
+
+    /// Apply a move after checking that it's legal
+    pub fn try_move(&mut self, square: usize) -> Result<(), MoveError> {
+        if square >= 9 {
+            return Err(MoveError::OffBoard(square));
+        }
+        if self.winner.is_some() {
+            return Err(MoveError::GameOver(square));
+        }
+        if self.board[square].is_some() {
+            return Err(MoveError::Taken(square));
+        }
+        self.apply_move(square);
+        Ok(())
+    }
+
+    /// Apply a move to the gamestate
+    pub fn apply_move(&mut self, square: usize) {
+        self.board[square] = Some(self.next_player);
+        self.hash ^= piece_key(square, self.next_player) ^ O_TO_MOVE_KEY;
+        self.next_player = self.next_player.opponent();
+        self.winner = self.winner.or_else(|| self.line_winner_through(square));
+    }
+
+    /// Apply a move in place, returning what's needed to take it back with `unmake_move`
+    pub fn make_move(&mut self, square: usize) -> Undo {
+        let undo = Undo {
+            square,
+            winner: self.winner,
+        };
+        self.apply_move(square);
+        undo
+    }
+
+    /// Take back the last move made with `make_move`, restoring the position before it
+    pub fn unmake_move(&mut self, undo: Undo) {
+        self.next_player = self.next_player.opponent();
+        self.board[undo.square] = None;
+        self.hash ^= piece_key(undo.square, self.next_player) ^ O_TO_MOVE_KEY;
+        self.winner = undo.winner;
+    }
+
+    /// Swap sides under the pie rule, so the second player takes over the first player's mark.
+    /// The board and the turn are unchanged, only which side the computer plays
+    pub fn swap_sides(&mut self) {
+        self.computer_player = self.computer_player.opponent();
+        // The hash is of the board and the side to move, which the swap leaves alone, and results
+        // keyed by it are for the side to move, so they hold whichever side the computer plays
+    }
+
+    /// Whether the pie rule allows swapping, which is only right after the first move
+    pub fn can_swap(&self) -> bool {
+        self.board.iter().flatten().count() == 1
+    }
+
+    /// Get a new `GameState` with the given move applied
+    pub fn with_move(&self, square: usize) -> Self {
+        let mut new_state = self.clone();
+        new_state.apply_move(square);
+        new_state
+    }
+
+    /// Get a stable 64-bit id for the board and side to move, the same whatever order the moves
+    /// were played in
+    pub fn position_id(&self) -> u64 {
+        self.hash
+    }
+
+    /// The same position with the marks replaced, for boards that play the same, like turned ones
+    pub(crate) fn with_board(&self, board: [Option<Player>; 9]) -> Self {
+        let mut state = Self {
+            board,
+            ..self.clone()
+        };
+        state.hash = state.full_hash();
+        state
+    }
+
+    /// Compute the Zobrist hash from scratch, for positions set up without moves
+    fn full_hash(&self) -> u64 {
+        let pieces = self
+            .board
+            .iter()
+            .enumerate()
+            .filter_map(|(square, piece)| piece.map(|player| piece_key(square, player)))
+            .fold(0, |hash, key| hash ^ key);
+        match self.next_player {
+            Player::X => pieces,
+            Player::O => pieces ^ O_TO_MOVE_KEY,
+        }
+    }
+
+    /// The mark on each square, from the top left, row by row
+    pub fn board(&self) -> &[Option<Player>; 9] {
+        &self.board
+    }
+
+    pub fn next_player(&self) -> Player {
+        self.next_player
+    }
+
+    /// The side with a line, if either has one
+    pub fn winner(&self) -> Option<Player> {
+        self.winner
+    }
+
+    /// Every position that can come up in a game from the empty board, finished ones included,
+    /// each once: all 5478 of them, with the computer as O. Positions come in order of how many
+    /// marks are on the board and are worked out as they're needed, so only two layers are held
+    /// at a time
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::GameState;
+    ///
+    /// assert_eq!(GameState::reachable_positions().count(), 5478);
+    /// ```
+    pub fn reachable_positions() -> ReachablePositions {
+        ReachablePositions {
+            layer: vec![GameState::new(Player::O)].into_iter(),
+            next: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Encode the board as a base-3 number with 1 for X and 2 for O, the first square lowest
+    pub(crate) fn board_code(&self) -> u32 {
+        self.board.iter().rev().fold(0, |code, square| {
+            code * 3
+                + match square {
+                    None => 0,
+                    Some(Player::X) => 1,
+                    Some(Player::O) => 2,
+                }
+        })
+    }
+
+    /// Get a list of open squares, i.e. squares that are possible options for moves
+    pub fn open_squares(&self) -> Vec<Selection> {
+        self.legal_moves().map(Selection::new).collect()
+    }
+
+    /// Iterate over the open squares without allocating, for use in the search
+    pub fn legal_moves(&self) -> LegalMoves {
+        let empty = self
+            .board
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.is_none())
+            .fold(0, |mask, (i, _)| mask | 1 << i);
+        LegalMoves { empty }
+    }
+
+    /// Get the squares where the given player could complete a line on their next move
+    pub fn threats(&self, player: Player) -> Vec<usize> {
+        let mut squares: Vec<usize> = LINES
+            .iter()
+            .filter_map(|line| {
+                let owned = line
+                    .iter()
+                    .filter(|&&i| self.board[i] == Some(player))
+                    .count();
+                let open = line.iter().find(|&&i| self.board[i].is_none());
+                if owned == 2 {
+                    open.copied()
+                } else {
+                    None
+                }
+            })
+            .collect();
+        squares.sort_unstable();
+        squares.dedup();
+        squares
+    }
+
+    /// Get whether the game is still going, won, or tied
+    pub fn status(&self) -> GameStatus {
+        match self.winner {
+            Some(winner) => GameStatus::Won(winner),
+            None if self.board.iter().all(|s| s.is_some()) => GameStatus::Tie,
+            None => GameStatus::InProgress,
+        }
+    }
+
+    /// Get the three squares of the completed line, if the game has been won
+    pub fn winning_line(&self) -> Option<[usize; 3]> {
+        LINES.iter().copied().find(|&[a, b, c]| {
+            get_line_winner(self.board[a], self.board[b], self.board[c]).is_some()
+        })
+    }
+
+    /// Get the winner of a line through the given square, if any
+    fn line_winner_through(&self, square: usize) -> Option<Player> {
+        LINES_THROUGH[square].iter().find_map(|&line| {
+            let [a, b, c] = LINES[line];
+            get_line_winner(self.board[a], self.board[b], self.board[c])
+        })
+    }
+
+    /// Return the winner or None if there is no winner, scanning the whole board
+    #[cfg(test)]
+    fn check_winner(&self) -> Option<Player> {
+        for i in 0..3 {
+            // Check rows
+            if let Some(winner) = get_line_winner(
+                self.board[i * 3],
+                self.board[i * 3 + 1],
+                self.board[i * 3 + 2],
+            ) {
+                return Some(winner);
+            }
+
+            // Check columns
+            if let Some(winner) =
+                get_line_winner(self.board[i], self.board[i + 3], self.board[i + 6])
+            {
+                return Some(winner);
+            }
+        }
+
+        // Check diagonals
+        if let Some(winner) = get_line_winner(self.board[0], self.board[4], self.board[8]) {
+            return Some(winner);
+        }
+
+        if let Some(winner) = get_line_winner(self.board[2], self.board[4], self.board[6]) {
+            return Some(winner);
+        }
+
+        None
+    }
+}
+
+impl Game for GameState {
+    type Move = usize;
+
+    fn next_player(&self) -> Player {
+        self.next_player
+    }
+
+    fn legal_moves(&self) -> Vec<usize> {
+        GameState::legal_moves(self).collect()
+    }
+
+    fn play(&mut self, square: usize) -> Result<(), MoveError> {
+        self.try_move(square)
+    }
+
+    fn status(&self) -> GameStatus {
+        GameState::status(self)
+    }
+
+    fn describe(&self, square: usize) -> String {
+        Selection::new(square).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incremental_winner_matches_full_scan() {
+        for state in GameState::reachable_positions() {
+            if state.winner.is_some() {
+                continue;
+            }
+            for square in state.legal_moves() {
+                let after = state.with_move(square);
+                assert_eq!(
+                    after.winner,
+                    after.check_winner(),
+                    "{square} played on {:?}",
+                    state.board
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        for state in GameState::reachable_positions() {
+            assert_eq!(state.position_id(), state.full_hash(), "{:?}", state.board);
+            for square in state.legal_moves() {
+                let mut after = state.clone();
+                let undo = after.make_move(square);
+                assert_eq!(after.position_id(), after.full_hash());
+                after.unmake_move(undo);
+                assert_eq!(after.position_id(), state.position_id());
+            }
+        }
+        // Set up from a board or reached in another order, the position has the same id
+        let played = GameState::from_moves(Player::O, &[4, 0, 8]).unwrap();
+        let set_up = GameState::from_board(Player::O, played.board).unwrap();
+        assert_eq!(set_up.position_id(), played.position_id());
+        let transposed = GameState::from_moves(Player::O, &[8, 0, 4]).unwrap();
+        assert_eq!(transposed.position_id(), played.position_id());
+    }
+}
@@ -0,0 +1,102 @@
+//! The [`Game`] trait that tic-tac-toe, its variants and the other games all implement, and a
+//! search written once against it
+//!
+//! The games keep their own searches where they know something the trait doesn't, like Nim's
+//! formula or the solved tic-tac-toe table. [`best_moves`] works for any of them, looking a fixed
+//! number of moves ahead and falling back on [`Game::evaluate`] where it stops.
+
+use crate::error::MoveError;
+use crate::game::{GameStatus, Player};
+
+/// Scores of this or more are forced wins, higher the sooner they come
+pub const WIN: i32 = 1_000_000;
+
+/// A two-player game with perfect information, at least as far as the rules go
+pub trait Game: Clone {
+    /// A move, such as a square or a line between two dots
+    type Move: Copy + PartialEq + std::fmt::Debug;
+
+    fn next_player(&self) -> Player;
+
+    /// Every move the side to move can make, none once the game is over
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
+    /// Make a move for the side to move. Games with extra turns keep the same side to move
+    fn play(&mut self, m: Self::Move) -> Result<(), MoveError>;
+
+    fn status(&self) -> GameStatus;
+
+    /// A move's name for menus and messages, like "Top Left" or "a1-b1"
+    fn describe(&self, m: Self::Move) -> String;
+
+    /// How good the position looks for the side to move when a search stops short of the end,
+    /// positive when ahead. The default says nothing either way
+    fn evaluate(&self) -> i32 {
+        0
+    }
+}
+
+/// Every legal move with its score for the side to move, looking `depth` moves ahead
+pub fn evaluate_moves<G: Game>(game: &G, depth: usize) -> Vec<(G::Move, i32)> {
+    let player = game.next_player();
+    game.legal_moves()
+        .into_iter()
+        .map(|m| {
+            let after = with_move(game, m);
+            (m, score_after(&after, player, depth, -i32::MAX, i32::MAX))
+        })
+        .collect()
+}
+
+/// The moves with the best score for the side to move, looking `depth` moves ahead
+pub fn best_moves<G: Game>(game: &G, depth: usize) -> Vec<G::Move> {
+    let scored = evaluate_moves(game, depth);
+    let Some(best) = scored.iter().map(|&(_, score)| score).max() else {
+        return Vec::new();
+    };
+    scored
+        .into_iter()
+        .filter(|&(_, score)| score == best)
+        .map(|(m, _)| m)
+        .collect()
+}
+
+fn with_move<G: Game>(game: &G, m: G::Move) -> G {
+    let mut after = game.clone();
+    after.play(m).expect("legal moves can be played");
+    after
+}
+
+/// The score of a position for `player`, who just moved, with `depth` moves left to look at
+fn score_after<G: Game>(game: &G, player: Player, depth: usize, alpha: i32, beta: i32) -> i32 {
+    if game.next_player() == player {
+        alpha_beta(game, depth, alpha, beta)
+    } else {
+        -alpha_beta(game, depth, -beta, -alpha)
+    }
+}
+
+/// The score of a position for the side to move
+fn alpha_beta<G: Game>(game: &G, depth: usize, mut alpha: i32, beta: i32) -> i32 {
+    let player = game.next_player();
+    match game.status() {
+        GameStatus::Won(winner) if winner == player => return WIN + depth as i32,
+        GameStatus::Won(_) => return -(WIN + depth as i32),
+        GameStatus::Tie => return 0,
+        GameStatus::InProgress => {}
+    }
+    if depth == 0 {
+        return game.evaluate();
+    }
+    let mut best = -i32::MAX;
+    for m in game.legal_moves() {
+        let after = with_move(game, m);
+        let score = score_after(&after, player, depth - 1, alpha, beta);
+        best = best.max(score);
+        alpha = alpha.max(score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
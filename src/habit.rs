@@ -0,0 +1,331 @@
+//! Habits: a fixed way of playing written down as a few rules, like `open center, then corners`,
+//! and the line that beats it
+//!
+//! A habit is a list of rules, each a word saying which of the player's moves it's for followed by
+//! what the player goes for, in order of preference:
+//!
+//! - `open` (or `first`) is for the player's first move, `then` (or `later`) for every move after
+//!   it, `always` for every move, and `move 3` for just the third
+//! - `win` completes a line, `block` stops the opponent's, `center`, `corners` and `edges` are
+//!   what they say, `opposite` is the corner across from one of the opponent's, and a square is
+//!   `1`-`9` or `a1`-`c3` as in move lists
+//!
+//! Commas, semicolons and words like `the` and `take` can go anywhere, and preferences before the
+//! first rule word are for every move. On each move the rules for it are tried in the order
+//! they're written. Where a preference fits several squares the first from the top left is taken,
+//! and where nothing fits the first open square is, so the habit always plays the same move in
+//! the same position and the engine can plan against it.
+
+use crate::ai::GameResult;
+use crate::analysis::{find_blunder, LossReason};
+use crate::error::Error;
+use crate::game::{GameState, GameStatus, Player};
+use crate::import::parse_square;
+use crate::solved;
+use std::fmt::Display;
+use std::str::FromStr;
+
+const CORNERS: [usize; 4] = [0, 2, 6, 8];
+const EDGES: [usize; 4] = [1, 3, 5, 7];
+
+/// Words that read naturally in a habit but don't change it
+const FILLER: [&str; 10] = [
+    "a", "an", "and", "the", "take", "play", "go", "for", "in", "on",
+];
+
+/// Which of the player's moves a rule is for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum When {
+    Opening,
+    Later,
+    Always,
+    Move(usize),
+}
+
+impl When {
+    /// Whether the rule is for the player's `n`th move, counting from 1
+    fn covers(self, n: usize) -> bool {
+        match self {
+            When::Opening => n == 1,
+            When::Later => n > 1,
+            When::Always => true,
+            When::Move(m) => n == m,
+        }
+    }
+}
+
+/// What a player goes for
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Preference {
+    Win,
+    Block,
+    Center,
+    Corners,
+    Edges,
+    Opposite,
+    Square(usize),
+}
+
+impl Preference {
+    /// The first square the preference fits for the side to move, if any
+    fn square(self, state: &GameState) -> Option<usize> {
+        let player = state.next_player();
+        let open = |square: &usize| state.board()[*square].is_none();
+        match self {
+            Preference::Win => state.threats(player).first().copied(),
+            Preference::Block => state.threats(player.opponent()).first().copied(),
+            Preference::Center => Some(4).filter(open),
+            Preference::Corners => CORNERS.into_iter().find(open),
+            Preference::Edges => EDGES.into_iter().find(open),
+            Preference::Opposite => CORNERS
+                .into_iter()
+                .filter(|&corner| state.board()[8 - corner] == Some(player.opponent()))
+                .find(open),
+            Preference::Square(square) => Some(square).filter(open),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Rule {
+    when: When,
+    preferences: Vec<Preference>,
+}
+
+/// A fixed way of playing, read from rules like `open center, then block, corners`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Habit {
+    rules: Vec<Rule>,
+}
+
+impl Habit {
+    /// The move the habit plays for the side to move
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::habit::Habit;
+    /// use simple_tic_tac_toe::{GameState, Player};
+    ///
+    /// let habit: Habit = "always open center, then corners".parse().unwrap();
+    /// let state = GameState::new(Player::O);
+    /// assert_eq!(habit.choose(&state), 4);
+    /// assert_eq!(habit.choose(&state.with_move(4).with_move(0)), 2);
+    /// ```
+    pub fn choose(&self, state: &GameState) -> usize {
+        let player = state.next_player();
+        let n = state
+            .board()
+            .iter()
+            .filter(|&&square| square == Some(player))
+            .count()
+            + 1;
+        self.rules
+            .iter()
+            .filter(|rule| rule.when.covers(n))
+            .flat_map(|rule| &rule.preferences)
+            .find_map(|preference| preference.square(state))
+            .or_else(|| state.board().iter().position(Option::is_none))
+            .expect("the habit is only asked for a move in a game in progress")
+    }
+}
+
+impl FromStr for Habit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = Vec::new();
+        let mut rule = Rule {
+            when: When::Always,
+            preferences: Vec::new(),
+        };
+        let lowered = s.to_lowercase();
+        let mut words = lowered
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '.'))
+            .filter(|word| !word.is_empty() && !FILLER.contains(word));
+        while let Some(word) = words.next() {
+            let when = match word {
+                "open" | "opening" | "first" => Some(When::Opening),
+                "then" | "later" | "after" => Some(When::Later),
+                "always" => Some(When::Always),
+                "move" => {
+                    let n = words
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .filter(|&n| (1..=5).contains(&n))
+                        .ok_or_else(|| Error::parse("`move` should be followed by 1-5"))?;
+                    Some(When::Move(n))
+                }
+                _ => None,
+            };
+            if let Some(when) = when {
+                let done = std::mem::replace(
+                    &mut rule,
+                    Rule {
+                        when,
+                        preferences: Vec::new(),
+                    },
+                );
+                if !done.preferences.is_empty() {
+                    rules.push(done);
+                }
+                continue;
+            }
+            let preference = match word {
+                "win" | "wins" => Preference::Win,
+                "block" | "blocks" => Preference::Block,
+                "center" | "centre" | "middle" => Preference::Center,
+                "corner" | "corners" => Preference::Corners,
+                "edge" | "edges" | "side" | "sides" => Preference::Edges,
+                "opposite" => Preference::Opposite,
+                square => Preference::Square(parse_square(square).ok_or_else(|| {
+                    Error::parse(format!(
+                        "`{word}` is not part of a habit, expected open, then, always, move, \
+                         win, block, center, corners, edges, opposite or a square"
+                    ))
+                })?),
+            };
+            rule.preferences.push(preference);
+        }
+        if !rule.preferences.is_empty() {
+            rules.push(rule);
+        }
+        if rules.is_empty() {
+            return Err(Error::parse("the habit doesn't say where to play"));
+        }
+        Ok(Self { rules })
+    }
+}
+
+impl Display for Habit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, rule) in self.rules.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            match rule.when {
+                When::Opening => write!(f, "open")?,
+                When::Later => write!(f, "then")?,
+                When::Always => write!(f, "always")?,
+                When::Move(n) => write!(f, "move {n}")?,
+            }
+            for (j, preference) in rule.preferences.iter().enumerate() {
+                let separator = if j == 0 { " " } else { ", " };
+                match preference {
+                    Preference::Win => write!(f, "{separator}win")?,
+                    Preference::Block => write!(f, "{separator}block")?,
+                    Preference::Center => write!(f, "{separator}center")?,
+                    Preference::Corners => write!(f, "{separator}corners")?,
+                    Preference::Edges => write!(f, "{separator}edges")?,
+                    Preference::Opposite => write!(f, "{separator}opposite")?,
+                    Preference::Square(square) => write!(f, "{separator}{}", square + 1)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where the habit first threw the game away in the line that beats it
+#[derive(Clone, Debug)]
+pub struct Mistake {
+    /// The move's place in the line, counting from 0
+    pub ply: usize,
+    /// The square the habit played
+    pub played: usize,
+    /// The squares that would have held the draw
+    pub better: Vec<usize>,
+    /// Why the move lost, when it's a missed block or walks into a fork
+    pub reason: Option<LossReason>,
+}
+
+/// The line the engine plays to beat a habit, looking ahead knowing every move the habit makes
+#[derive(Clone, Debug)]
+pub struct Punishment {
+    /// The side the habit plays
+    pub habit_player: Player,
+    /// Every move of the game from the empty board, the habit's and the engine's
+    pub moves: Vec<usize>,
+    /// How the game ends, a tie if the habit holds up
+    pub status: GameStatus,
+    /// The habit's losing move, if it loses
+    pub mistake: Option<Mistake>,
+}
+
+impl Punishment {
+    /// Work out the engine's best line against `habit` playing `habit_player`, winning as fast as
+    /// it can
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::habit::{Habit, Punishment};
+    /// use simple_tic_tac_toe::{GameStatus, Player};
+    ///
+    /// // Always answering in the first corner free leaves a fork open
+    /// let habit: Habit = "win, block, corners".parse().unwrap();
+    /// let punishment = Punishment::new(&habit, Player::O);
+    /// assert_eq!(punishment.status, GameStatus::Won(Player::X));
+    /// assert!(punishment.mistake.is_some());
+    /// ```
+    pub fn new(habit: &Habit, habit_player: Player) -> Self {
+        let mut state = GameState::new(habit_player.opponent());
+        let (_, moves) = best_line(habit, &mut state.clone());
+        let mut mistake = None;
+        for (ply, &square) in moves.iter().enumerate() {
+            if state.next_player() == habit_player && mistake.is_none() {
+                mistake = find_mistake(&state, square, ply);
+            }
+            state.apply_move(square);
+        }
+        Self {
+            habit_player,
+            moves,
+            status: state.status(),
+            mistake,
+        }
+    }
+}
+
+/// The engine's score with the best play against the habit from here, higher for winning sooner
+/// or losing later, and the moves that get it
+fn best_line(habit: &Habit, state: &mut GameState) -> (i32, Vec<usize>) {
+    let engine = state.computer_player;
+    let played = state.board().iter().flatten().count() as i32;
+    match state.status() {
+        GameStatus::Won(winner) if winner == engine => return (10 - played, Vec::new()),
+        GameStatus::Won(_) => return (played - 10, Vec::new()),
+        GameStatus::Tie => return (0, Vec::new()),
+        _ => {}
+    }
+    let squares: Vec<usize> = if state.next_player() == engine {
+        state.legal_moves().collect()
+    } else {
+        vec![habit.choose(state)]
+    };
+    let mut best: Option<(i32, Vec<usize>)> = None;
+    for square in squares {
+        let undo = state.make_move(square);
+        let (score, mut line) = best_line(habit, state);
+        state.unmake_move(undo);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            line.insert(0, square);
+            best = Some((score, line));
+        }
+    }
+    best.expect("a game in progress has open squares")
+}
+
+/// The mistake in playing `square`, if it turns a game that isn't lost into a loss
+fn find_mistake(state: &GameState, square: usize, ply: usize) -> Option<Mistake> {
+    let loses = |square| solved::result(&state.with_move(square)) == Some(GameResult::Win);
+    if !loses(square) {
+        return None;
+    }
+    let better: Vec<usize> = state.legal_moves().filter(|&other| !loses(other)).collect();
+    if better.is_empty() {
+        return None;
+    }
+    Some(Mistake {
+        ply,
+        played: square,
+        better,
+        reason: find_blunder(state, square),
+    })
+}
@@ -0,0 +1,160 @@
+//! The handshake both JSON protocols, the engine [`protocol`](crate::protocol) and the game
+//! [`server`](crate::server), can start with, so programs built against different releases
+//! agree on what they both understand instead of sending each other messages they can't read
+//!
+//! The client says `hello` with the newest version of the protocol it speaks and the
+//! capabilities it wants, and gets back a `welcome` with the version both sides will speak, the
+//! older of the two, and the capabilities they both have:
+//!
+//! ```text
+//! > {"type": "hello", "version": 1, "capabilities": ["spectating", "chat"]}
+//! < {"type": "welcome", "version": 1, "capabilities": ["spectating"], "variants": ["standard"]}
+//! ```
+//!
+//! Requests that need a capability left out of the welcome are turned down. A client that never
+//! says hello is treated as speaking version 1 with every capability, as clients did before the
+//! handshake, and a client that gets an error back for its hello is talking to a server from
+//! before the handshake, which speaks version 1. Capabilities one side hasn't heard of are
+//! dropped rather than failing the hello.
+
+use crate::error::ProtocolError;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Something one side of a protocol can do beyond the basics every version has
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Capability {
+    /// Games that send the squares each move changed instead of the whole board
+    Deltas,
+    /// Asking the engine for the best moves
+    Hints,
+    /// Offering the engine a draw
+    DrawOffers,
+    /// Starting a game from a position rather than the empty board
+    Positions,
+    /// Watching a game without a seat in it
+    Spectating,
+    /// Messages between the players
+    Chat,
+    /// A capability from a newer release, which nothing here supports
+    #[serde(other)]
+    Unknown,
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Capability::Deltas => write!(f, "deltas"),
+            Capability::Hints => write!(f, "hints"),
+            Capability::DrawOffers => write!(f, "draw_offers"),
+            Capability::Positions => write!(f, "positions"),
+            Capability::Spectating => write!(f, "spectating"),
+            Capability::Chat => write!(f, "chat"),
+            Capability::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The first request of a client that knows about the handshake
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    /// The newest version the client speaks
+    pub version: u32,
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+}
+
+/// The answer to a [`Hello`], with what both sides understand
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Welcome {
+    pub version: u32,
+    pub capabilities: Vec<Capability>,
+    /// The games that can be played, by name, like `"standard"` tic-tac-toe
+    #[serde(default)]
+    pub variants: Vec<String>,
+}
+
+impl Welcome {
+    /// What a side that never said hello gets: version 1 with all of `capabilities`
+    pub fn assumed(capabilities: &[Capability]) -> Self {
+        Self {
+            version: 1,
+            capabilities: capabilities.to_vec(),
+            variants: vec!["standard".to_string()],
+        }
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+/// What one side of a protocol speaks
+#[derive(Copy, Clone, Debug)]
+pub struct Support {
+    pub oldest: u32,
+    pub newest: u32,
+    pub capabilities: &'static [Capability],
+    pub variants: &'static [&'static str],
+}
+
+impl Support {
+    /// Answer a hello, with the version and capabilities both sides have
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::handshake::{Capability, Hello, Support};
+    ///
+    /// let support = Support {
+    ///     oldest: 1,
+    ///     newest: 2,
+    ///     capabilities: &[Capability::Hints, Capability::Deltas],
+    ///     variants: &["standard"],
+    /// };
+    /// // A newer client that wants something this side has never heard of
+    /// let hello: Hello =
+    ///     serde_json::from_str(r#"{"version": 3, "capabilities": ["hints", "teleporting"]}"#)
+    ///         .unwrap();
+    /// let welcome = support.negotiate(&hello).unwrap();
+    /// assert_eq!(welcome.version, 2);
+    /// assert_eq!(welcome.capabilities, [Capability::Hints]);
+    ///
+    /// // A client too old to talk to
+    /// let hello = Hello { version: 0, capabilities: Vec::new() };
+    /// assert!(support.negotiate(&hello).is_err());
+    /// ```
+    pub fn negotiate(&self, hello: &Hello) -> Result<Welcome, ProtocolError> {
+        let version = hello.version.min(self.newest);
+        if version < self.oldest {
+            return Err(ProtocolError::UnsupportedVersion {
+                version: hello.version,
+                oldest: self.oldest,
+                newest: self.newest,
+            });
+        }
+        let capabilities = self
+            .capabilities
+            .iter()
+            .copied()
+            .filter(|capability| hello.capabilities.contains(capability))
+            .collect();
+        Ok(Welcome {
+            version,
+            capabilities,
+            variants: self.variants(),
+        })
+    }
+
+    /// The welcome for a client that never said hello
+    pub fn assumed(&self) -> Welcome {
+        Welcome {
+            variants: self.variants(),
+            ..Welcome::assumed(self.capabilities)
+        }
+    }
+
+    fn variants(&self) -> Vec<String> {
+        self.variants.iter().map(|name| name.to_string()).collect()
+    }
+}
@@ -0,0 +1,168 @@
+//! Rendering boards as images, for sharing finished games outside the terminal
+
+#[cfg(any(feature = "png", feature = "gif"))]
+use crate::error::Error;
+use crate::game::{GameState, Player};
+
+/// Size of one square in pixels
+const CELL: u32 = 100;
+/// Space around the grid in pixels
+const MARGIN: u32 = 10;
+/// Width and height of the whole image
+const SIZE: u32 = CELL * 3 + MARGIN * 2;
+
+/// Colors used to draw a board image
+#[derive(Copy, Clone, Debug)]
+pub struct Theme {
+    pub background: &'static str,
+    pub grid: &'static str,
+    pub x: &'static str,
+    pub o: &'static str,
+    pub win: &'static str,
+}
+
+impl Theme {
+    /// Dark pieces on a white background
+    pub fn light() -> Self {
+        Self {
+            background: "#ffffff",
+            grid: "#333333",
+            x: "#d9480f",
+            o: "#1864ab",
+            win: "#f59f00",
+        }
+    }
+
+    /// Bright pieces on a near-black background
+    pub fn dark() -> Self {
+        Self {
+            background: "#1a1b26",
+            grid: "#a9b1d6",
+            x: "#ff7a93",
+            o: "#7aa2f7",
+            win: "#e0af68",
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+/// Get the pixel coordinates of the center of a square
+fn center(square: usize) -> (u32, u32) {
+    let col = (square % 3) as u32;
+    let row = (square / 3) as u32;
+    (
+        MARGIN + col * CELL + CELL / 2,
+        MARGIN + row * CELL + CELL / 2,
+    )
+}
+
+/// Draw the board as an SVG document, with the winning line struck through if there is one
+pub fn render_svg(state: &GameState, theme: &Theme) -> String {
+    let Theme {
+        background,
+        grid,
+        x: x_color,
+        o: o_color,
+        win: win_color,
+    } = *theme;
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{SIZE}" height="{SIZE}" viewBox="0 0 {SIZE} {SIZE}">"#
+    );
+    svg.push_str(&format!(
+        r#"<rect width="{SIZE}" height="{SIZE}" fill="{background}"/>"#
+    ));
+
+    for i in 1..3 {
+        let offset = MARGIN + i * CELL;
+        let end = SIZE - MARGIN;
+        svg.push_str(&format!(
+            r#"<line x1="{offset}" y1="{MARGIN}" x2="{offset}" y2="{end}" stroke="{grid}" stroke-width="4" stroke-linecap="round"/>"#
+        ));
+        svg.push_str(&format!(
+            r#"<line x1="{MARGIN}" y1="{offset}" x2="{end}" y2="{offset}" stroke="{grid}" stroke-width="4" stroke-linecap="round"/>"#
+        ));
+    }
+
+    // Pieces are drawn as shapes rather than text so rendering doesn't depend on installed fonts
+    let reach = CELL * 3 / 10;
+    for (square, piece) in state.board().iter().enumerate() {
+        let (x, y) = center(square);
+        match piece {
+            Some(Player::X) => {
+                let (left, right, top, bottom) = (x - reach, x + reach, y - reach, y + reach);
+                for (y1, y2) in [(top, bottom), (bottom, top)] {
+                    svg.push_str(&format!(
+                        r#"<line x1="{left}" y1="{y1}" x2="{right}" y2="{y2}" stroke="{x_color}" stroke-width="10" stroke-linecap="round"/>"#
+                    ));
+                }
+            }
+            Some(Player::O) => {
+                svg.push_str(&format!(
+                    r#"<circle cx="{x}" cy="{y}" r="{reach}" fill="none" stroke="{o_color}" stroke-width="10"/>"#
+                ));
+            }
+            None => {}
+        }
+    }
+
+    if let Some([first, _, last]) = state.winning_line() {
+        let (x1, y1) = center(first);
+        let (x2, y2) = center(last);
+        svg.push_str(&format!(
+            r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="{win_color}" stroke-width="8" stroke-linecap="round" opacity="0.8"/>"#
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Rasterize the board with resvg
+#[cfg(any(feature = "png", feature = "gif"))]
+fn rasterize(state: &GameState, theme: &Theme) -> crate::Result<resvg::tiny_skia::Pixmap> {
+    use resvg::{tiny_skia, usvg};
+
+    let tree = usvg::Tree::from_str(&render_svg(state, theme), &usvg::Options::default())
+        .map_err(|e| Error::Image(e.into()))?;
+    let mut pixmap = tiny_skia::Pixmap::new(SIZE, SIZE)
+        .ok_or_else(|| Error::Image("failed to allocate image buffer".into()))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+/// Draw the board as a PNG image
+#[cfg(feature = "png")]
+pub fn render_png(state: &GameState, theme: &Theme) -> crate::Result<Vec<u8>> {
+    rasterize(state, theme)?
+        .encode_png()
+        .map_err(|e| Error::Image(e.into()))
+}
+
+/// Draw a sequence of boards as an animated GIF that loops forever, showing each for `delay_ms`
+#[cfg(feature = "gif")]
+pub fn render_gif(states: &[GameState], theme: &Theme, delay_ms: u16) -> crate::Result<Vec<u8>> {
+    let image_error = |e: gif::EncodingError| Error::Image(e.into());
+    let mut data = Vec::new();
+    {
+        let size = SIZE as u16;
+        let mut encoder = gif::Encoder::new(&mut data, size, size, &[]).map_err(image_error)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(image_error)?;
+        for state in states {
+            // The background is opaque, so the premultiplied pixels are plain RGBA
+            let mut pixels = rasterize(state, theme)?.take();
+            let mut frame = gif::Frame::from_rgba_speed(size, size, &mut pixels, 10);
+            // GIF delays are in hundredths of a second
+            frame.delay = delay_ms / 10;
+            encoder.write_frame(&frame).map_err(image_error)?;
+        }
+    }
+    Ok(data)
+}
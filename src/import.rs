@@ -0,0 +1,271 @@
+//! Games written down elsewhere, read into saved games that can be replayed, inspected and
+//! exported like any other
+//!
+//! Three notations are read:
+//!
+//! - Move lists, like `5 1 9 3 2`: squares numbered 1-9 from the top left, or as a column letter
+//!   and row number like `b2` with `a1` in the top left. Move numbers like `1.` are skipped, and a
+//!   move can start with its mark, as in `X5 O1`
+//! - Position strings, as read by [`parse_position`](crate::position::parse_position), which
+//!   become a game set up from that position with no moves yet
+//! - JSON, either a game saved by this program or the shapes other implementations commonly
+//!   write: a list of moves, an object with a `moves` (or `history`) list, or an object with a
+//!   `board`. Moves in JSON are squares numbered 0-8, or objects with a `square`, `index` or
+//!   `position`, or a `row` and `col` (or `column`) counted from 0
+
+use crate::error::Error;
+use crate::game::{GameState, Player};
+use crate::position::parse_position;
+use crate::record::GameRecord;
+use serde_json::Value;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The notations games can be imported from
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Notation {
+    Moves,
+    Position,
+    Json,
+}
+
+impl Notation {
+    /// Guess the notation from the text: JSON starts with a bracket, and only position strings
+    /// have a `/`
+    pub fn detect(text: &str) -> Self {
+        let text = text.trim_start();
+        if text.starts_with(['{', '[']) {
+            Notation::Json
+        } else if text.contains('/') {
+            Notation::Position
+        } else {
+            Notation::Moves
+        }
+    }
+}
+
+impl Display for Notation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Notation::Moves => write!(f, "moves"),
+            Notation::Position => write!(f, "position"),
+            Notation::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for Notation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "moves" => Ok(Notation::Moves),
+            "position" => Ok(Notation::Position),
+            "json" => Ok(Notation::Json),
+            _ => Err(Error::parse(format!(
+                "`{s}` is not a notation, expected moves, position or json"
+            ))),
+        }
+    }
+}
+
+/// Read a game in `notation`, or the one it looks like if None, with the human as `human`
+/// unless the game says who played which side
+///
+/// ```
+/// use simple_tic_tac_toe::import::import_game;
+/// use simple_tic_tac_toe::Player;
+///
+/// let game = import_game("1. 5 1 2. b3 c1", None, Player::X).unwrap();
+/// assert_eq!(game.squares(), [4, 0, 7, 2]);
+///
+/// // A move on a taken square, and a move marked for the wrong side
+/// assert!(import_game("5 5", None, Player::X).is_err());
+/// assert!(import_game("X5 X1", None, Player::X).is_err());
+/// ```
+pub fn import_game(
+    text: &str,
+    notation: Option<Notation>,
+    human: Player,
+) -> crate::Result<GameRecord> {
+    let record = match notation.unwrap_or_else(|| Notation::detect(text)) {
+        Notation::Moves => import_moves(text, human)?,
+        Notation::Position => {
+            let state = parse_position(text)?;
+            let mut record = GameRecord::new(human.opponent(), Vec::new());
+            record.start = Some(*state.board());
+            record
+        }
+        Notation::Json => import_json(text, human)?,
+    };
+    record.final_position()?;
+    Ok(record)
+}
+
+fn import_moves(text: &str, human: Player) -> crate::Result<GameRecord> {
+    let tokens = text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|token| !token.is_empty() && !is_move_number(token));
+    let mut squares = Vec::new();
+    for (i, token) in tokens.enumerate() {
+        let n = i + 1;
+        let (mark, square) = match token.strip_prefix(['x', 'X']) {
+            Some(rest) => (Some(Player::X), rest),
+            None => match token.strip_prefix(['o', 'O']) {
+                Some(rest) => (Some(Player::O), rest),
+                None => (None, token),
+            },
+        };
+        let square = square.strip_prefix(':').unwrap_or(square);
+        let square = parse_square(square).ok_or_else(|| {
+            Error::parse(format!(
+                "move {n} `{token}` is not a square, expected 1-9 or a1-c3"
+            ))
+        })?;
+        let expected = if i % 2 == 0 { Player::X } else { Player::O };
+        if let Some(mark) = mark.filter(|&mark| mark != expected) {
+            return Err(Error::parse(format!(
+                "move {n} `{token}` is marked {mark}, but it's {expected}'s move"
+            )));
+        }
+        squares.push(square);
+    }
+    game_from_moves(squares, human)
+}
+
+/// `1.` or `12.`, which numbers the moves rather than being one
+fn is_move_number(token: &str) -> bool {
+    token
+        .strip_suffix('.')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// A square as `1`-`9` or `a1`-`c3`, numbered from 0
+pub(crate) fn parse_square(s: &str) -> Option<usize> {
+    let mut chars = s.chars();
+    match (chars.next()?, chars.next(), chars.next()) {
+        (digit @ '1'..='9', None, None) => Some(digit as usize - '1' as usize),
+        (column, Some(row @ '1'..='3'), None) => {
+            let column = match column.to_ascii_lowercase() {
+                c @ 'a'..='c' => c as usize - 'a' as usize,
+                _ => return None,
+            };
+            Some((row as usize - '1' as usize) * 3 + column)
+        }
+        _ => None,
+    }
+}
+
+fn import_json(text: &str, human: Player) -> crate::Result<GameRecord> {
+    // A game saved by this program already says everything
+    if let Ok(record) = serde_json::from_str::<GameRecord>(text) {
+        return Ok(record);
+    }
+    let value: Value = serde_json::from_str(text)
+        .map_err(|e| Error::parse_with("the game is not valid JSON", e))?;
+    let moves = match &value {
+        Value::Array(moves) => moves,
+        Value::Object(fields) => {
+            if let Some(moves) = fields.get("moves").or_else(|| fields.get("history")) {
+                moves
+                    .as_array()
+                    .ok_or_else(|| Error::parse("`moves` should be a list of moves"))?
+            } else if let Some(board) = fields.get("board") {
+                let mut record = GameRecord::new(human.opponent(), Vec::new());
+                record.start = Some(json_board(board)?);
+                return Ok(record);
+            } else {
+                return Err(Error::parse(
+                    "expected a `moves`, `history` or `board` field in the JSON",
+                ));
+            }
+        }
+        _ => return Err(Error::parse("expected a list of moves or an object")),
+    };
+    let squares = moves
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            json_square(value)
+                .ok_or_else(|| Error::parse(format!("move {} `{value}` is not a square", i + 1)))
+        })
+        .collect::<crate::Result<_>>()?;
+    game_from_moves(squares, human)
+}
+
+/// A move as a square numbered 0-8, or an object holding one or a row and column
+fn json_square(value: &Value) -> Option<usize> {
+    let square = match value {
+        Value::Object(fields) => {
+            match ["square", "index", "position"]
+                .iter()
+                .find_map(|key| fields.get(*key))
+            {
+                Some(square) => square.as_u64()?,
+                None => {
+                    let row = fields.get("row")?.as_u64()?;
+                    let column = fields.get("col").or_else(|| fields.get("column"))?;
+                    let column = column.as_u64()?;
+                    if row > 2 || column > 2 {
+                        return None;
+                    }
+                    row * 3 + column
+                }
+            }
+        }
+        value => value.as_u64()?,
+    };
+    usize::try_from(square).ok().filter(|&square| square < 9)
+}
+
+/// A board as a list of 9 squares or 3 rows of 3, each `X`, `O` or empty
+fn json_board(value: &Value) -> crate::Result<[Option<Player>; 9]> {
+    let squares: Vec<&Value> = match value.as_array() {
+        Some(rows) if rows.len() == 3 && rows.iter().all(Value::is_array) => rows
+            .iter()
+            .flat_map(|row| row.as_array().into_iter().flatten())
+            .collect(),
+        Some(squares) => squares.iter().collect(),
+        None => return Err(Error::parse("`board` should be a list of squares")),
+    };
+    if squares.len() != 9 {
+        return Err(Error::parse(format!(
+            "`board` has {} squares, expected 9",
+            squares.len()
+        )));
+    }
+    let mut board = [None; 9];
+    for (i, square) in squares.into_iter().enumerate() {
+        board[i] = match square {
+            Value::Null => None,
+            Value::String(mark) => match mark.trim() {
+                "" | "." | "-" | "_" => None,
+                mark => Some(mark.parse().map_err(|_| {
+                    Error::parse(format!(
+                        "square {} `{mark}` is not a mark, expected X, O or empty",
+                        i + 1
+                    ))
+                })?),
+            },
+            other => {
+                return Err(Error::parse(format!(
+                    "square {} `{other}` is not a mark, expected X, O or empty",
+                    i + 1
+                )))
+            }
+        };
+    }
+    Ok(board)
+}
+
+/// A game from the empty board, checking each move as it goes
+fn game_from_moves(squares: Vec<usize>, human: Player) -> crate::Result<GameRecord> {
+    let mut state = GameState::new(human.opponent());
+    for (i, &square) in squares.iter().enumerate() {
+        state
+            .try_move(square)
+            .map_err(|e| Error::parse_with(format!("move {} can't be played", i + 1), e))?;
+    }
+    Ok(GameRecord::new(human.opponent(), squares))
+}
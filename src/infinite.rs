@@ -0,0 +1,247 @@
+//! "Infinite" tic-tac-toe, where each player has at most three marks on the board and placing a
+//! fourth takes away their oldest
+//!
+//! A line only wins once the oldest mark is gone, so the three marks making it must all be on the
+//! board together. The game never fills up, which means it can go on forever; callers decide when
+//! to call it a draw. With so few positions, the whole game is solved by working back from the
+//! won positions, see [`Solution`].
+
+use crate::error::MoveError;
+use crate::game::{GameStatus, Player, Selection, LINES};
+use crate::games::Game;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// How many marks each player keeps on the board
+pub const MARKS: usize = 3;
+
+/// A game of infinite tic-tac-toe
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InfiniteGame {
+    /// Each side's marks on the board, oldest first, X first
+    marks: [Vec<usize>; 2],
+    pub next_player: Player,
+}
+
+impl Default for InfiniteGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InfiniteGame {
+    pub fn new() -> Self {
+        Self {
+            marks: [Vec::new(), Vec::new()],
+            next_player: Player::X,
+        }
+    }
+
+    /// A player's marks on the board, oldest first
+    pub fn marks(&self, player: Player) -> &[usize] {
+        &self.marks[index(player)]
+    }
+
+    /// The mark a player loses on their next move, if they already have all of theirs down
+    pub fn expiring(&self, player: Player) -> Option<usize> {
+        let marks = self.marks(player);
+        (marks.len() == MARKS).then(|| marks[0])
+    }
+
+    pub fn board(&self) -> [Option<Player>; 9] {
+        let mut board = [None; 9];
+        for player in [Player::X, Player::O] {
+            for &square in self.marks(player) {
+                board[square] = Some(player);
+            }
+        }
+        board
+    }
+
+    /// Who has three in a row, if anyone. No game ends in a tie
+    pub fn status(&self) -> GameStatus {
+        let board = self.board();
+        LINES
+            .iter()
+            .find_map(|&[a, b, c]| {
+                board[a].filter(|&player| board[b] == Some(player) && board[c] == Some(player))
+            })
+            .map_or(GameStatus::InProgress, GameStatus::Won)
+    }
+
+    /// The empty squares. The side to move's oldest mark still blocks its square
+    pub fn legal_moves(&self) -> Vec<usize> {
+        if self.status() != GameStatus::InProgress {
+            return Vec::new();
+        }
+        let board = self.board();
+        (0..9).filter(|&square| board[square].is_none()).collect()
+    }
+
+    /// Place a mark for the side to move, taking away its oldest if it already has three down
+    pub fn play(&mut self, square: usize) -> Result<(), MoveError> {
+        if square >= 9 {
+            return Err(MoveError::OffBoard(square));
+        }
+        if self.status() != GameStatus::InProgress {
+            return Err(MoveError::GameOver(square));
+        }
+        if self.board()[square].is_some() {
+            return Err(MoveError::Taken(square));
+        }
+        let marks = &mut self.marks[index(self.next_player)];
+        if marks.len() == MARKS {
+            marks.remove(0);
+        }
+        marks.push(square);
+        self.next_player = self.next_player.opponent();
+        Ok(())
+    }
+
+    pub fn with_move(&self, square: usize) -> Self {
+        let mut game = self.clone();
+        game.play(square).expect("the move is legal");
+        game
+    }
+}
+
+fn index(player: Player) -> usize {
+    match player {
+        Player::X => 0,
+        Player::O => 1,
+    }
+}
+
+/// The result of a position with perfect play, for the side to move
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// A forced win in this many moves, counting both sides
+    Win(u32),
+    /// A forced loss in this many moves, counting both sides
+    Loss(u32),
+    /// Neither side can force a win, so good play goes on forever
+    Draw,
+}
+
+/// Every position reachable from the empty board, solved
+///
+/// Positions are worked out in rounds going back from the won ones: a position is a win in `n`
+/// when some move leads to a loss in `n - 1`, and a loss in `n` when every move leads to a win
+/// and the slowest is a win in `n - 1`. Whatever is left when a round decides nothing new is a
+/// draw.
+pub struct Solution {
+    outcomes: HashMap<InfiniteGame, Outcome>,
+}
+
+impl Solution {
+    pub fn new() -> Self {
+        let mut positions = vec![InfiniteGame::new()];
+        let mut ids = HashMap::from([(InfiniteGame::new(), 0)]);
+        let mut children: Vec<Vec<usize>> = Vec::new();
+        let mut next = 0;
+        while next < positions.len() {
+            let mut these = Vec::new();
+            for square in positions[next].legal_moves() {
+                let child = positions[next].with_move(square);
+                let id = *ids.entry(child.clone()).or_insert_with(|| {
+                    positions.push(child);
+                    positions.len() - 1
+                });
+                these.push(id);
+            }
+            children.push(these);
+            next += 1;
+        }
+
+        // Won positions have no moves, and the side to move there has lost
+        let mut outcomes: Vec<Option<Outcome>> = positions
+            .iter()
+            .map(|game| (game.status() != GameStatus::InProgress).then_some(Outcome::Loss(0)))
+            .collect();
+        for round in 1.. {
+            let mut decided = Vec::new();
+            for (id, moves) in children.iter().enumerate() {
+                if outcomes[id].is_some() {
+                    continue;
+                }
+                let wins = moves
+                    .iter()
+                    .any(|&child| outcomes[child] == Some(Outcome::Loss(round - 1)));
+                let loses = moves
+                    .iter()
+                    .all(|&child| matches!(outcomes[child], Some(Outcome::Win(_))));
+                if wins {
+                    decided.push((id, Outcome::Win(round)));
+                } else if loses {
+                    decided.push((id, Outcome::Loss(round)));
+                }
+            }
+            if decided.is_empty() {
+                break;
+            }
+            for (id, outcome) in decided {
+                outcomes[id] = Some(outcome);
+            }
+        }
+
+        let outcomes = positions
+            .into_iter()
+            .zip(outcomes)
+            .map(|(game, outcome)| (game, outcome.unwrap_or(Outcome::Draw)))
+            .collect();
+        Self { outcomes }
+    }
+
+    /// The result with perfect play for the side to move
+    pub fn outcome(&self, game: &InfiniteGame) -> Outcome {
+        self.outcomes[game]
+    }
+
+    /// Pick one of the best moves: the fastest win, a move that keeps the draw, or failing
+    /// those the slowest loss
+    pub fn decide(&self, game: &InfiniteGame, rng: &mut impl Rng) -> Option<usize> {
+        let rank = |square: usize| match self.outcome(&game.with_move(square)) {
+            Outcome::Loss(n) => (2, -(n as i64)),
+            Outcome::Draw => (1, 0),
+            Outcome::Win(n) => (0, n as i64),
+        };
+        let moves = game.legal_moves();
+        let best = moves.iter().map(|&square| rank(square)).max()?;
+        let best_moves: Vec<usize> = moves
+            .into_iter()
+            .filter(|&square| rank(square) == best)
+            .collect();
+        best_moves.choose(rng).copied()
+    }
+}
+
+impl Default for Solution {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game for InfiniteGame {
+    type Move = usize;
+
+    fn next_player(&self) -> Player {
+        self.next_player
+    }
+
+    fn legal_moves(&self) -> Vec<usize> {
+        InfiniteGame::legal_moves(self)
+    }
+
+    fn play(&mut self, square: usize) -> Result<(), MoveError> {
+        InfiniteGame::play(self, square)
+    }
+
+    fn status(&self) -> GameStatus {
+        InfiniteGame::status(self)
+    }
+
+    fn describe(&self, square: usize) -> String {
+        Selection::new(square).to_string()
+    }
+}
@@ -0,0 +1,200 @@
+//! A minimal IRC client over plain TCP, enough for a bot to join channels and talk in them
+
+use crate::error::Error;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::net::TcpStream;
+use std::time::Instant;
+
+/// Where and as whom to connect
+#[derive(Clone, Debug)]
+pub struct IrcOptions {
+    pub server: String,
+    pub port: u16,
+    pub nick: String,
+    /// Sent with `PASS` before registering, for servers that need one
+    pub password: Option<String>,
+}
+
+/// A message sent to a channel or to the bot directly
+#[derive(Clone, Debug)]
+pub struct ChatMessage {
+    /// The nick of the sender
+    pub sender: String,
+    /// The channel the message was sent to, or the bot's own nick for a private message
+    pub target: String,
+    pub text: String,
+}
+
+impl ChatMessage {
+    /// Where a reply should go: the same channel, or back to the sender for private messages
+    pub fn reply_target(&self) -> &str {
+        if self.target.starts_with('#') {
+            &self.target
+        } else {
+            &self.sender
+        }
+    }
+}
+
+/// What waiting for a message until a deadline turned up
+#[derive(Clone, Debug)]
+pub enum Received {
+    Message(ChatMessage),
+    TimedOut,
+    /// The server closed the connection
+    Closed,
+}
+
+/// A connection to an IRC server
+pub struct IrcClient {
+    address: String,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    /// The start of a line cut off by a read timeout
+    partial: Vec<u8>,
+}
+
+impl IrcClient {
+    /// Connect and register with the server, returning once it has welcomed the bot
+    pub fn connect(options: &IrcOptions) -> crate::Result<Self> {
+        let address = format!("{}:{}", options.server, options.port);
+        let stream =
+            TcpStream::connect(&address).map_err(Error::network("connect to", &address))?;
+        let reader = BufReader::new(
+            stream
+                .try_clone()
+                .map_err(Error::network("connect to", &address))?,
+        );
+        let mut client = Self {
+            address,
+            reader,
+            writer: BufWriter::new(stream),
+            partial: Vec::new(),
+        };
+
+        if let Some(password) = &options.password {
+            client.send(&format!("PASS {password}"))?;
+        }
+        client.send(&format!("NICK {}", options.nick))?;
+        client.send(&format!("USER {} 0 * :simple-tic-tac-toe", options.nick))?;
+        // 001 is the welcome reply, sent once registration is done
+        while let Line::Text(line) = client.read_line(None)? {
+            if let Some(command) = client.handle_ping(&line)? {
+                if command == "001" {
+                    return Ok(client);
+                }
+            }
+        }
+        let closed = std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "the server closed the connection",
+        );
+        Err(Error::network("register with", &client.address)(closed))
+    }
+
+    pub fn join(&mut self, channel: &str) -> crate::Result<()> {
+        self.send(&format!("JOIN {channel}"))
+    }
+
+    /// Wait for the next message to a channel or to the bot, answering pings on the way. Returns
+    /// None once the server closes the connection
+    pub fn next_message(&mut self) -> crate::Result<Option<ChatMessage>> {
+        match self.receive(None)? {
+            Received::Message(message) => Ok(Some(message)),
+            Received::TimedOut | Received::Closed => Ok(None),
+        }
+    }
+
+    /// Wait for the next message like `next_message`, but only until `deadline`
+    pub fn next_message_until(&mut self, deadline: Instant) -> crate::Result<Received> {
+        self.receive(Some(deadline))
+    }
+
+    fn receive(&mut self, deadline: Option<Instant>) -> crate::Result<Received> {
+        loop {
+            let line = match self.read_line(deadline)? {
+                Line::Text(line) => line,
+                Line::TimedOut => return Ok(Received::TimedOut),
+                Line::Closed => return Ok(Received::Closed),
+            };
+            if self.handle_ping(&line)?.as_deref() != Some("PRIVMSG") {
+                continue;
+            }
+            // :nick!user@host PRIVMSG target :text
+            let Some((prefix, rest)) = line.strip_prefix(':').and_then(|l| l.split_once(' '))
+            else {
+                continue;
+            };
+            let sender = prefix.split('!').next().unwrap_or(prefix);
+            let Some((target, text)) = rest
+                .strip_prefix("PRIVMSG ")
+                .and_then(|r| r.split_once(" :"))
+            else {
+                continue;
+            };
+            return Ok(Received::Message(ChatMessage {
+                sender: sender.to_string(),
+                target: target.to_string(),
+                text: text.to_string(),
+            }));
+        }
+    }
+
+    /// Send a line of text to a channel or nick
+    pub fn say(&mut self, target: &str, text: &str) -> crate::Result<()> {
+        self.send(&format!("PRIVMSG {target} :{text}"))
+    }
+
+    /// Answer a ping, returning the command of any other line
+    fn handle_ping(&mut self, line: &str) -> crate::Result<Option<String>> {
+        if let Some(token) = line.strip_prefix("PING ") {
+            self.send(&format!("PONG {token}"))?;
+            return Ok(None);
+        }
+        let command = match line.strip_prefix(':') {
+            Some(rest) => rest.split(' ').nth(1),
+            None => line.split(' ').next(),
+        };
+        Ok(command.map(str::to_string))
+    }
+
+    fn read_line(&mut self, deadline: Option<Instant>) -> crate::Result<Line> {
+        let timeout = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) if !timeout.is_zero() => Some(timeout),
+                _ => return Ok(Line::TimedOut),
+            },
+            None => None,
+        };
+        self.reader
+            .get_ref()
+            .set_read_timeout(timeout)
+            .map_err(Error::network("read from", &self.address))?;
+        match self.reader.read_until(b'\n', &mut self.partial) {
+            Ok(0) => return Ok(Line::Closed),
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(Line::TimedOut)
+            }
+            Err(e) => return Err(Error::network("read from", &self.address)(e)),
+        }
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.partial)).into_owned();
+        tracing::trace!(line = line.trim_end(), "irc <");
+        Ok(Line::Text(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    fn send(&mut self, line: &str) -> crate::Result<()> {
+        tracing::trace!(line, "irc >");
+        // Lines can't contain line breaks, which would start a new command
+        let line = line.replace(['\r', '\n'], " ");
+        write!(self.writer, "{line}\r\n")
+            .and_then(|()| self.writer.flush())
+            .map_err(Error::network("write to", &self.address))
+    }
+}
+
+enum Line {
+    Text(String),
+    TimedOut,
+    Closed,
+}
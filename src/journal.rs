@@ -0,0 +1,168 @@
+//! An append-only log of the game being played, written as each move is made, so a game cut off
+//! by a crash or a closed terminal can be picked up exactly where it stopped
+//!
+//! The journal is JSON lines: how the game was set up, then one line for each move and swap. It's
+//! removed once the game is over, so a journal that's still there is an interrupted game.
+
+use crate::error::Error;
+use crate::game::Player;
+use crate::record::{GameRecord, MoveRecord};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// When the journal is flushed to disk
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SyncPolicy {
+    /// fsync after every line, so even a power cut loses nothing
+    #[default]
+    Always,
+    /// Leave it to the OS, which is enough to survive the program crashing but not the machine
+    Never,
+}
+
+/// How the journaled game was started, enough to start it again the same way
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalStart {
+    /// The command line that started the game
+    pub args: Vec<String>,
+    /// In seconds since the Unix epoch
+    pub started_at: u64,
+    /// The side the human started as
+    pub human_player: Player,
+    /// The computer's blunder chance, which for the adaptive difficulty depends on the stats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blunder_chance: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<[Option<Player>; 9]>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Entry {
+    Start(JournalStart),
+    Move(MoveRecord),
+    Swap,
+}
+
+/// An interrupted game read back from its journal
+#[derive(Clone, Debug)]
+pub struct Recovered {
+    pub start: JournalStart,
+    /// The game up to the last move journaled
+    pub game: GameRecord,
+}
+
+/// The journal of the game being played
+pub struct Journal {
+    file: File,
+    path: PathBuf,
+    sync: SyncPolicy,
+}
+
+impl Journal {
+    /// The journal location, e.g. `~/.local/share/simple-tic-tac-toe/journal.jsonl` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("simple-tic-tac-toe").join("journal.jsonl"))
+    }
+
+    /// Start a journal for a new game, replacing any journal already at `path`
+    pub fn create(path: &Path, sync: SyncPolicy, start: JournalStart) -> crate::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+        }
+        let file = File::create(path).map_err(Error::io("create", path))?;
+        let mut journal = Self {
+            file,
+            path: path.to_path_buf(),
+            sync,
+        };
+        journal.append(&Entry::Start(start))?;
+        Ok(journal)
+    }
+
+    pub fn record_move(&mut self, played: &MoveRecord) -> crate::Result<()> {
+        self.append(&Entry::Move(played.clone()))
+    }
+
+    /// Record the players swapping sides under the pie rule
+    pub fn record_swap(&mut self) -> crate::Result<()> {
+        self.append(&Entry::Swap)
+    }
+
+    /// The game is over, so there's nothing to restore
+    pub fn finish(self) -> crate::Result<()> {
+        discard(&self.path)
+    }
+
+    fn append(&mut self, entry: &Entry) -> crate::Result<()> {
+        let line = serde_json::to_string(entry)? + "\n";
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(Error::io("write", &self.path))?;
+        if self.sync == SyncPolicy::Always {
+            self.file
+                .sync_data()
+                .map_err(Error::io("sync", &self.path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Read the game left in the journal at `path`, if there is one. A last line cut off partway
+/// through, as a crash while writing leaves it, is ignored
+pub fn recover(path: &Path) -> crate::Result<Option<Recovered>> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::io("read", path)(e)),
+    };
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let mut entries = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) if i + 1 == lines.len() && !text.ends_with('\n') => break,
+            Err(e) => {
+                let message = format!("bad entry on line {} of {}", i + 1, path.display());
+                return Err(Error::parse_with(message, e));
+            }
+        }
+    }
+    let mut entries = entries.into_iter();
+    let Some(Entry::Start(start)) = entries.next() else {
+        // Cut off before the game got going
+        return Ok(None);
+    };
+    let mut game = GameRecord::new(start.human_player.opponent(), Vec::new());
+    game.start = start.start;
+    for entry in entries {
+        match entry {
+            Entry::Move(played) => game.moves.push(played),
+            Entry::Swap => game.swapped = true,
+            Entry::Start(_) => {
+                return Err(Error::parse(format!(
+                    "{} has a second start line",
+                    path.display()
+                )))
+            }
+        }
+    }
+    game.final_position()?;
+    Ok(Some(Recovered { start, game }))
+}
+
+/// Remove the journal at `path`, if there is one
+pub fn discard(path: &Path) -> crate::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Error::io("remove", path)(e)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,168 @@
+//! Short strategy lessons, each a few positions with a question whose answer the engine checks
+
+use crate::ai::perfect_play_winner;
+use crate::game::GameState;
+
+/// A topic with the positions that teach it
+pub struct Lesson {
+    pub title: &'static str,
+    /// Shown before the first exercise
+    pub introduction: &'static str,
+    pub exercises: &'static [Exercise],
+}
+
+/// One position to find a move in
+pub struct Exercise {
+    /// The position in the notation of [`crate::position`]
+    pub position: &'static str,
+    pub question: &'static str,
+    pub goal: Goal,
+    /// Shown after a wrong answer
+    pub hint: &'static str,
+    /// Shown once the exercise is solved
+    pub explanation: &'static str,
+}
+
+/// What makes an answer right
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// Any move that keeps the best result with perfect play
+    BestMove,
+    /// A move that leaves the opponent the fewest replies that don't lose
+    FewestSafeReplies,
+    /// A move that stops the opponent completing a line next turn
+    Block,
+    /// A move that completes a line
+    Win,
+    /// A move that makes two threats at once
+    Fork,
+}
+
+impl Goal {
+    /// Check an answer with the engine, for the side to move in `state`
+    pub fn is_met(self, state: &GameState, square: usize) -> bool {
+        let player = state.next_player();
+        let after = state.with_move(square);
+        match self {
+            Goal::BestMove => {
+                let mut state = state.clone();
+                state.computer_player = player;
+                state
+                    .get_best_computer_moves()
+                    .iter()
+                    .any(|m| m.square == square)
+            }
+            Goal::FewestSafeReplies => {
+                let fewest = state
+                    .legal_moves()
+                    .map(|square| safe_replies(&state.with_move(square)))
+                    .min();
+                Some(safe_replies(&after)) == fewest
+            }
+            Goal::Block => state.threats(player.opponent()).contains(&square),
+            Goal::Win => after.winner() == Some(player),
+            Goal::Fork => after.threats(player).len() >= 2,
+        }
+    }
+}
+
+/// Number of moves for the side to move that don't lose with perfect play
+pub fn safe_replies(state: &GameState) -> usize {
+    let player = state.next_player();
+    state
+        .legal_moves()
+        .filter(|&square| perfect_play_winner(&state.with_move(square)) != Some(player.opponent()))
+        .count()
+}
+
+/// Every lesson, in the order they build on each other
+pub fn lessons() -> &'static [Lesson] {
+    &LESSONS
+}
+
+const LESSONS: [Lesson; 3] = [
+    Lesson {
+        title: "Openings: center and corners",
+        introduction: "Every first move ties with perfect play, but they aren't equally tricky to answer. \
+            The center and the corners are the strongest squares, since they are on the most lines.",
+        exercises: &[
+            Exercise {
+                position: ".../.../... x",
+                question: "Which first move leaves O the fewest replies that don't lose?",
+                goal: Goal::FewestSafeReplies,
+                hint: "Think about the squares on the most lines, and which of them O can answer in more ways.",
+                explanation: "After a corner, O's only safe reply is the center. Any other move loses.",
+            },
+            Exercise {
+                position: ".../.x./... o",
+                question: "X took the center. Where should O answer?",
+                goal: Goal::BestMove,
+                hint: "An edge lets X build two lines at once. Look for a square on three lines.",
+                explanation: "A corner holds. Against the center, every edge reply loses.",
+            },
+            Exercise {
+                position: "x../.../... o",
+                question: "X took a corner. Where should O answer?",
+                goal: Goal::BestMove,
+                hint: "There is only one move that doesn't lose.",
+                explanation: "Only the center holds against a corner opening.",
+            },
+        ],
+    },
+    Lesson {
+        title: "Blocking",
+        introduction: "When your opponent has two in a line with the third square open, they win next \
+            turn unless you take that square. Always check for threats before anything else.",
+        exercises: &[
+            Exercise {
+                position: "xx./.o./... o",
+                question: "X threatens to win. Where must O play?",
+                goal: Goal::Block,
+                hint: "Look along the top row.",
+                explanation: "Top Right is the only move that stops X winning next turn.",
+            },
+            Exercise {
+                position: "x.o/.x./... o",
+                question: "Where must O play now?",
+                goal: Goal::Block,
+                hint: "Diagonals count too.",
+                explanation: "Bottom Right stops the diagonal from Top Left through the center.",
+            },
+            Exercise {
+                position: "oo./xx./x.. o",
+                question: "X threatens a line, but it's O's move. What should O do?",
+                goal: Goal::Win,
+                hint: "Before blocking, check whether you can win yourself.",
+                explanation: "Completing your own line beats blocking: the game is over before X's threat matters.",
+            },
+        ],
+    },
+    Lesson {
+        title: "Forks",
+        introduction: "A fork makes two threats at once. Your opponent can only block one, so a fork \
+            wins. Making forks and stopping them before they happen is most of tic-tac-toe strategy.",
+        exercises: &[
+            Exercise {
+                position: "xox/.../..o x",
+                question: "Find the move that makes two threats at once.",
+                goal: Goal::Fork,
+                hint: "You hold two corners. A third corner can line up with both.",
+                explanation: "Bottom Left threatens both the left column and the diagonal through the center.",
+            },
+            Exercise {
+                position: "x../.../oxo x",
+                question: "Find the fork. It isn't always a corner.",
+                goal: Goal::Fork,
+                hint: "Look for a square on a line with each of your marks.",
+                explanation: "Top Middle threatens the top row and the middle column.",
+            },
+            Exercise {
+                position: "x../.o./..x o",
+                question: "X holds opposite corners. Where should O play to stop a fork?",
+                goal: Goal::BestMove,
+                hint: "Taking a corner lets X fork with the last corner. Make X answer a threat instead.",
+                explanation: "An edge makes a threat through the center that X has to block, and X's block makes no fork.",
+            },
+        ],
+    },
+];
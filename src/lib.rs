@@ -0,0 +1,81 @@
+//! The tic-tac-toe engine behind the `simple-tic-tac-toe` game, usable on its own for analysis and
+//! rendering games outside the terminal
+//!
+//! # Stability
+//!
+//! The library follows semantic versioning. While the version is 0.x, a release that breaks the
+//! public API bumps the minor version and any other release bumps the patch version. The public
+//! API is everything exported with the default features off, plus what each feature adds when
+//! it's on; the `simple-tic-tac-toe` binary and its command line aren't covered.
+//!
+//! The enums in the API, like [`Player`], [`GameStatus`], [`MoveError`](error::MoveError),
+//! [`Error`] and [`Difficulty`](ai::Difficulty), are `#[non_exhaustive]`, so matches on them need a
+//! wildcard arm and a new variant isn't a breaking change. Saved games, stats and other files
+//! written by one release can be read by later ones. Breaking changes are listed in
+//! `CHANGELOG.md`.
+
+pub mod ai;
+pub mod analysis;
+pub mod boxes;
+pub mod builder;
+pub mod chat;
+#[cfg(feature = "net")]
+pub mod client;
+pub mod config;
+pub mod csv;
+pub mod diff;
+pub mod drill;
+pub mod engine;
+pub mod error;
+pub mod explorer;
+pub mod fog;
+pub mod game;
+pub mod games;
+pub mod habit;
+pub mod handshake;
+pub mod image;
+pub mod import;
+pub mod infinite;
+#[cfg(feature = "net")]
+pub mod irc;
+pub mod journal;
+pub mod lessons;
+pub mod markdown;
+#[cfg(feature = "matrix")]
+pub mod matrix;
+#[cfg(feature = "ai-mcts")]
+pub mod mcts;
+pub mod mnk;
+pub mod nim;
+pub mod numerical;
+pub mod observer;
+pub mod odds;
+pub mod overlay;
+pub mod position;
+pub mod post;
+pub mod protocol;
+pub mod quantum;
+pub mod record;
+pub mod render;
+pub mod report;
+pub mod rl;
+pub mod saves;
+#[cfg(feature = "net")]
+pub mod server;
+pub mod session_log;
+pub mod slippery;
+pub mod solved;
+pub mod sos;
+pub mod stats;
+pub mod symmetry;
+pub mod tablebase;
+#[cfg(feature = "net")]
+pub mod telnet;
+pub mod think_time;
+pub mod tournament;
+#[cfg(feature = "net")]
+pub mod twitch;
+pub mod worker;
+
+pub use error::{Error, Result};
+pub use game::{GameState, GameStatus, Player, Selection};
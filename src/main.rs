@@ -1,288 +1,5208 @@
-use inquire::Select;
-use rand::Rng;
+use anyhow::{bail, Context};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use inquire::ui::RenderConfig;
+use inquire::validator::Validation;
+use inquire::{Confirm, CustomType, Select, Text};
+use rand::rngs::{StdRng, ThreadRng};
+use rand::{Rng, SeedableRng};
+use simple_tic_tac_toe::ai::{
+    minimax, perfect_play_winner, principal_variation, Decision, Difficulty, GameResult,
+    SearchStats,
+};
+use simple_tic_tac_toe::analysis::{analyze_loss, find_blunder, game_tree_dot, LossReason};
+use simple_tic_tac_toe::boxes::{self, DotsAndBoxes};
+use simple_tic_tac_toe::builder::GameSetup;
+use simple_tic_tac_toe::chat::{coordinate, ChatGames};
+use simple_tic_tac_toe::client::{Committed, Connection, Seat, SeatBook};
+use simple_tic_tac_toe::config::{Config, Profile, VariantConfig};
+use simple_tic_tac_toe::csv::{self, history_csv, stats_csv, Day, Filter};
+use simple_tic_tac_toe::drill::{find_drills, first_try_rate, schedule, DrillAttempt, DrillLog};
+use simple_tic_tac_toe::explorer::{self_play_game, OpeningNode, OpeningTree, Results};
+use simple_tic_tac_toe::fog::{render_view, Attempt, FogGame};
+use simple_tic_tac_toe::games::Game;
+use simple_tic_tac_toe::habit::{Habit, Punishment};
+use simple_tic_tac_toe::handshake::Capability;
+use simple_tic_tac_toe::image::{render_svg, Theme};
+use simple_tic_tac_toe::import::{import_game, Notation};
+use simple_tic_tac_toe::infinite::{InfiniteGame, Solution, MARKS};
+use simple_tic_tac_toe::irc::{IrcClient, IrcOptions, Received};
+use simple_tic_tac_toe::journal::{self, Journal, JournalStart, Recovered};
+use simple_tic_tac_toe::lessons::{lessons, Lesson};
+use simple_tic_tac_toe::markdown::game_report;
+use simple_tic_tac_toe::mnk::{self, Board, Computer, EngineKind, Rules};
+use simple_tic_tac_toe::nim::NimGame;
+use simple_tic_tac_toe::numerical::{self, NumberMove, NumericalGame, TARGET};
+use simple_tic_tac_toe::observer::{GameObserver, Hint, Observers};
+use simple_tic_tac_toe::odds::{Odds, OddsOptions};
+use simple_tic_tac_toe::overlay::Overlay;
+use simple_tic_tac_toe::position::parse_position;
+use simple_tic_tac_toe::post;
+use simple_tic_tac_toe::protocol::Session;
+use simple_tic_tac_toe::quantum::{self, Pick, QuantumGame, Solver};
+use simple_tic_tac_toe::record::{Ending, GameRecord, MoveRecord};
+use simple_tic_tac_toe::render::{
+    describe_board, render_big_board, render_board, render_grid, share_text, side_by_side, Pieces,
+};
+use simple_tic_tac_toe::report::{
+    AnalysisReport, PlayReport, PositionReport, StatsReport, TournamentReport,
+};
+use simple_tic_tac_toe::rl::{compare_with_minimax, QTable, TrainingOptions};
+use simple_tic_tac_toe::saves::{SaveDir, SaveSlot};
+use simple_tic_tac_toe::server::{self, Event, GameUpdate, Request};
+use simple_tic_tac_toe::session_log::{Answer, SessionLog};
+use simple_tic_tac_toe::slippery::{self, SlipperyGame};
+use simple_tic_tac_toe::sos::{self, Letter, SosGame, SosMove};
+use simple_tic_tac_toe::stats::{adaptive_blunder_chance, blunder_rate, Stats, StatsEntry};
+use simple_tic_tac_toe::tablebase::{self, Tablebase};
+use simple_tic_tac_toe::telnet;
+use simple_tic_tac_toe::tournament::{run_games, Tournament};
+use simple_tic_tac_toe::twitch::{write_overlay, Votes, TWITCH_SERVER};
+use simple_tic_tac_toe::{GameState, GameStatus, Player, Selection};
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::io::IsTerminal;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::Level;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-enum Player {
-    X,
-    O,
+/// Self-play games to work out `--odds` from
+const ODDS_GAMES: usize = 20_000;
+/// How often the stand-in for a human with no history blunders
+const DEFAULT_BLUNDER_RATE: f64 = 0.3;
+
+/// The frames of the spinner drawn while the computer pretends to think
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+const SPINNER_FRAME: Duration = Duration::from_millis(100);
+
+/// How often `analyze --watch` looks at its file
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The profile picked with `--profile`, set once at startup
+static PROFILE: OnceLock<Profile> = OnceLock::new();
+
+/// Fireworks going up and bursting, a frame at a time, each drawn over the last
+const FIREWORKS: [[&str; 5]; 5] = [
+    ["", "", "", "", "        |"],
+    ["", "", "", "        |", "        '"],
+    ["", "        *", "", "", ""],
+    ["", r"      \ | /", "     -- * --", r"      / | \", ""],
+    [
+        "   .   *    .   +",
+        r"  *   \ | /   .",
+        " +   -- * --  *",
+        r"  .   / | \    +",
+        "    *   .   *",
+    ],
+];
+const FIREWORKS_FRAME: Duration = Duration::from_millis(250);
+
+const WIN_BANNERS: [&str; 2] = [
+    r"
+ +-----------------+
+ |  Y O U   W I N  |
+ +-----------------+",
+    r"
+   \o/   Victory!   \o/
+    |               |
+   / \             / \",
+];
+
+const LOSS_BANNERS: [&str; 2] = [
+    r"
+ +---------------------+
+ |  G A M E   O V E R  |
+ +---------------------+",
+    r"
+   .-----------.
+   | beep boop |  The machine wins.
+   '-----------'",
+];
+
+const TIE_BANNERS: [&str; 2] = [
+    r"
+ +-----------------------+
+ |  C A T ' S   G A M E  |
+ +-----------------------+",
+    r"
+   =^.^=   Nobody wins, the cat takes it.",
+];
+
+/// Pick a random banner from the bank
+fn random_banner(banners: &[&'static str], rng: &mut impl Rng) -> &'static str {
+    banners[rng.gen_range(0..banners.len())]
+}
+
+/// Get the computer's remark about how the human lost
+fn loss_remark(reason: LossReason) -> String {
+    match reason {
+        LossReason::Fork(4) => "The center fork got you this time.".to_string(),
+        LossReason::Fork(square @ (0 | 2 | 6 | 8)) => {
+            format!(
+                "You fell for the corner fork at {}.",
+                Selection::new(square)
+            )
+        }
+        LossReason::Fork(square) => {
+            format!("An edge fork at {}, sneaky!", Selection::new(square))
+        }
+        LossReason::MissedBlock(square) => {
+            format!("You forgot to block me at {}.", Selection::new(square))
+        }
+    }
+}
+
+/// Warn about a move that loses by force, for coach mode
+fn blunder_warning(reason: LossReason) -> String {
+    match reason {
+        LossReason::Fork(square) => format!("This allows a fork at {}", Selection::new(square)),
+        LossReason::MissedBlock(square) => {
+            format!("This doesn't block me at {}", Selection::new(square))
+        }
+    }
+}
+
+/// Announce the result of a finished game, with banners and remarks unless taunts are disabled
+fn print_game_over(
+    record: &GameRecord,
+    user_player: Player,
+    taunts: bool,
+    rng: &mut impl Rng,
+) -> anyhow::Result<()> {
+    match record.ending {
+        Some(Ending::Resigned(_)) => println!("You resigned."),
+        Some(Ending::DrawAgreed) => println!("The computer accepted your draw offer."),
+        None => {}
+    }
+
+    match record.status()? {
+        GameStatus::Won(player) if player == user_player => {
+            if profile().celebrations {
+                celebrate();
+            } else if taunts {
+                println!("{}", random_banner(&WIN_BANNERS, rng));
+            }
+            println!(
+                "{}",
+                wording("Congratulations, you won!", "You won! Well done!")
+            );
+        }
+        GameStatus::Won(_) => {
+            if taunts {
+                println!("{}", random_banner(&LOSS_BANNERS, rng));
+            }
+            println!(
+                "{}",
+                wording(
+                    "You lost, better luck next time.",
+                    "The computer won. Try again!"
+                )
+            );
+            if taunts {
+                let mut start = record.position(0)?;
+                start.computer_player = user_player.opponent();
+                if let Some(reason) = analyze_loss(&start, &record.squares()) {
+                    println!("{}", loss_remark(reason));
+                }
+            }
+        }
+        GameStatus::Tie => {
+            if taunts {
+                println!("{}", random_banner(&TIE_BANNERS, rng));
+            }
+            println!("{}", wording("The game ended in a tie.", "It's a tie!"));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Set off fireworks, drawn one frame after another in place unless animations are off
+fn celebrate() {
+    let animate = std::io::stdout().is_terminal() && !profile().no_animations;
+    if !animate {
+        println!("{}", FIREWORKS[FIREWORKS.len() - 1].join("\n"));
+        return;
+    }
+    for (i, frame) in FIREWORKS.iter().enumerate() {
+        if i != 0 {
+            // Back up over the last frame to draw this one over it
+            print!("\x1b[{}A", frame.len());
+        }
+        for line in frame {
+            println!("\x1b[2K{line}");
+        }
+        std::thread::sleep(FIREWORKS_FRAME);
+    }
+}
+
+/// Print the numbers behind a computer move
+fn print_search_stats(decision: &Decision) {
+    // Games saved before depth and timing were recorded only have the node count
+    if decision.depth == 0 {
+        println!("(Searched {} positions)", decision.nodes);
+        return;
+    }
+    let moves = if decision.depth == 1 { "move" } else { "moves" };
+    println!(
+        "(Looked at {} positions up to {} {moves} ahead in {} µs, {:.0}% from the transposition table)",
+        decision.nodes,
+        decision.depth,
+        decision.elapsed_us,
+        decision.hit_rate() * 100.0
+    );
+}
+
+/// Prints the search statistics after each computer move, for `--show-search-stats`
+struct SearchStatsPrinter;
+
+impl GameObserver for SearchStatsPrinter {
+    fn on_move(&mut self, _state: &GameState, _square: usize, decision: Option<&Decision>) {
+        if let Some(decision) = decision {
+            print_search_stats(decision);
+        }
+    }
+}
+
+/// Adds each finished game to the local stats store
+struct StatsRecorder {
+    stats: Stats,
+    difficulty: Difficulty,
+    blunder_chance: Option<f64>,
+}
+
+impl GameObserver for StatsRecorder {
+    fn on_game_over(&mut self, record: &GameRecord) {
+        let entry = StatsEntry::new(
+            record.clone(),
+            record.human_player(),
+            self.difficulty,
+            self.blunder_chance,
+        );
+        if let Err(e) = entry.and_then(|entry| self.stats.append(&entry)) {
+            // Go through anyhow to print the underlying I/O error too
+            let e = anyhow::Error::from(e);
+            eprintln!("Couldn't record the game in your stats: {e:#}");
+        }
+    }
+}
+
+/// Keeps the stream overlay up to date with the game
+struct OverlayWriter(Overlay);
+
+impl OverlayWriter {
+    /// Report a failed update without stopping the game over it
+    fn report(result: simple_tic_tac_toe::Result<()>) {
+        if let Err(e) = result {
+            // Go through anyhow to print the underlying I/O error too
+            let e = anyhow::Error::from(e);
+            eprintln!("Couldn't update the stream overlay: {e:#}");
+        }
+    }
+}
+
+impl GameObserver for OverlayWriter {
+    fn on_move(&mut self, state: &GameState, square: usize, _decision: Option<&Decision>) {
+        Self::report(self.0.record_move(state, square));
+    }
+
+    fn on_game_over(&mut self, record: &GameRecord) {
+        Self::report(self.0.record_result(record));
+    }
+}
+
+/// Where the human's answers come from: the terminal, the terminal while keeping a recording for
+/// `--record`, or a recording for `--replay-input`
+enum Input {
+    Terminal,
+    Recording {
+        log: SessionLog,
+        path: PathBuf,
+    },
+    Replaying {
+        log: SessionLog,
+        /// How many of the recorded answers and decisions have been used
+        answers: usize,
+        decisions: usize,
+    },
+}
+
+impl Input {
+    /// The seed for the session's random choices
+    fn seed(&self) -> u64 {
+        match self {
+            Input::Terminal => rand::random(),
+            Input::Recording { log, .. } | Input::Replaying { log, .. } => log.seed,
+        }
+    }
+
+    /// Ask the human to pick one of `options`
+    fn select<T: Display>(&mut self, prompt: &str, options: Vec<T>) -> anyhow::Result<T> {
+        if let Some(Answer::Select { index, .. }) = self.next_answer(prompt)? {
+            let Some(choice) = options.into_iter().nth(index) else {
+                bail!("the recording picks an option that isn't offered at \"{prompt}\"");
+            };
+            println!("> {prompt} {choice}");
+            return Ok(choice);
+        }
+        let (index, choice) = if profile().number_keys {
+            select_by_number(prompt, options)?
+        } else {
+            let page_size = options.len();
+            let choice = Select::new(prompt, options)
+                .with_page_size(page_size)
+                .raw_prompt()?;
+            (choice.index, choice.value)
+        };
+        self.record(Answer::Select {
+            prompt: prompt.to_string(),
+            index,
+            label: choice.to_string(),
+        })?;
+        Ok(choice)
+    }
+
+    /// Ask the human a yes or no question, defaulting to no
+    fn confirm(&mut self, prompt: &str) -> anyhow::Result<bool> {
+        if let Some(Answer::Confirm { value, .. }) = self.next_answer(prompt)? {
+            println!("> {prompt} {}", if value { "Yes" } else { "No" });
+            return Ok(value);
+        }
+        let value = Confirm::new(prompt).with_default(false).prompt()?;
+        self.record(Answer::Confirm {
+            prompt: prompt.to_string(),
+            value,
+        })?;
+        Ok(value)
+    }
+
+    /// Keep a decision the computer made, or check it against the recording when replaying
+    fn decision(&mut self, decision: &Decision) -> anyhow::Result<()> {
+        match self {
+            Input::Terminal => Ok(()),
+            Input::Recording { log, .. } => {
+                log.decisions.push(decision.clone());
+                self.save()
+            }
+            Input::Replaying { log, decisions, .. } => {
+                let recorded = log.decisions.get(*decisions).map(|d| d.chosen);
+                *decisions += 1;
+                if recorded != Some(decision.chosen) {
+                    bail!(
+                        "the replay diverged from the recording: the computer played {} but the recording has {}",
+                        Selection::new(decision.chosen),
+                        recorded.map_or("no move".to_string(), |s| Selection::new(s).to_string())
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// When replaying, take the next recorded answer, checking it was for the same prompt
+    fn next_answer(&mut self, prompt: &str) -> anyhow::Result<Option<Answer>> {
+        let Input::Replaying { log, answers, .. } = self else {
+            return Ok(None);
+        };
+        let Some(answer) = log.answers.get(*answers) else {
+            bail!("the recording ends before \"{prompt}\"");
+        };
+        if answer.prompt() != prompt {
+            bail!(
+                "the replay diverged from the recording: asked \"{prompt}\" but the recording has \"{}\"",
+                answer.prompt()
+            );
+        }
+        *answers += 1;
+        Ok(Some(answer.clone()))
+    }
+
+    fn record(&mut self, answer: Answer) -> anyhow::Result<()> {
+        if let Input::Recording { log, .. } = self {
+            log.answers.push(answer);
+        }
+        self.save()
+    }
+
+    /// Write the recording so far, after every change so it survives a crash
+    fn save(&self) -> anyhow::Result<()> {
+        if let Input::Recording { log, path } = self {
+            log.save(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Ask for one of `options` by typing its number, for players who can't use the arrow keys
+fn select_by_number<T: Display>(prompt: &str, options: Vec<T>) -> anyhow::Result<(usize, T)> {
+    for (i, option) in options.iter().enumerate() {
+        println!("{}. {option}", i + 1);
+    }
+    let count = options.len();
+    let help = format!("Type a number from 1 to {count}");
+    let number = CustomType::<usize>::new(prompt)
+        .with_help_message(&help)
+        .with_error_message("Type one of the numbers above")
+        .with_validator(move |&number: &usize| {
+            Ok(if (1..=count).contains(&number) {
+                Validation::Valid
+            } else {
+                Validation::Invalid(format!("There are only {count} options").into())
+            })
+        })
+        .prompt()?;
+    let choice = options
+        .into_iter()
+        .nth(number - 1)
+        .expect("the number was checked against the options");
+    Ok((number - 1, choice))
+}
+
+/// The profile picked with `--profile`, or the default one
+fn profile() -> &'static Profile {
+    PROFILE.get_or_init(Profile::default)
+}
+
+/// Pick the wording of a prompt or message, the shorter one for the simple prompts profile
+fn wording(usual: &'static str, simple: &'static str) -> &'static str {
+    if profile().simple_prompts {
+        simple
+    } else {
+        usual
+    }
+}
+
+/// Show a board, in words for screen readers
+fn show_board(game: &GameState, pieces: &Pieces) {
+    if profile().screen_reader {
+        println!("{}", describe_board(game.board()));
+    } else if profile().big_board {
+        println!("{}", render_big_board(game.board(), pieces));
+    } else {
+        println!("{}", game.render(pieces));
+    }
+}
+
+/// Describe a set of squares in words, grouping them as corners or edges where possible
+fn describe_squares(squares: &[usize]) -> String {
+    const CORNERS: [usize; 4] = [0, 2, 6, 8];
+    const EDGES: [usize; 4] = [1, 3, 5, 7];
+
+    match squares {
+        [square] => Selection::new(*square).to_string(),
+        _ if squares.iter().all(|s| CORNERS.contains(s)) => "a corner".to_string(),
+        _ if squares.iter().all(|s| EDGES.contains(s)) => "an edge".to_string(),
+        _ => {
+            let names: Vec<String> = squares
+                .iter()
+                .map(|&s| Selection::new(s).to_string())
+                .collect();
+            match names.split_last() {
+                Some((last, rest)) if !rest.is_empty() => format!("{} or {last}", rest.join(", ")),
+                _ => names.concat(),
+            }
+        }
+    }
+}
+
+/// Show how the computer would reply to each of the human's possible moves
+fn print_predictions(game: &GameState) {
+    for m in game.open_squares() {
+        let next = game.with_move(m.square);
+        if next.winner().is_some() {
+            println!("If you play {m}, you win.");
+        } else if next.open_squares().is_empty() {
+            println!("If you play {m}, the game is a tie.");
+        } else {
+            let replies: Vec<usize> = next
+                .get_best_computer_moves()
+                .iter()
+                .map(|r| r.square)
+                .collect();
+            println!(
+                "If you play {m}, I will take {}.",
+                describe_squares(&replies)
+            );
+        }
+    }
+}
+
+/// Work out the odds for the human from self-play, with a stand-in blundering as often as the
+/// human has in their history
+fn build_odds(
+    stats: Option<&Stats>,
+    human: Player,
+    computer_blunder_chance: f64,
+    seed: u64,
+) -> anyhow::Result<Odds> {
+    let entries = match stats {
+        Some(stats) => stats.entries()?,
+        None => Vec::new(),
+    };
+    let human_blunder_chance = match blunder_rate(&entries)? {
+        Some(rate) => rate,
+        None => {
+            println!(
+                "There are no games in your history yet, so the odds are for a player who \
+                 blunders {}% of the time.",
+                100.0 * DEFAULT_BLUNDER_RATE
+            );
+            DEFAULT_BLUNDER_RATE
+        }
+    };
+    let options = OddsOptions {
+        human,
+        human_blunder_chance,
+        computer_blunder_chance,
+        games: ODDS_GAMES,
+        seed,
+        threads: thread_count(None),
+    };
+    tracing::info!(
+        human_blunder_chance,
+        computer_blunder_chance,
+        "working out odds"
+    );
+    Ok(with_progress(options.games, |progress| {
+        options.build(progress)
+    }))
+}
+
+/// Show how the self-play games from a position went for the human, next to the result with
+/// best play
+fn print_odds(game: &GameState, odds: &Odds) {
+    let human = odds.human();
+    let best = match perfect_play_winner(game) {
+        Some(winner) if winner == human => "you win",
+        Some(_) => "you lose",
+        None => "it's a tie",
+    };
+    let Some(results) = odds.get(game) else {
+        println!("None of the practice games got here. With best play {best}.");
+        return;
+    };
+    let (wins, losses) = if human == Player::X {
+        (results.x_wins, results.o_wins)
+    } else {
+        (results.o_wins, results.x_wins)
+    };
+    let percent = |n: u32| (100.0 * f64::from(n) / f64::from(results.games())).round();
+    println!(
+        "You win {}% of games from here at your level and lose {}%. With best play {best}.",
+        percent(wins),
+        percent(losses)
+    );
+}
+
+/// Explain a computer move by showing the score and best continuation for every candidate
+fn print_explanation(before: &GameState, chosen: usize) {
+    let mut candidates: Vec<(GameResult, usize, Vec<usize>)> = before
+        .open_squares()
+        .iter()
+        .map(|m| {
+            let after = before.with_move(m.square);
+            let result = minimax(&after, &mut SearchStats::default());
+            (result, m.square, principal_variation(&after))
+        })
+        .collect();
+    // Best moves first, with the chosen move leading its group
+    candidates.sort_by_key(|&(result, square, _)| (std::cmp::Reverse(result), square != chosen));
+
+    println!(
+        "I played {}. Here is how each option plays out:",
+        Selection::new(chosen)
+    );
+    for (result, square, line) in &candidates {
+        let mut player = before.next_player().opponent();
+        let continuation: Vec<String> = line
+            .iter()
+            .map(|&s| {
+                let who = if player == before.computer_player {
+                    "me"
+                } else {
+                    "you"
+                };
+                player = player.opponent();
+                format!("{who} {}", Selection::new(s))
+            })
+            .collect();
+        let marker = if *square == chosen { '*' } else { ' ' };
+        println!(
+            " {marker} {:<14} {:<5} {}",
+            Selection::new(*square).to_string(),
+            result.to_string(),
+            continuation.join(", ")
+        );
+    }
+
+    let best = candidates[0].0;
+    let worse = candidates.iter().filter(|(r, _, _)| *r < best).count();
+    if worse == 0 {
+        println!(
+            "Every option leads to a {best} with best play, so I picked one of them at random."
+        );
+    } else {
+        println!(
+            "Only {} of them reach a {best}; the other {worse} would have given you a better result.",
+            candidates.len() - worse
+        );
+    }
+}
+
+/// Something the human can do when it is their turn
+#[derive(Copy, Clone, Debug)]
+enum Action {
+    Move(Selection),
+    Why,
+    Swap,
+    OfferDraw,
+    Resign,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Move(selection) => write!(f, "{selection}"),
+            Action::Why => write!(f, "{}", wording("Why did you play that?", "Why?")),
+            Action::Swap => write!(
+                f,
+                "{}",
+                wording("Swap sides and take the computer's move", "Swap sides")
+            ),
+            Action::OfferDraw => write!(f, "{}", wording("Offer a draw", "Call it a tie")),
+            Action::Resign => write!(f, "{}", wording("Resign", "Give up")),
+        }
+    }
+}
+
+/// Parse a comma-separated list of squares numbered 1-9 from the top left
+fn parse_square(s: &str) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(n @ 1..=9) => Ok(n - 1),
+        _ => Err(format!("`{s}` is not a square number between 1 and 9")),
+    }
+}
+
+/// Built-in styles for drawing the pieces on the board
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum PieceStyle {
+    Ascii,
+    Emoji,
+}
+
+/// Which engine the computer uses to choose its moves
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Opponent {
+    /// Exact minimax search
+    Minimax,
+    /// A Q-table learned with the `train` subcommand
+    Rl,
+}
+
+/// How `analyze --batch` writes its results, one line per position
+#[derive(Copy, Clone, Debug, PartialEq, ValueEnum)]
+enum BatchFormat {
+    Text,
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Json,
+}
+
+/// Color themes for exported images
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum ThemeName {
+    Light,
+    Dark,
+}
+
+impl ThemeName {
+    fn theme(self) -> Theme {
+        match self {
+            ThemeName::Light => Theme::light(),
+            ThemeName::Dark => Theme::dark(),
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    play: PlayArgs,
+
+    /// Log engine activity to stderr at the given level (error, warn, info, debug, trace)
+    #[arg(long, global = true)]
+    log_level: Option<Level>,
+
+    /// Write logs as JSON instead of human-readable text
+    #[arg(long, global = true, requires = "log_level")]
+    log_json: bool,
+
+    /// Read settings from this file instead of the default config location
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Play with a bundle of settings: accessible, or one from the config file
+    #[arg(long, global = true)]
+    profile: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play a game against the computer (the default)
+    Play(PlayArgs),
+    /// Play several boards against the computer at once, moving on each in turn
+    Simul(SimulArgs),
+    /// Teach the reinforcement-learning opponent by self-play
+    Train(TrainArgs),
+    /// Manage the table of solved positions the computer looks moves up in
+    Tablebase {
+        #[command(subcommand)]
+        command: TablebaseCommand,
+    },
+    /// Evaluate a position and optionally export its game tree
+    Analyze(AnalyzeArgs),
+    /// Save a board as an SVG or PNG image
+    ExportImage(ExportImageArgs),
+    /// Step through a saved game, optionally exporting it as an animated GIF
+    Replay(ReplayArgs),
+    /// Show what the computer saw when it made a move in a saved game
+    InspectMove(InspectMoveArgs),
+    /// Set or clear the glyph and note on a move of a saved game
+    Annotate(AnnotateArgs),
+    /// Pick a game from the save directory, named saves and autosaves alike, and replay it
+    Load(LoadArgs),
+    /// Set up a position mark by mark, then play it out against the computer or analyze it
+    Setup(SetupArgs),
+    /// Join an IRC channel and play games with anyone who types `!ttt start`
+    Irc(IrcArgs),
+    /// Log in to Matrix and play games in the rooms the bot is invited to
+    Matrix(MatrixArgs),
+    /// Play a correspondence game by email or forum post, one move per message
+    Post {
+        #[command(subcommand)]
+        command: PostCommand,
+    },
+    /// Serve games as plain lines of text over TCP, for playing with `nc` or `telnet`
+    Telnet(TelnetArgs),
+    /// Host games between two people for `games` to play on
+    Serve(ServeArgs),
+    /// Play several games on a `serve` server at once, switching between them from a menu
+    Games(GamesArgs),
+    /// Create, join, play and watch games on a `serve` server a command at a time, without menus
+    Client {
+        /// The server's address and port
+        #[arg(long, default_value = "localhost:4040", global = true)]
+        server: String,
+
+        #[command(subcommand)]
+        command: ClientCommand,
+    },
+    /// Let a Twitch channel's chat vote on one side's moves against the computer
+    Twitch(TwitchArgs),
+    /// Browse how often each opening was played in your games and how those games ended
+    Explorer(ExplorerArgs),
+    /// Practice the positions from your games where you made a losing mistake
+    Drill(DrillArgs),
+    /// Describe how you usually play, see the line that beats it and find where to break the habit
+    Habit(HabitArgs),
+    /// Guess the engine's move at every turn of a perfect-play game
+    Guess(GuessArgs),
+    /// Work through interactive lessons on openings, blocking and forks
+    Learn(LearnArgs),
+    /// Play in the fog, seeing only the squares next to your own marks
+    Fog(FogArgs),
+    /// Play with gravity, where each mark falls to the bottom of the column you choose
+    Gravity(GravityArgs),
+    /// Play with only three marks each on the board, where a fourth takes away your oldest
+    Infinite(InfiniteArgs),
+    /// Play numerical tic-tac-toe, placing digits to make a line add up to 15
+    Numerical(NumericalArgs),
+    /// Play SOS, writing S or O and scoring a point for each S-O-S you complete
+    Sos(SosArgs),
+    /// Play dots and boxes, drawing lines between dots to close boxes
+    DotsAndBoxes(DotsAndBoxesArgs),
+    /// Play Nim, taking counters from heaps until someone takes the last one
+    Nim(NimArgs),
+    /// Play a rule variant set up in the config file, or list them
+    Variant(VariantArgs),
+    /// Play a party game where marks sometimes slide onto a square next to the one you pick
+    Slippery(SlipperyArgs),
+    /// Experimental: both players choose a square at once, and a square both choose is lost
+    Quantum(QuantumArgs),
+    /// Pit computer players of different strengths against each other in a round robin
+    Tournament(TournamentArgs),
+    /// Work with the record of your finished games, one game at a time
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommand,
+    },
+    /// Work with the move-by-move history of your finished games
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Read a game written down elsewhere into a saved game, for replay and the other commands
+    /// that take one
+    Import(ImportArgs),
+}
+
+#[derive(Args, Clone)]
+struct PieceArgs {
+    /// How to draw the pieces on the board
+    #[arg(long, value_enum, default_value_t = PieceStyle::Ascii)]
+    pieces: PieceStyle,
+
+    /// Custom piece (e.g. an emoji) to draw for X
+    #[arg(long)]
+    x_piece: Option<String>,
+
+    /// Custom piece (e.g. an emoji) to draw for O
+    #[arg(long)]
+    o_piece: Option<String>,
+}
+
+impl PieceArgs {
+    fn pieces(&self) -> Pieces {
+        let mut pieces = match self.pieces {
+            PieceStyle::Ascii if !profile().emoji => Pieces::ascii(),
+            PieceStyle::Ascii | PieceStyle::Emoji => Pieces::emoji(),
+        };
+        if let Some(x) = &self.x_piece {
+            pieces.x = x.clone();
+        }
+        if let Some(o) = &self.o_piece {
+            pieces.o = o.clone();
+        }
+        pieces
+    }
+}
+
+#[derive(Args, Clone)]
+struct PlayArgs {
+    #[command(flatten)]
+    pieces: PieceArgs,
+
+    /// Don't show end-of-game banners or computer remarks
+    #[arg(long)]
+    no_taunts: bool,
+
+    /// Before each of your moves, show how the computer would reply to it
+    #[arg(long)]
+    predict: bool,
+
+    /// Before each of your moves, show how often players at your level win from there, from
+    /// games the computer plays against a stand-in for you
+    #[arg(long, conflicts_with_all = ["moves", "pipe"])]
+    odds: bool,
+
+    /// How strongly the computer plays; adaptive tunes itself from your past results
+    #[arg(long, default_value_t = Difficulty::Perfect, conflicts_with_all = ["moves", "pipe"])]
+    difficulty: Difficulty,
+
+    /// Which engine the computer plays with
+    #[arg(long, value_enum, default_value_t = Opponent::Minimax, conflicts_with_all = ["moves", "pipe"])]
+    opponent: Opponent,
+
+    /// Q-table to load for `--opponent rl` instead of the one in the default location
+    #[arg(long)]
+    rl_table: Option<PathBuf>,
+
+    /// After each computer move, show how many positions it looked at and how long it took
+    #[arg(long)]
+    show_search_stats: bool,
+
+    /// Keep the board, the session's score and the last move in text files in this directory,
+    /// for a streaming program to show
+    #[arg(long, conflicts_with_all = ["moves", "pipe"])]
+    overlay: Option<PathBuf>,
+
+    /// Also serve the overlay text over HTTP on this local port
+    #[arg(long)]
+    overlay_port: Option<u16>,
+
+    /// Have the computer pause before each move as if thinking, longer for harder choices
+    #[arg(long, conflicts_with_all = ["moves", "pipe"])]
+    think_time: bool,
+
+    /// Have the computer reply at once even if the config file asks for think time
+    #[arg(long, conflicts_with = "think_time")]
+    no_think_time: bool,
+
+    /// Warn before you play a move that loses by force and let you pick another
+    #[arg(long)]
+    coach: bool,
+
+    /// Save the finished game to this file so it can be replayed or exported later
+    #[arg(long)]
+    save: Option<PathBuf>,
+
+    /// Save the finished game in the save directory under this name, for `load`
+    #[arg(long)]
+    slot: Option<String>,
+
+    /// Write the finished game up as Markdown to this file, with the settings and every move
+    #[arg(long, conflicts_with = "pipe")]
+    markdown: Option<PathBuf>,
+
+    /// Play as this side instead of being asked
+    #[arg(long)]
+    player: Option<Player>,
+
+    /// Record the seed, settings, your answers and the computer's decisions to this file, so the
+    /// session can be re-run exactly with --replay-input, e.g. for a bug report
+    #[arg(long, conflicts_with_all = ["moves", "json", "pipe"])]
+    record: Option<PathBuf>,
+
+    /// Re-run a session recorded with --record, answering every prompt from the recording
+    #[arg(long, conflicts_with_all = ["moves", "json", "pipe", "record"])]
+    replay_input: Option<PathBuf>,
+
+    /// Let the second player swap sides after the first move instead of replying to it
+    #[arg(long, conflicts_with_all = ["moves", "pipe"])]
+    pie_rule: bool,
+
+    /// Play these moves for you without prompting, as square numbers 1-9 from the top left
+    #[arg(long, value_delimiter = ',', value_parser = parse_square)]
+    moves: Vec<usize>,
+
+    /// Play non-interactively using --moves and print the result as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Read JSON commands from stdin and write JSON events to stdout, one per line, for GUI wrappers
+    #[arg(long, conflicts_with_all = ["json", "moves"])]
+    pipe: bool,
+}
+
+#[derive(Args)]
+struct SetupArgs {
+    /// Start editing from this position string, e.g. "x.o/.x./..o x", instead of an empty board
+    #[arg(long, value_parser = parse_position)]
+    position: Option<GameState>,
+
+    #[command(flatten)]
+    play: PlayArgs,
+}
+
+#[derive(Args)]
+struct IrcArgs {
+    /// Host name of the IRC server
+    #[arg(long)]
+    server: String,
+
+    #[arg(long, default_value_t = 6667)]
+    port: u16,
+
+    #[arg(long, default_value = "tictactoe")]
+    nick: String,
+
+    /// Channel to join, e.g. "#games"; can be given more than once
+    #[arg(long = "channel", required = true)]
+    channels: Vec<String>,
+
+    /// Server password, for servers that need one
+    #[arg(long)]
+    password: Option<String>,
+
+    /// Word that starts every command to the bot
+    #[arg(long, default_value = "!ttt")]
+    prefix: String,
+}
+
+#[derive(Args)]
+struct MatrixArgs {
+    /// URL of the homeserver, e.g. "https://matrix.org"
+    #[arg(long)]
+    homeserver: String,
+
+    /// User name or full Matrix ID of the bot's account
+    #[arg(long)]
+    user: String,
+
+    #[arg(long)]
+    password: String,
+
+    /// Word that starts every command to the bot
+    #[arg(long, default_value = "!ttt")]
+    prefix: String,
+}
+
+#[derive(Args)]
+struct TelnetArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "0.0.0.0")]
+    bind: String,
+
+    #[arg(long, default_value_t = 2323)]
+    port: u16,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "0.0.0.0")]
+    bind: String,
+
+    #[arg(long, default_value_t = 4040)]
+    port: u16,
+}
+
+#[derive(Args)]
+struct GamesArgs {
+    /// The server's address and port
+    #[arg(long, default_value = "localhost:4040")]
+    server: String,
+
+    /// Instead of the menu, keep watching the games on every server and say when it's your move
+    #[arg(long)]
+    poll: bool,
+
+    /// Also show a desktop notification when it's your move or a game ends
+    #[arg(long)]
+    notify: bool,
+}
+
+#[derive(Args)]
+struct TwitchArgs {
+    /// Channel whose chat plays
+    #[arg(long)]
+    channel: String,
+
+    /// Twitch account the bot logs in as
+    #[arg(long)]
+    nick: String,
+
+    /// OAuth token for the account, with or without the "oauth:" prefix
+    #[arg(long)]
+    token: String,
+
+    /// Side the chat plays
+    #[arg(long, default_value_t = Player::X)]
+    player: Player,
+
+    /// How long each vote stays open, in seconds
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    vote_seconds: u64,
+
+    /// Keep the board and the vote tally in this text file, for a stream overlay
+    #[arg(long)]
+    overlay: Option<PathBuf>,
+
+    /// Connect to this IRC server instead of Twitch's
+    #[arg(long, default_value = TWITCH_SERVER)]
+    server: String,
+
+    #[arg(long, default_value_t = 6667)]
+    port: u16,
+}
+
+#[derive(Args)]
+struct ExplorerArgs {
+    /// How many moves into each game to follow
+    #[arg(long, default_value_t = 3)]
+    depth: usize,
+
+    /// Also include this many games of the computer playing itself
+    #[arg(long, default_value_t = 0)]
+    self_play: u32,
+
+    /// How often each side blunders in the self-play games, between 0 and 1
+    #[arg(long, default_value_t = 0.3, requires = "self_play")]
+    blunder_chance: f64,
+
+    /// Print the whole tree instead of browsing it
+    #[arg(long)]
+    print: bool,
+}
+
+#[derive(Args)]
+struct DrillArgs {
+    /// How many positions to practice
+    #[arg(long, default_value_t = 5)]
+    count: usize,
+}
+
+#[derive(Args)]
+struct HabitArgs {
+    /// How you usually play, like "open center, then corners", or asked for if left out
+    habit: Option<String>,
+
+    /// The side you play with the habit instead of being asked
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct GuessArgs {
+    /// Guess the moves of this saved game instead of a new perfect-play game
+    #[arg(long)]
+    game: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct LearnArgs {
+    /// Start this lesson, counting from 1, instead of choosing from a list
+    #[arg(long)]
+    lesson: Option<NonZeroUsize>,
+}
+
+#[derive(Args)]
+struct FogArgs {
+    #[command(flatten)]
+    pieces: PieceArgs,
+
+    /// Play as this side instead of being asked
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct GravityArgs {
+    #[command(flatten)]
+    pieces: PieceArgs,
+
+    /// Number of rows and columns on the board
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u8).range(3..=8))]
+    size: u8,
+
+    /// How many marks in a row win, the board size if not given
+    #[arg(long, value_parser = clap::value_parser!(u8).range(3..=8))]
+    win_length: Option<u8>,
+
+    /// How the computer picks its moves (tablebase, alpha-beta or mcts), chosen by board size
+    /// if not given
+    #[arg(long)]
+    engine: Option<EngineKind>,
+
+    /// Don't let the computer think while you decide on your move
+    #[arg(long)]
+    no_ponder: bool,
+
+    /// Play as this side instead of being asked
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct InfiniteArgs {
+    #[command(flatten)]
+    pieces: PieceArgs,
+
+    /// Play as this side instead of being asked
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct NumericalArgs {
+    /// Play as this side, X with the odd digits or O with the even ones, instead of being asked
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct SlipperyArgs {
+    /// How often a mark slides onto a random empty square next to the one it was aimed at,
+    /// between 0 and 1
+    #[arg(long, default_value_t = 0.25)]
+    slip_chance: f64,
+
+    #[command(flatten)]
+    pieces: PieceArgs,
+
+    /// Play as this side instead of being asked, X going first
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct QuantumArgs {
+    #[command(flatten)]
+    pieces: PieceArgs,
+
+    /// Play as this side instead of being asked
+    #[arg(long)]
+    player: Option<Player>,
+
+    /// Show how often the best mix of choices picks each square before each pick
+    #[arg(long)]
+    hints: bool,
+}
+
+#[derive(Args)]
+struct TournamentArgs {
+    /// How often each entrant blunders, between 0 and 1, separated by commas
+    #[arg(long, value_delimiter = ',', default_values_t = [0.0, 0.1, 0.3, 1.0])]
+    entrants: Vec<f64>,
+
+    /// Games each entrant plays as X against each other one, and as many again as O
+    #[arg(long, default_value_t = 1000)]
+    games: usize,
+
+    /// Seed the games for reproducible results
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// How many threads to play on, one per core by default
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+
+    /// Print the standings and every pairing's results as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct SosArgs {
+    /// Number of rows and columns on the board
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u8).range(3..=8))]
+    size: u8,
+
+    /// How many letters ahead the computer looks
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=9))]
+    depth: u8,
+
+    /// Play as this side instead of being asked, X going first
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct DotsAndBoxesArgs {
+    /// Number of boxes across
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=boxes::MAX_SIZE as i64))]
+    width: u8,
+
+    /// Number of boxes down
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=boxes::MAX_SIZE as i64))]
+    height: u8,
+
+    /// Play as this side instead of being asked, X going first
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct NimArgs {
+    /// How many counters each heap starts with
+    #[arg(long, value_delimiter = ',', default_value = "3,4,5")]
+    heaps: Vec<u32>,
+
+    /// Play as this side instead of being asked, X going first
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct VariantArgs {
+    /// Name of the variant's `[variants.<name>]` table; leave out to list them
+    name: Option<String>,
+
+    #[command(flatten)]
+    pieces: PieceArgs,
+
+    /// How the computer picks its moves (tablebase, alpha-beta or mcts), chosen by board size
+    /// if not given
+    #[arg(long)]
+    engine: Option<EngineKind>,
+
+    /// Don't let the computer think while you decide on your move
+    #[arg(long)]
+    no_ponder: bool,
+
+    /// Play as this side instead of being asked, X going first
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Args)]
+struct SimulArgs {
+    #[command(flatten)]
+    pieces: PieceArgs,
+
+    /// Number of boards to play at once
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(2..=6))]
+    boards: u8,
+
+    /// Play as this side on every board instead of being asked
+    #[arg(long)]
+    player: Option<Player>,
+}
+
+#[derive(Subcommand)]
+enum TablebaseCommand {
+    /// Solve every position and save the table, so play and analysis look results up instead of
+    /// searching
+    Build {
+        /// Where to write the table instead of the default location
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum StatsCommand {
+    /// Write one row for each recorded game, with its settings and result
+    Export(ExportArgs),
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Write one row for each move of each recorded game
+    Export(ExportArgs),
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Write CSV
+    #[arg(long, required_unless_present = "json")]
+    csv: bool,
+
+    /// Write JSON instead
+    #[arg(long, conflicts_with = "csv")]
+    json: bool,
+
+    /// Where to write the export instead of standard output
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    /// Only games finished on or after this day, like 2024-05-31, in UTC
+    #[arg(long)]
+    since: Option<Day>,
+
+    /// Only games finished on or before this day
+    #[arg(long)]
+    until: Option<Day>,
+
+    /// Only games played at this difficulty
+    #[arg(long)]
+    difficulty: Option<Difficulty>,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// File holding the game: a list of moves, a position string or JSON
+    input: PathBuf,
+
+    /// Where to save the game
+    output: PathBuf,
+
+    /// Which notation the game is in (moves, position or json), guessed if not given
+    #[arg(long)]
+    notation: Option<Notation>,
+
+    /// The side you played, unless the game says
+    #[arg(long, default_value_t = Player::X)]
+    player: Player,
+}
+
+#[derive(Subcommand)]
+enum PostCommand {
+    /// Start a game saved to a file and print the first post
+    New {
+        /// Saved game file to keep the game in
+        game: PathBuf,
+
+        /// Play as this side, X by default
+        #[arg(long, default_value_t = Player::X)]
+        player: Player,
+
+        /// Replace the file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Play the move from a reply, e.g. a line like "move b2", and print the next post
+    Reply {
+        /// Saved game file the game is kept in
+        game: PathBuf,
+
+        /// File containing the reply, read from stdin if not given
+        reply: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ClientCommand {
+    /// Start a game and print the code for the opponent to join it with
+    Create {
+        /// Play as this side, X by default
+        #[arg(long, default_value_t = Player::X)]
+        player: Player,
+
+        /// Experimental: a quantum game, where both choose a square at once
+        #[arg(long)]
+        quantum: bool,
+    },
+    /// Take the open seat in a game
+    Join {
+        /// The game's code
+        code: String,
+    },
+    /// Play a game joined earlier to the end, reading moves from stdin as square numbers 1-9
+    /// from the top left
+    Play {
+        /// The game's code
+        code: String,
+
+        /// Play just this move and stop
+        #[arg(value_parser = parse_square)]
+        square: Option<usize>,
+    },
+    /// Follow a game's moves until it's over, without a seat in it
+    #[command(alias = "spectate")]
+    Watch {
+        /// The game's code
+        code: String,
+    },
+}
+
+#[derive(Args)]
+struct TrainArgs {
+    /// Number of self-play games to learn from
+    #[arg(long, default_value_t = TrainingOptions::default().episodes)]
+    episodes: u32,
+
+    /// Learning rate
+    #[arg(long, default_value_t = TrainingOptions::default().alpha)]
+    alpha: f64,
+
+    /// Chance of exploring a random move during training
+    #[arg(long, default_value_t = TrainingOptions::default().epsilon)]
+    epsilon: f64,
+
+    /// Seed the training for a reproducible table
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Where to write the Q-table instead of the default location
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// After training, play this many games against minimax from each side and report the results
+    #[arg(long)]
+    compare: Option<u32>,
+}
+
+#[derive(Args)]
+struct AnalyzeArgs {
+    /// Moves leading to the position, as square numbers 1-9 from the top left (e.g. 5,1,9)
+    #[arg(long, value_delimiter = ',', value_parser = parse_square)]
+    moves: Vec<usize>,
+
+    /// The position to analyze as a position string, e.g. "x.o/.x./..o x", instead of the moves
+    /// leading to it
+    #[arg(long, value_parser = parse_position, conflicts_with = "moves")]
+    position: Option<GameState>,
+
+    /// Evaluate every position string in this file, one per line, or in stdin for "-"
+    #[arg(long, conflicts_with_all = ["moves", "position", "dot"])]
+    batch: Option<PathBuf>,
+
+    /// Evaluate the position string in this file, and again every time the file changes, until
+    /// stopped with Ctrl-C
+    #[arg(long, conflicts_with_all = ["moves", "position", "batch", "dot"])]
+    watch: Option<PathBuf>,
+
+    /// Output format for --batch; --json is the same as --format json
+    #[arg(long, value_enum, default_value_t = BatchFormat::Text, requires = "batch", conflicts_with = "json")]
+    format: BatchFormat,
+
+    /// Write the game tree from the position to this file in Graphviz format
+    #[arg(long)]
+    dot: Option<std::path::PathBuf>,
+
+    /// Only expand the game tree this many moves deep
+    #[arg(long, requires = "dot")]
+    depth: Option<usize>,
+
+    /// Print the evaluation as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct ExportImageArgs {
+    /// Image file to write; the format is chosen from the extension (.svg or .png)
+    output: PathBuf,
+
+    /// Moves leading to the board, as square numbers 1-9 from the top left (e.g. 5,1,9)
+    #[arg(long, value_delimiter = ',', value_parser = parse_square, conflicts_with = "game")]
+    moves: Vec<usize>,
+
+    /// Saved game to take the board from
+    #[arg(long)]
+    game: Option<PathBuf>,
+
+    /// Show the board after this many moves of the saved game instead of the final board
+    #[arg(long = "move", requires = "game")]
+    move_number: Option<usize>,
+
+    /// Color theme for the image
+    #[arg(long, value_enum, default_value_t = ThemeName::Light)]
+    theme: ThemeName,
+}
+
+#[derive(Args)]
+struct InspectMoveArgs {
+    /// Saved game containing the move
+    game: PathBuf,
+
+    /// Which move of the game to inspect, counting from 1
+    move_number: NonZeroUsize,
+}
+
+#[derive(Args)]
+struct AnnotateArgs {
+    /// Saved game containing the move
+    game: PathBuf,
+
+    /// Which move of the game to annotate, counting from 1
+    move_number: NonZeroUsize,
+
+    /// A glyph like `!` or `??`, a note, or both as in `?? walks into a fork`. Leave it out to
+    /// clear the move's annotation
+    #[arg(default_value = "")]
+    text: String,
+}
+
+#[derive(Args)]
+struct LoadArgs {
+    /// The save to replay, chosen from a list if not given
+    name: Option<String>,
+
+    /// List the saves instead of replaying one
+    #[arg(long, conflicts_with = "name")]
+    list: bool,
+}
+
+#[derive(Args)]
+struct ReplayArgs {
+    /// Saved game to replay
+    game: PathBuf,
+
+    /// Write the replay as an animated GIF to this file instead of printing it
+    #[arg(long)]
+    gif: Option<PathBuf>,
+
+    /// Write the game up as Markdown to this file instead of printing it
+    #[arg(long, conflicts_with = "gif")]
+    markdown: Option<PathBuf>,
+
+    /// Ask for a glyph and note after each move, then save them to the game
+    #[arg(long, conflicts_with_all = ["gif", "markdown"])]
+    annotate: bool,
+
+    /// How long to show each move in the GIF, in milliseconds
+    #[arg(long, default_value_t = 800, requires = "gif")]
+    delay: u16,
+
+    /// Color theme for the GIF
+    #[arg(long, value_enum, default_value_t = ThemeName::Light, requires = "gif")]
+    theme: ThemeName,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    if let Some(level) = cli.log_level {
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(level)
+            .with_writer(std::io::stderr);
+        if cli.log_json {
+            subscriber.json().init();
+        } else {
+            subscriber.init();
+        }
+    }
+
+    let config = match cli.config.or_else(Config::default_path) {
+        Some(path) => Config::load(&path)?,
+        None => Config::default(),
+    };
+
+    let profile = match &cli.profile {
+        Some(name) => config.profile(name)?,
+        None => Profile::default(),
+    };
+    if profile.no_color {
+        inquire::set_global_render_config(RenderConfig::empty());
+    }
+    PROFILE
+        .set(profile)
+        .expect("the profile is only set at startup");
+
+    if let Some(path) = Tablebase::default_path().filter(|path| path.exists()) {
+        tablebase::install(Tablebase::load(&path)?);
+        tracing::info!(path = %path.display(), "using tablebase");
+    }
+
+    match cli.command {
+        Some(Command::Play(args)) => play(args, &config),
+        Some(Command::Simul(args)) => simul(args),
+        Some(Command::Train(args)) => train(args),
+        Some(Command::Tablebase { command }) => tablebase_command(command),
+        Some(Command::Analyze(args)) => analyze(args),
+        Some(Command::ExportImage(args)) => export_image(args),
+        Some(Command::Replay(args)) => replay(args),
+        Some(Command::InspectMove(args)) => inspect_move(args),
+        Some(Command::Annotate(args)) => annotate(args),
+        Some(Command::Load(args)) => load(args),
+        Some(Command::Setup(args)) => setup(args, &config),
+        Some(Command::Irc(args)) => run_irc(args),
+        Some(Command::Matrix(args)) => run_matrix(args),
+        Some(Command::Post { command }) => post(command),
+        Some(Command::Twitch(args)) => run_twitch(args),
+        Some(Command::Explorer(args)) => explorer(args),
+        Some(Command::Drill(args)) => drill(args),
+        Some(Command::Habit(args)) => habit(args),
+        Some(Command::Guess(args)) => guess(args),
+        Some(Command::Learn(args)) => learn(args),
+        Some(Command::Fog(args)) => fog(args),
+        Some(Command::Gravity(args)) => gravity(args),
+        Some(Command::Infinite(args)) => infinite(args),
+        Some(Command::Numerical(args)) => numerical(args),
+        Some(Command::Sos(args)) => play_sos(args),
+        Some(Command::DotsAndBoxes(args)) => dots_and_boxes(args),
+        Some(Command::Nim(args)) => nim(args),
+        Some(Command::Variant(args)) => variant(args, &config),
+        Some(Command::Slippery(args)) => slippery(args),
+        Some(Command::Quantum(args)) => quantum(args),
+        Some(Command::Tournament(args)) => tournament(args),
+        Some(Command::Stats {
+            command: StatsCommand::Export(args),
+        }) => export_records(args, false),
+        Some(Command::History {
+            command: HistoryCommand::Export(args),
+        }) => export_records(args, true),
+        Some(Command::Import(args)) => import(args),
+        Some(Command::Telnet(args)) => Ok(telnet::serve(&format!("{}:{}", args.bind, args.port))?),
+        Some(Command::Serve(args)) => Ok(server::serve(
+            &format!("{}:{}", args.bind, args.port),
+            &config.server,
+        )?),
+        Some(Command::Games(args)) => games(args),
+        Some(Command::Client { server, command }) => client(&server, command),
+        None => play(cli.play, &config),
+    }
+}
+
+fn simul(args: SimulArgs) -> anyhow::Result<()> {
+    let pieces = args.pieces.pieces();
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    let game = GameState::builder()
+        .human_player(user_player)
+        .build()?
+        .state;
+    let mut games = vec![game; args.boards.into()];
+
+    // The computer opens on every board before the human's first pass when it plays X
+    for (i, game) in games.iter_mut().enumerate() {
+        if game.next_player() == game.computer_player {
+            let selection = game.get_random_computer_move();
+            println!("Board {}: computer moved to {selection}", i + 1);
+            game.apply_move(selection.square);
+        }
+    }
+
+    while games.iter().any(|g| g.status() == GameStatus::InProgress) {
+        for i in 0..games.len() {
+            if games[i].status() != GameStatus::InProgress {
+                continue;
+            }
+            print_boards(&games, &pieces);
+
+            let selection = Select::new(
+                &format!("Board {}: where will you move?", i + 1),
+                games[i].open_squares(),
+            )
+            .with_page_size(9)
+            .prompt()?;
+            let game = &mut games[i];
+            game.apply_move(selection.square);
+
+            if game.status() == GameStatus::InProgress {
+                let reply = game.get_random_computer_move();
+                println!("Board {}: computer moved to {reply}", i + 1);
+                game.apply_move(reply.square);
+            }
+        }
+    }
+
+    print_boards(&games, &pieces);
+    let (mut wins, mut losses, mut ties) = (0, 0, 0);
+    for (i, game) in games.iter().enumerate() {
+        let result = match game.status() {
+            GameStatus::Won(player) if player == user_player => {
+                wins += 1;
+                "you won"
+            }
+            GameStatus::Won(_) => {
+                losses += 1;
+                "you lost"
+            }
+            _ => {
+                ties += 1;
+                "tie"
+            }
+        };
+        println!("Board {}: {result}", i + 1);
+    }
+    println!("Simul finished: {wins} won, {losses} lost, {ties} tied.");
+    Ok(())
+}
+
+/// Print every board of a simul next to each other, labelled with their numbers
+fn print_boards(games: &[GameState], pieces: &Pieces) {
+    let blocks: Vec<String> = games
+        .iter()
+        .enumerate()
+        .map(|(i, game)| {
+            let label = match game.status() {
+                GameStatus::InProgress => format!("Board {}", i + 1),
+                _ => format!("Board {} (over)", i + 1),
+            };
+            format!("{label}\n{}", game.render(pieces))
+        })
+        .collect();
+    println!("{}", side_by_side(&blocks, 4));
+}
+
+fn tablebase_command(command: TablebaseCommand) -> anyhow::Result<()> {
+    match command {
+        TablebaseCommand::Build { output } => {
+            let Some(path) = output.or_else(Tablebase::default_path) else {
+                bail!("couldn't find a data directory, pass --output");
+            };
+            let table = Tablebase::build();
+            table.save(&path)?;
+            println!(
+                "Solved {} positions, saved to {}",
+                table.len(),
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn train(args: TrainArgs) -> anyhow::Result<()> {
+    let Some(path) = args.output.or_else(QTable::default_path) else {
+        bail!("couldn't find a data directory, pass --output");
+    };
+    if !(0.0..=1.0).contains(&args.epsilon) {
+        bail!("--epsilon must be between 0 and 1");
+    }
+
+    let table = QTable::train(&TrainingOptions {
+        episodes: args.episodes,
+        alpha: args.alpha,
+        epsilon: args.epsilon,
+        seed: args.seed,
+    });
+    table.save(&path)?;
+    println!(
+        "Learned values for {} positions from {} games, saved to {}",
+        table.len(),
+        args.episodes,
+        path.display()
+    );
+
+    if let Some(games) = args.compare {
+        for side in [Player::X, Player::O] {
+            let tally = compare_with_minimax(&table, side, games);
+            println!(
+                "As {side} against minimax: {} won, {} lost, {} tied",
+                tally.wins, tally.losses, tally.ties
+            );
+        }
+    }
+    Ok(())
+}
+
+fn analyze(args: AnalyzeArgs) -> anyhow::Result<()> {
+    if let Some(path) = &args.batch {
+        let format = if args.json {
+            BatchFormat::Json
+        } else {
+            args.format
+        };
+        return analyze_batch(path, format);
+    }
+    if let Some(path) = &args.watch {
+        return analyze_watch(path, args.json);
+    }
+
+    let mut state = match args.position {
+        Some(state) => state,
+        None => GameState::from_moves(Player::X, &args.moves)?,
+    };
+    // Evaluate from the point of view of the side to move
+    state.computer_player = state.next_player();
+
+    if let Some(path) = &args.dot {
+        std::fs::write(path, game_tree_dot(&state, args.depth))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        if !args.json {
+            println!("Wrote game tree to {}", path.display());
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string(&AnalysisReport::from(&state))?);
+        return Ok(());
+    }
+
+    print_analysis(&state);
+    Ok(())
+}
+
+/// Evaluate a file of position strings, skipping blank lines and `#` comments. Lines that aren't
+/// valid positions are reported in the output instead of stopping the batch
+fn analyze_batch(path: &Path, format: BatchFormat) -> anyhow::Result<()> {
+    use std::io::{BufRead, Write};
+
+    let reader: Box<dyn BufRead> = if path == Path::new("-") {
+        Box::new(std::io::stdin().lock())
+    } else {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Box::new(std::io::BufReader::new(file))
+    };
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    if format == BatchFormat::Csv {
+        writeln!(
+            out,
+            "line,position,status,winner_with_best_play,best_moves,error"
+        )?;
+    }
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.with_context(|| format!("failed to read {}", path.display()))?;
+        let input = line.trim();
+        if input.is_empty() || input.starts_with('#') {
+            continue;
+        }
+        let number = index + 1;
+        let report = parse_position(input).map(|state| AnalysisReport::from(&state));
+        match (format, report) {
+            (BatchFormat::Json, Ok(report)) => {
+                serde_json::to_writer(&mut out, &report)?;
+                writeln!(out)?;
+            }
+            (BatchFormat::Json, Err(e)) => {
+                let error =
+                    serde_json::json!({ "line": number, "input": input, "error": e.to_string() });
+                writeln!(out, "{error}")?;
+            }
+            (BatchFormat::Csv, Ok(report)) => {
+                let (status, _) = status_and_evaluation(&report);
+                let winner = report
+                    .winner_with_best_play
+                    .map(|p| p.to_string())
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "{number},{},{status},{winner},{},",
+                    report.position.notation,
+                    square_numbers(&report.best_moves)
+                )?;
+            }
+            (BatchFormat::Csv, Err(e)) => {
+                writeln!(
+                    out,
+                    "{number},{},,,,{}",
+                    csv::quote(input),
+                    csv::quote(&e.to_string())
+                )?;
+            }
+            (BatchFormat::Text, Ok(report)) => {
+                let (_, evaluation) = status_and_evaluation(&report);
+                write!(out, "{}: {evaluation}", report.position.notation)?;
+                if !report.best_moves.is_empty() {
+                    write!(out, ", best moves {}", square_numbers(&report.best_moves))?;
+                }
+                writeln!(out)?;
+            }
+            (BatchFormat::Text, Err(e)) => writeln!(out, "line {number}: {e}")?,
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Evaluate the first position string in a file whenever its contents change, skipping blank lines
+/// and `#` comments like the batch input. A bad position or a missing file is reported and
+/// watched until it's fixed
+fn analyze_watch(path: &Path, json: bool) -> anyhow::Result<()> {
+    if !json {
+        println!("Watching {}, press Ctrl-C to stop", path.display());
+    }
+    let mut last = None;
+    loop {
+        // Polling is plenty for a file this small, and works the same on every platform
+        let contents = match std::fs::read_to_string(path) {
+            Ok(text) => Ok(text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(format!(
+                "{} doesn't exist yet, waiting for it",
+                path.display()
+            )),
+            Err(e) => Err(format!("failed to read {}: {e}", path.display())),
+        };
+        if last.as_ref() != Some(&contents) {
+            let position = contents.as_ref().map_err(Clone::clone).and_then(|text| {
+                let line = text
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty() && !line.starts_with('#'))
+                    .ok_or_else(|| format!("{} has no position in it", path.display()))?;
+                parse_position(line).map_err(|e| format!("{line}: {e}"))
+            });
+            match (position, json) {
+                (Ok(state), true) => {
+                    println!("{}", serde_json::to_string(&AnalysisReport::from(&state))?)
+                }
+                (Ok(state), false) => {
+                    println!();
+                    print_analysis(&state);
+                }
+                (Err(e), true) => println!("{}", serde_json::json!({ "error": e })),
+                (Err(e), false) => println!("\n{e}"),
+            }
+            last = Some(contents);
+        }
+        std::thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// The status column of the batch CSV and a short evaluation in words
+fn status_and_evaluation(report: &AnalysisReport) -> (&'static str, String) {
+    match (report.position.status, report.winner_with_best_play) {
+        (GameStatus::Won(winner), _) => ("won", format!("{winner} has won")),
+        (GameStatus::Tie, _) => ("tie", "the game ended in a tie".to_string()),
+        (_, Some(winner)) => ("in_progress", format!("{winner} wins with best play")),
+        (_, None) => ("in_progress", "tie with best play".to_string()),
+    }
+}
+
+/// Squares as the 1-9 numbers used on the command line, separated by spaces
+fn square_numbers(squares: &[usize]) -> String {
+    let numbers: Vec<String> = squares.iter().map(|s| (s + 1).to_string()).collect();
+    numbers.join(" ")
+}
+
+/// Print who wins the position with best play and the best moves for the side to move
+fn print_analysis(state: &GameState) {
+    println!("{state}");
+    match state.winner() {
+        Some(winner) => println!("{winner} has won."),
+        None if state.open_squares().is_empty() => println!("The game ended in a tie."),
+        None => {
+            match perfect_play_winner(state) {
+                Some(winner) => println!(
+                    "{} to move. {winner} wins with best play.",
+                    state.next_player()
+                ),
+                None => println!(
+                    "{} to move. The game is a tie with best play.",
+                    state.next_player()
+                ),
+            }
+            let best: Vec<usize> = state
+                .get_best_computer_moves()
+                .iter()
+                .map(|m| m.square)
+                .collect();
+            println!("Best moves: {}", describe_squares(&best));
+        }
+    }
+}
+
+fn export_image(args: ExportImageArgs) -> anyhow::Result<()> {
+    let state = match &args.game {
+        Some(path) => {
+            let record = GameRecord::load(path)?;
+            match args.move_number {
+                Some(n) => record.position(n)?,
+                None => record.final_position()?,
+            }
+        }
+        None => GameState::from_moves(Player::X, &args.moves)?,
+    };
+
+    write_image(&state, &args.theme.theme(), &args.output)?;
+    println!("Wrote {}", args.output.display());
+    Ok(())
+}
+
+/// Write the board to an image file in the format given by the file extension
+fn write_image(state: &GameState, theme: &Theme, path: &Path) -> anyhow::Result<()> {
+    let data = match path.extension().and_then(|e| e.to_str()) {
+        Some("svg") => render_svg(state, theme).into_bytes(),
+        #[cfg(feature = "png")]
+        Some("png") => simple_tic_tac_toe::image::render_png(state, theme)?,
+        #[cfg(not(feature = "png"))]
+        Some("png") => bail!("PNG export requires building with the `png` feature"),
+        _ => bail!("unsupported image format, use a .svg or .png file name"),
+    };
+    std::fs::write(path, data).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn inspect_move(args: InspectMoveArgs) -> anyhow::Result<()> {
+    let record = GameRecord::load(&args.game)?;
+    let index = args.move_number.get() - 1;
+    let Some(played) = record.moves.get(index) else {
+        bail!("the game only has {} moves", record.moves.len());
+    };
+    let square = played.square;
+    let before = record.position(index)?;
+    let mover = before.next_player();
+
+    println!("Position before move {}:", args.move_number);
+    println!("{before}");
+    print_move_details(played);
+    if mover != before.computer_player {
+        println!("{mover} (human) played {}.", Selection::new(square));
+        return Ok(());
+    }
+    println!("{mover} (computer) played {}.", Selection::new(square));
+
+    let Some(decision) = record.decision(index) else {
+        println!("No decision data was recorded for this move.");
+        return Ok(());
+    };
+    // Search the position again so changes to the engine since the game show up
+    let current = before.evaluate_computer_moves(&mut SearchStats::default());
+
+    print_search_stats(decision);
+    println!("Candidates:");
+    for candidate in &decision.candidates {
+        let marker = if candidate.square == decision.chosen {
+            '*'
+        } else {
+            ' '
+        };
+        let now = current
+            .iter()
+            .find(|c| c.square == candidate.square)
+            .map(|c| c.result);
+        let note = match now {
+            Some(result) if result != candidate.result => format!(" (engine now says {result})"),
+            _ => String::new(),
+        };
+        println!(
+            " {marker} {:<14} {}{note}",
+            Selection::new(candidate.square).to_string(),
+            candidate.result
+        );
+    }
+    Ok(())
+}
+
+fn annotate(args: AnnotateArgs) -> anyhow::Result<()> {
+    let mut record = GameRecord::load(&args.game)?;
+    let moves = record.moves.len();
+    let Some(played) = record.moves.get_mut(args.move_number.get() - 1) else {
+        bail!("the game only has {moves} moves");
+    };
+    played.annotate(&args.text);
+    record.save(&args.game)?;
+    Ok(())
+}
+
+fn save_dir() -> anyhow::Result<SaveDir> {
+    let Some(dir) = SaveDir::default_dir() else {
+        bail!("couldn't find a data directory to keep saves in");
+    };
+    Ok(SaveDir::new(&dir))
+}
+
+fn load(args: LoadArgs) -> anyhow::Result<()> {
+    let saves = save_dir()?;
+    let path = match args.name {
+        Some(name) => {
+            let path = saves.path(&name)?;
+            if !path.exists() {
+                bail!("there is no save named `{name}`, see `load --list`");
+            }
+            path
+        }
+        None => {
+            let slots = saves.slots()?;
+            if args.list {
+                for slot in &slots {
+                    println!("{}", slot.preview());
+                }
+                return Ok(());
+            }
+            if slots.is_empty() {
+                bail!("there are no saved games yet, play one with `play --slot NAME`");
+            }
+            let previews = slots.iter().map(SaveSlot::preview).collect();
+            let chosen = Select::new("Which game?", previews).raw_prompt()?;
+            slots[chosen.index].path.clone()
+        }
+    };
+    replay(ReplayArgs {
+        game: path,
+        gif: None,
+        markdown: None,
+        annotate: false,
+        delay: 800,
+        theme: ThemeName::Light,
+    })
+}
+
+/// The think time, evaluation and annotation saved with a move, whichever it has
+fn print_move_details(played: &MoveRecord) {
+    if let Some(ms) = played.think_time {
+        println!("Took {:.1} seconds.", ms as f64 / 1000.0);
+    }
+    match played.evaluation {
+        Some(GameResult::Tie) => println!("With best play afterwards: a tie."),
+        Some(result) => println!(
+            "With best play afterwards: a {result} for {}.",
+            played.player
+        ),
+        None => {}
+    }
+    if let Some(annotation) = &played.annotation {
+        println!("Note: {annotation}");
+    }
+}
+
+fn replay(args: ReplayArgs) -> anyhow::Result<()> {
+    let mut record = GameRecord::load(&args.game)?;
+    let positions = record.positions()?;
+
+    if let Some(path) = args.gif {
+        write_gif(&positions, &args.theme.theme(), args.delay, &path)?;
+        println!("Wrote {}", path.display());
+        return Ok(());
+    }
+    if let Some(path) = args.markdown {
+        write_markdown(&record, &[], &path)?;
+        return Ok(());
+    }
+
+    for (i, (played, state)) in record.moves.iter_mut().zip(&positions[1..]).enumerate() {
+        println!(
+            "Move {}: {} to {}{}",
+            i + 1,
+            played.player,
+            Selection::new(played.square),
+            played
+                .glyph
+                .map_or(String::new(), |glyph| glyph.to_string())
+        );
+        println!("{state}");
+        print_move_details(played);
+        if args.annotate {
+            let current = [
+                played.glyph.map(|glyph| glyph.to_string()),
+                played.annotation.clone(),
+            ];
+            let current = current.into_iter().flatten().collect::<Vec<_>>().join(" ");
+            let text = Text::new(&format!("Annotation for move {}:", i + 1))
+                .with_initial_value(&current)
+                .with_help_message("a glyph like ?? and a note, either, or blank for none")
+                .prompt()?;
+            played.annotate(&text);
+        }
+        if i == 0 && record.swapped {
+            println!("The players swapped sides.");
+        }
+    }
+    match record.ending {
+        Some(Ending::Resigned(player)) => println!("{player} resigned."),
+        Some(Ending::DrawAgreed) => println!("The players agreed to a draw."),
+        None => {}
+    }
+    if args.annotate {
+        record.save(&args.game)?;
+        println!("Saved the annotations to {}", args.game.display());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "gif")]
+fn write_gif(
+    positions: &[GameState],
+    theme: &Theme,
+    delay: u16,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let data = simple_tic_tac_toe::image::render_gif(positions, theme, delay)?;
+    std::fs::write(path, data).with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(not(feature = "gif"))]
+fn write_gif(_: &[GameState], _: &Theme, _: u16, _: &Path) -> anyhow::Result<()> {
+    bail!("GIF export requires building with the `gif` feature")
+}
+
+/// Copy the share summary to the clipboard, printing it instead if there is no clipboard
+fn share(text: &str) {
+    #[cfg(feature = "clipboard")]
+    match arboard::Clipboard::new().and_then(|mut c| c.set_text(text)) {
+        Ok(()) => {
+            println!("Copied to the clipboard!");
+            return;
+        }
+        Err(e) => println!("Couldn't use the clipboard ({e}), here it is to copy by hand:"),
+    }
+
+    println!("{text}");
+}
+
+/// A choice in the board editor
+#[derive(Clone)]
+enum SetupAction {
+    /// Change what's on a square, showing what's there now
+    Square(usize, Option<Player>),
+    Play,
+    Analyze,
+    Quit,
+}
+
+impl Display for SetupAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SetupAction::Square(square, Some(player)) => {
+                write!(f, "{} ({player})", Selection::new(*square))
+            }
+            SetupAction::Square(square, None) => write!(f, "{} (empty)", Selection::new(*square)),
+            SetupAction::Play => write!(f, "Play it out against the computer"),
+            SetupAction::Analyze => write!(f, "Analyze the position"),
+            SetupAction::Quit => write!(f, "Quit"),
+        }
+    }
+}
+
+/// How long to wait for the game server to answer a request
+const SERVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long `games --poll` waits before connecting again to a server it lost
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// A game followed by `games`, with the last state the server sent
+struct FollowedGame {
+    seat: Seat,
+    update: Option<GameUpdate>,
+}
+
+impl FollowedGame {
+    fn is_over(&self) -> bool {
+        self.update
+            .as_ref()
+            .is_some_and(|update| update.result() != GameStatus::InProgress)
+    }
+
+    /// Where the game stands for the human
+    fn standing(&self) -> &'static str {
+        let player = self.seat.player;
+        let Some(update) = &self.update else {
+            return "waiting for the server";
+        };
+        match update.result() {
+            GameStatus::Won(winner) if winner == player => "you won",
+            GameStatus::Won(_) => "you lost",
+            GameStatus::Tie => "a tie",
+            _ if update.waiting => "waiting for an opponent to join",
+            _ if update.is_turn_of(player) => "your move",
+            _ => "their move",
+        }
+    }
+}
+
+impl Display for FollowedGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, you're {}: {}",
+            self.seat.code,
+            self.seat.player,
+            self.standing()
+        )
+    }
+}
+
+/// A choice in the `games` menu
+enum GamesAction {
+    Open(usize, String),
+    New,
+    Join,
+    Refresh,
+    Quit,
+}
+
+impl Display for GamesAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GamesAction::Open(_, label) => write!(f, "{label}"),
+            GamesAction::New => write!(f, "Start a new game"),
+            GamesAction::Join => write!(f, "Join a game by its code"),
+            GamesAction::Refresh => write!(f, "Check for moves"),
+            GamesAction::Quit => write!(f, "Quit"),
+        }
+    }
+}
+
+/// The games followed on one server, and the connection to it
+struct GamesClient {
+    server: String,
+    connection: Connection,
+    events: Receiver<Event>,
+    games: Vec<FollowedGame>,
+    book: SeatBook,
+    /// Seats on other servers, kept as they are
+    other_seats: Vec<Seat>,
+    /// Whether notices also go to the desktop
+    desktop: bool,
+}
+
+impl GamesClient {
+    /// Connect and rejoin every game still being played on the server
+    fn connect(server: &str, desktop: bool) -> anyhow::Result<Self> {
+        let Some(path) = SeatBook::default_path() else {
+            bail!("couldn't find a data directory to keep the games in");
+        };
+        let book = SeatBook::new(&path);
+        let (other_seats, seats): (Vec<Seat>, Vec<Seat>) = book
+            .seats()?
+            .into_iter()
+            .partition(|seat| seat.server != server);
+        let (connection, events) = Connection::connect(server)?;
+        let mut client = Self {
+            server: server.to_string(),
+            connection,
+            events,
+            games: Vec::new(),
+            book,
+            other_seats,
+            desktop,
+        };
+        for seat in &seats {
+            client.connection.send(&Request::Rejoin {
+                code: seat.code.clone(),
+                token: seat.token.clone(),
+            })?;
+        }
+        client.games = seats
+            .into_iter()
+            .map(|seat| FollowedGame { seat, update: None })
+            .collect();
+        // Wait for the server to answer about each game, so the menu starts out right
+        while client.games.iter().any(|game| game.update.is_none()) {
+            client.next_event(None)?;
+        }
+        Ok(client)
+    }
+
+    /// Take in an event. Unless it's about `looking_at`, tell the human when it's their move or
+    /// a game ends
+    fn apply(&mut self, event: &Event, looking_at: Option<&str>) {
+        match event {
+            Event::Joined {
+                code,
+                token,
+                player,
+            } if !self.games.iter().any(|game| &game.seat.code == code) => {
+                self.games.push(FollowedGame {
+                    seat: Seat {
+                        server: self.server.clone(),
+                        code: code.clone(),
+                        token: token.clone(),
+                        player: *player,
+                        committed: None,
+                    },
+                    update: None,
+                });
+            }
+            Event::State(update) => {
+                let Some(game) = self
+                    .games
+                    .iter_mut()
+                    .find(|game| game.seat.code == update.code)
+                else {
+                    return;
+                };
+                let player = game.seat.player;
+                let was_waiting = game
+                    .update
+                    .as_ref()
+                    .is_none_or(|old| !old.is_turn_of(player));
+                let was_over = game.is_over();
+                game.update = Some(update.clone());
+                if looking_at == Some(update.code.as_str()) {
+                    return;
+                }
+                let notice = if was_waiting && update.is_turn_of(player) {
+                    format!("It's your move in game {}.", update.code)
+                } else if !was_over && game.is_over() {
+                    format!("Game {} is over, {}.", update.code, game.standing())
+                } else {
+                    return;
+                };
+                println!("\x07{notice}");
+                if self.desktop {
+                    desktop_notification(&notice);
+                }
+            }
+            Event::Error {
+                code: Some(code), ..
+            } => {
+                // A game the server doesn't know any more, most likely since it restarted
+                if let Some(i) = self
+                    .games
+                    .iter()
+                    .position(|game| &game.seat.code == code && game.update.is_none())
+                {
+                    println!("Game {code} is no longer on the server.");
+                    self.games.remove(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Take in everything the server has sent so far
+    fn drain(&mut self, looking_at: Option<&str>) -> anyhow::Result<()> {
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => self.apply(&event, looking_at),
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => bail!("the server closed the connection"),
+            }
+        }
+    }
+
+    /// Wait for the next event and take it in
+    fn next_event(&mut self, looking_at: Option<&str>) -> anyhow::Result<Event> {
+        let event = match self.events.recv_timeout(SERVER_TIMEOUT) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => bail!("the server didn't answer"),
+            Err(RecvTimeoutError::Disconnected) => bail!("the server closed the connection"),
+        };
+        self.apply(&event, looking_at);
+        Ok(event)
+    }
+
+    /// Take in events until every game is over
+    fn listen(&mut self) -> anyhow::Result<()> {
+        while !self.games.iter().all(FollowedGame::is_over) {
+            let Ok(event) = self.events.recv() else {
+                bail!("the server closed the connection");
+            };
+            self.apply(&event, None);
+        }
+        Ok(())
+    }
+
+    /// Wait for the event `answer` picks out, taking in the rest on the way
+    fn wait(
+        &mut self,
+        looking_at: Option<&str>,
+        answer: impl Fn(&Event) -> bool,
+    ) -> anyhow::Result<Event> {
+        loop {
+            let event = self.next_event(looking_at)?;
+            if answer(&event) {
+                return Ok(event);
+            }
+            if let Event::Error {
+                code: None,
+                message,
+            } = &event
+            {
+                bail!("the server turned the request down: {message}");
+            }
+        }
+    }
+
+    /// Remember the games still being played for next time
+    fn save(&self) -> anyhow::Result<()> {
+        let mut seats = self.other_seats.clone();
+        seats.extend(
+            self.games
+                .iter()
+                .filter(|game| !game.is_over())
+                .map(|game| game.seat.clone()),
+        );
+        Ok(self.book.save(&seats)?)
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        let player = Select::new("Play X or O?", vec![Player::X, Player::O]).prompt()?;
+        self.connection.send(&Request::Create {
+            player: Some(player),
+            quantum: false,
+        })?;
+        if let Event::Joined { code, .. } =
+            self.wait(None, |event| matches!(event, Event::Joined { .. }))?
+        {
+            println!("Started game {code}. Tell your opponent to join it with that code.");
+        }
+        self.save()
+    }
+
+    fn join_game(&mut self) -> anyhow::Result<()> {
+        let code = Text::new("Game code:")
+            .prompt()?
+            .trim()
+            .to_ascii_uppercase();
+        self.connection
+            .send(&Request::Join { code: code.clone() })?;
+        match self.wait(None, |event| match event {
+            Event::Joined { code: joined, .. } => *joined == code,
+            Event::Error { code: failed, .. } => failed.as_ref() == Some(&code),
+            _ => false,
+        })? {
+            Event::Error { message, .. } => println!("Couldn't join: {message}."),
+            _ => println!("Joined game {code}."),
+        }
+        self.save()
+    }
+
+    /// Show a game, and play a move in it if it's the human's turn
+    fn open(&mut self, index: usize) -> anyhow::Result<()> {
+        let seat = self.games[index].seat.clone();
+        let Some(update) = self.games[index].update.clone() else {
+            return Ok(());
+        };
+        println!(
+            "{}",
+            GameState::from_board(seat.player.opponent(), update.board)?
+        );
+        println!("{}", self.games[index]);
+        if update.quantum.is_some() {
+            println!("Quantum games are played with `client play {}`.", seat.code);
+            return Ok(());
+        }
+        if !update.is_turn_of(seat.player) {
+            return Ok(());
+        }
+        let mut options: Vec<Option<Selection>> = update
+            .board
+            .iter()
+            .enumerate()
+            .filter(|(_, mark)| mark.is_none())
+            .map(|(square, _)| Some(Selection::new(square)))
+            .collect();
+        options.push(None);
+        let labels: Vec<String> = options
+            .iter()
+            .map(|option| option.map_or("Back".to_string(), |selection| selection.to_string()))
+            .collect();
+        let chosen = Select::new("Where will you move?", labels).raw_prompt()?;
+        let Some(selection) = options[chosen.index] else {
+            return Ok(());
+        };
+        self.connection.send(&Request::Move {
+            code: seat.code.clone(),
+            token: seat.token.clone(),
+            square: selection.square,
+        })?;
+        let code = seat.code.as_str();
+        let answer = self.wait(Some(code), |event| match event {
+            Event::State(update) => update.code == code,
+            Event::Error { code: failed, .. } => failed.as_deref() == Some(code),
+            _ => false,
+        })?;
+        match answer {
+            Event::Error { message, .. } => println!("The move wasn't played: {message}."),
+            _ => println!("{}", self.games[index]),
+        }
+        self.save()
+    }
+}
+
+#[cfg(feature = "notify")]
+fn desktop_notification(text: &str) {
+    let shown = notify_rust::Notification::new()
+        .summary("Tic-tac-toe")
+        .body(text)
+        .show();
+    if let Err(e) = shown {
+        tracing::warn!(error = %e, "couldn't show a desktop notification");
+    }
+}
+
+#[cfg(not(feature = "notify"))]
+fn desktop_notification(_: &str) {}
+
+/// Follow every game joined on a server, switching between them from a menu, or with `--poll`
+/// watch the games on every server without one
+fn games(args: GamesArgs) -> anyhow::Result<()> {
+    if args.notify && !cfg!(feature = "notify") {
+        bail!("desktop notifications require building with the `notify` feature");
+    }
+    if args.poll {
+        return poll_games(args.notify);
+    }
+    let mut client = GamesClient::connect(&args.server, args.notify)?;
+    client.save()?;
+    loop {
+        client.drain(None)?;
+        let mut actions: Vec<GamesAction> = client
+            .games
+            .iter()
+            .enumerate()
+            .map(|(i, game)| GamesAction::Open(i, game.to_string()))
+            .collect();
+        actions.extend([
+            GamesAction::New,
+            GamesAction::Join,
+            GamesAction::Refresh,
+            GamesAction::Quit,
+        ]);
+        match Select::new("Games:", actions).prompt()? {
+            GamesAction::Open(i, _) => client.open(i)?,
+            GamesAction::New => client.start_game()?,
+            GamesAction::Join => client.join_game()?,
+            GamesAction::Refresh => {}
+            GamesAction::Quit => return client.save(),
+        }
+    }
+}
+
+/// Watch every game still being played, one thread for each server, until they're all over
+fn poll_games(desktop: bool) -> anyhow::Result<()> {
+    let Some(path) = SeatBook::default_path() else {
+        bail!("couldn't find a data directory to keep the games in");
+    };
+    let mut servers: Vec<String> = SeatBook::new(&path)
+        .seats()?
+        .into_iter()
+        .map(|seat| seat.server)
+        .collect();
+    servers.sort();
+    servers.dedup();
+    if servers.is_empty() {
+        bail!("there are no games to watch, start or join one with `games`");
+    }
+    let watchers: Vec<_> = servers
+        .into_iter()
+        .map(|server| std::thread::spawn(move || watch_server(&server, desktop)))
+        .collect();
+    for watcher in watchers {
+        let _ = watcher.join();
+    }
+    Ok(())
+}
+
+/// Watch the games on one server, connecting again whenever the connection is lost
+fn watch_server(server: &str, desktop: bool) {
+    loop {
+        let result = GamesClient::connect(server, desktop).and_then(|mut client| {
+            println!("Watching {} games on {server}.", client.games.len());
+            client.listen()
+        });
+        match result {
+            Ok(()) => {
+                println!("Every game on {server} is over.");
+                return;
+            }
+            Err(e) => eprintln!("Lost {server}: {e:#}. Trying again in a while."),
+        }
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Carry out one `client` command against the server at `server`
+fn client(server: &str, command: ClientCommand) -> anyhow::Result<()> {
+    let Some(path) = SeatBook::default_path() else {
+        bail!("couldn't find a data directory to keep the games in");
+    };
+    let book = SeatBook::new(&path);
+    let (mut connection, events) = Connection::connect(server)?;
+    match command {
+        ClientCommand::Create { player, quantum } => {
+            if quantum
+                && !connection
+                    .agreed()
+                    .variants
+                    .iter()
+                    .any(|name| name == "quantum")
+            {
+                bail!("the server at {server} doesn't host quantum games");
+            }
+            connection.send(&Request::Create {
+                player: Some(player),
+                quantum,
+            })?;
+            let seat = take_seat(&events, server, None)?;
+            println!(
+                "Started game {}, playing {}. Your opponent joins it with `client join {}`.",
+                seat.code, seat.player, seat.code
+            );
+            remember_seat(&book, seat)
+        }
+        ClientCommand::Join { code } => {
+            let code = code.trim().to_ascii_uppercase();
+            connection.send(&Request::Join { code: code.clone() })?;
+            let seat = take_seat(&events, server, Some(&code))?;
+            println!("Joined game {code}, playing {}.", seat.player);
+            remember_seat(&book, seat)
+        }
+        ClientCommand::Play { code, square } => {
+            let code = code.trim().to_ascii_uppercase();
+            let Some(seat) = book
+                .seats()?
+                .into_iter()
+                .find(|seat| seat.server == server && seat.code == code)
+            else {
+                bail!("you don't have a seat in game {code} on {server}, join it first");
+            };
+            connection.send(&Request::Rejoin {
+                code: code.clone(),
+                token: seat.token.clone(),
+            })?;
+            let mut seat = seat;
+            let over = play_remote(&mut connection, &events, &book, &mut seat, square)?;
+            if over {
+                forget_seat(&book, &seat)?;
+            }
+            Ok(())
+        }
+        ClientCommand::Watch { code } => {
+            let code = code.trim().to_ascii_uppercase();
+            if !connection.agreed().has(Capability::Spectating) {
+                bail!("the server at {server} is too old to watch games on");
+            }
+            connection.send(&Request::Watch { code: code.clone() })?;
+            loop {
+                let Ok(event) = events.recv() else {
+                    bail!("the server closed the connection");
+                };
+                match event {
+                    Event::State(update) if update.code == code => {
+                        println!("{}", render_update(&update));
+                        let standing = match update.result() {
+                            GameStatus::Won(winner) if update.resigned.is_some() => {
+                                format!("{} resigned, {winner} won", winner.opponent())
+                            }
+                            GameStatus::Won(winner) => format!("{winner} won"),
+                            GameStatus::Tie => "a tie".to_string(),
+                            _ if update.waiting => "waiting for a second player".to_string(),
+                            _ => format!("{} to move", update.next_player),
+                        };
+                        println!("{code}: {standing}");
+                        if update.result() != GameStatus::InProgress {
+                            return Ok(());
+                        }
+                    }
+                    Event::Error { message, .. } => bail!("couldn't watch game {code}: {message}"),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Wait for the server to hand out a seat, in the game `code` if it's given
+fn take_seat(events: &Receiver<Event>, server: &str, code: Option<&str>) -> anyhow::Result<Seat> {
+    loop {
+        match events.recv_timeout(SERVER_TIMEOUT) {
+            Ok(Event::Joined {
+                code: joined,
+                token,
+                player,
+            }) if code.is_none_or(|code| code == joined) => {
+                return Ok(Seat {
+                    server: server.to_string(),
+                    code: joined,
+                    token,
+                    player,
+                    committed: None,
+                })
+            }
+            Ok(Event::Error { message, .. }) => {
+                bail!("the server turned the request down: {message}")
+            }
+            Ok(_) => {}
+            Err(RecvTimeoutError::Timeout) => bail!("the server didn't answer"),
+            Err(RecvTimeoutError::Disconnected) => bail!("the server closed the connection"),
+        }
+    }
+}
+
+/// Keep a seat for `client play` and `games` to find
+fn remember_seat(book: &SeatBook, seat: Seat) -> anyhow::Result<()> {
+    let mut seats = book.seats()?;
+    seats.push(seat);
+    Ok(book.save(&seats)?)
+}
+
+fn forget_seat(book: &SeatBook, seat: &Seat) -> anyhow::Result<()> {
+    let mut seats = book.seats()?;
+    seats.retain(|kept| !same_game(kept, seat));
+    Ok(book.save(&seats)?)
+}
+
+/// Keep what's changed about a seat, like a square committed to
+fn update_seat(book: &SeatBook, seat: &Seat) -> anyhow::Result<()> {
+    let mut seats = book.seats()?;
+    for kept in &mut seats {
+        if same_game(kept, seat) {
+            *kept = seat.clone();
+        }
+    }
+    Ok(book.save(&seats)?)
+}
+
+fn same_game(a: &Seat, b: &Seat) -> bool {
+    a.server == b.server && a.code == b.code
+}
+
+/// Draw the board from a game server, with `#` on the squares lost in a quantum game
+fn render_update(update: &GameUpdate) -> String {
+    let pieces = Pieces::default();
+    let lost = update.quantum.as_ref().map_or(&[][..], |pick| &pick.lost);
+    let cells: Vec<&str> = (0..9)
+        .map(|square| {
+            if lost.contains(&square) {
+                "#"
+            } else {
+                pieces.get(update.board[square])
+            }
+        })
+        .collect();
+    render_grid(&cells, 3, pieces.cell_width())
+}
+
+/// Play moves from the seat until the game is over, or just `square` if it's given. In a quantum
+/// game the square is committed to, and revealed once the opponent has committed too. Returns
+/// whether the game is over, and stops early if stdin runs out
+fn play_remote(
+    connection: &mut Connection,
+    events: &Receiver<Event>,
+    book: &SeatBook,
+    seat: &mut Seat,
+    square: Option<usize>,
+) -> anyhow::Result<bool> {
+    use std::io::Write;
+
+    let mut lines = std::io::stdin().lines();
+    let mut last: Option<GameUpdate> = None;
+    let mut played = false;
+    loop {
+        let Ok(event) = events.recv() else {
+            bail!("the server closed the connection");
+        };
+        match event {
+            Event::State(update) if update.code == seat.code => {
+                let pick = update.quantum.clone();
+                if seat.committed.is_some()
+                    && pick
+                        .as_ref()
+                        .is_none_or(|pick| !pick.committed.contains(&seat.player))
+                {
+                    // The pick the square was committed to is over
+                    seat.committed = None;
+                    update_seat(book, seat)?;
+                }
+                println!("{}", render_update(&update));
+                let our_move = update.is_turn_of(seat.player);
+                let game = FollowedGame {
+                    seat: seat.clone(),
+                    update: Some(update.clone()),
+                };
+                println!("{game}");
+                last = Some(update);
+                if game.is_over() {
+                    return Ok(true);
+                }
+                if our_move && pick.is_some_and(|pick| pick.committed.contains(&seat.player)) {
+                    let Some(committed) = seat.committed.clone() else {
+                        bail!(
+                            "the square committed to in game {} wasn't kept, so it can't be revealed",
+                            seat.code
+                        );
+                    };
+                    println!(
+                        "Both sides have committed, revealing {}.",
+                        Selection::new(committed.square)
+                    );
+                    connection.send(&Request::Reveal {
+                        code: seat.code.clone(),
+                        token: seat.token.clone(),
+                        square: committed.square,
+                        nonce: committed.nonce,
+                    })?;
+                    continue;
+                }
+                if played {
+                    return Ok(false);
+                }
+                if !our_move {
+                    if square.is_some() {
+                        bail!("it isn't your move in game {}", seat.code);
+                    }
+                    continue;
+                }
+            }
+            // Until the first state the error is about rejoining, which there's no getting past
+            Event::Error { message, .. } if square.is_some() || last.is_none() => {
+                bail!("the server turned the request down: {message}")
+            }
+            Event::Error { message, .. } => {
+                println!("The move wasn't played: {message}.");
+                // Ask for another move, unless the error was about something else
+                if !last
+                    .as_ref()
+                    .is_some_and(|update| update.is_turn_of(seat.player))
+                {
+                    continue;
+                }
+            }
+            _ => continue,
+        }
+        let Some(update) = &last else { continue };
+        let open: Vec<usize> = (0..9)
+            .filter(|&square| {
+                update.board[square].is_none()
+                    && update
+                        .quantum
+                        .as_ref()
+                        .is_none_or(|pick| !pick.lost.contains(&square))
+            })
+            .collect();
+        let square = match square {
+            Some(square) if open.contains(&square) => {
+                played = true;
+                square
+            }
+            Some(square) => bail!("{} can't be chosen", Selection::new(square)),
+            None => loop {
+                print!("Your move (1-9): ");
+                std::io::stdout().flush()?;
+                let Some(line) = lines.next() else {
+                    return Ok(false);
+                };
+                match parse_square(&line?) {
+                    Ok(square) if open.contains(&square) => break square,
+                    Ok(square) => println!("{} can't be chosen.", Selection::new(square)),
+                    Err(e) => println!("{e}."),
+                }
+            },
+        };
+        if update.quantum.is_some() {
+            let nonce = quantum::new_nonce();
+            let commitment = quantum::commitment(square, &nonce);
+            // Kept before it's sent, so a crash can't leave a commitment nobody can reveal
+            seat.committed = Some(Committed { square, nonce });
+            update_seat(book, seat)?;
+            connection.send(&Request::Commit {
+                code: seat.code.clone(),
+                token: seat.token.clone(),
+                commitment,
+            })?;
+        } else {
+            connection.send(&Request::Move {
+                code: seat.code.clone(),
+                token: seat.token.clone(),
+                square,
+            })?;
+        }
+    }
+}
+
+fn import(args: ImportArgs) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(&args.input)
+        .with_context(|| format!("failed to read {}", args.input.display()))?;
+    let record = import_game(&text, args.notation, args.player)
+        .with_context(|| format!("couldn't import {}", args.input.display()))?;
+    record.save(&args.output)?;
+    println!(
+        "Saved a game of {} moves to {}",
+        record.moves.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// Write the stats store out as CSV, a row per game or with `moves` a row per move, or as JSON
+fn export_records(args: ExportArgs, moves: bool) -> anyhow::Result<()> {
+    let Some(path) = Stats::default_path() else {
+        bail!("couldn't find a data directory to read the stats from");
+    };
+    let entries = Stats::new(&path).entries()?;
+    let filter = Filter {
+        since: args.since,
+        until: args.until,
+        difficulty: args.difficulty,
+    };
+    let export = if args.json {
+        serde_json::to_string(&StatsReport::new(&entries, &filter, moves)?)? + "\n"
+    } else if moves {
+        history_csv(&entries, &filter)?
+    } else {
+        stats_csv(&entries, &filter)
+    };
+    match args.output {
+        Some(path) => {
+            std::fs::write(&path, export)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!("Wrote {}", path.display());
+        }
+        None => print!("{export}"),
+    }
+    Ok(())
+}
+
+/// Present the positions of past mistakes until the right move is found in each
+fn drill(args: DrillArgs) -> anyhow::Result<()> {
+    let entries = match Stats::default_path() {
+        Some(path) => Stats::new(&path).entries()?,
+        None => Vec::new(),
+    };
+    let mut drills = find_drills(&entries)?;
+    if drills.is_empty() {
+        println!("There are no losing mistakes in your recorded games to practice. Well played!");
+        return Ok(());
+    }
+    let log = DrillLog::default_path().map(|path| DrillLog::new(&path));
+    let earlier = match &log {
+        Some(log) => log.attempts()?,
+        None => Vec::new(),
+    };
+    schedule(&mut drills, &earlier);
+    drills.truncate(args.count);
+
+    let mut attempts = Vec::new();
+    for (i, drill) in drills.iter().enumerate() {
+        let state = drill.state()?;
+        println!("Drill {} of {}:", i + 1, drills.len());
+        println!("{state}");
+        println!(
+            "You played {} here in one of your games and lost. Find the best move for {}.",
+            Selection::new(drill.blunder),
+            state.next_player()
+        );
+        let best: Vec<usize> = state
+            .get_best_computer_moves()
+            .iter()
+            .map(|m| m.square)
+            .collect();
+        let mut tries = 0;
+        loop {
+            tries += 1;
+            let choice = Select::new("Your move:", state.open_squares()).prompt()?;
+            if best.contains(&choice.square) {
+                println!("Correct!");
+                break;
+            }
+            match find_blunder(&state, choice.square) {
+                Some(reason) => println!("{}. Try again.", blunder_warning(reason)),
+                None => println!("That doesn't lose, but there's a better move. Try again."),
+            }
+        }
+        let attempt = DrillAttempt::new(&drill.position, tries);
+        if let Some(log) = &log {
+            log.append(&attempt)?;
+        }
+        attempts.push(attempt);
+    }
+
+    let first_try = attempts.iter().filter(|a| a.tries == 1).count();
+    println!(
+        "You found the best move first try in {first_try} of {} positions.",
+        attempts.len()
+    );
+    if let (Some(before), Some(now)) = (first_try_rate(&earlier), first_try_rate(&attempts)) {
+        println!(
+            "First-try rate: {:.0}% this session, {:.0}% in your {} earlier drills.",
+            now * 100.0,
+            before * 100.0,
+            earlier.len()
+        );
+    }
+    Ok(())
+}
+
+/// Play out the engine's best line against a habit, stopping where the habit goes wrong for the
+/// user to find the move that holds
+fn habit(args: HabitArgs) -> anyhow::Result<()> {
+    let text = match args.habit {
+        Some(text) => text,
+        None => Text::new("How do you usually play?")
+            .with_help_message("e.g. open center, then block, corners")
+            .prompt()?,
+    };
+    let habit: Habit = text.parse()?;
+    println!("Your habit: {habit}");
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Do you play it as X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    let punishment = Punishment::new(&habit, user_player);
+
+    let mut state = GameState::new(user_player.opponent());
+    for (ply, &square) in punishment.moves.iter().enumerate() {
+        let mistake = punishment.mistake.as_ref().filter(|m| m.ply == ply);
+        if let Some(mistake) = mistake {
+            println!("{state}");
+            println!("Here your habit throws the game away. Where should you play instead?");
+            loop {
+                let choice = Select::new("Your move:", state.open_squares()).prompt()?;
+                if mistake.better.contains(&choice.square) {
+                    println!("Correct! That holds the draw.");
+                    break;
+                }
+                match find_blunder(&state, choice.square) {
+                    Some(reason) => println!("{}. Try again.", blunder_warning(reason)),
+                    None => println!("That loses too. Try again."),
+                }
+            }
+            print!("Your habit plays {}", Selection::new(square));
+            match mistake.reason {
+                Some(reason) => println!(". {}.", blunder_warning(reason)),
+                None => println!(", and loses by force."),
+            }
+        } else if state.next_player() == user_player {
+            println!("Your habit plays {}.", Selection::new(square));
+        } else {
+            println!("I play {}.", Selection::new(square));
+        }
+        state.apply_move(square);
+    }
+    println!("{state}");
+
+    match (punishment.status, &punishment.mistake) {
+        (GameStatus::Won(winner), Some(mistake)) if winner != user_player => {
+            let better: Vec<String> = mistake
+                .better
+                .iter()
+                .map(|&square| Selection::new(square).to_string())
+                .collect();
+            println!(
+                "Your habit loses in {} moves. Break it at your move {}: play {} instead of {}.",
+                punishment.moves.len(),
+                mistake.ply / 2 + 1,
+                better.join(" or "),
+                Selection::new(mistake.played)
+            );
+        }
+        (GameStatus::Won(winner), _) if winner != user_player => {
+            println!("Your habit loses in {} moves.", punishment.moves.len());
+        }
+        _ => println!("Your habit holds up: the best I can do against it is a draw."),
+    }
+    Ok(())
+}
+
+/// Step through a game, asking for a guess before each move and scoring the guesses against the
+/// engine's evaluation
+fn guess(args: GuessArgs) -> anyhow::Result<()> {
+    let record = match &args.game {
+        Some(path) => GameRecord::load(path)?,
+        None => self_play_game(0.0, &mut rand::thread_rng()),
+    };
+    let positions = record.positions()?;
+
+    let mut as_good = 0;
+    let mut exact = 0;
+    for (i, (played, before)) in record.squares().into_iter().zip(&positions).enumerate() {
+        println!("Move {}:", i + 1);
+        println!("{before}");
+        let choice = Select::new(
+            &format!("What will {} play?", before.next_player()),
+            before.open_squares(),
+        )
+        .prompt()?;
+
+        let mut evaluated = before.clone();
+        evaluated.computer_player = evaluated.next_player();
+        let candidates = evaluated.evaluate_computer_moves(&mut SearchStats::default());
+        let result_of = |square: usize| {
+            candidates
+                .iter()
+                .find(|c| c.square == square)
+                .map(|c| c.result)
+                .expect("every open square is evaluated")
+        };
+        let best = result_of(played);
+        let guessed = result_of(choice.square);
+        let outcome = |result: GameResult| match result {
+            GameResult::Tie => "a tie".to_string(),
+            result => format!("a {result} for {}", before.next_player()),
+        };
+        if choice.square == played {
+            exact += 1;
+            as_good += 1;
+            println!("Right, {} played {}.", before.next_player(), choice);
+        } else if guessed > best {
+            as_good += 1;
+            println!(
+                "{} played {}, leading to {}. {choice} is even better, leading to {}.",
+                before.next_player(),
+                Selection::new(played),
+                outcome(best),
+                outcome(guessed)
+            );
+        } else if guessed == best {
+            as_good += 1;
+            println!(
+                "{} played {}, but {choice} is just as good: both lead to {}.",
+                before.next_player(),
+                Selection::new(played),
+                outcome(best)
+            );
+        } else {
+            println!(
+                "{} played {}, leading to {}. {choice} leads to {}.",
+                before.next_player(),
+                Selection::new(played),
+                outcome(best),
+                outcome(guessed)
+            );
+        }
+    }
+    println!("{}", positions[positions.len() - 1]);
+    println!(
+        "You matched the move played {exact} of {} times, and {as_good} of your guesses were at least as good.",
+        record.moves.len()
+    );
+    Ok(())
+}
+
+/// Choose lessons from a menu, or go straight to one, until the user quits
+fn learn(args: LearnArgs) -> anyhow::Result<()> {
+    let lessons = lessons();
+    if let Some(number) = args.lesson {
+        let Some(lesson) = lessons.get(number.get() - 1) else {
+            bail!("there are only {} lessons", lessons.len());
+        };
+        return run_lesson(lesson);
+    }
+
+    let mut titles: Vec<String> = lessons
+        .iter()
+        .enumerate()
+        .map(|(i, lesson)| format!("{}. {}", i + 1, lesson.title))
+        .collect();
+    titles.push("Quit".to_string());
+    loop {
+        let page_size = titles.len();
+        let choice = Select::new("Which lesson would you like?", titles.clone())
+            .with_page_size(page_size)
+            .raw_prompt()?;
+        match lessons.get(choice.index) {
+            Some(lesson) => run_lesson(lesson)?,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Ask each exercise of a lesson until it's answered right
+fn run_lesson(lesson: &Lesson) -> anyhow::Result<()> {
+    println!("{}", lesson.title);
+    println!("{}", lesson.introduction);
+    let mut first_try = 0;
+    for (i, exercise) in lesson.exercises.iter().enumerate() {
+        let state = parse_position(exercise.position)?;
+        println!();
+        println!("Exercise {} of {}:", i + 1, lesson.exercises.len());
+        println!("{state}");
+        println!("{}", exercise.question);
+        let mut tries = 0;
+        loop {
+            tries += 1;
+            let choice = Select::new(
+                &format!("Your move as {}:", state.next_player()),
+                state.open_squares(),
+            )
+            .prompt()?;
+            if exercise.goal.is_met(&state, choice.square) {
+                println!("Correct! {}", exercise.explanation);
+                break;
+            }
+            println!("Not quite. {}", exercise.hint);
+        }
+        if tries == 1 {
+            first_try += 1;
+        }
+    }
+    println!(
+        "Lesson complete: {first_try} of {} exercises right on the first try.",
+        lesson.exercises.len()
+    );
+    Ok(())
+}
+
+/// Play a fog of war game against the computer, showing the human only what they can see
+fn fog(args: FogArgs) -> anyhow::Result<()> {
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    let mut turns = FogTurns {
+        pieces: args.pieces.pieces(),
+        user_player,
+        rng: rand::thread_rng(),
+    };
+    play_turns(
+        FogGame::new(user_player.opponent()),
+        user_player,
+        &mut turns,
+    )
+}
+
+/// Fog of war, where the human only sees their own marks and the ones they've run into
+struct FogTurns {
+    pieces: Pieces,
+    user_player: Player,
+    rng: ThreadRng,
+}
+
+impl Turns<FogGame> for FogTurns {
+    fn show(&self, game: &FogGame) -> String {
+        render_view(&game.view(self.user_player), &self.pieces)
+    }
+
+    fn show_end(&self, game: &FogGame) -> String {
+        render_board(game.board(), &self.pieces)
+    }
+
+    fn ask(&mut self, game: &FogGame) -> anyhow::Result<usize> {
+        let squares: Vec<Selection> = game
+            .candidate_squares()
+            .into_iter()
+            .map(Selection::new)
+            .collect();
+        Ok(Select::new("Where will you try to move?", squares)
+            .prompt()?
+            .square)
+    }
+
+    fn decide(&mut self, game: &FogGame) -> Option<usize> {
+        Some(game.decide_computer_move(&mut self.rng))
+    }
+
+    fn play(&mut self, game: &mut FogGame, square: usize, human: bool) -> anyhow::Result<()> {
+        match (game.attempt(square)?, human) {
+            (Attempt::Placed, true) => println!("You moved to {}.", Selection::new(square)),
+            (Attempt::Blocked, true) => println!(
+                "{} was already taken, so your turn is wasted.",
+                Selection::new(square)
+            ),
+            (Attempt::Placed, false) => println!("The computer moved."),
+            (Attempt::Blocked, false) => println!(
+                "The computer ran into your mark at {} and lost its turn.",
+                Selection::new(square)
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Play quantum tic-tac-toe against the computer, which chooses from the best mix of squares
+///
+/// Both sides choose at once instead of taking turns, so this doesn't go through [`play_turns`]
+fn quantum(args: QuantumArgs) -> anyhow::Result<()> {
+    let pieces = args.pieces.pieces();
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    let mut game = QuantumGame::new();
+    let mut solver = Solver::new();
+    let mut rng = rand::thread_rng();
+    println!(
+        "You and the computer choose a square at the same time. If you both choose the same one, it's lost to both of you and you choose again."
+    );
+    while game.status() == GameStatus::InProgress {
+        println!("{}", render_quantum(&game, &pieces));
+        // Chosen before the human's choice so it can't depend on it
+        let computer = solver.choose(&game, user_player.opponent(), &mut rng);
+        if args.hints {
+            let mix: Vec<String> = solver
+                .strategy(&game, user_player)
+                .into_iter()
+                .filter(|&(_, chance)| chance > 0.005)
+                .map(|(square, chance)| {
+                    format!("{} {:.0}%", Selection::new(square), chance * 100.0)
+                })
+                .collect();
+            println!("The best mix: {}.", mix.join(", "));
+        }
+        let squares: Vec<Selection> = game
+            .open_squares()
+            .into_iter()
+            .map(Selection::new)
+            .collect();
+        let human = Select::new("Which square will you choose?", squares)
+            .prompt()?
+            .square;
+        let (x, o) = match user_player {
+            Player::X => (human, computer),
+            _ => (computer, human),
+        };
+        match game.pick(x, o)? {
+            Pick::Placed => println!(
+                "You chose {} and the computer chose {}.",
+                Selection::new(human),
+                Selection::new(computer)
+            ),
+            Pick::Collided => println!(
+                "You both chose {}, so it's lost. Choose again.",
+                Selection::new(human)
+            ),
+        }
+    }
+    println!("{}", render_quantum(&game, &pieces));
+    print_result(game.status(), user_player);
+    Ok(())
+}
+
+/// Draw a quantum game, with `#` on the squares lost to collisions
+fn render_quantum(game: &QuantumGame, pieces: &Pieces) -> String {
+    let cells: Vec<&str> = (0..9)
+        .map(|square| {
+            if game.is_lost(square) {
+                "#"
+            } else {
+                pieces.get(game.board()[square])
+            }
+        })
+        .collect();
+    render_grid(&cells, 3, pieces.cell_width())
+}
+
+/// Play slippery tic-tac-toe against the computer, which plays for the best odds
+///
+/// Where a mark lands is down to chance rather than the move, so this doesn't go through
+/// [`play_turns`]
+fn slippery(args: SlipperyArgs) -> anyhow::Result<()> {
+    let pieces = args.pieces.pieces();
+    let mut game = SlipperyGame::new(args.slip_chance)?;
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    let mut search = slippery::Search::new(&game);
+    let mut rng = rand::thread_rng();
+    println!(
+        "Careful: each mark has a {:.0}% chance of sliding onto an empty square next to where you aim it.",
+        game.slip_chance() * 100.0
+    );
+    while game.status() == GameStatus::InProgress {
+        let square = if game.next_player == user_player {
+            println!("{}", render_board(game.board(), &pieces));
+            let squares: Vec<Selection> = game
+                .open_squares()
+                .into_iter()
+                .map(Selection::new)
+                .collect();
+            Select::new("Where will you aim?", squares).prompt()?.square
+        } else {
+            search
+                .decide(&game, &mut rng)
+                .expect("a game in progress has an open square")
+        };
+        let landed = game.play(square, &mut rng)?;
+        let who = if game.next_player == user_player {
+            "The computer's mark"
+        } else {
+            "Your mark"
+        };
+        if landed == square {
+            println!("{who} landed on {}.", Selection::new(landed));
+        } else {
+            println!(
+                "{who} slipped from {} to {}!",
+                Selection::new(square),
+                Selection::new(landed)
+            );
+        }
+    }
+    println!("{}", render_board(game.board(), &pieces));
+    print_result(game.status(), user_player);
+    Ok(())
+}
+/// Play a game with gravity against the computer, which solves the board as it goes
+fn gravity(args: GravityArgs) -> anyhow::Result<()> {
+    let pieces = args.pieces.pieces();
+    let size = usize::from(args.size);
+    let win_length = args.win_length.map_or(size, usize::from);
+    let board = Board::new(Rules::new(size, size, win_length)?.with_gravity(true));
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    let computer = Computer::new(board.rules(), args.engine)?;
+    println!("Get {win_length} in a row. Marks fall to the bottom of the column you pick.");
+    play_board(
+        board,
+        computer,
+        user_player,
+        &pieces,
+        false,
+        !args.no_ponder,
+    )
+}
+
+/// Play a variant from the config file, or list them when none is named
+fn variant(args: VariantArgs, config: &Config) -> anyhow::Result<()> {
+    let Some(name) = args.name else {
+        if config.variants.is_empty() {
+            println!("No variants are set up. Add a [variants.<name>] table to the config file.");
+        }
+        for (name, variant) in &config.variants {
+            println!("{name}: {}", describe_variant(variant));
+        }
+        return Ok(());
+    };
+    let Some(variant) = config.variants.get(&name) else {
+        bail!("there is no variant called `{name}` in the config file");
+    };
+    let board = Board::new(variant.rules()?);
+    let computer = Computer::new(board.rules(), args.engine)?;
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    println!("{name}: {}", describe_variant(variant));
+    play_board(
+        board,
+        computer,
+        user_player,
+        &args.pieces.pieces(),
+        variant.pie_rule,
+        !args.no_ponder,
+    )
+}
+
+/// The rules of a variant in a few words, e.g. `4x4, 3 in a row loses, gravity`
+fn describe_variant(variant: &VariantConfig) -> String {
+    let mut parts = vec![
+        format!("{}x{}", variant.width, variant.height),
+        format!(
+            "{} in a row {}",
+            variant.win_length,
+            if variant.misere { "loses" } else { "wins" }
+        ),
+    ];
+    for (on, name) in [
+        (variant.gravity, "gravity"),
+        (variant.wrap, "lines wrap around the edges"),
+        (variant.pie_rule, "pie rule"),
+    ] {
+        if on {
+            parts.push(name.to_string());
+        }
+    }
+    parts.join(", ")
+}
+
+/// Play a game on a board of any size against the computer, which ponders on the player's time
+/// if `ponder` is set. Under the pie rule the second player may take over the first player's
+/// mark after one move
+fn play_board(
+    board: Board,
+    mut computer: Computer,
+    user_player: Player,
+    pieces: &Pieces,
+    pie_rule: bool,
+    ponder: bool,
+) -> anyhow::Result<()> {
+    tracing::info!(engine = %computer.engine(), "computer engine");
+    let rules = *board.rules();
+    let cache =
+        mnk::Search::cache_path(&rules).filter(|_| computer.engine() == EngineKind::AlphaBeta);
+    if let Some(path) = &cache {
+        let search = mnk::Search::load(path, &rules)?;
+        tracing::info!(path = %path.display(), positions = search.len(), "loaded search table");
+        computer = computer.with_search(search);
+    }
+    let mut turns = BoardTurns {
+        computer,
+        pieces,
+        pie_rule,
+        ponder,
+        rng: rand::thread_rng(),
+    };
+    play_turns(board, user_player, &mut turns)?;
+    if let Some(path) = &cache {
+        turns.computer.search().save(path, &rules)?;
+    }
+    Ok(())
+}
+
+/// A board of any size, with squares named by row and column
+struct BoardTurns<'a> {
+    computer: Computer,
+    pieces: &'a Pieces,
+    pie_rule: bool,
+    ponder: bool,
+    rng: ThreadRng,
+}
+
+impl Turns<Board> for BoardTurns<'_> {
+    fn show(&self, board: &Board) -> String {
+        board.render(self.pieces)
+    }
+
+    fn before_turn(
+        &mut self,
+        board: &Board,
+        user_player: &mut Player,
+    ) -> anyhow::Result<Option<String>> {
+        if !self.pie_rule || board.cells().iter().flatten().count() != 1 {
+            return Ok(None);
+        }
+        if board.next_player == *user_player {
+            println!("{}", board.render(self.pieces));
+            if Confirm::new("Swap sides and take the computer's move?")
+                .with_default(false)
+                .prompt()?
+            {
+                *user_player = user_player.opponent();
+                println!("You play {user_player} now.");
+            }
+        } else if self.computer.expects_to_lose(board, &mut self.rng) {
+            *user_player = user_player.opponent();
+            println!("The computer swaps sides, so you play {user_player} now.");
+        }
+        Ok(None)
+    }
+
+    fn ask(&mut self, board: &Board) -> anyhow::Result<mnk::Move> {
+        let width = board.rules().width;
+        let moves = board.legal_moves();
+        let names: Vec<String> = moves
+            .iter()
+            .map(|&m| match m {
+                mnk::Move::Square(square) => GridSquare {
+                    square,
+                    size: width,
+                }
+                .to_string(),
+                m => m.to_string(),
+            })
+            .collect();
+        let pondering = self.ponder.then(|| self.computer.ponder(board)).flatten();
+        let choice = Select::new("Your move:", names).raw_prompt();
+        if let Some(pondering) = pondering {
+            self.computer.stop_pondering(pondering);
+        }
+        Ok(moves[choice?.index])
+    }
+
+    fn decide(&mut self, board: &Board) -> Option<mnk::Move> {
+        self.computer.decide(board, &mut self.rng)
+    }
+
+    fn play(&mut self, board: &mut Board, m: mnk::Move, human: bool) -> anyhow::Result<()> {
+        let square = board.play(m)?;
+        if !human {
+            println!(
+                "The computer played {}.",
+                GridSquare {
+                    square,
+                    size: board.rules().width
+                }
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Play infinite tic-tac-toe against the computer, calling a draw when a position comes up for
+/// the third time
+fn infinite(args: InfiniteArgs) -> anyhow::Result<()> {
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    println!("You only keep {MARKS} marks on the board: your fourth takes away your oldest.");
+    let mut turns = InfiniteTurns {
+        pieces: args.pieces.pieces(),
+        solution: Solution::new(),
+        seen: HashMap::new(),
+        rng: rand::thread_rng(),
+    };
+    play_turns(InfiniteGame::new(), user_player, &mut turns)
+}
+
+/// Infinite tic-tac-toe, where each side's oldest mark goes when it has too many
+struct InfiniteTurns {
+    pieces: Pieces,
+    solution: Solution,
+    /// How many times each position has come up
+    seen: HashMap<InfiniteGame, u32>,
+    rng: ThreadRng,
+}
+
+impl Turns<InfiniteGame> for InfiniteTurns {
+    fn show(&self, game: &InfiniteGame) -> String {
+        render_board(&game.board(), &self.pieces)
+    }
+
+    fn before_turn(
+        &mut self,
+        game: &InfiniteGame,
+        _: &mut Player,
+    ) -> anyhow::Result<Option<String>> {
+        let times = self.seen.entry(game.clone()).or_insert(0);
+        *times += 1;
+        Ok((*times == 3)
+            .then(|| "The same position came up three times, so it's a draw.".to_string()))
+    }
+
+    fn ask(&mut self, game: &InfiniteGame) -> anyhow::Result<usize> {
+        if let Some(square) = game.expiring(game.next_player) {
+            println!(
+                "Your mark at {} goes when you move.",
+                Selection::new(square)
+            );
+        }
+        let squares: Vec<Selection> = game.legal_moves().into_iter().map(Selection::new).collect();
+        Ok(Select::new("Where will you move?", squares)
+            .prompt()?
+            .square)
+    }
+
+    fn decide(&mut self, game: &InfiniteGame) -> Option<usize> {
+        self.solution.decide(game, &mut self.rng)
+    }
+
+    fn play(&mut self, game: &mut InfiniteGame, square: usize, human: bool) -> anyhow::Result<()> {
+        if !human {
+            match game.expiring(game.next_player) {
+                Some(gone) => println!(
+                    "The computer moved to {}, taking away its mark at {}.",
+                    Selection::new(square),
+                    Selection::new(gone)
+                ),
+                None => println!("The computer moved to {}.", Selection::new(square)),
+            }
+        }
+        game.play(square)?;
+        Ok(())
+    }
+}
+
+/// Play numerical tic-tac-toe against the computer, asking for a square and then a digit
+fn numerical(args: NumericalArgs) -> anyhow::Result<()> {
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    println!("X plays the odd digits and O the even ones, each once. Whoever fills a line adding up to {TARGET} wins.");
+    let mut turns = NumericalTurns {
+        search: numerical::Search::new(),
+        rng: rand::thread_rng(),
+    };
+    play_turns(NumericalGame::new(), user_player, &mut turns)
+}
+
+struct NumericalTurns {
+    search: numerical::Search,
+    rng: ThreadRng,
+}
+
+impl Turns<NumericalGame> for NumericalTurns {
+    fn show(&self, game: &NumericalGame) -> String {
+        game.to_string()
+    }
+
+    fn ask(&mut self, game: &NumericalGame) -> anyhow::Result<NumberMove> {
+        let squares: Vec<Selection> = game
+            .open_squares()
+            .into_iter()
+            .map(Selection::new)
+            .collect();
+        let square = Select::new("Where will you play?", squares)
+            .prompt()?
+            .square;
+        let digit = Select::new("Which digit?", game.digits_left(game.next_player())).prompt()?;
+        Ok(NumberMove { square, digit })
+    }
+
+    fn decide(&mut self, game: &NumericalGame) -> Option<NumberMove> {
+        self.search.decide(game, &mut self.rng)
+    }
+
+    fn play(&mut self, game: &mut NumericalGame, m: NumberMove, human: bool) -> anyhow::Result<()> {
+        game.play(m)?;
+        if !human {
+            println!(
+                "The computer played {} on {}.",
+                m.digit,
+                Selection::new(m.square)
+            );
+        }
+        Ok(())
+    }
 }
 
-impl Player {
-    /// Get the opponent for the given player
-    fn opponent(self) -> Self {
-        match self {
-            Player::X => Player::O,
-            Player::O => Player::X,
+/// Play SOS against the computer, asking for a square and then a letter
+fn play_sos(args: SosArgs) -> anyhow::Result<()> {
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    println!("Write S or O. Each S-O-S you complete scores a point and earns another turn.");
+    let mut turns = SosTurns {
+        search: sos::Search::new(args.depth.into()),
+        rng: rand::thread_rng(),
+    };
+    play_turns(SosGame::new(args.size.into())?, user_player, &mut turns)
+}
+
+struct SosTurns {
+    search: sos::Search,
+    rng: ThreadRng,
+}
+
+impl Turns<SosGame> for SosTurns {
+    fn show(&self, game: &SosGame) -> String {
+        game.to_string()
+    }
+
+    fn ask(&mut self, game: &SosGame) -> anyhow::Result<SosMove> {
+        let size = game.size();
+        let squares: Vec<GridSquare> = game
+            .open_squares()
+            .into_iter()
+            .map(|square| GridSquare { square, size })
+            .collect();
+        let square = Select::new("Where will you write?", squares)
+            .prompt()?
+            .square;
+        let letter = Select::new("Which letter?", vec![Letter::S, Letter::O]).prompt()?;
+        Ok(SosMove { square, letter })
+    }
+
+    fn decide(&mut self, game: &SosGame) -> Option<SosMove> {
+        self.search.decide(game, &mut self.rng)
+    }
+
+    fn play(&mut self, game: &mut SosGame, m: SosMove, human: bool) -> anyhow::Result<()> {
+        let player = game.next_player;
+        if !human {
+            let square = GridSquare {
+                square: m.square,
+                size: game.size(),
+            };
+            println!("The computer wrote {} at {square}.", m.letter);
+        }
+        match game.play(m)? {
+            0 => {}
+            made => println!("{player} scores {made} and goes again."),
+        }
+        Ok(())
+    }
+}
+
+/// Play dots and boxes against the computer, asking for lines by the dots at their ends
+fn dots_and_boxes(args: DotsAndBoxesArgs) -> anyhow::Result<()> {
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    println!("Draw a line between two dots. Closing a box claims it and earns another turn.");
+    let game = DotsAndBoxes::new(args.width.into(), args.height.into())?;
+    let mut turns = BoxesTurns {
+        search: boxes::Search::new(),
+        rng: rand::thread_rng(),
+    };
+    play_turns(game, user_player, &mut turns)
+}
+
+struct BoxesTurns {
+    search: boxes::Search,
+    rng: ThreadRng,
+}
+
+impl Turns<DotsAndBoxes> for BoxesTurns {
+    fn show(&self, game: &DotsAndBoxes) -> String {
+        game.to_string()
+    }
+
+    fn ask(&mut self, game: &DotsAndBoxes) -> anyhow::Result<usize> {
+        let lines = game.legal_moves();
+        let names: Vec<String> = lines.iter().map(|&line| game.line_name(line)).collect();
+        let choice = Select::new("Which line will you draw?", names).raw_prompt()?;
+        Ok(lines[choice.index])
+    }
+
+    fn decide(&mut self, game: &DotsAndBoxes) -> Option<usize> {
+        self.search.decide(game, &mut self.rng)
+    }
+
+    fn play(&mut self, game: &mut DotsAndBoxes, line: usize, human: bool) -> anyhow::Result<()> {
+        let player = game.next_player;
+        if !human {
+            println!("The computer drew {}.", game.line_name(line));
+        }
+        match game.play(line)? {
+            0 => {}
+            closed => println!("{player} closes {closed} and goes again."),
+        }
+        Ok(())
+    }
+}
+
+/// Play Nim against the computer, which plays perfectly
+fn nim(args: NimArgs) -> anyhow::Result<()> {
+    let user_player = match args.player {
+        Some(player) => player,
+        None => Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?,
+    };
+    let game = NimGame::new(args.heaps)?;
+    println!("Take any number of counters from one heap. Whoever takes the last counter wins.");
+    play_turns(game, user_player, &mut NimTurns)
+}
+
+struct NimTurns;
+
+impl Turns<NimGame> for NimTurns {
+    fn show(&self, game: &NimGame) -> String {
+        game.to_string()
+    }
+
+    fn decide(&mut self, game: &NimGame) -> Option<<NimGame as Game>::Move> {
+        game.best_move()
+    }
+}
+
+/// How a game is shown and its moves asked for and made in [`play_turns`]. The defaults ask for
+/// moves from a menu of their names
+trait Turns<G: Game> {
+    /// The position as the human sees it before their move
+    fn show(&self, game: &G) -> String;
+
+    /// The whole board once the game is over
+    fn show_end(&self, game: &G) -> String {
+        self.show(game)
+    }
+
+    /// Anything to do before each turn, which can change the side the human plays. A message
+    /// ends the game there, without a winner
+    fn before_turn(
+        &mut self,
+        _game: &G,
+        _user_player: &mut Player,
+    ) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Ask the human for their move
+    fn ask(&mut self, game: &G) -> anyhow::Result<G::Move> {
+        let moves = game.legal_moves();
+        let names: Vec<String> = moves.iter().map(|&m| game.describe(m)).collect();
+        Ok(moves[Select::new("Your move:", names).raw_prompt()?.index])
+    }
+
+    /// The computer's move
+    fn decide(&mut self, game: &G) -> Option<G::Move>;
+
+    /// Make a move, saying what happened
+    fn play(&mut self, game: &mut G, m: G::Move, human: bool) -> anyhow::Result<()> {
+        if !human {
+            println!("The computer's move: {}.", game.describe(m));
+        }
+        game.play(m)?;
+        Ok(())
+    }
+}
+
+/// Play any game against the computer until it's over, the way `turns` says to
+fn play_turns<G: Game>(
+    mut game: G,
+    mut user_player: Player,
+    turns: &mut impl Turns<G>,
+) -> anyhow::Result<()> {
+    while game.status() == GameStatus::InProgress {
+        if let Some(message) = turns.before_turn(&game, &mut user_player)? {
+            println!("{}", turns.show_end(&game));
+            println!("{message}");
+            return Ok(());
         }
+        let human = game.next_player() == user_player;
+        let m = if human {
+            println!("{}", turns.show(&game));
+            turns.ask(&game)?
+        } else {
+            turns
+                .decide(&game)
+                .expect("a game in progress has a legal move")
+        };
+        turns.play(&mut game, m, human)?;
+    }
+    println!("{}", turns.show_end(&game));
+    print_result(game.status(), user_player);
+    Ok(())
+}
+
+/// Tell the human how a finished game went for them
+fn print_result(status: GameStatus, user_player: Player) {
+    match status {
+        GameStatus::Won(winner) if winner == user_player => println!("You win!"),
+        GameStatus::Won(_) => println!("The computer wins."),
+        _ => println!("It's a tie."),
     }
 }
 
-impl Display for Player {
+/// A square on a board of any size, named by its row and column for menus
+struct GridSquare {
+    square: usize,
+    size: usize,
+}
+
+impl Display for GridSquare {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}",
-            match self {
-                Player::X => 'X',
-                Player::O => 'O',
-            }
+            "row {}, column {}",
+            self.square / self.size + 1,
+            self.square % self.size + 1
         )
     }
 }
 
-// Hack to have inquire display a nice name for the board index representing the square on the board
-#[derive(Copy, Clone, Debug)]
-struct Selection {
-    pub square: usize,
-}
-
-impl Selection {
-    const SQUARES: [&'static str; 9] = [
-        "Top Left",
-        "Top Middle",
-        "Top Right",
-        "Middle Left",
-        "Middle",
-        "Middle Right",
-        "Bottom Left",
-        "Bottom Middle",
-        "Bottom Right",
-    ];
+/// An option in the opening explorer
+enum ExplorerChoice {
+    /// Follow a move, with how the games that played it ended
+    Move(usize, Results),
+    Back,
+    Quit,
+}
 
-    fn new(square: usize) -> Self {
-        Self { square }
+impl Display for ExplorerChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExplorerChoice::Move(square, results) => write!(
+                f,
+                "{} ({} games, {})",
+                Selection::new(*square),
+                results.games(),
+                results.summary()
+            ),
+            ExplorerChoice::Back => write!(f, "Back"),
+            ExplorerChoice::Quit => write!(f, "Quit"),
+        }
     }
 }
 
-impl Display for Selection {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", Self::SQUARES[self.square])
+fn explorer(args: ExplorerArgs) -> anyhow::Result<()> {
+    if !(0.0..=1.0).contains(&args.blunder_chance) {
+        bail!(
+            "the blunder chance must be between 0 and 1, got {}",
+            args.blunder_chance
+        );
+    }
+    let mut tree = OpeningTree::new(args.depth);
+    let mut from_history = 0;
+    if let Some(path) = Stats::default_path() {
+        for entry in Stats::new(&path).entries()? {
+            if tree.add(&entry.game)? {
+                from_history += 1;
+            }
+        }
+    }
+    let games = args.self_play as usize;
+    let seed = rand::random();
+    let threads = thread_count(None);
+    let self_play = with_progress(games, |progress| {
+        run_games(games, seed, threads, progress, |_, rng| {
+            self_play_game(args.blunder_chance, rng)
+        })
+    });
+    for game in &self_play {
+        tree.add(game)?;
+    }
+    if tree.root.results.games() == 0 {
+        bail!("there are no finished games to explore yet, play some or pass --self-play");
+    }
+    println!(
+        "{from_history} games from your history and {} self-play games.",
+        args.self_play
+    );
+
+    if args.print {
+        println!("All games: {}", tree.root.results.summary());
+        print_opening_tree(&tree.root, 0);
+        return Ok(());
+    }
+
+    let mut line: Vec<usize> = Vec::new();
+    loop {
+        let node = tree
+            .root
+            .get(&line)
+            .expect("only moves in the tree are offered");
+        println!("{}", GameState::from_moves(Player::X, &line)?);
+        if line.is_empty() {
+            println!("All games: {}", node.results.summary());
+        } else {
+            let moves: Vec<String> = line
+                .iter()
+                .map(|&square| Selection::new(square).to_string())
+                .collect();
+            println!(
+                "{}: {} games, {}",
+                moves.join(", "),
+                node.results.games(),
+                node.results.summary()
+            );
+        }
+
+        let mut choices: Vec<ExplorerChoice> = node
+            .moves_by_popularity()
+            .into_iter()
+            .map(|(square, child)| ExplorerChoice::Move(square, child.results))
+            .collect();
+        if choices.is_empty() {
+            println!("No games went further than this within the explored depth.");
+        }
+        if !line.is_empty() {
+            choices.push(ExplorerChoice::Back);
+        }
+        choices.push(ExplorerChoice::Quit);
+        let page_size = choices.len();
+        match Select::new("Which move would you like to follow?", choices)
+            .with_page_size(page_size)
+            .prompt()?
+        {
+            ExplorerChoice::Move(square, _) => line.push(square),
+            ExplorerChoice::Back => {
+                line.pop();
+            }
+            ExplorerChoice::Quit => return Ok(()),
+        }
     }
 }
 
-/// Return the winner for a given line or None if there is no winner
-fn get_line_winner(a: Option<Player>, b: Option<Player>, c: Option<Player>) -> Option<Player> {
-    if a.is_some() && a == b && b == c {
-        a
-    } else {
-        None
+/// Print every move in the tree, indented by how deep into the game it is
+fn print_opening_tree(node: &OpeningNode, depth: usize) {
+    for (square, child) in node.moves_by_popularity() {
+        println!(
+            "{:indent$}{} ({} games, {})",
+            "",
+            Selection::new(square),
+            child.results.games(),
+            child.results.summary(),
+            indent = depth * 2
+        );
+        print_opening_tree(child, depth + 1);
+    }
+}
+
+fn tournament(args: TournamentArgs) -> anyhow::Result<()> {
+    if args.entrants.len() < 2 {
+        bail!("a tournament needs at least two entrants");
+    }
+    if let Some(&chance) = args
+        .entrants
+        .iter()
+        .find(|chance| !(0.0..=1.0).contains(*chance))
+    {
+        bail!("the blunder chance must be between 0 and 1, got {chance}");
+    }
+    let tournament = Tournament {
+        entrants: args.entrants,
+        games: args.games,
+        seed: args.seed.unwrap_or_else(rand::random),
+        threads: thread_count(args.threads),
+    };
+    let start = Instant::now();
+    let standings = with_progress(tournament.total_games(), |progress| {
+        tournament.run(progress)
+    });
+    if args.json {
+        let report = TournamentReport::new(&tournament, &standings);
+        println!("{}", serde_json::to_string(&report)?);
+        return Ok(());
+    }
+    println!(
+        "{} games with seed {} on {} thread{} in {:.1}s",
+        tournament.total_games(),
+        tournament.seed,
+        tournament.threads,
+        if tournament.threads == 1 { "" } else { "s" },
+        start.elapsed().as_secs_f64()
+    );
+
+    let name = |entrant: usize| format!("blunders {}%", 100.0 * standings.entrants[entrant]);
+    let games_each = 2 * tournament.games * (standings.entrants.len() - 1);
+    for (rank, entrant) in standings.ranking().into_iter().enumerate() {
+        println!(
+            "{}. {}: {} points from {games_each} games",
+            rank + 1,
+            name(entrant),
+            standings.points(entrant)
+        );
+    }
+    println!();
+    for pairing in &standings.pairings {
+        println!(
+            "{} as X against {}: {}",
+            name(pairing.x),
+            name(pairing.o),
+            pairing.results.summary()
+        );
     }
+    Ok(())
+}
+
+/// The threads to play games on, one per core unless told otherwise
+fn thread_count(threads: Option<u16>) -> usize {
+    threads.map_or_else(
+        || std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        usize::from,
+    )
+}
+
+/// Wait out the computer's pause before a move, drawing a spinner on stderr meanwhile
+fn pretend_to_think(pause: Duration) {
+    let show = std::io::stderr().is_terminal() && !profile().no_animations;
+    let start = Instant::now();
+    for frame in SPINNER.iter().cycle() {
+        let elapsed = start.elapsed();
+        if elapsed >= pause {
+            break;
+        }
+        if show {
+            eprint!("\r{frame} Thinking...");
+        }
+        std::thread::sleep((pause - elapsed).min(SPINNER_FRAME));
+    }
+    if show {
+        eprint!("\r{:width$}\r", "", width = "| Thinking...".len());
+    }
+}
+
+/// Run a batch of games, showing on stderr how many of them have finished while they play
+fn with_progress<T: Send>(games: usize, run: impl FnOnce(&AtomicUsize) -> T + Send) -> T {
+    let finished = AtomicUsize::new(0);
+    let show = std::io::stderr().is_terminal() && !profile().no_animations;
+    std::thread::scope(|scope| {
+        let worker = scope.spawn(|| run(&finished));
+        while !worker.is_finished() {
+            if show {
+                eprint!(
+                    "\rPlayed {} of {games} games",
+                    finished.load(Ordering::Relaxed)
+                );
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        if show {
+            eprintln!("\rPlayed {games} of {games} games");
+        }
+        worker
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd)]
-enum GameResult {
-    Loss = -1,
-    Tie = 0,
-    Win = 1,
+/// Edit a board square by square, then play the position out or analyze it
+fn setup(
+    SetupArgs {
+        position,
+        play: args,
+    }: SetupArgs,
+    config: &Config,
+) -> anyhow::Result<()> {
+    if args.pipe || args.json || !args.moves.is_empty() || args.record.is_some() {
+        bail!("setup only plays interactively, without --moves, --json, --pipe or --record");
+    }
+    if args.replay_input.is_some() || args.pie_rule {
+        bail!("setup can't be used with --replay-input or --pie-rule");
+    }
+
+    let pieces = args.pieces.pieces();
+    let mut board = position.map_or([None; 9], |state| *state.board());
+    loop {
+        println!("{}", render_board(&board, &pieces));
+        let position = GameState::from_board(Player::O, board);
+        match &position {
+            Ok(state) => {
+                match state.status() {
+                    GameStatus::Won(winner) => println!("{winner} has won."),
+                    GameStatus::Tie => println!("The board is full."),
+                    _ => println!("{} to move.", state.next_player()),
+                }
+                println!("Position string: {}", state.to_position_string());
+            }
+            Err(e) => println!("This position can't come up in a game: {e}."),
+        }
+
+        let mut actions: Vec<SetupAction> = (0..9)
+            .map(|square| SetupAction::Square(square, board[square]))
+            .collect();
+        actions.extend([SetupAction::Play, SetupAction::Analyze, SetupAction::Quit]);
+        let page_size = actions.len();
+        match Select::new("What would you like to do?", actions)
+            .with_page_size(page_size)
+            .prompt()?
+        {
+            SetupAction::Square(square, _) => {
+                let options = vec!["X", "O", "Empty"];
+                let prompt = format!("What goes on {}?", Selection::new(square));
+                board[square] = match Select::new(&prompt, options).prompt()? {
+                    "X" => Some(Player::X),
+                    "O" => Some(Player::O),
+                    _ => None,
+                };
+            }
+            SetupAction::Analyze => match position {
+                Ok(mut state) => {
+                    state.computer_player = state.next_player();
+                    print_analysis(&state);
+                }
+                Err(e) => println!("Fix the position first: {e}."),
+            },
+            SetupAction::Play => match position {
+                Ok(state) if state.status() != GameStatus::InProgress => {
+                    println!("The game is already over in this position.");
+                }
+                Ok(_) => {
+                    let overlay = stream_overlay(&args)?;
+                    return play_interactive(
+                        args,
+                        config,
+                        Input::Terminal,
+                        Some(board),
+                        None,
+                        overlay.as_ref(),
+                    );
+                }
+                Err(e) => println!("Fix the position first: {e}."),
+            },
+            SetupAction::Quit => return Ok(()),
+        }
+    }
 }
 
-/// Minimax algorithm to choose the best move for the computer
-fn minimax(state: &GameState) -> GameResult {
-    if let Some(winner) = state.winner {
-        if winner == state.computer_player {
-            return GameResult::Win;
+/// Play a game without prompting, taking the human's moves from the command line
+fn play_scripted(args: PlayArgs) -> anyhow::Result<()> {
+    let user_player = args.player.unwrap_or(Player::X);
+    let mut game = GameState::builder()
+        .human_player(user_player)
+        .build()?
+        .state;
+    let mut record = GameRecord::new(game.computer_player, Vec::new());
+    let mut human_moves = args.moves.iter();
+
+    while game.status() == GameStatus::InProgress {
+        let (square, decision) = if game.next_player() == user_player {
+            match human_moves.next() {
+                Some(&square) => (square, None),
+                None => break,
+            }
         } else {
-            return GameResult::Loss;
+            let decision = game.decide_computer_move();
+            (decision.chosen, Some(decision))
+        };
+        if game.board()[square].is_some() {
+            bail!("{} is already taken", Selection::new(square));
         }
+        game.apply_move(square);
+        record.push(square, decision);
+    }
+    if human_moves.next().is_some() {
+        bail!("the game ended before all of the moves were played");
     }
 
-    // Check tied game state
-    let possible_moves = state.open_squares();
-    if possible_moves.is_empty() {
-        return GameResult::Tie;
+    if let Some(path) = &args.save {
+        record.save(path)?;
     }
 
-    if state.next_player == state.computer_player {
-        // Unwrap since we already checked possible_moves.is_empty()
-        possible_moves
-            .iter()
-            .map(|m| minimax(&state.with_move(m.square)))
-            .max()
-            .unwrap()
+    if args.json {
+        let report = PlayReport {
+            human_player: user_player,
+            computer_player: game.computer_player,
+            moves: record.squares(),
+            position: PositionReport::from(&game),
+        };
+        println!("{}", serde_json::to_string(&report)?);
     } else {
-        possible_moves
-            .iter()
-            .map(|m| minimax(&state.with_move(m.square)))
-            .min()
-            .unwrap()
+        println!("{game}");
+        match game.status() {
+            GameStatus::InProgress => println!("Your move."),
+            _ => print_game_over(
+                &record,
+                user_player,
+                !args.no_taunts,
+                &mut rand::thread_rng(),
+            )?,
+        }
     }
+    Ok(())
 }
 
-#[derive(Clone)]
-struct GameState {
-    board: [Option<Player>; 9],
-    next_player: Player,
-    winner: Option<Player>,
-    computer_player: Player,
-}
+/// Run the JSON line protocol over stdin and stdout until stdin is closed
+fn run_pipe() -> anyhow::Result<()> {
+    use std::io::{BufRead, Write};
 
-impl GameState {
-    fn new(computer_player: Player) -> Self {
-        Self {
-            board: [None; 9],
-            next_player: Player::X,
-            winner: None,
-            computer_player,
+    let mut session = Session::new();
+    let mut stdout = std::io::stdout().lock();
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        for event in session.handle_line(&line) {
+            serde_json::to_writer(&mut stdout, &event)?;
+            writeln!(stdout)?;
         }
+        stdout.flush()?;
     }
+    Ok(())
+}
+
+fn post(command: PostCommand) -> anyhow::Result<()> {
+    let (path, record) = match command {
+        PostCommand::New {
+            game,
+            player,
+            force,
+        } => {
+            if game.exists() && !force {
+                bail!(
+                    "{} already exists, pass --force to replace it",
+                    game.display()
+                );
+            }
+            (game, post::new_game(player))
+        }
+        PostCommand::Reply { game, reply } => {
+            let text = match reply {
+                Some(path) => std::fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+                None => std::io::read_to_string(std::io::stdin())?,
+            };
+            let mut record = GameRecord::load(&game)?;
+            if record.status()? != GameStatus::InProgress {
+                bail!("the game in {} is already over", game.display());
+            }
+            post::play_reply(&mut record, post::parse_reply(&text)?)?;
+            (game, record)
+        }
+    };
+    record.save(&path)?;
+    println!("{}", post::render_post(&record)?);
+    Ok(())
+}
 
-    /// Apply a move to the gamestate
-    fn apply_move(&mut self, square: usize) {
-        self.board[square] = Some(self.next_player);
-        self.next_player = self.next_player.opponent();
-        self.winner = self.check_winner();
+/// Play games in IRC channels until the server closes the connection
+fn run_irc(args: IrcArgs) -> anyhow::Result<()> {
+    let options = IrcOptions {
+        server: args.server,
+        port: args.port,
+        nick: args.nick,
+        password: args.password,
+    };
+    let mut client = IrcClient::connect(&options)?;
+    for channel in &args.channels {
+        client.join(channel)?;
     }
+    tracing::info!(server = options.server, channels = ?args.channels, "joined");
 
-    /// Get a new `GameState` with the given move applied
-    fn with_move(&self, square: usize) -> Self {
-        let mut new_state = self.clone();
-        new_state.apply_move(square);
-        new_state
+    let mut games = ChatGames::new(&args.prefix);
+    while let Some(message) = client.next_message()? {
+        for line in games.handle(&message.sender, &message.text) {
+            client.say(message.reply_target(), &line)?;
+        }
     }
+    Ok(())
+}
 
-    /// Get a list of the best moves
-    fn get_best_computer_moves(&self) -> Vec<Selection> {
-        // Start with the remaining possible moves
-        let possible_moves = self.open_squares();
+#[cfg(feature = "matrix")]
+fn run_matrix(args: MatrixArgs) -> anyhow::Result<()> {
+    use simple_tic_tac_toe::matrix::{run_matrix_bot, MatrixOptions};
 
-        let mut best_so_far = GameResult::Loss;
-        // The list of moves that lead to wins
-        let mut winning_moves = Vec::new();
+    run_matrix_bot(&MatrixOptions {
+        homeserver: args.homeserver,
+        user: args.user,
+        password: args.password,
+        prefix: args.prefix,
+    })?;
+    Ok(())
+}
+
+#[cfg(not(feature = "matrix"))]
+fn run_matrix(_: MatrixArgs) -> anyhow::Result<()> {
+    bail!("the Matrix bot requires building with the `matrix` feature")
+}
 
-        for m in possible_moves {
-            let move_result = minimax(&self.with_move(m.square));
+/// Play games with the chat voting on its side's moves, until the server closes the connection
+fn run_twitch(args: TwitchArgs) -> anyhow::Result<()> {
+    let token = args.token.strip_prefix("oauth:").unwrap_or(&args.token);
+    let mut client = IrcClient::connect(&IrcOptions {
+        server: args.server,
+        port: args.port,
+        nick: args.nick.to_lowercase(),
+        password: Some(format!("oauth:{token}")),
+    })?;
+    let channel = format!("#{}", args.channel.trim_start_matches('#').to_lowercase());
+    client.join(&channel)?;
+    let window = Duration::from_secs(args.vote_seconds);
+    let overlay = args.overlay.as_deref();
+    let mut rng = rand::thread_rng();
 
-            if move_result > best_so_far {
-                best_so_far = move_result;
-                winning_moves.clear();
-                winning_moves.push(m);
-            } else if move_result == best_so_far {
-                winning_moves.push(m);
+    loop {
+        let mut game = GameState::new(args.player.opponent());
+        client.say(
+            &channel,
+            &format!("New game! Chat plays {} against the computer.", args.player),
+        )?;
+        while game.status() == GameStatus::InProgress {
+            if game.next_player() == game.computer_player {
+                let square = game.decide_computer_move().chosen;
+                game.apply_move(square);
+                client.say(
+                    &channel,
+                    &format!("The computer played {}.", coordinate(square)),
+                )?;
+                continue;
             }
+
+            let mut votes = Votes::new();
+            client.say(
+                &channel,
+                &format!(
+                    "Vote for {}'s move by typing a square from a1 (top left) to c3 (bottom right), voting closes in {}s.",
+                    args.player, args.vote_seconds
+                ),
+            )?;
+            let status = format!("Chat plays {}, vote now!", args.player);
+            update_overlay(overlay, &game, &status, &votes)?;
+            let mut extended = false;
+            let square = loop {
+                let deadline = Instant::now() + window;
+                loop {
+                    match client.next_message_until(deadline)? {
+                        Received::Message(message) => {
+                            if votes.cast(&game, &message.sender, &message.text).is_some() {
+                                update_overlay(overlay, &game, &status, &votes)?;
+                            }
+                        }
+                        Received::TimedOut => break,
+                        Received::Closed => return Ok(()),
+                    }
+                }
+                if let Some(square) = votes.winner(&mut rng) {
+                    break square;
+                }
+                if !extended {
+                    client.say(
+                        &channel,
+                        "No votes yet, voting stays open until someone votes.",
+                    )?;
+                    extended = true;
+                }
+            };
+            client.say(
+                &channel,
+                &format!("Chat plays {} ({}).", coordinate(square), votes.summary()),
+            )?;
+            game.apply_move(square);
         }
 
-        winning_moves
+        let result = match game.status() {
+            GameStatus::Won(winner) if winner == args.player => "chat wins!",
+            GameStatus::Won(_) => "the computer wins.",
+            _ => "it's a tie.",
+        };
+        client.say(&channel, &format!("Game over, {result}"))?;
+        update_overlay(
+            overlay,
+            &game,
+            &format!("Game over, {result}"),
+            &Votes::new(),
+        )?;
+        // Leave the final board up for a while before the next game, still answering pings
+        let deadline = Instant::now() + window;
+        loop {
+            match client.next_message_until(deadline)? {
+                Received::Message(_) => {}
+                Received::TimedOut => break,
+                Received::Closed => return Ok(()),
+            }
+        }
     }
+}
 
-    /// Randomly choose one of the best moves to avoid repetitive games
-    fn get_random_computer_move(&self) -> Selection {
-        let mut rng = rand::thread_rng();
-        let best_moves = self.get_best_computer_moves();
-        best_moves[rng.gen_range(0..best_moves.len())]
+fn update_overlay(
+    path: Option<&Path>,
+    state: &GameState,
+    status: &str,
+    votes: &Votes,
+) -> anyhow::Result<()> {
+    if let Some(path) = path {
+        write_overlay(path, state, status, votes)?;
     }
+    Ok(())
+}
 
-    /// Get a list of open squares, i.e. squares that are possible options for moves
-    fn open_squares(&self) -> Vec<Selection> {
-        self.board
-            .iter()
-            .enumerate()
-            .filter_map(|(i, s)| {
-                if s.is_none() {
-                    Some(Selection::new(i))
-                } else {
-                    None
-                }
-            })
-            .collect()
+fn play(args: PlayArgs, config: &Config) -> anyhow::Result<()> {
+    if args.pipe {
+        return run_pipe();
+    }
+    if args.json || !args.moves.is_empty() {
+        return play_scripted(args);
+    }
+    if let Some(path) = &args.replay_input {
+        return replay_session(path);
     }
 
-    /// Return the winner or None if there is no winner
-    fn check_winner(&self) -> Option<Player> {
-        for i in 0..3 {
-            // Check rows
-            if let Some(winner) = get_line_winner(
-                self.board[i * 3],
-                self.board[i * 3 + 1],
-                self.board[i * 3 + 2],
-            ) {
-                return Some(winner);
-            }
+    if args.record.is_none() {
+        if let Some(recovered) = interrupted_game()? {
+            return resume_game(recovered, config);
+        }
+    }
 
-            // Check columns
-            if let Some(winner) =
-                get_line_winner(self.board[i], self.board[i + 3], self.board[i + 6])
-            {
-                return Some(winner);
+    let input = match &args.record {
+        Some(path) => {
+            let log = SessionLog::new(rand::random(), args_without_record(), config.clone());
+            Input::Recording {
+                log,
+                path: path.clone(),
             }
         }
-
-        // Check diagonals
-        if let Some(winner) = get_line_winner(self.board[0], self.board[4], self.board[8]) {
-            return Some(winner);
+        None => Input::Terminal,
+    };
+    let overlay = stream_overlay(&args)?;
+    if !profile().rematch || !matches!(input, Input::Terminal) {
+        return play_interactive(args, config, input, None, None, overlay.as_ref());
+    }
+    loop {
+        play_interactive(
+            args.clone(),
+            config,
+            Input::Terminal,
+            None,
+            None,
+            overlay.as_ref(),
+        )?;
+        if !Confirm::new("Play again?").with_default(true).prompt()? {
+            return Ok(());
         }
+    }
+}
 
-        if let Some(winner) = get_line_winner(self.board[2], self.board[4], self.board[6]) {
-            return Some(winner);
+/// The game left in the journal by a crash or a closed terminal, if there is one and the human
+/// wants it back
+fn interrupted_game() -> anyhow::Result<Option<Recovered>> {
+    let Some(path) = Journal::default_path() else {
+        return Ok(None);
+    };
+    let recovered = match journal::recover(&path) {
+        Ok(Some(recovered)) => recovered,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            eprintln!("Warning: couldn't restore the interrupted game: {e}");
+            journal::discard(&path)?;
+            return Ok(None);
         }
+    };
+    let prompt = format!(
+        "Your last game was interrupted after {} moves. Pick it up where it stopped?",
+        recovered.game.moves.len()
+    );
+    if Confirm::new(&prompt).with_default(true).prompt()? {
+        Ok(Some(recovered))
+    } else {
+        journal::discard(&path)?;
+        Ok(None)
+    }
+}
 
-        None
+/// Carry on an interrupted game with the command line it was started with
+fn resume_game(recovered: Recovered, config: &Config) -> anyhow::Result<()> {
+    let cli = Cli::try_parse_from(&recovered.start.args)?;
+    let args = match cli.command {
+        Some(Command::Play(args)) => args,
+        // Games set up with `setup` have the position in the journal and the defaults otherwise
+        _ => cli.play,
+    };
+    let overlay = stream_overlay(&args)?;
+    play_interactive(
+        args,
+        config,
+        Input::Terminal,
+        None,
+        Some(recovered),
+        overlay.as_ref(),
+    )
+}
+
+/// Re-run a session recorded with `--record`, with the same command line and settings
+fn replay_session(path: &Path) -> anyhow::Result<()> {
+    let log = SessionLog::load(path)?;
+    if log.version != env!("CARGO_PKG_VERSION") {
+        eprintln!(
+            "Warning: the session was recorded with version {}, the replay may differ",
+            log.version
+        );
     }
+    let cli = Cli::try_parse_from(&log.args)?;
+    let args = match cli.command {
+        Some(Command::Play(args)) => args,
+        None => cli.play,
+        Some(_) => bail!("{} isn't a recording of a game", path.display()),
+    };
+    let config = log.config.clone();
+    play_interactive(
+        args,
+        &config,
+        Input::Replaying {
+            log,
+            answers: 0,
+            decisions: 0,
+        },
+        None,
+        None,
+        None,
+    )
 }
 
-fn get_char(square: Option<Player>) -> char {
-    match square {
-        Some(Player::X) => 'X',
-        Some(Player::O) => 'O',
-        None => '.',
+/// The stream overlay asked for on the command line or by the profile, serving it over HTTP too
+/// if asked
+fn stream_overlay(args: &PlayArgs) -> anyhow::Result<Option<Overlay>> {
+    let dir = match &args.overlay {
+        Some(dir) => dir.clone(),
+        None if profile().overlay => {
+            let Some(dir) = Overlay::default_dir() else {
+                bail!("couldn't find a data directory, pass --overlay");
+            };
+            dir
+        }
+        None => return Ok(None),
+    };
+    let overlay = Overlay::new(&dir, args.pieces.pieces())?;
+    println!("Keeping the stream overlay in {}", dir.display());
+    if let Some(port) = args.overlay_port.or(profile().overlay_port) {
+        let address = format!("127.0.0.1:{port}");
+        overlay.serve(&address)?;
+        println!("Serving the stream overlay at http://{address}/");
     }
+    Ok(Some(overlay))
 }
 
-impl Display for GameState {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut s = String::new();
-        for i in (0..9).step_by(3) {
-            s.push_str(&format!(
-                " {} | {} | {} \n",
-                get_char(self.board[i]),
-                get_char(self.board[i + 1]),
-                get_char(self.board[i + 2]),
-            ));
-            if i != 6 {
-                s.push_str("---|---|---\n");
-            }
+/// The command line without `--record` and its value, for storing in a recording
+fn args_without_record() -> Vec<String> {
+    let mut args = Vec::new();
+    let mut iter = std::env::args();
+    while let Some(arg) = iter.next() {
+        if arg == "--record" {
+            iter.next();
+        } else if !arg.starts_with("--record=") {
+            args.push(arg);
         }
-        write!(f, "{s}")
     }
+    args
 }
 
-fn main() -> anyhow::Result<()> {
-    let user_player = Select::new("Will you play X or O?", vec![Player::X, Player::O]).prompt()?;
-    let mut game = GameState::new(user_player.opponent());
+/// Play a game in the terminal, taking the human's answers from `input`, from the empty board,
+/// from `start` if given or from where an interrupted game stopped, and keeping `overlay` up to
+/// date if there is one
+fn play_interactive(
+    args: PlayArgs,
+    config: &Config,
+    mut input: Input,
+    start: Option<[Option<Player>; 9]>,
+    resume: Option<Recovered>,
+    overlay: Option<&Overlay>,
+) -> anyhow::Result<()> {
+    let start = resume.as_ref().map_or(start, |resume| resume.game.start);
+    let pieces = args.pieces.pieces();
+    let mut rng = StdRng::seed_from_u64(input.seed());
+    // Replays don't count towards the stats
+    let stats = match input {
+        Input::Replaying { .. } => None,
+        _ => Stats::default_path().map(|path| Stats::new(&path)),
+    };
+    // Catch a bad save name before the game rather than after it
+    if let Some(name) = &args.slot {
+        save_dir()?.path(name)?;
+    }
+    let difficulty = profile().difficulty.unwrap_or(args.difficulty);
+    let mut builder = GameState::builder()
+        .difficulty(difficulty)
+        .pie_rule(args.pie_rule);
+    if let Some(board) = start {
+        builder = builder.board(board);
+    }
+    if difficulty == Difficulty::Adaptive {
+        let chance = match &mut input {
+            Input::Replaying { log, .. } => log.blunder_chance.unwrap_or(0.0),
+            _ => match resume
+                .as_ref()
+                .and_then(|resume| resume.start.blunder_chance)
+            {
+                Some(chance) => chance,
+                None => {
+                    let entries = match &stats {
+                        Some(stats) => stats.entries()?,
+                        None => Vec::new(),
+                    };
+                    adaptive_blunder_chance(&entries, config.adaptive.target_win_rate)
+                }
+            },
+        };
+        tracing::info!(blunder_chance = chance, "adaptive difficulty");
+        if let Input::Recording { log, .. } = &mut input {
+            log.blunder_chance = Some(chance);
+        }
+        builder = builder.blunder_chance(chance);
+    }
+    input.save()?;
 
-    while game.winner.is_none() {
-        let possible_moves = game.open_squares();
-        if possible_moves.is_empty() {
-            break;
+    let rl_table = match args.opponent {
+        Opponent::Minimax => None,
+        Opponent::Rl => {
+            let Some(path) = args.rl_table.or_else(QTable::default_path) else {
+                bail!("couldn't find a data directory, pass --rl-table");
+            };
+            if !path.exists() {
+                bail!("no Q-table at {}, run `train` first", path.display());
+            }
+            Some(QTable::load(&path)?)
         }
+    };
 
-        let next_move = if game.next_player == user_player {
-            println!("{game}");
-            let page_size = possible_moves.len();
-            Select::new("Where will you move?", possible_moves)
-                .with_page_size(page_size)
-                .prompt()?
+    let resumed_player = resume.as_ref().map(|resume| resume.start.human_player);
+    let mut user_player = match resumed_player.or(args.player) {
+        Some(player) => player,
+        None => input.select(
+            wording("Will you play X or O?", "Do you want to be X or O?"),
+            vec![Player::X, Player::O],
+        )?,
+    };
+    let GameSetup {
+        state: mut game,
+        ai,
+    } = builder.human_player(user_player).build()?;
+    let odds = if args.odds {
+        if matches!(args.opponent, Opponent::Rl) {
+            bail!("--odds only works against the minimax opponent");
+        }
+        Some(build_odds(
+            stats.as_ref(),
+            user_player,
+            ai.blunder_chance.unwrap_or(0.0),
+            input.seed(),
+        )?)
+    } else {
+        None
+    };
+    // Replays answer every prompt at once, so there's nobody to pause for
+    let think_time = (args.think_time || config.think_time.enabled && !args.no_think_time)
+        .then(|| config.think_time.think_time())
+        .filter(|_| !matches!(input, Input::Replaying { .. }));
+    let mut observers = Observers::new();
+    if let Some(overlay) = overlay {
+        overlay.start(&game)?;
+        observers.add(OverlayWriter(overlay.clone()));
+    }
+    if args.show_search_stats {
+        observers.add(SearchStatsPrinter);
+    }
+    if let Some(stats) = stats {
+        observers.add(StatsRecorder {
+            stats,
+            difficulty: ai.difficulty,
+            blunder_chance: ai.blunder_chance,
+        });
+    }
+    let mut record = GameRecord::new(game.computer_player, Vec::new());
+    record.start = start;
+    // The position the computer last moved from and the square it chose, for `why`
+    let mut last_computer_move: Option<(GameState, usize)> = None;
+
+    // Replays are already on disk, in their recordings
+    let journal_path = Journal::default_path()
+        .filter(|_| config.journal.enabled && !matches!(input, Input::Replaying { .. }));
+    let mut journal = match journal_path {
+        Some(path) => {
+            let journal_start = match &resume {
+                Some(resume) => resume.start.clone(),
+                None => JournalStart {
+                    args: args_without_record(),
+                    started_at: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |since| since.as_secs()),
+                    human_player: user_player,
+                    blunder_chance: ai.blunder_chance,
+                    start,
+                },
+            };
+            Some(Journal::create(&path, config.journal.sync, journal_start)?)
+        }
+        None => None,
+    };
+    if let Some(resume) = resume {
+        for (i, played) in resume.game.moves.into_iter().enumerate() {
+            game.apply_move(played.square);
+            observers.on_move(&game, played.square, played.decision.as_ref());
+            if let Some(journal) = &mut journal {
+                journal.record_move(&played)?;
+            }
+            record.moves.push(played);
+            if i == 0 && resume.game.swapped {
+                game.swap_sides();
+                record.swapped = true;
+                user_player = user_player.opponent();
+                if let Some(journal) = &mut journal {
+                    journal.record_swap()?;
+                }
+            }
+        }
+        println!("Picked the game up after move {}.", record.moves.len());
+    }
+
+    'game: while game.status() == GameStatus::InProgress {
+        let swap_allowed = swap_offered(ai.pie_rule, &game, &record);
+        if swap_allowed && game.next_player() == game.computer_player && game.computer_should_swap()
+        {
+            println!("The computer swaps sides and takes over your {user_player}.");
+            game.swap_sides();
+            record.swapped = true;
+            user_player = user_player.opponent();
+            if let Some(journal) = &mut journal {
+                journal.record_swap()?;
+            }
+            continue;
+        }
+
+        let possible_moves = game.open_squares();
+        let turn_started = Instant::now();
+        let mut decision = None;
+        let mut annotation = None;
+        let next_move = if game.next_player() == user_player {
+            show_board(&game, &pieces);
+            if args.predict {
+                print_predictions(&game);
+            }
+            // The odds are for the side the human started as, so they stop after a swap
+            if let Some(odds) = odds.as_ref().filter(|odds| odds.human() == user_player) {
+                print_odds(&game, odds);
+            }
+
+            let mut actions: Vec<Action> = possible_moves.into_iter().map(Action::Move).collect();
+            if last_computer_move.is_some() {
+                actions.push(Action::Why);
+            }
+            if swap_allowed {
+                actions.push(Action::Swap);
+            }
+            actions.push(Action::OfferDraw);
+            actions.push(Action::Resign);
+            loop {
+                let prompt = wording("Where will you move?", "Where do you want to go?");
+                match input.select(prompt, actions.clone())? {
+                    Action::Move(selection) => {
+                        if !args.coach {
+                            break selection;
+                        }
+                        let Some(reason) = find_blunder(&game, selection.square) else {
+                            break selection;
+                        };
+                        let square = selection.square;
+                        observers.on_hint(&game, &Hint::Blunder { square, reason });
+                        let warning = blunder_warning(reason);
+                        if input.confirm(&format!("{warning}, play anyway?"))? {
+                            annotation = Some(format!("Played despite the coach: {warning}."));
+                            break selection;
+                        }
+                    }
+                    Action::Why => {
+                        if let Some((before, chosen)) = &last_computer_move {
+                            print_explanation(before, *chosen);
+                            observers.on_hint(&game, &Hint::Explanation { square: *chosen });
+                        }
+                    }
+                    Action::Swap => {
+                        println!(
+                            "You swap sides and take over the computer's {}.",
+                            game.next_player().opponent()
+                        );
+                        game.swap_sides();
+                        record.swapped = true;
+                        user_player = user_player.opponent();
+                        last_computer_move = None;
+                        if let Some(journal) = &mut journal {
+                            journal.record_swap()?;
+                        }
+                        continue 'game;
+                    }
+                    Action::OfferDraw => {
+                        if game.computer_accepts_draw() {
+                            record.ending = Some(Ending::DrawAgreed);
+                            break 'game;
+                        }
+                        println!("The computer declines, it thinks it can still win.");
+                    }
+                    Action::Resign => {
+                        record.ending = Some(Ending::Resigned(user_player));
+                        break 'game;
+                    }
+                }
+            }
         } else {
-            let computer_selection = game.get_random_computer_move();
-            println!("Computer moved to {computer_selection}");
+            let computer_selection = match &rl_table {
+                Some(table) => Selection::new(table.best_move(&game)),
+                None => {
+                    let computer_decision = game
+                        .decide_computer_move_with_rng(ai.blunder_chance.unwrap_or(0.0), &mut rng);
+                    if let Some(think_time) = think_time {
+                        // Not the session's generator, so recordings replay the same with or
+                        // without pauses
+                        pretend_to_think(
+                            think_time.pause(&computer_decision, &mut rand::thread_rng()),
+                        );
+                    }
+                    input.decision(&computer_decision)?;
+                    let selection = Selection::new(computer_decision.chosen);
+                    decision = Some(computer_decision);
+                    selection
+                }
+            };
+            if profile().verbose {
+                println!(
+                    "The computer put its {} on {computer_selection}.",
+                    game.computer_player
+                );
+            } else {
+                println!("Computer moved to {computer_selection}");
+            }
+            last_computer_move = Some((game.clone(), computer_selection.square));
             computer_selection
         };
 
+        if profile().verbose && game.next_player() == user_player {
+            println!("You put your {user_player} on {next_move}.");
+        }
         game.apply_move(next_move.square);
+        observers.on_move(&game, next_move.square, decision.as_ref());
+        let played = record.push(next_move.square, decision);
+        played.stamp(turn_started.elapsed());
+        played.evaluate(&game);
+        played.annotation = annotation;
+        if let Some(journal) = &mut journal {
+            journal.record_move(played)?;
+        }
+    }
+    if let Some(journal) = journal {
+        journal.finish()?;
     }
 
-    println!("{game}");
+    show_board(&game, &pieces);
 
-    match game.winner {
-        Some(player) => {
-            if player == user_player {
-                println!("Congratulations, you won!");
-            } else {
-                println!("You lost, better luck next time.");
-            }
+    print_game_over(&record, user_player, !args.no_taunts, &mut rng)?;
+    observers.on_game_over(&record);
+
+    if input.confirm("Copy a summary of the game to share?")? {
+        share(&share_text(&game, record.status()?));
+    }
+
+    record.opponent = Some(match args.opponent {
+        Opponent::Minimax => format!("computer, {difficulty}"),
+        Opponent::Rl => "reinforcement learning".to_string(),
+    });
+    if let Some(path) = args.save {
+        record.save(&path)?;
+        println!("Saved the game to {}", path.display());
+    }
+    if let Some(name) = &args.slot {
+        save_dir()?.save(name, &record)?;
+        println!("Saved the game as {name}");
+    }
+    // Replays are already saved, in their recordings
+    if !matches!(input, Input::Replaying { .. }) {
+        if let Some(dir) = SaveDir::default_dir() {
+            SaveDir::new(&dir).autosave(&record, config.saves.autosave)?;
+        }
+    }
+    if let Some(path) = &args.markdown {
+        let mut settings = vec![("Difficulty", difficulty.to_string())];
+        if let Some(chance) = ai.blunder_chance {
+            settings.push(("Blunder chance", format!("{}%", (100.0 * chance).round())));
+        }
+        if matches!(args.opponent, Opponent::Rl) {
+            settings.push(("Opponent", "reinforcement learning".to_string()));
         }
-        None => println!("The game ended in a tie."),
+        if ai.pie_rule {
+            settings.push(("Pie rule", "on".to_string()));
+        }
+        write_markdown(&record, &settings, path)?;
     }
 
     Ok(())
 }
+
+/// Whether the side to move can swap under the pie rule: right after the first move, and only if
+/// nobody has swapped yet, so the second player gets exactly one chance
+fn swap_offered(pie_rule: bool, game: &GameState, record: &GameRecord) -> bool {
+    pie_rule && game.can_swap() && !record.swapped
+}
+
+fn write_markdown(
+    record: &GameRecord,
+    settings: &[(&str, String)],
+    path: &Path,
+) -> anyhow::Result<()> {
+    let report = game_report(record, settings)?;
+    std::fs::write(path, report).with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn boxed_banners_have_straight_edges() {
+        for banner in WIN_BANNERS.iter().chain(&LOSS_BANNERS).chain(&TIE_BANNERS) {
+            let lines: Vec<&str> = banner.lines().filter(|line| !line.is_empty()).collect();
+            if !lines[0].trim_start().starts_with('+') {
+                continue;
+            }
+            let width = lines[0].width();
+            for line in &lines {
+                assert_eq!(line.width(), width, "{banner}");
+            }
+        }
+    }
+
+    #[test]
+    fn pie_rule_swap_is_offered_once() {
+        // The computer opens as X, so the human is offered the swap
+        let mut game = GameState::new(Player::X);
+        let mut record = GameRecord::new(game.computer_player, Vec::new());
+        let square = game.get_random_computer_move().square;
+        game.apply_move(square);
+        record.moves.push(MoveRecord::new(Player::X, square));
+        assert!(swap_offered(true, &game, &record));
+        assert!(!swap_offered(false, &game, &record));
+
+        // After the human swaps, the computer is to move with one mark still on the board, but
+        // can't swap back
+        game.swap_sides();
+        record.swapped = true;
+        assert_eq!(game.next_player(), game.computer_player);
+        assert!(game.can_swap());
+        assert!(!swap_offered(true, &game, &record));
+        let replayed = record.position(1).unwrap();
+        assert_eq!(replayed.computer_player, game.computer_player);
+        assert_eq!(replayed.board(), game.board());
+    }
+}
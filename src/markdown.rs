@@ -0,0 +1,136 @@
+//! Finished games written up as Markdown, with the settings, a board diagram for every move, how
+//! each move left the result with best play and the final result, for blog posts and notes
+
+use crate::ai::{perfect_play_winner, GameResult};
+use crate::game::{GameState, GameStatus, Player, Selection};
+use crate::record::{Ending, GameRecord, MoveRecord};
+
+/// Write a game up as Markdown. `settings` are extra rows for the settings table, such as the
+/// difficulty, which the record doesn't keep
+pub fn game_report(record: &GameRecord, settings: &[(&str, String)]) -> crate::Result<String> {
+    let positions = record.positions()?;
+    let human = record.human_player();
+    let start = if record.start.is_some() {
+        "a set-up position"
+    } else {
+        "the empty board"
+    };
+
+    let mut lines = vec![
+        format!("# Tic-tac-toe: {human} against the computer"),
+        String::new(),
+        "## Settings".to_string(),
+        String::new(),
+        "| Setting | Value |".to_string(),
+        "| --- | --- |".to_string(),
+        format!("| You played | {human} |"),
+        format!("| The computer played | {} |", human.opponent()),
+        format!("| Started from | {start} |"),
+    ];
+    lines.extend(
+        settings
+            .iter()
+            .map(|(name, value)| format!("| {name} | {value} |")),
+    );
+
+    lines.extend([
+        String::new(),
+        "## Moves".to_string(),
+        String::new(),
+        format!(
+            "The game started from {start}, {}:",
+            best_play(&positions[0])
+        ),
+        String::new(),
+        diagram(&positions[0]),
+    ]);
+    for (i, (played, window)) in record.moves.iter().zip(positions.windows(2)).enumerate() {
+        let (before, after) = (&window[0], &window[1]);
+        let player = before.next_player();
+        let mut notes = vec![format!("Now {}.", best_play(after))];
+        if outcome_for(after, player) < outcome_for(before, player) {
+            notes.push(format!("A mistake, {player} had a better move."));
+        }
+        if let Some(decision) = record.decision(i) {
+            let candidates: Vec<String> = decision
+                .candidates
+                .iter()
+                .map(|c| format!("{} ({})", Selection::new(c.square), c.result))
+                .collect();
+            notes.push(format!("The computer weighed {}.", candidates.join(", ")));
+        }
+        if let Some(ms) = played.think_time {
+            notes.push(format!("It took {:.1} seconds.", ms as f64 / 1000.0));
+        }
+        if let Some(annotation) = &played.annotation {
+            notes.push(annotation.clone());
+        }
+        if i == 0 && record.swapped {
+            notes.push("The players swapped sides after this move.".to_string());
+        }
+        lines.extend([
+            String::new(),
+            format!(
+                "### {}. {player} to {}{}",
+                i + 1,
+                Selection::new(played.square),
+                glyph(played)
+            ),
+            String::new(),
+            diagram(after),
+            String::new(),
+            notes.join(" "),
+        ]);
+    }
+
+    lines.extend([String::new(), "## Result".to_string(), String::new()]);
+    match record.ending {
+        Some(Ending::Resigned(player)) => lines.push(format!("{player} resigned.")),
+        Some(Ending::DrawAgreed) => lines.push("The players agreed to a draw.".to_string()),
+        None => {}
+    }
+    lines.push(match record.status()? {
+        GameStatus::Won(winner) => format!("{winner} won."),
+        GameStatus::Tie => "The game was a tie.".to_string(),
+        GameStatus::InProgress => "The game wasn't finished.".to_string(),
+    });
+    Ok(lines.join("\n") + "\n")
+}
+
+/// The move's glyph, if it has one
+fn glyph(played: &MoveRecord) -> String {
+    played
+        .glyph
+        .map_or(String::new(), |glyph| glyph.to_string())
+}
+
+/// A board in a code block, so it keeps its spacing
+fn diagram(state: &GameState) -> String {
+    format!("```text\n{state}```")
+}
+
+/// The result with best play for both sides from a position, in words
+fn best_play(state: &GameState) -> String {
+    match state.status() {
+        GameStatus::InProgress => match perfect_play_winner(state) {
+            Some(winner) => format!("{winner} wins with best play"),
+            None => "a tie with best play".to_string(),
+        },
+        GameStatus::Won(winner) => format!("{winner} has won"),
+        GameStatus::Tie => "a tie".to_string(),
+    }
+}
+
+/// The result with best play for one side
+fn outcome_for(state: &GameState, player: Player) -> GameResult {
+    let winner = match state.status() {
+        GameStatus::Won(winner) => Some(winner),
+        GameStatus::Tie => None,
+        GameStatus::InProgress => perfect_play_winner(state),
+    };
+    match winner {
+        Some(winner) if winner == player => GameResult::Win,
+        Some(_) => GameResult::Loss,
+        None => GameResult::Tie,
+    }
+}
@@ -0,0 +1,192 @@
+//! A Matrix bot playing the chat games in rooms it's invited to
+//!
+//! Commands are the same text commands as the other chat bots. Each board the bot sends gets a
+//! keycap reaction for every open square, so the player can move by clicking one instead of
+//! typing. Only unencrypted rooms are supported.
+
+use crate::chat::ChatGames;
+use crate::error::{BoxError, Error};
+use matrix_sdk::config::SyncSettings;
+use matrix_sdk::ruma::events::reaction::{OriginalSyncReactionEvent, ReactionEventContent};
+use matrix_sdk::ruma::events::relation::Annotation;
+use matrix_sdk::ruma::events::room::member::StrippedRoomMemberEvent;
+use matrix_sdk::ruma::events::room::message::{
+    MessageType, OriginalSyncRoomMessageEvent, RoomMessageEventContent,
+};
+use matrix_sdk::ruma::OwnedEventId;
+use matrix_sdk::{Client, Room};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Which account to log in as
+#[derive(Clone, Debug)]
+pub struct MatrixOptions {
+    /// URL of the homeserver, e.g. `https://matrix.org`
+    pub homeserver: String,
+    pub user: String,
+    pub password: String,
+    /// Word that starts every command to the bot
+    pub prefix: String,
+}
+
+struct Bot {
+    games: ChatGames,
+    /// The latest board sent to each player, the only message their reactions count as moves on
+    boards: HashMap<String, OwnedEventId>,
+}
+
+/// Log in and play games until the connection to the homeserver fails
+pub fn run_matrix_bot(options: &MatrixOptions) -> crate::Result<()> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(failed("start the async runtime"))?
+        .block_on(run(options))
+}
+
+async fn run(options: &MatrixOptions) -> crate::Result<()> {
+    let client = Client::builder()
+        .homeserver_url(&options.homeserver)
+        .build()
+        .await
+        .map_err(failed("connect to the homeserver"))?;
+    client
+        .matrix_auth()
+        .login_username(&options.user, &options.password)
+        .initial_device_display_name("simple-tic-tac-toe")
+        .send()
+        .await
+        .map_err(failed("log in to the homeserver"))?;
+    // Sync once before listening so messages sent while the bot was away aren't answered
+    let response = client
+        .sync_once(SyncSettings::default())
+        .await
+        .map_err(failed("sync with the homeserver"))?;
+    tracing::info!(user = options.user, "logged in");
+
+    let bot = Arc::new(Mutex::new(Bot {
+        games: ChatGames::new(&options.prefix),
+        boards: HashMap::new(),
+    }));
+    client.add_event_handler(on_invite);
+    let messages = bot.clone();
+    client.add_event_handler(
+        move |event: OriginalSyncRoomMessageEvent, room: Room, client: Client| {
+            let bot = messages.clone();
+            async move {
+                let MessageType::Text(text) = &event.content.msgtype else {
+                    return;
+                };
+                if client.user_id() == Some(&*event.sender) {
+                    return;
+                }
+                respond(&bot, &room, event.sender.as_str(), &text.body).await;
+            }
+        },
+    );
+    client.add_event_handler(move |event: OriginalSyncReactionEvent, room: Room| {
+        let bot = bot.clone();
+        async move {
+            let user = event.sender.as_str();
+            let Annotation { event_id, key, .. } = &event.content.relates_to;
+            let Some(square) = parse_keycap(key) else {
+                return;
+            };
+            let command = {
+                let bot = bot.lock().unwrap();
+                if bot.boards.get(user) != Some(event_id) {
+                    return;
+                }
+                format!("{} {}", bot.games.prefix(), square + 1)
+            };
+            respond(&bot, &room, user, &command).await;
+        }
+    });
+
+    client
+        .sync(SyncSettings::default().token(response.next_batch))
+        .await
+        .map_err(failed("sync with the homeserver"))
+}
+
+/// Join every room the bot is invited to
+async fn on_invite(event: StrippedRoomMemberEvent, room: Room, client: Client) {
+    if client.user_id() != Some(&*event.state_key) {
+        return;
+    }
+    if let Err(e) = room.join().await {
+        tracing::warn!(room = %room.room_id(), error = %e, "failed to join room");
+    }
+}
+
+/// Handle a command from `user` and send the reply, with move reactions if their game goes on
+async fn respond(bot: &Mutex<Bot>, room: &Room, user: &str, message: &str) {
+    let (lines, open_squares) = {
+        let mut bot = bot.lock().unwrap();
+        let lines = bot.games.handle(user, message);
+        let open_squares: Vec<usize> = match bot.games.game(user) {
+            Some(game) => game.legal_moves().collect(),
+            None => {
+                bot.boards.remove(user);
+                Vec::new()
+            }
+        };
+        (lines, open_squares)
+    };
+    if lines.is_empty() {
+        return;
+    }
+
+    let text = lines.join("\n");
+    let html = format!("<pre>{}</pre>", escape_html(&text));
+    let sent = match room
+        .send(RoomMessageEventContent::notice_html(text, html))
+        .await
+    {
+        Ok(sent) => sent.response.event_id,
+        Err(e) => {
+            tracing::warn!(room = %room.room_id(), error = %e, "failed to send reply");
+            return;
+        }
+    };
+    if open_squares.is_empty() {
+        return;
+    }
+    bot.lock()
+        .unwrap()
+        .boards
+        .insert(user.to_string(), sent.clone());
+    for square in open_squares {
+        let reaction = Annotation::new(sent.clone(), keycap(square));
+        if let Err(e) = room.send(ReactionEventContent::new(reaction)).await {
+            tracing::warn!(room = %room.room_id(), error = %e, "failed to add move reaction");
+            return;
+        }
+    }
+}
+
+/// The keycap emoji for a square, 1️⃣ for the top left through 9️⃣ for the bottom right
+fn keycap(square: usize) -> String {
+    format!("{}\u{fe0f}\u{20e3}", square + 1)
+}
+
+fn parse_keycap(key: &str) -> Option<usize> {
+    let digit = key
+        .strip_suffix("\u{fe0f}\u{20e3}")
+        .or_else(|| key.strip_suffix('\u{20e3}'))?;
+    let digit: usize = digit.parse().ok()?;
+    (1..=9).contains(&digit).then(|| digit - 1)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn failed<E: Into<BoxError>>(action: &'static str) -> impl FnOnce(E) -> Error {
+    move |e| Error::Matrix {
+        action,
+        source: e.into(),
+    }
+}
@@ -0,0 +1,319 @@
+//! Monte Carlo tree search, which works for any [`Game`] and any board size by playing random
+//! games to the end instead of searching every line
+//!
+//! Each iteration walks down the tree by UCT, always taking the child with the best win rate plus
+//! a bonus for being little explored, adds one new move, plays randomly from there to the end and
+//! credits the result to every move on the way. It's only ever an estimate, but it gets better
+//! the more iterations it has, and it doesn't need [`Game::evaluate`].
+//!
+//! The tree is capped in size so a long think on a big board can't run out of memory. When it
+//! reaches the cap, the least visited half of it is thrown away and those moves go back to being
+//! untried, to be grown again if the search comes back to them.
+//!
+//! A [`Tree`] can be kept between moves: moving it on to the position after a move keeps what it
+//! found below that move, so thinking done earlier, even on the opponent's time, isn't wasted.
+
+use crate::game::{GameStatus, Player};
+use crate::games::Game;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::cmp::Reverse;
+
+/// How much the search favours moves it has tried less, the usual square root of 2
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+/// Random games longer than this are scored as ties, for games that can go on forever
+const MAX_PLAYOUT: usize = 1000;
+
+/// The tree size cap unless one is given, 64 MiB
+const MEMORY_LIMIT: usize = 64 << 20;
+
+/// A search with a fixed number of iterations per decision
+#[derive(Copy, Clone, Debug)]
+pub struct Mcts {
+    iterations: u32,
+    /// Roughly how many bytes the tree may take up
+    memory_limit: usize,
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node<M> {
+    /// The player who made the move leading here, whose wins `reward` counts
+    mover: Player,
+    children: Vec<(M, usize)>,
+    untried: Vec<M>,
+    visits: u32,
+    /// One for each win from here, a half for each tie
+    reward: f64,
+}
+
+impl Mcts {
+    pub fn new(iterations: u32) -> Self {
+        Self {
+            iterations: iterations.max(1),
+            memory_limit: MEMORY_LIMIT,
+        }
+    }
+
+    /// Cap the tree at roughly this many bytes, pruning it whenever it gets there
+    pub fn with_memory_limit(self, bytes: usize) -> Self {
+        Self {
+            memory_limit: bytes,
+            ..self
+        }
+    }
+
+    /// An empty tree for a position, to grow with [`Mcts::think`] or [`Tree::grow`]
+    pub fn tree<G: Game>(&self, game: &G) -> Tree<G> {
+        let mut tree = Tree {
+            game: game.clone(),
+            nodes: Vec::new(),
+            root: 0,
+            memory: 0,
+            memory_limit: self.memory_limit,
+        };
+        tree.reset();
+        tree
+    }
+
+    /// Grow a tree until its root has been visited as many times as this search's iterations.
+    /// A tree that got a head start needs fewer
+    pub fn think<G: Game>(&self, tree: &mut Tree<G>, rng: &mut impl Rng) {
+        while tree.visits() < self.iterations {
+            tree.grow(rng);
+        }
+    }
+
+    /// Every legal move with the share of the random games after it that the side to move won,
+    /// counting ties as half
+    pub fn evaluate<G: Game>(&self, game: &G, rng: &mut impl Rng) -> Vec<(G::Move, f64)> {
+        let mut tree = self.tree(game);
+        self.think(&mut tree, rng);
+        tree.evaluate()
+    }
+
+    /// The move the search tried most, which is the one it trusts most
+    pub fn decide<G: Game>(&self, game: &G, rng: &mut impl Rng) -> Option<G::Move> {
+        let mut tree = self.tree(game);
+        self.think(&mut tree, rng);
+        tree.best_move()
+    }
+}
+
+/// A search tree rooted at one position, which can be grown a little at a time
+#[derive(Clone, Debug)]
+pub struct Tree<G: Game> {
+    game: G,
+    /// Every node after its parent. Nodes before the root, or beside it, are left over from
+    /// earlier positions until the tree is next pruned
+    nodes: Vec<Node<G::Move>>,
+    root: usize,
+    /// Roughly how many bytes the nodes take up, left over ones included
+    memory: usize,
+    memory_limit: usize,
+}
+
+impl<G: Game> Tree<G> {
+    /// The position at the root
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// How many random games have been played from the root
+    pub fn visits(&self) -> u32 {
+        self.nodes[self.root].visits
+    }
+
+    /// Every move tried from the root with the share of the random games after it that the side
+    /// to move won, counting ties as half
+    pub fn evaluate(&self) -> Vec<(G::Move, f64)> {
+        self.nodes[self.root]
+            .children
+            .iter()
+            .map(|&(m, child)| {
+                let child = &self.nodes[child];
+                (m, child.reward / f64::from(child.visits))
+            })
+            .collect()
+    }
+
+    /// The move tried most from the root
+    pub fn best_move(&self) -> Option<G::Move> {
+        self.nodes[self.root]
+            .children
+            .iter()
+            .max_by_key(|&&(_, child)| self.nodes[child].visits)
+            .map(|&(m, _)| m)
+    }
+
+    /// Run one iteration: walk down, add a move, play the rest out at random and record it
+    pub fn grow(&mut self, rng: &mut impl Rng) {
+        if self.memory > self.memory_limit && self.nodes.len() > 1 {
+            self.prune();
+        }
+        let nodes = &mut self.nodes;
+        let mut state = self.game.clone();
+        let mut path = vec![self.root];
+        let mut current = self.root;
+        while nodes[current].untried.is_empty() && !nodes[current].children.is_empty() {
+            let (m, child) = best_child(nodes, current);
+            play(&mut state, m);
+            path.push(child);
+            current = child;
+        }
+        if !nodes[current].untried.is_empty() {
+            let index = rng.gen_range(0..nodes[current].untried.len());
+            let m = nodes[current].untried.swap_remove(index);
+            let mover = state.next_player();
+            play(&mut state, m);
+            let node = Node {
+                mover,
+                children: Vec::new(),
+                untried: state.legal_moves(),
+                visits: 0,
+                reward: 0.0,
+            };
+            self.memory += footprint(&node);
+            nodes.push(node);
+            let child = nodes.len() - 1;
+            nodes[current].children.push((m, child));
+            path.push(child);
+        }
+        let status = playout(state, rng);
+        for &id in &path {
+            let node = &mut nodes[id];
+            node.visits += 1;
+            node.reward += match status {
+                GameStatus::Won(winner) if winner == node.mover => 1.0,
+                GameStatus::Won(_) => 0.0,
+                _ => 0.5,
+            };
+        }
+    }
+
+    /// Move the root on to the position after a move, keeping what was found below it
+    pub fn play(&mut self, m: G::Move) {
+        play(&mut self.game, m);
+        let child = self.nodes[self.root]
+            .children
+            .iter()
+            .find(|&&(tried, _)| tried == m)
+            .map(|&(_, child)| child);
+        match child {
+            Some(child) => self.root = child,
+            None => self.reset(),
+        }
+    }
+
+    /// Start over with just the root
+    fn reset(&mut self) {
+        let root = Node {
+            mover: self.game.next_player().opponent(),
+            children: Vec::new(),
+            untried: self.game.legal_moves(),
+            visits: 0,
+            reward: 0.0,
+        };
+        self.memory = footprint(&root);
+        self.nodes = vec![root];
+        self.root = 0;
+    }
+
+    /// Throw away the nodes left over from earlier positions, and then the least visited of the
+    /// rest until at most half the nodes are left
+    ///
+    /// A node never has more visits than its parent and always comes after it, so taking nodes by
+    /// most visits, earliest first, takes every kept node's parent before it and the root first.
+    fn prune(&mut self) {
+        let nodes = &self.nodes;
+        let mut order = subtree(nodes, self.root);
+        order.sort_by_key(|&id| (Reverse(nodes[id].visits), id));
+        order.truncate(nodes.len().div_ceil(2));
+        self.keep(&order);
+    }
+
+    /// Keep only the given nodes, in that order, each after its parent. The first becomes the
+    /// root, and moves to children that aren't kept go back to being untried
+    fn keep(&mut self, ids: &[usize]) {
+        self.root = 0;
+        let mut new_ids = vec![None; self.nodes.len()];
+        for (new_id, &id) in ids.iter().enumerate() {
+            new_ids[id] = Some(new_id);
+        }
+        let mut old: Vec<Option<Node<G::Move>>> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.nodes.reserve(ids.len());
+        self.memory = 0;
+        for &id in ids {
+            let mut node = old[id].take().expect("each node is kept once");
+            node.children
+                .retain_mut(|(m, child)| match new_ids[*child] {
+                    Some(new_id) => {
+                        *child = new_id;
+                        true
+                    }
+                    None => {
+                        node.untried.push(*m);
+                        false
+                    }
+                });
+            self.memory += footprint(&node);
+            self.nodes.push(node);
+        }
+    }
+}
+
+/// A node and everything below it, each node after its parent
+fn subtree<M>(nodes: &[Node<M>], root: usize) -> Vec<usize> {
+    let mut ids = vec![root];
+    let mut next = 0;
+    while next < ids.len() {
+        ids.extend(nodes[ids[next]].children.iter().map(|&(_, child)| child));
+        next += 1;
+    }
+    ids
+}
+
+/// Roughly the bytes a node takes up, counting room for all its children
+fn footprint<M>(node: &Node<M>) -> usize {
+    let moves = node.untried.len() + node.children.len();
+    std::mem::size_of::<Node<M>>()
+        + moves * (std::mem::size_of::<M>() + std::mem::size_of::<(M, usize)>())
+}
+
+/// The child with the best upper confidence bound
+fn best_child<M: Copy>(nodes: &[Node<M>], parent: usize) -> (M, usize) {
+    let log_visits = f64::from(nodes[parent].visits).ln();
+    let bound = |child: usize| {
+        let visits = f64::from(nodes[child].visits);
+        nodes[child].reward / visits + EXPLORATION * (log_visits / visits).sqrt()
+    };
+    *nodes[parent]
+        .children
+        .iter()
+        .max_by(|&&(_, a), &&(_, b)| bound(a).total_cmp(&bound(b)))
+        .expect("only called on nodes with children")
+}
+
+fn play<G: Game>(state: &mut G, m: G::Move) {
+    state.play(m).expect("the tree only holds legal moves");
+}
+
+/// Play random moves to the end of the game
+fn playout<G: Game>(mut state: G, rng: &mut impl Rng) -> GameStatus {
+    for _ in 0..MAX_PLAYOUT {
+        let Some(&m) = state.legal_moves().choose(rng) else {
+            return state.status();
+        };
+        play(&mut state, m);
+    }
+    GameStatus::Tie
+}
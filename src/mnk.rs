@@ -0,0 +1,1032 @@
+//! Tic-tac-toe on a board of any size with any line length to win (an m,n,k-game), optionally
+//! with gravity, where marks fall to the lowest empty square of a column like Connect Four, with
+//! lines that wrap around the edges, or misère, where making a line loses
+//!
+//! Marks are kept row by row from the top left, like the 3x3 board, and X always moves first so
+//! the side to move follows from the marks. The search here is a plain alpha-beta with a
+//! transposition table, which solves boards up to about 4x4 outright. [`Computer`] picks between
+//! it, the solved 3x3 tables and MCTS by how big the board is.
+
+use crate::ai::{CancelToken, GameResult};
+use crate::error::{Error, MoveError};
+use crate::game::{GameState, GameStatus, Player};
+use crate::games::Game;
+#[cfg(feature = "ai-mcts")]
+use crate::mcts::{Mcts, Tree};
+use crate::render::{render_grid, Pieces};
+use crate::{solved, tablebase};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+#[cfg(feature = "storage")]
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread::JoinHandle;
+
+/// The shape of the board and how a game on it is won
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub width: usize,
+    pub height: usize,
+    /// How many marks in a row win
+    pub win_length: usize,
+    /// Whether moves pick a column and the mark falls to the bottom of it
+    pub gravity: bool,
+    /// Whether completing a line loses instead of wins
+    pub misere: bool,
+    /// Whether lines carry on from one edge of the board to the opposite one
+    pub wrap: bool,
+}
+
+impl Rules {
+    pub fn new(width: usize, height: usize, win_length: usize) -> crate::Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(Error::Config(format!(
+                "a {width}x{height} board has no squares"
+            )));
+        }
+        if win_length == 0 || win_length > width.max(height) {
+            return Err(Error::Config(format!(
+                "{win_length} in a row can't be made on a {width}x{height} board"
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            win_length,
+            gravity: false,
+            misere: false,
+            wrap: false,
+        })
+    }
+
+    pub fn with_gravity(self, gravity: bool) -> Self {
+        Self { gravity, ..self }
+    }
+
+    pub fn with_misere(self, misere: bool) -> Self {
+        Self { misere, ..self }
+    }
+
+    pub fn with_wrap(self, wrap: bool) -> Self {
+        Self { wrap, ..self }
+    }
+
+    pub fn squares(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Whether these are the rules of ordinary tic-tac-toe
+    pub fn is_standard(&self) -> bool {
+        *self == Self::new(3, 3, 3).expect("3 in a row fits on a 3x3 board")
+    }
+
+    /// A rough upper bound on the number of positions: each square empty, X or O, or under
+    /// gravity each column filled to any height with any marks
+    pub fn state_space(&self) -> f64 {
+        if self.gravity {
+            let column = 2f64.powi(self.height as i32 + 1) - 1.0;
+            column.powi(self.width as i32)
+        } else {
+            3f64.powi(self.squares() as i32)
+        }
+    }
+}
+
+/// Positions the alpha-beta search can be trusted to solve in a few seconds
+#[cfg(feature = "ai-mcts")]
+const ALPHA_BETA_STATES: f64 = 1e8;
+
+/// Ways for the computer to choose its moves
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EngineKind {
+    /// Look the position up in the solved tables, only for ordinary tic-tac-toe
+    Tablebase,
+    /// Solve the position with [`Search`]
+    AlphaBeta,
+    /// Estimate the best move with [`Mcts`], for boards too big to solve
+    #[cfg(feature = "ai-mcts")]
+    Mcts,
+}
+
+impl EngineKind {
+    /// The exact engine with the least work that can handle the rules, or MCTS when the board is
+    /// too big for either. Without the `ai-mcts` feature, alpha-beta takes on every board
+    pub fn for_rules(rules: &Rules) -> Self {
+        if rules.is_standard() {
+            return EngineKind::Tablebase;
+        }
+        #[cfg(feature = "ai-mcts")]
+        if rules.state_space() > ALPHA_BETA_STATES {
+            return EngineKind::Mcts;
+        }
+        EngineKind::AlphaBeta
+    }
+}
+
+impl Display for EngineKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineKind::Tablebase => write!(f, "tablebase"),
+            EngineKind::AlphaBeta => write!(f, "alpha-beta"),
+            #[cfg(feature = "ai-mcts")]
+            EngineKind::Mcts => write!(f, "mcts"),
+        }
+    }
+}
+
+impl FromStr for EngineKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tablebase" => Ok(EngineKind::Tablebase),
+            "alpha-beta" => Ok(EngineKind::AlphaBeta),
+            #[cfg(feature = "ai-mcts")]
+            "mcts" => Ok(EngineKind::Mcts),
+            #[cfg(not(feature = "ai-mcts"))]
+            "mcts" => Err(Error::Config(
+                "the mcts engine requires building with the `ai-mcts` feature".to_string(),
+            )),
+            _ => Err(Error::parse(format!(
+                "`{s}` is not an engine, expected tablebase, alpha-beta or mcts"
+            ))),
+        }
+    }
+}
+
+/// A move, which names a column under gravity rules and a square otherwise
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Move {
+    Square(usize),
+    Column(usize),
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Move::Square(square) => write!(f, "square {}", square + 1),
+            Move::Column(column) => write!(f, "column {}", column + 1),
+        }
+    }
+}
+
+/// Widest board, counting the extra bits an anti-diagonal shifts in, whose rows fit in a `u64`
+const PACKED_WIDTH: usize = 64;
+
+/// A game in progress under some [`Rules`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    rules: Rules,
+    cells: Vec<Option<Player>>,
+    /// Each player's marks with a word for each row, column 0 the lowest bit, X first. Only kept
+    /// when the rows are narrow enough and lines don't wrap, see [`Board::completes_line`]
+    rows: Option<[Vec<u64>; 2]>,
+    pub next_player: Player,
+    winner: Option<Player>,
+}
+
+impl Board {
+    pub fn new(rules: Rules) -> Self {
+        let packed = !rules.wrap && rules.width + rules.win_length <= PACKED_WIDTH;
+        Self {
+            rules,
+            cells: vec![None; rules.squares()],
+            rows: packed.then(|| [vec![0; rules.height], vec![0; rules.height]]),
+            next_player: Player::X,
+            winner: None,
+        }
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn cells(&self) -> &[Option<Player>] {
+        &self.cells
+    }
+
+    pub fn status(&self) -> GameStatus {
+        match self.winner {
+            Some(player) => GameStatus::Won(player),
+            None if self.cells.iter().all(Option::is_some) => GameStatus::Tie,
+            None => GameStatus::InProgress,
+        }
+    }
+
+    /// Every move the side to move can make
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.status() != GameStatus::InProgress {
+            return Vec::new();
+        }
+        if self.rules.gravity {
+            (0..self.rules.width)
+                .filter(|&column| self.landing_square(column).is_some())
+                .map(Move::Column)
+                .collect()
+        } else {
+            (0..self.cells.len())
+                .filter(|&square| self.cells[square].is_none())
+                .map(Move::Square)
+                .collect()
+        }
+    }
+
+    /// The square a move puts a mark on, if it's legal. Under gravity rules a square can be given
+    /// instead of a column as long as it's the one the mark would fall to
+    pub fn target(&self, m: Move) -> Result<usize, MoveError> {
+        if self.status() != GameStatus::InProgress {
+            return Err(MoveError::Finished);
+        }
+        let width = self.rules.width;
+        match m {
+            Move::Column(column) if column >= width || !self.rules.gravity => {
+                Err(MoveError::NoColumn(column))
+            }
+            Move::Column(column) => self
+                .landing_square(column)
+                .ok_or(MoveError::ColumnFull(column)),
+            Move::Square(square) if square >= self.cells.len() => Err(MoveError::OffBoard(square)),
+            Move::Square(square) if self.cells[square].is_some() => Err(MoveError::Occupied {
+                row: square / width,
+                column: square % width,
+            }),
+            Move::Square(square)
+                if self.rules.gravity && self.landing_square(square % width) != Some(square) =>
+            {
+                Err(MoveError::Floating {
+                    row: square / width,
+                    column: square % width,
+                })
+            }
+            Move::Square(square) => Ok(square),
+        }
+    }
+
+    /// Make a move for the side to move, returning the square the mark went on
+    pub fn play(&mut self, m: Move) -> Result<usize, MoveError> {
+        let square = self.target(m)?;
+        self.place(square);
+        Ok(square)
+    }
+
+    /// The lowest empty square of a column
+    fn landing_square(&self, column: usize) -> Option<usize> {
+        (0..self.rules.height)
+            .rev()
+            .map(|row| row * self.rules.width + column)
+            .find(|&square| self.cells[square].is_none())
+    }
+
+    fn place(&mut self, square: usize) {
+        let player = self.next_player;
+        self.cells[square] = Some(player);
+        let width = self.rules.width;
+        if let Some(rows) = &mut self.rows {
+            rows[index(player)][square / width] |= 1 << (square % width);
+        }
+        self.next_player = player.opponent();
+        if self.completes_line(square) {
+            self.winner = Some(if self.rules.misere {
+                player.opponent()
+            } else {
+                player
+            });
+        }
+    }
+
+    /// Take back a mark placed on a board that had no winner before it
+    fn unplace(&mut self, square: usize) {
+        if let (Some(rows), Some(player)) = (&mut self.rows, self.cells[square]) {
+            let width = self.rules.width;
+            rows[index(player)][square / width] &= !(1 << (square % width));
+        }
+        self.cells[square] = None;
+        self.next_player = self.next_player.opponent();
+        self.winner = None;
+    }
+
+    /// Whether the mark on a square is part of a line long enough to end the game
+    ///
+    /// Win checks are most of the work in a random playout, so boards with packed rows check
+    /// whole rows at a time: ANDing a row with itself shifted along by one bit up to
+    /// `win_length - 1` times leaves a bit set wherever a line starts, and the same with
+    /// consecutive rows, shifted along for the diagonals, finds the other lines.
+    fn completes_line(&self, square: usize) -> bool {
+        let Some(rows) = &self.rows else {
+            return self.completes_line_by_squares(square);
+        };
+        let player = self.cells[square].expect("the square was just played");
+        let rows = &rows[index(player)];
+        let Rules {
+            width, win_length, ..
+        } = self.rules;
+        let (row, column) = (square / width, square % width);
+        // Bits from `low` to `high` inclusive
+        let bits = |low: usize, high: usize| (u64::MAX >> (63 - high)) & (u64::MAX << low);
+
+        let mut across = rows[row];
+        for shift in 1..win_length {
+            across &= rows[row] >> shift;
+        }
+        if across & bits(column.saturating_sub(win_length - 1), column) != 0 {
+            return true;
+        }
+
+        let Some(last) = self.rules.height.checked_sub(win_length) else {
+            return false;
+        };
+        (row.saturating_sub(win_length - 1)..=row.min(last)).any(|top| {
+            let (mut down, mut right, mut left) = (u64::MAX, u64::MAX, u64::MAX);
+            for i in 0..win_length {
+                down &= rows[top + i];
+                right &= rows[top + i] >> i;
+                left &= rows[top + i] << i;
+            }
+            // A line from `top` through the square starts this many columns along
+            let offset = row - top;
+            down & 1 << column != 0
+                || column >= offset && right & 1 << (column - offset) != 0
+                || left & 1 << (column + offset) != 0
+        })
+    }
+
+    /// [`Board::completes_line`] a square at a time, for wide boards and lines that wrap
+    fn completes_line_by_squares(&self, square: usize) -> bool {
+        let Rules {
+            width,
+            height,
+            win_length,
+            wrap,
+            ..
+        } = self.rules;
+        let player = self.cells[square];
+        let (row, column) = ((square / width) as isize, (square % width) as isize);
+        let (w, h) = (width as isize, height as isize);
+        let run = |dr: isize, dc: isize| {
+            (1..win_length as isize)
+                .map(|i| (row + dr * i, column + dc * i))
+                .map(|(r, c)| {
+                    if wrap {
+                        Some((r.rem_euclid(h), c.rem_euclid(w)))
+                    } else {
+                        ((0..h).contains(&r) && (0..w).contains(&c)).then_some((r, c))
+                    }
+                })
+                .take_while(|cell| {
+                    cell.is_some_and(|(r, c)| self.cells[(r * w + c) as usize] == player)
+                })
+                .count()
+        };
+        [(0, 1), (1, 0), (1, 1), (1, -1)].iter().any(|&(dr, dc)| {
+            let mut length = 1 + run(dr, dc) + run(-dr, -dc);
+            if wrap {
+                // A line all the way around the board would otherwise count its squares twice
+                let around = match (dr, dc) {
+                    (0, _) => width,
+                    (_, 0) => height,
+                    _ => lcm(width, height),
+                };
+                length = length.min(around);
+            }
+            length >= win_length
+        })
+    }
+
+    /// Draw the board, numbering the columns underneath under gravity rules
+    pub fn render(&self, pieces: &Pieces) -> String {
+        let cells: Vec<&str> = self.cells.iter().map(|&cell| pieces.get(cell)).collect();
+        let mut s = render_grid(&cells, self.rules.width, pieces.cell_width());
+        if self.rules.gravity {
+            let numbers: Vec<String> = (1..=self.rules.width)
+                .map(|n| format!(" {n:<width$} ", width = pieces.cell_width()))
+                .collect();
+            s.push('\n');
+            s.push_str(&numbers.join(" "));
+        }
+        s
+    }
+}
+
+/// The first bytes of a cached search table, then a format version byte
+const CACHE_MAGIC: &[u8; 4] = b"TTMN";
+const CACHE_VERSION: u8 = 1;
+
+/// An alpha-beta search that remembers the positions it has solved between moves, and between
+/// runs if its table is saved to the cache
+///
+/// A cached table is the bytes `TTMN`, a format version byte and the rules it was searched
+/// under, then one record per position: the board with two bits a square, 1 for X and 2 for O,
+/// a byte saying whether the score is exact, a lower or an upper bound, and the score.
+#[derive(Debug, Default)]
+pub struct Search {
+    table: HashMap<Vec<Option<Player>>, Entry>,
+    /// Stops the search midway, leaving the table as it was before the position being searched
+    cancel: Option<CancelToken>,
+    /// Set once the search noticed it was cancelled, after which nothing goes in the table
+    cancelled: bool,
+}
+
+/// A solved position's score, or a bound on it when the search was cut off
+#[derive(Copy, Clone, Debug)]
+enum Entry {
+    Exact(i32),
+    AtLeast(i32),
+    AtMost(i32),
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where the table for some rules is cached, e.g.
+    /// `~/.cache/simple-tic-tac-toe/mnk/4x4-4-wrap.bin` on Linux
+    #[cfg(feature = "storage")]
+    pub fn cache_path(rules: &Rules) -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| {
+            dir.join("simple-tic-tac-toe")
+                .join("mnk")
+                .join(format!("{}.bin", rules_key(rules)))
+        })
+    }
+
+    /// Positions in the table
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Read a table written by `save` under the same rules. A missing table, or one from other
+    /// rules or an older format, gives an empty one to start over with
+    pub fn load(path: &Path, rules: &Rules) -> crate::Result<Self> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(Error::io("read", path)(e)),
+        };
+        let not_a_table = || {
+            Error::parse(format!(
+                "{} is not a search table, delete it to start over",
+                path.display()
+            ))
+        };
+        let header = cache_header(rules);
+        if data.len() < header.len() || data[..CACHE_MAGIC.len()] != *CACHE_MAGIC {
+            return Err(not_a_table());
+        }
+        let (found, records) = data.split_at(header.len());
+        if found != header.as_slice() {
+            tracing::debug!(path = %path.display(), "ignoring a stale search table");
+            return Ok(Self::new());
+        }
+        let key_len = rules.squares().div_ceil(4);
+        let record_len = key_len + 5;
+        if records.len() % record_len != 0 {
+            return Err(not_a_table());
+        }
+        let mut table = HashMap::with_capacity(records.len() / record_len);
+        for record in records.chunks_exact(record_len) {
+            let (key, entry) = record.split_at(key_len);
+            let cells = (0..rules.squares())
+                .map(|square| match key[square / 4] >> (2 * (square % 4)) & 3 {
+                    0 => Ok(None),
+                    1 => Ok(Some(Player::X)),
+                    2 => Ok(Some(Player::O)),
+                    _ => Err(not_a_table()),
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+            let score = i32::from_le_bytes(entry[1..].try_into().expect("scores are four bytes"));
+            let entry = match entry[0] {
+                0 => Entry::Exact(score),
+                1 => Entry::AtLeast(score),
+                2 => Entry::AtMost(score),
+                _ => return Err(not_a_table()),
+            };
+            table.insert(cells, entry);
+        }
+        Ok(Self {
+            table,
+            ..Self::default()
+        })
+    }
+
+    /// Write the table to a file for the rules it was searched under, creating its directory if
+    /// needed
+    pub fn save(&self, path: &Path, rules: &Rules) -> crate::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+        }
+        let mut data = cache_header(rules);
+        for (cells, entry) in &self.table {
+            let mut key = vec![0u8; cells.len().div_ceil(4)];
+            for (square, cell) in cells.iter().enumerate() {
+                let code = match cell {
+                    None => 0,
+                    Some(Player::X) => 1,
+                    Some(Player::O) => 2,
+                };
+                key[square / 4] |= code << (2 * (square % 4));
+            }
+            data.extend_from_slice(&key);
+            let (kind, score) = match *entry {
+                Entry::Exact(score) => (0, score),
+                Entry::AtLeast(score) => (1, score),
+                Entry::AtMost(score) => (2, score),
+            };
+            data.push(kind);
+            data.extend_from_slice(&score.to_le_bytes());
+        }
+        std::fs::write(path, data).map_err(Error::io("write", path))
+    }
+
+    /// Every legal move with the result it leads to for the side to move with perfect play
+    pub fn evaluate(&mut self, board: &Board) -> Vec<(Move, GameResult)> {
+        self.scores(board)
+            .into_iter()
+            .map(|(m, score)| (m, result_of(score)))
+            .collect()
+    }
+
+    /// Pick one of the moves that wins fastest, or failing that ties or loses slowest
+    pub fn decide(&mut self, board: &Board, rng: &mut impl Rng) -> Option<Move> {
+        let scored = self.scores(board);
+        let best = scored.iter().map(|&(_, score)| score).max()?;
+        let best_moves: Vec<Move> = scored
+            .into_iter()
+            .filter(|&(_, score)| score == best)
+            .map(|(m, _)| m)
+            .collect();
+        best_moves.choose(rng).copied()
+    }
+
+    fn scores(&mut self, board: &Board) -> Vec<(Move, i32)> {
+        let mut board = board.clone();
+        board
+            .legal_moves()
+            .into_iter()
+            .map(|m| {
+                let square = board.target(m).expect("legal moves have a target");
+                board.place(square);
+                let score = -self.negamax(&mut board, -i32::MAX, i32::MAX);
+                board.unplace(square);
+                (m, score)
+            })
+            .collect()
+    }
+
+    /// The score for the side to move: positive for a win, more so the sooner it comes, zero for
+    /// a tie and negative for a loss
+    fn negamax(&mut self, board: &mut Board, mut alpha: i32, mut beta: i32) -> i32 {
+        let empty = board.cells.iter().filter(|cell| cell.is_none()).count() as i32;
+        match board.winner {
+            Some(player) if player == board.next_player => return empty + 1,
+            Some(_) => return -(empty + 1),
+            None => {}
+        }
+        if empty == 0 {
+            return 0;
+        }
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            self.cancelled = true;
+        }
+        if self.cancelled {
+            return 0;
+        }
+        match self.table.get(&board.cells) {
+            Some(&Entry::Exact(score)) => return score,
+            Some(&Entry::AtLeast(score)) => alpha = alpha.max(score),
+            Some(&Entry::AtMost(score)) => beta = beta.min(score),
+            None => {}
+        }
+        if alpha >= beta {
+            return alpha;
+        }
+
+        let original_alpha = alpha;
+        let mut best = -i32::MAX;
+        for square in self.ordered_targets(board) {
+            board.place(square);
+            let score = -self.negamax(board, -beta, -alpha);
+            board.unplace(square);
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        let entry = if best <= original_alpha {
+            Entry::AtMost(best)
+        } else if best >= beta {
+            Entry::AtLeast(best)
+        } else {
+            Entry::Exact(best)
+        };
+        if !self.cancelled {
+            self.table.insert(board.cells.clone(), entry);
+        }
+        best
+    }
+
+    /// The squares the legal moves land on, nearest the middle of the board first since those
+    /// are on the most lines
+    fn ordered_targets(&self, board: &Board) -> Vec<usize> {
+        let Rules { width, height, .. } = board.rules;
+        let mut squares: Vec<usize> = board
+            .legal_moves()
+            .into_iter()
+            .map(|m| board.target(m).expect("legal moves have a target"))
+            .collect();
+        squares.sort_by_key(|&square| {
+            let row = (2 * (square / width)).abs_diff(height - 1);
+            let column = (2 * (square % width)).abs_diff(width - 1);
+            row.max(column)
+        });
+        squares
+    }
+}
+
+/// The computer player, using whichever engine suits the board unless told otherwise
+#[derive(Debug)]
+pub struct Computer {
+    engine: EngineKind,
+    search: Search,
+    #[cfg(feature = "ai-mcts")]
+    mcts: Mcts,
+    /// The MCTS tree from the last move, to carry on growing from the next position
+    #[cfg(feature = "ai-mcts")]
+    tree: Option<Tree<Board>>,
+}
+
+/// The computer thinking in a background thread on the opponent's time, see
+/// [`Computer::ponder`]
+#[derive(Debug)]
+pub struct Pondering {
+    cancel: CancelToken,
+    thread: JoinHandle<Thoughts>,
+}
+
+/// What a pondering thread hands back when it stops
+#[derive(Debug)]
+enum Thoughts {
+    Search(Search),
+    #[cfg(feature = "ai-mcts")]
+    Tree(Tree<Board>),
+}
+
+impl Computer {
+    /// A computer for games under `rules`, with `engine` overriding the automatic choice
+    pub fn new(rules: &Rules, engine: Option<EngineKind>) -> crate::Result<Self> {
+        let engine = engine.unwrap_or_else(|| EngineKind::for_rules(rules));
+        if engine == EngineKind::Tablebase && !rules.is_standard() {
+            return Err(Error::Config(
+                "the tablebase only covers ordinary 3x3 tic-tac-toe".to_string(),
+            ));
+        }
+        Ok(Self {
+            engine,
+            search: Search::new(),
+            #[cfg(feature = "ai-mcts")]
+            mcts: Mcts::default(),
+            #[cfg(feature = "ai-mcts")]
+            tree: None,
+        })
+    }
+
+    pub fn engine(&self) -> EngineKind {
+        self.engine
+    }
+
+    /// The alpha-beta search, e.g. to load its table from the cache or save it there
+    pub fn search(&self) -> &Search {
+        &self.search
+    }
+
+    pub fn with_search(self, search: Search) -> Self {
+        Self { search, ..self }
+    }
+
+    /// Choose a move for the side to move
+    pub fn decide(&mut self, board: &Board, rng: &mut impl Rng) -> Option<Move> {
+        match self.engine {
+            EngineKind::Tablebase => {
+                let results = tablebase_results(board);
+                let best = results.iter().map(|&(_, result)| result).max()?;
+                let best_moves: Vec<Move> = results
+                    .into_iter()
+                    .filter(|&(_, result)| result == best)
+                    .map(|(m, _)| m)
+                    .collect();
+                best_moves.choose(rng).copied()
+            }
+            EngineKind::AlphaBeta => self.search.decide(board, rng),
+            #[cfg(feature = "ai-mcts")]
+            EngineKind::Mcts => {
+                let mut tree = self.tree_for(board);
+                self.mcts.think(&mut tree, rng);
+                let m = tree.best_move()?;
+                tree.play(m);
+                self.tree = Some(tree);
+                Some(m)
+            }
+        }
+    }
+
+    /// The kept MCTS tree moved on to `board` if it's from one move earlier, or a new one
+    #[cfg(feature = "ai-mcts")]
+    fn tree_for(&mut self, board: &Board) -> Tree<Board> {
+        if let Some(mut tree) = self.tree.take() {
+            if tree.game() == board {
+                return tree;
+            }
+            let previous = tree.game().clone();
+            let played = previous.legal_moves().into_iter().find(|&m| {
+                let mut after = previous.clone();
+                after.play(m).is_ok() && after == *board
+            });
+            if let Some(m) = played {
+                tree.play(m);
+                return tree;
+            }
+        }
+        self.mcts.tree(board)
+    }
+
+    /// Start thinking about `board`, where the opponent is to move, on a background thread
+    /// until [`Computer::stop_pondering`]. Alpha-beta solves the positions after each reply, the
+    /// likeliest first, and MCTS grows its tree, so either has a head start on whatever the
+    /// opponent plays. The tablebase has nothing to gain, so it doesn't ponder
+    pub fn ponder(&mut self, board: &Board) -> Option<Pondering> {
+        if board.status() != GameStatus::InProgress {
+            return None;
+        }
+        let cancel = CancelToken::new();
+        let thread = match self.engine {
+            EngineKind::Tablebase => return None,
+            EngineKind::AlphaBeta => {
+                let mut search = std::mem::take(&mut self.search);
+                search.cancel = Some(cancel.clone());
+                let board = board.clone();
+                let token = cancel.clone();
+                std::thread::spawn(move || {
+                    for square in search.ordered_targets(&board) {
+                        if token.is_cancelled() {
+                            break;
+                        }
+                        let mut after = board.clone();
+                        after.place(square);
+                        if after.status() == GameStatus::InProgress {
+                            search.scores(&after);
+                        }
+                    }
+                    Thoughts::Search(search)
+                })
+            }
+            #[cfg(feature = "ai-mcts")]
+            EngineKind::Mcts => {
+                let mut tree = self.tree_for(board);
+                let token = cancel.clone();
+                std::thread::spawn(move || {
+                    let mut rng = rand::thread_rng();
+                    while !token.is_cancelled() && tree.game().status() == GameStatus::InProgress {
+                        tree.grow(&mut rng);
+                    }
+                    Thoughts::Tree(tree)
+                })
+            }
+        };
+        Some(Pondering { cancel, thread })
+    }
+
+    /// Stop pondering, keeping whatever it found
+    pub fn stop_pondering(&mut self, pondering: Pondering) {
+        pondering.cancel.cancel();
+        let thoughts = pondering
+            .thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        match thoughts {
+            Thoughts::Search(mut search) => {
+                search.cancel = None;
+                search.cancelled = false;
+                self.search = search;
+            }
+            #[cfg(feature = "ai-mcts")]
+            Thoughts::Tree(tree) => self.tree = Some(tree),
+        }
+    }
+
+    /// Whether the side to move expects to lose whatever it plays, e.g. to take up the pie rule
+    #[cfg_attr(not(feature = "ai-mcts"), allow(unused_variables))]
+    pub fn expects_to_lose(&mut self, board: &Board, rng: &mut impl Rng) -> bool {
+        match self.engine {
+            EngineKind::Tablebase => tablebase_results(board)
+                .iter()
+                .all(|&(_, result)| result == GameResult::Loss),
+            EngineKind::AlphaBeta => self
+                .search
+                .evaluate(board)
+                .iter()
+                .all(|&(_, result)| result == GameResult::Loss),
+            #[cfg(feature = "ai-mcts")]
+            EngineKind::Mcts => self
+                .mcts
+                .evaluate(board, rng)
+                .iter()
+                .all(|&(_, share)| share < 0.5),
+        }
+    }
+}
+
+/// Every legal move on an ordinary 3x3 board with its result for the side to move, from the
+/// installed tablebase or the one built in
+fn tablebase_results(board: &Board) -> Vec<(Move, GameResult)> {
+    let cells: [Option<Player>; 9] = board
+        .cells
+        .as_slice()
+        .try_into()
+        .expect("the tablebase is only used for 3x3 boards");
+    let state = GameState::from_board(board.next_player.opponent(), cells)
+        .expect("positions from a game can come up");
+    board
+        .legal_moves()
+        .into_iter()
+        .map(|m| {
+            let square = board.target(m).expect("legal moves have a target");
+            let after = state.with_move(square);
+            let result = tablebase::installed()
+                .and_then(|tablebase| tablebase.probe(&after))
+                .or_else(|| solved::result(&after))
+                .expect("legal moves lead to legal positions");
+            // The tables give the result for the side to move next
+            (m, result.reversed())
+        })
+        .collect()
+}
+
+fn index(player: Player) -> usize {
+    match player {
+        Player::X => 0,
+        Player::O => 1,
+    }
+}
+
+/// A short name for some rules, like `4x4-4-gravity`
+#[cfg(feature = "storage")]
+fn rules_key(rules: &Rules) -> String {
+    let mut key = format!("{}x{}-{}", rules.width, rules.height, rules.win_length);
+    for (on, name) in [
+        (rules.gravity, "gravity"),
+        (rules.misere, "misere"),
+        (rules.wrap, "wrap"),
+    ] {
+        if on {
+            key.push('-');
+            key.push_str(name);
+        }
+    }
+    key
+}
+
+/// The start of a cached table, which has to match exactly for the table to be used
+fn cache_header(rules: &Rules) -> Vec<u8> {
+    let mut header = CACHE_MAGIC.to_vec();
+    header.push(CACHE_VERSION);
+    for dimension in [rules.width, rules.height, rules.win_length] {
+        header.extend_from_slice(&(dimension as u32).to_le_bytes());
+    }
+    header.push(u8::from(rules.gravity) | u8::from(rules.misere) << 1 | u8::from(rules.wrap) << 2);
+    header
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+    let gcd = |mut a: usize, mut b: usize| {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a
+    };
+    a / gcd(a, b) * b
+}
+
+fn result_of(score: i32) -> GameResult {
+    match score.signum() {
+        1 => GameResult::Win,
+        -1 => GameResult::Loss,
+        _ => GameResult::Tie,
+    }
+}
+
+impl Game for Board {
+    type Move = Move;
+
+    fn next_player(&self) -> Player {
+        self.next_player
+    }
+
+    fn legal_moves(&self) -> Vec<Move> {
+        Board::legal_moves(self)
+    }
+
+    fn play(&mut self, m: Move) -> Result<(), MoveError> {
+        Board::play(self, m).map(|_| ())
+    }
+
+    fn status(&self) -> GameStatus {
+        Board::status(self)
+    }
+
+    fn describe(&self, m: Move) -> String {
+        m.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::time::Instant;
+
+    /// A board of `rules` after `moves` random moves, or fewer if the game ends first
+    fn random_board(rules: Rules, moves: usize, rng: &mut StdRng) -> Board {
+        let mut board = Board::new(rules);
+        for _ in 0..moves {
+            let Some(&m) = board.legal_moves().choose(rng) else {
+                break;
+            };
+            board.play(m).expect("the move is legal");
+        }
+        board
+    }
+
+    #[test]
+    fn packed_line_checks_match_the_square_by_square_ones() {
+        let mut rng = StdRng::seed_from_u64(174);
+        for _ in 0..1000 {
+            let (width, height) = (rng.gen_range(1..=12), rng.gen_range(1..=12));
+            let win_length = rng.gen_range(1..=width.max(height));
+            let rules = Rules::new(width, height, win_length)
+                .unwrap()
+                .with_gravity(rng.gen());
+            let mut board = Board::new(rules);
+            assert!(board.rows.is_some());
+            while let Some(&m) = board.legal_moves().choose(&mut rng) {
+                board.play(m).unwrap();
+                for square in (0..board.cells.len()).filter(|&s| board.cells[s].is_some()) {
+                    assert_eq!(
+                        board.completes_line(square),
+                        board.completes_line_by_squares(square),
+                        "square {square} of\n{board:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Nanoseconds per check with each way of finding lines, on half-full boards. Run with
+    /// `cargo test --release packed_line_checks_are_faster -- --ignored --nocapture`
+    #[test]
+    #[ignore = "a benchmark, only meaningful in release"]
+    fn packed_line_checks_are_faster() {
+        let mut rng = StdRng::seed_from_u64(174);
+        let boards = [
+            (
+                "7x6 Connect Four",
+                Rules::new(7, 6, 4).unwrap().with_gravity(true),
+            ),
+            ("15x15 gomoku", Rules::new(15, 15, 5).unwrap()),
+        ];
+        for (name, rules) in boards {
+            let boards: Vec<Board> = (0..2000)
+                .map(|_| random_board(rules, rules.squares() / 2, &mut rng))
+                .collect();
+            let squares: Vec<(&Board, usize)> = boards
+                .iter()
+                .flat_map(|board| {
+                    (0..board.cells.len())
+                        .filter(|&square| board.cells[square].is_some())
+                        .map(move |square| (board, square))
+                })
+                .collect();
+            let time = |check: fn(&Board, usize) -> bool| {
+                let start = Instant::now();
+                for _ in 0..20 {
+                    for &(board, square) in &squares {
+                        std::hint::black_box(check(board, square));
+                    }
+                }
+                start.elapsed().as_nanos() as f64 / (20 * squares.len()) as f64
+            };
+            let by_squares = time(Board::completes_line_by_squares);
+            let packed = time(Board::completes_line);
+            println!("{name}: {by_squares:.0} ns per check by squares, {packed:.0} ns packed");
+        }
+    }
+}
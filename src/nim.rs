@@ -0,0 +1,139 @@
+//! Nim, where players take turns taking any number of counters from one heap and whoever takes
+//! the last counter wins
+//!
+//! Nim is solved: the side to move loses with perfect play exactly when the heap sizes XOR to
+//! zero, and otherwise wins by taking counters to make them XOR to zero.
+
+use crate::error::{Error, MoveError};
+use crate::game::{GameStatus, Player};
+use crate::games::Game;
+use std::fmt::Display;
+
+/// Counters to take from one heap
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NimMove {
+    pub heap: usize,
+    pub count: u32,
+}
+
+/// A game of Nim
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NimGame {
+    heaps: Vec<u32>,
+    pub next_player: Player,
+}
+
+impl NimGame {
+    pub fn new(heaps: Vec<u32>) -> crate::Result<Self> {
+        if heaps.iter().all(|&heap| heap == 0) {
+            return Err(Error::Config(
+                "Nim needs at least one counter to take".to_string(),
+            ));
+        }
+        Ok(Self {
+            heaps,
+            next_player: Player::X,
+        })
+    }
+
+    pub fn heaps(&self) -> &[u32] {
+        &self.heaps
+    }
+
+    /// The last player to move has won once the heaps are empty
+    pub fn status(&self) -> GameStatus {
+        if self.heaps.iter().all(|&heap| heap == 0) {
+            GameStatus::Won(self.next_player.opponent())
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<NimMove> {
+        self.heaps
+            .iter()
+            .enumerate()
+            .flat_map(|(heap, &size)| (1..=size).map(move |count| NimMove { heap, count }))
+            .collect()
+    }
+
+    pub fn play(&mut self, m: NimMove) -> Result<(), MoveError> {
+        let Some(size) = self.heaps.get_mut(m.heap) else {
+            return Err(MoveError::NoSuchHeap(m.heap));
+        };
+        if m.count == 0 || m.count > *size {
+            return Err(MoveError::NotInHeap {
+                heap: m.heap,
+                count: m.count,
+            });
+        }
+        *size -= m.count;
+        self.next_player = self.next_player.opponent();
+        Ok(())
+    }
+
+    /// The heap sizes XORed together, which is zero exactly when the side to move is losing
+    pub fn nim_sum(&self) -> u32 {
+        self.heaps.iter().fold(0, |sum, &heap| sum ^ heap)
+    }
+
+    /// The perfect move: one that leaves a nim-sum of zero if there is one, otherwise a single
+    /// counter from the biggest heap to drag the game out
+    pub fn best_move(&self) -> Option<NimMove> {
+        let sum = self.nim_sum();
+        if sum != 0 {
+            return self
+                .heaps
+                .iter()
+                .enumerate()
+                .find(|&(_, &heap)| heap ^ sum < heap)
+                .map(|(heap, &size)| NimMove {
+                    heap,
+                    count: size - (size ^ sum),
+                });
+        }
+        let (heap, _) = self
+            .heaps
+            .iter()
+            .enumerate()
+            .filter(|&(_, &size)| size > 0)
+            .max_by_key(|&(_, &size)| size)?;
+        Some(NimMove { heap, count: 1 })
+    }
+}
+
+impl Display for NimGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self
+            .heaps
+            .iter()
+            .enumerate()
+            .map(|(i, &size)| format!("{}: {} ({size})", i + 1, "|".repeat(size as usize)))
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Game for NimGame {
+    type Move = NimMove;
+
+    fn next_player(&self) -> Player {
+        self.next_player
+    }
+
+    fn legal_moves(&self) -> Vec<NimMove> {
+        NimGame::legal_moves(self)
+    }
+
+    fn play(&mut self, m: NimMove) -> Result<(), MoveError> {
+        NimGame::play(self, m)
+    }
+
+    fn status(&self) -> GameStatus {
+        NimGame::status(self)
+    }
+
+    fn describe(&self, m: NimMove) -> String {
+        format!("Take {} from heap {}", m.count, m.heap + 1)
+    }
+}
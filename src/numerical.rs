@@ -0,0 +1,276 @@
+//! Numerical tic-tac-toe, where X plays the odd digits and O the even ones, each at most once, and
+//! any full line adding up to 15 wins for whoever completed it
+//!
+//! Either player can finish a line started by the other, so the board is kept as digits rather
+//! than marks. X moves first and the side to move follows from how many digits are down.
+
+use crate::error::MoveError;
+use crate::game::{GameStatus, Player, Selection, LINES};
+use crate::games::Game;
+use crate::render::render_grid;
+use crate::symmetry::Symmetry;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// What a full line must add up to
+pub const TARGET: u8 = 15;
+
+/// A digit to put on a square
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NumberMove {
+    pub square: usize,
+    pub digit: u8,
+}
+
+/// A game of numerical tic-tac-toe
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NumericalGame {
+    board: [Option<u8>; 9],
+    winner: Option<Player>,
+}
+
+impl NumericalGame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn board(&self) -> &[Option<u8>; 9] {
+        &self.board
+    }
+
+    pub fn next_player(&self) -> Player {
+        if self.board.iter().flatten().count().is_multiple_of(2) {
+            Player::X
+        } else {
+            Player::O
+        }
+    }
+
+    pub fn status(&self) -> GameStatus {
+        match self.winner {
+            Some(player) => GameStatus::Won(player),
+            None if self.board.iter().all(Option::is_some) => GameStatus::Tie,
+            None => GameStatus::InProgress,
+        }
+    }
+
+    /// The digits a player hasn't played yet, smallest first
+    pub fn digits_left(&self, player: Player) -> Vec<u8> {
+        let first = match player {
+            Player::X => 1,
+            Player::O => 2,
+        };
+        (first..=9)
+            .step_by(2)
+            .filter(|digit| !self.board.contains(&Some(*digit)))
+            .collect()
+    }
+
+    pub fn open_squares(&self) -> Vec<usize> {
+        (0..9)
+            .filter(|&square| self.board[square].is_none())
+            .collect()
+    }
+
+    /// Every square and digit the side to move can play
+    pub fn legal_moves(&self) -> Vec<NumberMove> {
+        if self.status() != GameStatus::InProgress {
+            return Vec::new();
+        }
+        let digits = self.digits_left(self.next_player());
+        self.open_squares()
+            .into_iter()
+            .flat_map(|square| {
+                digits
+                    .iter()
+                    .map(move |&digit| NumberMove { square, digit })
+            })
+            .collect()
+    }
+
+    pub fn play(&mut self, m: NumberMove) -> Result<(), MoveError> {
+        if m.square >= 9 {
+            return Err(MoveError::OffBoard(m.square));
+        }
+        if self.status() != GameStatus::InProgress {
+            return Err(MoveError::GameOver(m.square));
+        }
+        if self.board[m.square].is_some() {
+            return Err(MoveError::Taken(m.square));
+        }
+        if !self.digits_left(self.next_player()).contains(&m.digit) {
+            return Err(MoveError::NoSuchDigit(m.digit));
+        }
+        self.place(m);
+        Ok(())
+    }
+
+    /// Whether a move on an open square would fill a line that adds up to the target
+    fn completes_line(&self, m: NumberMove) -> bool {
+        LINES
+            .iter()
+            .filter(|line| line.contains(&m.square))
+            .any(|line| {
+                let sum = line
+                    .iter()
+                    .filter(|&&square| square != m.square)
+                    .map(|&square| self.board[square])
+                    .sum::<Option<u8>>();
+                sum.map(|sum| sum + m.digit) == Some(TARGET)
+            })
+    }
+
+    fn place(&mut self, m: NumberMove) {
+        if self.completes_line(m) {
+            self.winner = Some(self.next_player());
+        }
+        self.board[m.square] = Some(m.digit);
+    }
+
+    fn unplace(&mut self, m: NumberMove) {
+        self.board[m.square] = None;
+        self.winner = None;
+    }
+
+    /// The board as a number for the transposition table, four bits a square. Boards that are
+    /// rotations or reflections of each other play the same, so they share the smallest key
+    fn key(&self) -> u64 {
+        Symmetry::ALL
+            .iter()
+            .map(|symmetry| {
+                (0..9).fold(0, |key, square| {
+                    key << 4 | u64::from(self.board[symmetry.apply(square)].unwrap_or(0))
+                })
+            })
+            .min()
+            .expect("there are symmetries")
+    }
+}
+
+impl Display for NumericalGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let digits: Vec<String> = self
+            .board
+            .iter()
+            .map(|digit| digit.map_or(".".to_string(), |d| d.to_string()))
+            .collect();
+        let cells: Vec<&str> = digits.iter().map(String::as_str).collect();
+        write!(f, "{}", render_grid(&cells, 3, 1))
+    }
+}
+
+/// A solved position's score, or a bound on it when the search was cut off
+#[derive(Copy, Clone, Debug)]
+enum Entry {
+    Exact(i32),
+    AtLeast(i32),
+    AtMost(i32),
+}
+
+/// An alpha-beta search over squares and digits that remembers the positions it has solved
+#[derive(Debug, Default)]
+pub struct Search {
+    table: HashMap<u64, Entry>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pick one of the moves that wins fastest, or failing that ties or loses slowest
+    pub fn decide(&mut self, game: &NumericalGame, rng: &mut impl Rng) -> Option<NumberMove> {
+        let mut game = game.clone();
+        let mut best = -i32::MAX;
+        let mut best_moves = Vec::new();
+        for m in game.legal_moves() {
+            game.place(m);
+            // Moves worse than the best so far only need to be shown worse, not scored exactly
+            let score = -self.negamax(&mut game, -i32::MAX, -(best - 1).max(-i32::MAX));
+            game.unplace(m);
+            if score > best {
+                best = score;
+                best_moves.clear();
+            }
+            if score == best {
+                best_moves.push(m);
+            }
+        }
+        best_moves.choose(rng).copied()
+    }
+
+    /// The score for the side to move: positive for a win, more so the sooner it comes, zero for
+    /// a tie and negative for a loss
+    fn negamax(&mut self, game: &mut NumericalGame, mut alpha: i32, mut beta: i32) -> i32 {
+        let empty = game.board.iter().filter(|digit| digit.is_none()).count() as i32;
+        if game.winner.is_some() {
+            // The side that just moved won
+            return -(empty + 1);
+        }
+        if empty == 0 {
+            return 0;
+        }
+        let moves = game.legal_moves();
+        if moves.iter().any(|&m| game.completes_line(m)) {
+            return empty;
+        }
+        let key = game.key();
+        match self.table.get(&key) {
+            Some(&Entry::Exact(score)) => return score,
+            Some(&Entry::AtLeast(score)) => alpha = alpha.max(score),
+            Some(&Entry::AtMost(score)) => beta = beta.min(score),
+            None => {}
+        }
+        if alpha >= beta {
+            return alpha;
+        }
+
+        let original_alpha = alpha;
+        let mut best = -i32::MAX;
+        for m in moves {
+            game.place(m);
+            let score = -self.negamax(game, -beta, -alpha);
+            game.unplace(m);
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        let entry = if best <= original_alpha {
+            Entry::AtMost(best)
+        } else if best >= beta {
+            Entry::AtLeast(best)
+        } else {
+            Entry::Exact(best)
+        };
+        self.table.insert(key, entry);
+        best
+    }
+}
+
+impl Game for NumericalGame {
+    type Move = NumberMove;
+
+    fn next_player(&self) -> Player {
+        NumericalGame::next_player(self)
+    }
+
+    fn legal_moves(&self) -> Vec<NumberMove> {
+        NumericalGame::legal_moves(self)
+    }
+
+    fn play(&mut self, m: NumberMove) -> Result<(), MoveError> {
+        NumericalGame::play(self, m)
+    }
+
+    fn status(&self) -> GameStatus {
+        NumericalGame::status(self)
+    }
+
+    fn describe(&self, m: NumberMove) -> String {
+        format!("{} on {}", m.digit, Selection::new(m.square))
+    }
+}
@@ -0,0 +1,65 @@
+//! Hooks into a game as it's played, so features like stats recording can follow along without
+//! being written into the game loop
+
+use crate::ai::Decision;
+use crate::analysis::LossReason;
+use crate::game::GameState;
+use crate::record::GameRecord;
+
+/// Advice shown to the human during a game
+#[derive(Copy, Clone, Debug)]
+pub enum Hint {
+    /// The human was warned that playing on `square` loses by force
+    Blunder { square: usize, reason: LossReason },
+    /// The human asked why the computer played on `square`
+    Explanation { square: usize },
+}
+
+/// Something that wants to know what happens in a game. Every method does nothing by default, so
+/// observers only implement the events they care about
+pub trait GameObserver {
+    /// A move was played on `square`, leaving `state`; `decision` is set for the computer's moves
+    fn on_move(&mut self, _state: &GameState, _square: usize, _decision: Option<&Decision>) {}
+
+    /// The game finished, on the board or by resignation or agreement
+    fn on_game_over(&mut self, _record: &GameRecord) {}
+
+    /// The human was shown a hint in `state`
+    fn on_hint(&mut self, _state: &GameState, _hint: &Hint) {}
+}
+
+/// A list of observers that are all told about every event, in the order they were added
+#[derive(Default)]
+pub struct Observers {
+    observers: Vec<Box<dyn GameObserver>>,
+}
+
+impl Observers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, observer: impl GameObserver + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+}
+
+impl GameObserver for Observers {
+    fn on_move(&mut self, state: &GameState, square: usize, decision: Option<&Decision>) {
+        for observer in &mut self.observers {
+            observer.on_move(state, square, decision);
+        }
+    }
+
+    fn on_game_over(&mut self, record: &GameRecord) {
+        for observer in &mut self.observers {
+            observer.on_game_over(record);
+        }
+    }
+
+    fn on_hint(&mut self, state: &GameState, hint: &Hint) {
+        for observer in &mut self.observers {
+            observer.on_hint(state, hint);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Odds from self-play: how often games through each position end each way when the computer
+//! plays a stand-in for the human, which means more to a casual player than the result with
+//! perfect play
+
+use crate::explorer::Results;
+use crate::game::{GameState, Player};
+use crate::tournament::{play_game, run_games};
+use std::collections::HashMap;
+use std::sync::atomic::AtomicUsize;
+
+/// The players and games to work the odds out from
+#[derive(Clone, Debug)]
+pub struct OddsOptions {
+    /// The side the human plays
+    pub human: Player,
+    /// How often the stand-in for the human blunders
+    pub human_blunder_chance: f64,
+    /// How often the computer blunders
+    pub computer_blunder_chance: f64,
+    pub games: usize,
+    pub seed: u64,
+    pub threads: usize,
+}
+
+impl OddsOptions {
+    /// Play the games, counting each finished one in `progress`
+    pub fn build(&self, progress: &AtomicUsize) -> Odds {
+        let (x, o) = match self.human {
+            Player::X => (self.human_blunder_chance, self.computer_blunder_chance),
+            Player::O => (self.computer_blunder_chance, self.human_blunder_chance),
+        };
+        let games = run_games(self.games, self.seed, self.threads, progress, |_, rng| {
+            let record = play_game(x, o, rng);
+            let mut state = GameState::new(record.computer_player);
+            let mut positions = vec![state.position_id()];
+            for square in record.squares() {
+                state.apply_move(square);
+                positions.push(state.position_id());
+            }
+            (positions, state.status())
+        });
+        let mut positions: HashMap<u64, Results> = HashMap::new();
+        for (ids, status) in games {
+            for id in ids {
+                positions.entry(id).or_default().add(status);
+            }
+        }
+        Odds {
+            human: self.human,
+            positions,
+        }
+    }
+}
+
+/// How the self-play games through each position ended
+#[derive(Clone, Debug)]
+pub struct Odds {
+    human: Player,
+    positions: HashMap<u64, Results>,
+}
+
+impl Odds {
+    /// The side the human plays
+    pub fn human(&self) -> Player {
+        self.human
+    }
+
+    /// How the games through a position ended, or None if none of them reached it
+    pub fn get(&self, state: &GameState) -> Option<Results> {
+        self.positions.get(&state.position_id()).copied()
+    }
+}
@@ -0,0 +1,200 @@
+//! Text files with the current board, the session's score and the last move, kept up to date for
+//! a streaming program like OBS to show as text sources over the game
+//!
+//! Each file is replaced in one step so the overlay never shows a half-written board. The same
+//! text can also be served over HTTP on a local port, for browser sources.
+
+use crate::ai::GameResult;
+use crate::error::Error;
+use crate::game::{GameState, Selection};
+use crate::record::GameRecord;
+use crate::render::{render_board, Pieces};
+#[cfg(feature = "net")]
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(feature = "net")]
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "net")]
+use std::time::Duration;
+
+/// The files the overlay keeps, and the HTTP paths they're served at
+const BOARD: &str = "board.txt";
+const SCORE: &str = "score.txt";
+const LAST_MOVE: &str = "last_move.txt";
+
+/// How long a browser source may take to send its request, since requests are answered one at a
+/// time and one that never arrives would hold up the rest
+#[cfg(feature = "net")]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// The longest request line read, far more than any of the paths served need
+#[cfg(feature = "net")]
+const MAX_REQUEST_LINE: u64 = 8 * 1024;
+
+#[derive(Debug, Default)]
+struct Texts {
+    board: String,
+    score: String,
+    last_move: String,
+}
+
+#[derive(Debug)]
+struct Shared {
+    dir: PathBuf,
+    pieces: Pieces,
+    wins: u32,
+    losses: u32,
+    ties: u32,
+    texts: Texts,
+}
+
+/// The overlay for a session, which can be shared between its games so the score carries over
+#[derive(Clone, Debug)]
+pub struct Overlay {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Overlay {
+    /// Keep the overlay files in `dir`, which is created if need be, drawing the board with
+    /// `pieces`
+    pub fn new(dir: &Path, pieces: Pieces) -> crate::Result<Self> {
+        std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+        let overlay = Self {
+            shared: Arc::new(Mutex::new(Shared {
+                dir: dir.to_path_buf(),
+                pieces,
+                wins: 0,
+                losses: 0,
+                ties: 0,
+                texts: Texts::default(),
+            })),
+        };
+        overlay.update(|shared| shared.texts.score = shared.score())?;
+        Ok(overlay)
+    }
+
+    /// The overlay location unless one is given, e.g.
+    /// `~/.local/share/simple-tic-tac-toe/overlay` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("simple-tic-tac-toe").join("overlay"))
+    }
+
+    /// Show a new game's board, with no last move yet
+    pub fn start(&self, state: &GameState) -> crate::Result<()> {
+        self.update(|shared| {
+            shared.texts.board = render_board(state.board(), &shared.pieces);
+            shared.texts.last_move = String::new();
+        })
+    }
+
+    /// Also serve the overlay text on `address`, e.g. `127.0.0.1:8080`, from a thread of its own:
+    /// everything at `/`, or one file at `/board.txt`, `/score.txt` or `/last_move.txt`
+    #[cfg(feature = "net")]
+    pub fn serve(&self, address: &str) -> crate::Result<()> {
+        let listener = TcpListener::bind(address).map_err(Error::network("listen on", address))?;
+        tracing::info!(address, "serving the overlay");
+        let shared = Arc::clone(&self.shared);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = respond(stream, &shared) {
+                    tracing::debug!(error = %e, "overlay request failed");
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Show the board after a move on `square`, and the move
+    pub fn record_move(&self, state: &GameState, square: usize) -> crate::Result<()> {
+        let player = state.board()[square].map_or(String::new(), |player| player.to_string());
+        self.update(|shared| {
+            shared.texts.board = render_board(state.board(), &shared.pieces);
+            shared.texts.last_move = format!("{player} on {}", Selection::new(square));
+        })
+    }
+
+    /// Add a finished game's result to the score
+    pub fn record_result(&self, record: &GameRecord) -> crate::Result<()> {
+        let result = GameResult::for_player(record.status()?, record.human_player());
+        self.update(|shared| {
+            match result {
+                Some(GameResult::Win) => shared.wins += 1,
+                Some(GameResult::Loss) => shared.losses += 1,
+                Some(GameResult::Tie) => shared.ties += 1,
+                None => {}
+            }
+            shared.texts.score = shared.score();
+        })
+    }
+
+    /// Change the texts and write them all out again
+    fn update(&self, change: impl FnOnce(&mut Shared)) -> crate::Result<()> {
+        let mut shared = self.lock();
+        change(&mut shared);
+        for (name, text) in [
+            (BOARD, &shared.texts.board),
+            (SCORE, &shared.texts.score),
+            (LAST_MOVE, &shared.texts.last_move),
+        ] {
+            write_atomically(&shared.dir.join(name), text)?;
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Shared> {
+        self.shared
+            .lock()
+            .expect("nothing panics holding the overlay lock")
+    }
+}
+
+impl Shared {
+    fn score(&self) -> String {
+        format!(
+            "Wins {} - Losses {} - Ties {}",
+            self.wins, self.losses, self.ties
+        )
+    }
+}
+
+fn write_atomically(path: &Path, text: &str) -> crate::Result<()> {
+    let partial = path.with_extension("tmp");
+    std::fs::write(&partial, text).map_err(Error::io("write", &partial))?;
+    std::fs::rename(&partial, path).map_err(Error::io("write", path))
+}
+
+/// Answer one HTTP request with the overlay text it asks for
+#[cfg(feature = "net")]
+fn respond(mut stream: TcpStream, shared: &Mutex<Shared>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_LINE)).read_line(&mut request)?;
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let body = {
+        let shared = shared
+            .lock()
+            .expect("nothing panics holding the overlay lock");
+        let texts = &shared.texts;
+        match path.trim_start_matches('/') {
+            "" => Some(format!(
+                "{}\n{}\n{}\n",
+                texts.board, texts.score, texts.last_move
+            )),
+            BOARD => Some(texts.board.clone()),
+            SCORE => Some(texts.score.clone()),
+            LAST_MOVE => Some(texts.last_move.clone()),
+            _ => None,
+        }
+    };
+    let (status, body) = match body {
+        Some(body) => ("200 OK", body),
+        None => ("404 Not Found", "Not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\n\
+         Access-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
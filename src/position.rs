@@ -0,0 +1,202 @@
+//! A compact one-line notation for positions, like FEN in chess
+//!
+//! The board is written row by row from the top, with the rows separated by `/` and each square
+//! as `x`, `o`, or `.` for an empty square. A space and the side to move follow, so the position
+//! after X takes the center and O answers in the top left is `o../.x./... x`. Parsing accepts
+//! upper case marks and leaves the side to move optional, since it always follows from the
+//! marks when X moves first.
+
+use crate::error::Error;
+use crate::game::{GameState, Player};
+
+/// Read a position string, with the computer set to play the side to move, the way analysis
+/// looks at positions. Positions that can't come up in a game are rejected
+///
+/// ```
+/// use simple_tic_tac_toe::position::parse_position;
+/// use simple_tic_tac_toe::Player;
+///
+/// let state = parse_position("x.o/.x./..o x").unwrap();
+/// assert_eq!(state.next_player(), Player::X);
+/// assert_eq!(state.to_position_string(), "x.o/.x./..o x");
+/// assert_eq!(parse_position("X.O/.X./..O").unwrap().next_player(), Player::X);
+///
+/// // Wrong side to move, too few rows, an unknown mark, and too many X marks
+/// assert!(parse_position("x.o/.x./..o o").is_err());
+/// assert!(parse_position("x.o/.x. x").is_err());
+/// assert!(parse_position("x.o/.x./..q x").is_err());
+/// assert!(parse_position("xxx/.../... o").is_err());
+/// ```
+pub fn parse_position(s: &str) -> crate::Result<GameState> {
+    let mut parts = s.split_whitespace();
+    let Some(board_text) = parts.next() else {
+        return Err(Error::parse("the position is empty"));
+    };
+    let side = parts.next().map(parse_side).transpose()?;
+    if let Some(extra) = parts.next() {
+        return Err(Error::parse(format!(
+            "unexpected `{extra}` after the side to move"
+        )));
+    }
+
+    let rows: Vec<&str> = board_text.split('/').collect();
+    if rows.len() != 3 {
+        return Err(Error::parse(format!(
+            "expected 3 rows separated by `/`, found {}",
+            rows.len()
+        )));
+    }
+    let mut board = [None; 9];
+    for (row_index, row) in rows.iter().enumerate() {
+        let squares: Vec<char> = row.chars().collect();
+        if squares.len() != 3 {
+            return Err(Error::parse(format!(
+                "row {} has {} squares, expected 3",
+                row_index + 1,
+                squares.len()
+            )));
+        }
+        for (column, &mark) in squares.iter().enumerate() {
+            board[row_index * 3 + column] = match mark {
+                'x' | 'X' => Some(Player::X),
+                'o' | 'O' => Some(Player::O),
+                '.' => None,
+                _ => {
+                    return Err(Error::parse(format!(
+                        "`{mark}` in row {} is not a square, expected x, o or .",
+                        row_index + 1
+                    )))
+                }
+            };
+        }
+    }
+
+    let mut state = GameState::from_board(Player::X, board)?;
+    if let Some(side) = side.filter(|&side| side != state.next_player()) {
+        return Err(Error::Position(format!(
+            "it's {}'s move in this position, not {side}'s",
+            state.next_player()
+        )));
+    }
+    state.computer_player = state.next_player();
+    Ok(state)
+}
+
+fn parse_side(s: &str) -> crate::Result<Player> {
+    match s {
+        "x" | "X" => Ok(Player::X),
+        "o" | "O" => Ok(Player::O),
+        _ => Err(Error::parse(format!(
+            "`{s}` is not a side to move, expected x or o"
+        ))),
+    }
+}
+
+impl GameState {
+    /// Write the position in the notation read by [`parse_position`]
+    pub fn to_position_string(&self) -> String {
+        let rows: Vec<String> = self
+            .board()
+            .chunks(3)
+            .map(|row| {
+                row.iter()
+                    .map(|square| match square {
+                        Some(Player::X) => 'x',
+                        Some(Player::O) => 'o',
+                        None => '.',
+                    })
+                    .collect()
+            })
+            .collect();
+        let side = match self.next_player() {
+            Player::X => 'x',
+            Player::O => 'o',
+        };
+        format!("{} {side}", rows.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(s: &str) -> String {
+        match parse_position(s) {
+            Err(Error::Parse { message, .. }) => message,
+            other => panic!("expected a parse error for `{s}`, got {:?}", other.err()),
+        }
+    }
+
+    fn position_error(s: &str) -> String {
+        match parse_position(s) {
+            Err(Error::Position(message)) => message,
+            other => panic!("expected a position error for `{s}`, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn every_reachable_position_round_trips() {
+        for state in GameState::reachable_positions() {
+            let text = state.to_position_string();
+            let parsed = parse_position(&text).unwrap();
+            assert_eq!(parsed.board(), state.board(), "{text}");
+            assert_eq!(parsed.next_player(), state.next_player(), "{text}");
+            assert_eq!(parsed.winner(), state.winner(), "{text}");
+            assert_eq!(parsed.position_id(), state.position_id(), "{text}");
+            assert_eq!(parsed.to_position_string(), text);
+        }
+    }
+
+    #[test]
+    fn side_to_move_must_follow_from_the_marks() {
+        assert!(position_error("x../.../... x").contains("O's move"));
+        assert!(position_error(".../.../... o").contains("X's move"));
+        assert!(position_error("xx./.../... o").contains("X has 2 marks and O has 0"));
+        assert!(position_error("oo./x../... x").contains("X has 1 marks and O has 2"));
+    }
+
+    #[test]
+    fn both_sides_cannot_have_a_line() {
+        assert_eq!(position_error("xxx/ooo/x.. o"), "both sides have a line");
+        // And the side with the line can't have moved again after it
+        assert!(position_error("xxx/oo./o.. x").contains("X has a line"));
+    }
+
+    #[test]
+    fn board_must_have_three_rows_of_three() {
+        assert!(parse_error("x.o/.x. x").contains("found 2"));
+        assert!(parse_error("x.o/.x./..o/... x").contains("found 4"));
+        assert!(parse_error("x.o/.x/..o x").contains("row 2 has 2 squares"));
+        assert!(parse_error("x.o/.x./..o. x").contains("row 3 has 4 squares"));
+        assert!(parse_error("").contains("empty"));
+    }
+
+    #[test]
+    fn unknown_characters_are_rejected() {
+        assert!(parse_error("x.o/.x./..q x").contains("`q` in row 3"));
+        assert!(parse_error("x-o/.x./..o x").contains("`-` in row 1"));
+        assert!(parse_error("x.o/.x./..o z").contains("not a side to move"));
+    }
+
+    #[test]
+    fn trailing_tokens_are_rejected() {
+        assert!(parse_error("x.o/.x./..o x o").contains("unexpected `o`"));
+        assert!(parse_error("x.o/.x./..o x 1").contains("unexpected `1`"));
+    }
+
+    #[test]
+    fn case_and_whitespace_are_flexible() {
+        let expected = parse_position("x.o/.x./..o x").unwrap();
+        for text in [
+            "X.O/.X./..O X",
+            "x.o/.x./..o",
+            "  x.o/.x./..o   x  ",
+            "x.o/.x./..o\tX\n",
+            "X.o/.x./..O x",
+        ] {
+            let parsed = parse_position(text).unwrap();
+            assert_eq!(parsed.board(), expected.board(), "{text:?}");
+            assert_eq!(parsed.next_player(), expected.next_player(), "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Correspondence games played by email or forum post, one move per message
+//!
+//! The game is kept in an ordinary saved-game file between messages. Each post shows the board
+//! and the open squares as plain text, and the reply names a square on a line like `move b2`,
+//! using the same coordinates as the chat bots. Quoted lines starting with `>` are skipped, so
+//! replying above or below the previous post both work.
+
+use crate::chat::{coordinate, parse_coordinate, render_for_chat};
+use crate::error::Error;
+use crate::game::{GameState, GameStatus, Player};
+use crate::record::GameRecord;
+
+/// A new game for a human playing `human`, with the computer's opening move already made if it
+/// plays X
+pub fn new_game(human: Player) -> GameRecord {
+    let mut record = GameRecord::new(human.opponent(), Vec::new());
+    if human == Player::O {
+        let decision = GameState::new(Player::X).decide_computer_move();
+        record.push(decision.chosen, Some(decision));
+    }
+    record
+}
+
+/// Find the square named by the `move` line of a reply
+pub fn parse_reply(text: &str) -> crate::Result<usize> {
+    let mut words = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('>'))
+        .map(|line| line.split_whitespace())
+        .find_map(|mut words| {
+            words
+                .next()
+                .filter(|word| word.eq_ignore_ascii_case("move"))
+                .map(|_| words)
+        })
+        .ok_or_else(|| Error::parse("the reply has no line like `move b2`"))?;
+    let square = words
+        .next()
+        .ok_or_else(|| Error::parse("the `move` line doesn't name a square"))?;
+    parse_coordinate(square)
+        .ok_or_else(|| Error::parse(format!("`{square}` is not a square, expected a1 to c3")))
+}
+
+/// Play the human's move and the computer's answer, if the game isn't over by then
+pub fn play_reply(record: &mut GameRecord, square: usize) -> crate::Result<()> {
+    let mut state = record.final_position()?;
+    if state.next_player() == record.computer_player {
+        return Err(Error::Position("it's the computer's move".to_string()));
+    }
+    state.try_move(square)?;
+    record.push(square, None);
+    if state.status() == GameStatus::InProgress {
+        let decision = state.decide_computer_move();
+        record.push(decision.chosen, Some(decision));
+    }
+    Ok(())
+}
+
+/// The text of the next post: the computer's last move, the board, and either the open squares or
+/// the result
+pub fn render_post(record: &GameRecord) -> crate::Result<String> {
+    let state = record.final_position()?;
+    let mut lines = Vec::new();
+    if let Some(last) = record.moves.last() {
+        if last.decision.is_some() {
+            lines.push(format!("The computer played {}.", coordinate(last.square)));
+            lines.push(String::new());
+        }
+    }
+    lines.extend(render_for_chat(&state));
+    lines.push(String::new());
+    match state.status() {
+        GameStatus::InProgress => {
+            let open: Vec<String> = state.legal_moves().map(coordinate).collect();
+            lines.push(format!(
+                "You play {}. Reply with a line like `move {}`.",
+                record.human_player(),
+                open[0]
+            ));
+            lines.push(format!("Open squares: {}", open.join(" ")));
+        }
+        GameStatus::Won(winner) if winner == record.computer_player => {
+            lines.push("Game over, the computer wins.".to_string())
+        }
+        GameStatus::Won(_) => lines.push("Game over, you win!".to_string()),
+        GameStatus::Tie => lines.push("Game over, it's a tie.".to_string()),
+    }
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,290 @@
+//! Newline-delimited JSON protocol for driving games from another program, e.g. a GUI wrapper
+//!
+//! Each line on input is one [`Request`] and each line of output is one [`Event`], both tagged by
+//! a `"type"` field. Squares are numbered 0-8 from the top left and players are `"X"` and `"O"`.
+//!
+//! ```text
+//! > {"type": "new_game", "player": "O"}
+//! < {"type": "state", "board": [null, ...], "next_player": "X", "status": "in_progress"}
+//! < {"type": "ai_move", "square": 4}
+//! < {"type": "state", "board": [null, null, null, null, "X", ...], "next_player": "O", ...}
+//! > {"type": "move", "square": 0}
+//! ```
+//!
+//! A game started with `"deltas": true` gets the full state once, and after that only the squares
+//! each move changed, as a `changes` event.
+//!
+//! A program can start with the [`handshake`](crate::handshake) to find out which version and
+//! capabilities the engine has. Deltas, hints, draw offers and starting from a position are
+//! capabilities, so a program that says hello without them can't use them.
+
+use crate::diff::SquareChange;
+use crate::error::ProtocolError;
+use crate::game::{GameState, GameStatus, Player};
+use crate::handshake::{Capability, Hello, Support, Welcome};
+use crate::position::parse_position;
+use crate::report::PositionReport;
+use serde::{Deserialize, Serialize};
+
+/// The protocol versions and capabilities the engine has
+pub const SUPPORT: Support = Support {
+    oldest: 1,
+    newest: 1,
+    capabilities: &[
+        Capability::Deltas,
+        Capability::Hints,
+        Capability::DrawOffers,
+        Capability::Positions,
+    ],
+    variants: &["standard"],
+};
+
+/// A command sent to the engine
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Agree on a version and capabilities, answered with `welcome`
+    Hello(Hello),
+    /// Start a new game, with the human playing `player` (X if not given), from the empty board
+    /// or from `position` in the notation of [`crate::position`]. With `deltas`, updates after the
+    /// first state are sent as changes
+    NewGame {
+        #[serde(default)]
+        player: Option<Player>,
+        #[serde(default)]
+        position: Option<String>,
+        #[serde(default)]
+        deltas: bool,
+    },
+    /// Play the human's move on a square
+    Move { square: usize },
+    /// Ask for the best moves for the human
+    Hint,
+    /// Give up the current game
+    Resign,
+    /// Offer the computer a draw, which it accepts unless it can force a win
+    OfferDraw,
+}
+
+/// Something that happened in the game, sent back in response to a request
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The answer to a hello
+    Welcome(Welcome),
+    /// The current position, sent after every change
+    State(PositionReport),
+    /// The squares a move changed, sent instead of the state in games started with `deltas`
+    Changes {
+        changes: Vec<SquareChange>,
+        next_player: Player,
+        #[serde(flatten)]
+        status: GameStatus,
+    },
+    /// The computer played on a square
+    AiMove { square: usize },
+    /// The best squares for the human to play
+    Hint { squares: Vec<usize> },
+    /// The game is over; `resigned` is set if the human gave up and `draw_agreed` if the computer
+    /// accepted a draw offer
+    GameOver {
+        #[serde(flatten)]
+        status: GameStatus,
+        resigned: bool,
+        draw_agreed: bool,
+    },
+    /// The computer turned down a draw offer; the game goes on
+    DrawDeclined,
+    /// The request couldn't be carried out; the game is unchanged
+    Error { message: String },
+}
+
+/// The state of one protocol connection
+pub struct Session {
+    game: Option<GameState>,
+    /// Whether the current game sends changes instead of states
+    deltas: bool,
+    /// What was agreed in the handshake, or everything if there wasn't one
+    agreed: Welcome,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            game: None,
+            deltas: false,
+            agreed: SUPPORT.assumed(),
+        }
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle one line of input, returning the events to send back
+    pub fn handle_line(&mut self, line: &str) -> Vec<Event> {
+        match serde_json::from_str(line) {
+            Ok(request) => self.handle(request),
+            Err(e) => vec![Event::Error {
+                message: ProtocolError::InvalidRequest(e).to_string(),
+            }],
+        }
+    }
+
+    /// Handle a request, returning the events to send back
+    pub fn handle(&mut self, request: Request) -> Vec<Event> {
+        let result = match request {
+            Request::Hello(hello) => self.hello(&hello),
+            Request::NewGame {
+                player,
+                position,
+                deltas,
+            } => self.new_game(player.unwrap_or(Player::X), position.as_deref(), deltas),
+            Request::Move { square } => self.play_move(square),
+            Request::Hint => self.hint(),
+            Request::Resign => self.resign(),
+            Request::OfferDraw => self.offer_draw(),
+        };
+        result.unwrap_or_else(|e| {
+            vec![Event::Error {
+                message: e.to_string(),
+            }]
+        })
+    }
+
+    fn hello(&mut self, hello: &Hello) -> crate::Result<Vec<Event>> {
+        self.agreed = SUPPORT.negotiate(hello)?;
+        Ok(vec![Event::Welcome(self.agreed.clone())])
+    }
+
+    /// Turn the request down if it needs a capability that wasn't agreed on
+    fn require(&self, capability: Capability) -> Result<(), ProtocolError> {
+        if !self.agreed.has(capability) {
+            return Err(ProtocolError::NotAgreed(capability));
+        }
+        Ok(())
+    }
+
+    fn new_game(
+        &mut self,
+        human: Player,
+        position: Option<&str>,
+        deltas: bool,
+    ) -> crate::Result<Vec<Event>> {
+        if deltas {
+            self.require(Capability::Deltas)?;
+        }
+        if position.is_some() {
+            self.require(Capability::Positions)?;
+        }
+        self.deltas = deltas;
+        let mut game = match position {
+            Some(position) => parse_position(position)?,
+            None => GameState::new(human.opponent()),
+        };
+        game.computer_player = human.opponent();
+        let mut events = vec![Event::State(PositionReport::from(&game))];
+        let game = self.game.insert(game);
+        if game.status() != GameStatus::InProgress {
+            events.push(Self::game_over(game));
+        } else if game.next_player() == game.computer_player {
+            Self::computer_turn(game, self.deltas, &mut events);
+        }
+        Ok(events)
+    }
+
+    /// Get the current game if it is still being played
+    fn active_game(&mut self) -> Result<&mut GameState, ProtocolError> {
+        match &mut self.game {
+            Some(game) if game.status() == GameStatus::InProgress => Ok(game),
+            Some(_) => Err(ProtocolError::GameOver),
+            None => Err(ProtocolError::NoGame),
+        }
+    }
+
+    fn play_move(&mut self, square: usize) -> crate::Result<Vec<Event>> {
+        let deltas = self.deltas;
+        let game = self.active_game()?;
+        let before = game.clone();
+        game.try_move(square)?;
+        let mut events = vec![Self::update(deltas, &before, game)];
+        if game.status() == GameStatus::InProgress {
+            Self::computer_turn(game, deltas, &mut events);
+        } else {
+            events.push(Self::game_over(game));
+        }
+        Ok(events)
+    }
+
+    fn hint(&mut self) -> crate::Result<Vec<Event>> {
+        self.require(Capability::Hints)?;
+        let game = self.active_game()?;
+        let mut human_view = game.clone();
+        human_view.computer_player = game.next_player();
+        let squares = human_view
+            .get_best_computer_moves()
+            .iter()
+            .map(|m| m.square)
+            .collect();
+        Ok(vec![Event::Hint { squares }])
+    }
+
+    fn resign(&mut self) -> crate::Result<Vec<Event>> {
+        let game = self.active_game()?;
+        let events = vec![Event::GameOver {
+            status: GameStatus::Won(game.computer_player),
+            resigned: true,
+            draw_agreed: false,
+        }];
+        self.game = None;
+        Ok(events)
+    }
+
+    fn offer_draw(&mut self) -> crate::Result<Vec<Event>> {
+        self.require(Capability::DrawOffers)?;
+        let game = self.active_game()?;
+        if !game.computer_accepts_draw() {
+            return Ok(vec![Event::DrawDeclined]);
+        }
+        self.game = None;
+        Ok(vec![Event::GameOver {
+            status: GameStatus::Tie,
+            resigned: false,
+            draw_agreed: true,
+        }])
+    }
+
+    fn computer_turn(game: &mut GameState, deltas: bool, events: &mut Vec<Event>) {
+        let before = game.clone();
+        let square = game.get_random_computer_move().square;
+        game.apply_move(square);
+        events.push(Event::AiMove { square });
+        events.push(Self::update(deltas, &before, game));
+        if game.status() != GameStatus::InProgress {
+            events.push(Self::game_over(game));
+        }
+    }
+
+    /// The event for a change from `before` to `after`
+    fn update(deltas: bool, before: &GameState, after: &GameState) -> Event {
+        if deltas {
+            Event::Changes {
+                changes: before.diff(after),
+                next_player: after.next_player(),
+                status: after.status(),
+            }
+        } else {
+            Event::State(PositionReport::from(after))
+        }
+    }
+
+    fn game_over(game: &GameState) -> Event {
+        Event::GameOver {
+            status: game.status(),
+            resigned: false,
+            draw_agreed: false,
+        }
+    }
+}
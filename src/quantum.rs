@@ -0,0 +1,320 @@
+//! Quantum tic-tac-toe, an experimental variant where both players choose their squares at once
+//!
+//! Each pick both players secretly choose an open square and then show their choices together.
+//! Different squares both get their marks. The same square is a collision: the marks bounce off
+//! each other, the square is lost to both for the rest of the game, and both choose again. A
+//! player completing a line wins and both completing one on the same pick is a tie, as is a board
+//! with fewer than two open squares left.
+//!
+//! With no side to move there's no best move, only a best mix of moves. [`Solver`] treats each
+//! pick as a zero-sum game between the two choices and works out, with the simplex method, how
+//! often to choose each square so no guess by the opponent does better than the game's value.
+//!
+//! Over the network, whoever saw the other's choice first could always block it, so choices are
+//! made by commit and reveal: both sides send a [`commitment`] to their square, a hash of it and a
+//! random nonce, and only once both are in do they send the square and nonce, which the other
+//! side checks against the commitment.
+
+use crate::error::MoveError;
+use crate::game::{GameStatus, Player, LINES};
+use crate::symmetry::Symmetry;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Values closer than this are treated as equal by the simplex method
+const EPSILON: f64 = 1e-9;
+
+/// What happened to a pick
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pick {
+    /// Both marks went on the board
+    Placed,
+    /// Both chose the same square, which is now lost
+    Collided,
+}
+
+/// A quantum game, with the marks and the squares lost to collisions
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct QuantumGame {
+    board: [Option<Player>; 9],
+    lost: [bool; 9],
+}
+
+impl QuantumGame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn board(&self) -> &[Option<Player>; 9] {
+        &self.board
+    }
+
+    /// Whether a collision took the square out of the game
+    pub fn is_lost(&self, square: usize) -> bool {
+        self.lost.get(square).copied().unwrap_or(false)
+    }
+
+    /// The squares that can still be chosen, from the top left
+    pub fn open_squares(&self) -> Vec<usize> {
+        (0..9)
+            .filter(|&square| self.board[square].is_none() && !self.lost[square])
+            .collect()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        let lines = |player| {
+            LINES.iter().any(|line| {
+                line.iter()
+                    .all(|&square| self.board[square] == Some(player))
+            })
+        };
+        match (lines(Player::X), lines(Player::O)) {
+            (true, true) => GameStatus::Tie,
+            (true, false) => GameStatus::Won(Player::X),
+            (false, true) => GameStatus::Won(Player::O),
+            _ if self.open_squares().len() < 2 => GameStatus::Tie,
+            _ => GameStatus::InProgress,
+        }
+    }
+
+    /// Show both choices at once, X's first
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::quantum::{Pick, QuantumGame};
+    ///
+    /// let mut game = QuantumGame::new();
+    /// assert_eq!(game.pick(4, 4).unwrap(), Pick::Collided);
+    /// assert!(game.is_lost(4));
+    /// // The lost square can't be chosen again
+    /// assert!(game.pick(4, 0).is_err());
+    /// assert_eq!(game.pick(0, 8).unwrap(), Pick::Placed);
+    /// ```
+    pub fn pick(&mut self, x: usize, o: usize) -> Result<Pick, MoveError> {
+        let over = self.status() != GameStatus::InProgress;
+        for square in [x, o] {
+            if square >= 9 {
+                return Err(MoveError::OffBoard(square));
+            }
+            if over {
+                return Err(MoveError::GameOver(square));
+            }
+            if self.board[square].is_some() || self.lost[square] {
+                return Err(MoveError::Taken(square));
+            }
+        }
+        if x == o {
+            self.lost[x] = true;
+            return Ok(Pick::Collided);
+        }
+        self.board[x] = Some(Player::X);
+        self.board[o] = Some(Player::O);
+        Ok(Pick::Placed)
+    }
+
+    /// The same position with the board turned or flipped
+    fn transform(&self, symmetry: Symmetry) -> Self {
+        let mut turned = Self::default();
+        for square in 0..9 {
+            turned.board[symmetry.apply(square)] = self.board[square];
+            turned.lost[symmetry.apply(square)] = self.lost[square];
+        }
+        turned
+    }
+
+    /// A number for the position that every rotation and reflection of it shares
+    fn canonical_code(&self) -> u32 {
+        Symmetry::ALL
+            .iter()
+            .map(|&symmetry| {
+                let turned = self.transform(symmetry);
+                (0..9).fold(0, |code, square| {
+                    let cell = match (turned.board[square], turned.lost[square]) {
+                        (Some(Player::X), _) => 1,
+                        (Some(Player::O), _) => 2,
+                        (None, true) => 3,
+                        (None, false) => 0,
+                    };
+                    code * 4 + cell
+                })
+            })
+            .min()
+            .expect("there are symmetries")
+    }
+}
+
+/// The best mix of choices for both players, worked out once for each position and remembered
+#[derive(Default)]
+pub struct Solver {
+    values: HashMap<u32, f64>,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// What the game is worth to X with both players choosing their best mix: 1 for a sure win,
+    /// -1 for a sure loss, and in between the share of wins over losses X can count on
+    pub fn value(&mut self, game: &QuantumGame) -> f64 {
+        match game.status() {
+            GameStatus::Won(Player::X) => return 1.0,
+            GameStatus::Won(_) => return -1.0,
+            GameStatus::Tie => return 0.0,
+            GameStatus::InProgress => {}
+        }
+        let code = game.canonical_code();
+        if let Some(&value) = self.values.get(&code) {
+            return value;
+        }
+        let (value, _, _) = solve_matrix(&self.payoffs(game));
+        self.values.insert(code, value);
+        value
+    }
+
+    /// How often `player` should choose each open square, as pairs of a square and a chance
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::quantum::{QuantumGame, Solver};
+    /// use simple_tic_tac_toe::Player;
+    ///
+    /// let mut solver = Solver::new();
+    /// let mix = solver.strategy(&QuantumGame::new(), Player::X);
+    /// let total: f64 = mix.iter().map(|&(_, chance)| chance).sum();
+    /// assert!((total - 1.0).abs() < 1e-6);
+    /// // Both sides start out the same, so neither is ahead
+    /// assert!(solver.value(&QuantumGame::new()).abs() < 1e-6);
+    /// ```
+    pub fn strategy(&mut self, game: &QuantumGame, player: Player) -> Vec<(usize, f64)> {
+        let squares = game.open_squares();
+        let (_, x, o) = solve_matrix(&self.payoffs(game));
+        let mix = match player {
+            Player::X => x,
+            Player::O => o,
+        };
+        squares.into_iter().zip(mix).collect()
+    }
+
+    /// Choose a square for `player` at random from its best mix
+    pub fn choose(&mut self, game: &QuantumGame, player: Player, rng: &mut impl Rng) -> usize {
+        // The best mix often sticks to one square where others are as good, so turning the board
+        // first keeps the choices from being the same every game
+        let symmetry = Symmetry::ALL[rng.gen_range(0..Symmetry::ALL.len())];
+        let mix = self.strategy(&game.transform(symmetry), player);
+        let mut roll = rng.gen::<f64>();
+        let chosen = mix
+            .iter()
+            .find(|&&(_, chance)| {
+                roll -= chance;
+                roll < 0.0
+            })
+            // Rounding can leave a sliver past the last chance
+            .or_else(|| mix.iter().rev().find(|&&(_, chance)| chance > 0.0))
+            .expect("a game in progress has open squares");
+        symmetry.inverse().apply(chosen.0)
+    }
+
+    /// X's value after each pair of choices, X's choices down and O's across
+    fn payoffs(&mut self, game: &QuantumGame) -> Vec<Vec<f64>> {
+        let squares = game.open_squares();
+        squares
+            .iter()
+            .map(|&x| {
+                squares
+                    .iter()
+                    .map(|&o| {
+                        let mut after = *game;
+                        after.pick(x, o).expect("open squares can be picked");
+                        self.value(&after)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Solve the zero-sum game where the row player gets `payoffs[row][column]`, giving its value
+/// and the best mixes for the row and column players
+fn solve_matrix(payoffs: &[Vec<f64>]) -> (f64, Vec<f64>, Vec<f64>) {
+    let rows = payoffs.len();
+    let columns = payoffs[0].len();
+    // Shifting every payoff above zero keeps the game's value positive, which the method needs
+    let shift = 1.0 - payoffs.iter().flatten().copied().fold(0.0, f64::min);
+
+    // Maximize the sum of q with payoffs times q at most 1 for each row. The column player's mix
+    // is q over its sum, the row player's comes from the dual, and the value is one over the sum
+    let width = columns + rows + 1;
+    let mut tableau: Vec<Vec<f64>> = (0..rows)
+        .map(|row| {
+            let mut line = vec![0.0; width];
+            for column in 0..columns {
+                line[column] = payoffs[row][column] + shift;
+            }
+            line[columns + row] = 1.0;
+            line[width - 1] = 1.0;
+            line
+        })
+        .collect();
+    let mut objective = vec![0.0; width];
+    objective[..columns].fill(-1.0);
+    let mut basis: Vec<usize> = (columns..columns + rows).collect();
+
+    // Bland's rule, taking the first improving column and the lowest basic variable on ties,
+    // so the method can't cycle
+    while let Some(entering) = (0..width - 1).find(|&column| objective[column] < -EPSILON) {
+        let leaving = (0..rows)
+            .filter(|&row| tableau[row][entering] > EPSILON)
+            .min_by(|&a, &b| {
+                let ratio = |row: usize| tableau[row][width - 1] / tableau[row][entering];
+                ratio(a).total_cmp(&ratio(b)).then(basis[a].cmp(&basis[b]))
+            })
+            .expect("the payoffs are positive, so every column is bounded");
+        let pivot = tableau[leaving][entering];
+        for value in &mut tableau[leaving] {
+            *value /= pivot;
+        }
+        let pivot_row = tableau[leaving].clone();
+        for (row, line) in tableau.iter_mut().enumerate() {
+            if row != leaving {
+                let factor = line[entering];
+                for (value, pivot_value) in line.iter_mut().zip(&pivot_row) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+        let factor = objective[entering];
+        for (value, pivot_value) in objective.iter_mut().zip(&pivot_row) {
+            *value -= factor * pivot_value;
+        }
+        basis[leaving] = entering;
+    }
+
+    let total = objective[width - 1];
+    let mut column_mix = vec![0.0; columns];
+    for (row, &variable) in basis.iter().enumerate() {
+        if variable < columns {
+            column_mix[variable] = tableau[row][width - 1] / total;
+        }
+    }
+    let row_mix = (0..rows)
+        .map(|row| (objective[columns + row] / total).max(0.0))
+        .collect();
+    (1.0 / total - shift, row_mix, column_mix)
+}
+
+/// The commitment to `square` sent before revealing it, the SHA-256 of the square and `nonce`
+/// in hex
+#[cfg(feature = "net")]
+pub fn commitment(square: usize, nonce: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(format!("{square}:{nonce}"))
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// A random nonce for a commitment, long enough that the square can't be guessed from it
+#[cfg(feature = "net")]
+pub fn new_nonce() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
@@ -0,0 +1,357 @@
+use crate::ai::{perfect_play_winner, Decision, GameResult};
+use crate::error::Error;
+use crate::game::{GameState, GameStatus, Player};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How a game ended other than by being played out on the board
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ending {
+    /// The given player resigned, and lost
+    Resigned(Player),
+    /// Both sides agreed to a draw
+    DrawAgreed,
+}
+
+/// A judgement of a move in the marks chess uses, written after the move like `b2!`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Glyph {
+    #[serde(rename = "!!")]
+    Brilliant,
+    #[serde(rename = "!")]
+    Good,
+    #[serde(rename = "!?")]
+    Interesting,
+    #[serde(rename = "?!")]
+    Dubious,
+    #[serde(rename = "?")]
+    Mistake,
+    #[serde(rename = "??")]
+    Blunder,
+}
+
+impl Glyph {
+    const ALL: [Glyph; 6] = [
+        Glyph::Brilliant,
+        Glyph::Good,
+        Glyph::Interesting,
+        Glyph::Dubious,
+        Glyph::Mistake,
+        Glyph::Blunder,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Glyph::Brilliant => "!!",
+            Glyph::Good => "!",
+            Glyph::Interesting => "!?",
+            Glyph::Dubious => "?!",
+            Glyph::Mistake => "?",
+            Glyph::Blunder => "??",
+        }
+    }
+}
+
+impl Display for Glyph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+impl FromStr for Glyph {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Glyph::ALL
+            .into_iter()
+            .find(|glyph| glyph.symbol() == s)
+            .ok_or_else(|| {
+                Error::parse(format!(
+                    "`{s}` is not a glyph, expected !!, !, !?, ?!, ? or ??"
+                ))
+            })
+    }
+}
+
+/// One move of a game, with what was known about it when it was played
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MoveRecord {
+    /// The mark placed, which is the other side's after a pie rule swap
+    pub player: Player,
+    /// Numbered 0-8 from the top left
+    pub square: usize,
+    /// When the move was played, in milliseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// How long the move took, in milliseconds, from the turn starting to the move being made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub think_time: Option<u64>,
+    /// The result for `player` with best play from the position the move left
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evaluation: Option<GameResult>,
+    /// What the computer saw when making the move, if it was the computer's move
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<Decision>,
+    /// A judgement of the move, such as `??` for a blunder
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glyph: Option<Glyph>,
+    /// A note on the move, such as why it was played
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<String>,
+}
+
+impl MoveRecord {
+    pub fn new(player: Player, square: usize) -> Self {
+        Self {
+            player,
+            square,
+            timestamp: None,
+            think_time: None,
+            evaluation: None,
+            decision: None,
+            glyph: None,
+            annotation: None,
+        }
+    }
+
+    /// Replace the move's glyph and note with those in `text`: an optional glyph, then the
+    /// note. Blank text clears both
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::record::{Glyph, MoveRecord};
+    /// use simple_tic_tac_toe::Player;
+    ///
+    /// let mut played = MoveRecord::new(Player::X, 4);
+    /// played.annotate("?? walks into a fork");
+    /// assert_eq!(played.glyph, Some(Glyph::Blunder));
+    /// assert_eq!(played.annotation.as_deref(), Some("walks into a fork"));
+    ///
+    /// played.annotate("!");
+    /// assert_eq!(played.glyph, Some(Glyph::Good));
+    /// assert_eq!(played.annotation, None);
+    /// ```
+    pub fn annotate(&mut self, text: &str) {
+        let text = text.trim();
+        let (first, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let (glyph, note) = match first.parse() {
+            Ok(glyph) => (Some(glyph), rest.trim()),
+            Err(_) => (None, text),
+        };
+        self.glyph = glyph;
+        self.annotation = (!note.is_empty()).then(|| note.to_string());
+    }
+
+    /// Record the move as played now, after thinking for `think_time`
+    pub fn stamp(&mut self, think_time: Duration) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.timestamp = Some(now.as_millis() as u64);
+        self.think_time = Some(think_time.as_millis() as u64);
+    }
+
+    /// Record the result with best play from `after`, the position the move left
+    pub fn evaluate(&mut self, after: &GameState) {
+        let winner = match after.status() {
+            GameStatus::Won(winner) => Some(winner),
+            GameStatus::InProgress => perfect_play_winner(after),
+            GameStatus::Tie => None,
+        };
+        self.evaluation = Some(match winner {
+            Some(winner) if winner == self.player => GameResult::Win,
+            Some(_) => GameResult::Loss,
+            None => GameResult::Tie,
+        });
+    }
+}
+
+/// A game as saved to disk, with enough information to replay it move by move
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "SavedGame")]
+pub struct GameRecord {
+    /// The side the computer played at the start of the game
+    pub computer_player: Player,
+    /// The moves played in order
+    pub moves: Vec<MoveRecord>,
+    /// Set if the game was resigned or agreed drawn rather than finished on the board
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ending: Option<Ending>,
+    /// Set if the second player swapped sides after the first move under the pie rule
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub swapped: bool,
+    /// The board the game started from, if it was set up instead of starting empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<[Option<Player>; 9]>,
+    /// Who the computer was, such as `computer, hard`, for telling saved games apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opponent: Option<String>,
+}
+
+/// A saved game as read, which may be from before moves were saved as [`MoveRecord`]s, when they
+/// were bare squares with the computer's decisions in a list of their own
+#[derive(Deserialize)]
+struct SavedGame {
+    computer_player: Player,
+    moves: Vec<SavedMove>,
+    #[serde(default)]
+    decisions: Vec<Option<Decision>>,
+    #[serde(default)]
+    ending: Option<Ending>,
+    #[serde(default)]
+    swapped: bool,
+    #[serde(default)]
+    start: Option<[Option<Player>; 9]>,
+    #[serde(default)]
+    opponent: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedMove {
+    Square(usize),
+    Full(MoveRecord),
+}
+
+impl From<SavedGame> for GameRecord {
+    fn from(saved: SavedGame) -> Self {
+        let mut record = GameRecord::new(saved.computer_player, Vec::new());
+        record.ending = saved.ending;
+        record.swapped = saved.swapped;
+        record.start = saved.start;
+        record.opponent = saved.opponent;
+        let mut decisions = saved.decisions.into_iter();
+        for saved_move in saved.moves {
+            let decision = decisions.next().flatten();
+            match saved_move {
+                SavedMove::Square(square) => {
+                    record.push(square, decision);
+                }
+                SavedMove::Full(full) => record.moves.push(full),
+            }
+        }
+        record
+    }
+}
+
+impl GameRecord {
+    /// A game of the given squares, numbered 0-8 from the top left
+    pub fn new(computer_player: Player, squares: Vec<usize>) -> Self {
+        let mut record = Self {
+            computer_player,
+            moves: Vec::new(),
+            ending: None,
+            swapped: false,
+            start: None,
+            opponent: None,
+        };
+        for square in squares {
+            record.push(square, None);
+        }
+        record
+    }
+
+    /// Add a move to the end of the game, with the computer's decision if it was the computer's
+    /// move. The rest of the move's details can be filled in through what's returned
+    pub fn push(&mut self, square: usize, decision: Option<Decision>) -> &mut MoveRecord {
+        let mut record = MoveRecord::new(self.next_mark(), square);
+        record.decision = decision;
+        self.moves.push(record);
+        self.moves.last_mut().expect("a move was just added")
+    }
+
+    /// The mark the next move places, going by the start position and the moves so far
+    fn next_mark(&self) -> Player {
+        match self.moves.last() {
+            Some(last) => last.player.opponent(),
+            None => {
+                let board = self.start.unwrap_or_default();
+                let count = |player| board.iter().filter(|&&mark| mark == Some(player)).count();
+                if count(Player::X) > count(Player::O) {
+                    Player::O
+                } else {
+                    Player::X
+                }
+            }
+        }
+    }
+
+    /// The squares played in order, numbered 0-8 from the top left
+    pub fn squares(&self) -> Vec<usize> {
+        self.moves.iter().map(|m| m.square).collect()
+    }
+
+    /// Read a saved game from a JSON file
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(Error::io("read", path))?;
+        let record: Self = serde_json::from_str(&json)
+            .map_err(|e| Error::parse_with(format!("{} is not a saved game", path.display()), e))?;
+        // Make sure the moves actually make up a legal game
+        record.final_position()?;
+        Ok(record)
+    }
+
+    /// Write the game to a JSON file
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).map_err(Error::io("write", path))
+    }
+
+    /// Get the position after the first `n` moves of the game
+    pub fn position(&self, n: usize) -> crate::Result<GameState> {
+        if n > self.moves.len() {
+            return Err(Error::NoSuchPosition {
+                requested: n,
+                moves: self.moves.len(),
+            });
+        }
+        let mut state = match self.start {
+            Some(board) => GameState::from_board(self.computer_player, board)?,
+            None => GameState::new(self.computer_player),
+        };
+        for played in &self.moves[..n] {
+            state.try_move(played.square)?;
+        }
+        if self.swapped && n >= 1 {
+            state.swap_sides();
+        }
+        Ok(state)
+    }
+
+    /// The side the human played at the end of the game, after any pie rule swap
+    pub fn human_player(&self) -> Player {
+        if self.swapped {
+            self.computer_player
+        } else {
+            self.computer_player.opponent()
+        }
+    }
+
+    /// Get the computer's decision for a move, if it was the computer's move and it was recorded
+    pub fn decision(&self, index: usize) -> Option<&Decision> {
+        self.moves.get(index)?.decision.as_ref()
+    }
+
+    /// Get every position in the game, from the empty board to the final position
+    pub fn positions(&self) -> crate::Result<Vec<GameState>> {
+        (0..=self.moves.len()).map(|n| self.position(n)).collect()
+    }
+
+    /// Get the result of the game, taking resignations and agreed draws into account
+    pub fn status(&self) -> crate::Result<GameStatus> {
+        Ok(match self.ending {
+            Some(Ending::Resigned(player)) => GameStatus::Won(player.opponent()),
+            Some(Ending::DrawAgreed) => GameStatus::Tie,
+            None => self.final_position()?.status(),
+        })
+    }
+
+    /// Get the position at the end of the game
+    pub fn final_position(&self) -> crate::Result<GameState> {
+        self.position(self.moves.len())
+    }
+}
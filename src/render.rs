@@ -0,0 +1,184 @@
+use crate::game::{GameState, GameStatus, Player};
+use std::fmt::Display;
+use unicode_width::UnicodeWidthStr;
+
+/// The strings used to draw each kind of square on the board
+#[derive(Clone, Debug)]
+pub struct Pieces {
+    pub x: String,
+    pub o: String,
+    pub empty: String,
+}
+
+impl Pieces {
+    /// Plain X and O, as used by the default board display
+    pub fn ascii() -> Self {
+        Self {
+            x: "X".to_string(),
+            o: "O".to_string(),
+            empty: ".".to_string(),
+        }
+    }
+
+    /// Double-width emoji pieces that are easier to see on modern terminals
+    pub fn emoji() -> Self {
+        Self {
+            x: "❌".to_string(),
+            o: "⭕".to_string(),
+            empty: "·".to_string(),
+        }
+    }
+
+    pub fn get(&self, square: Option<Player>) -> &str {
+        match square {
+            Some(Player::X) => &self.x,
+            Some(Player::O) => &self.o,
+            None => &self.empty,
+        }
+    }
+
+    /// The terminal width of the widest piece, used to keep the grid lined up
+    pub fn cell_width(&self) -> usize {
+        [&self.x, &self.o, &self.empty]
+            .iter()
+            .map(|p| p.width())
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+impl Default for Pieces {
+    fn default() -> Self {
+        Self::ascii()
+    }
+}
+
+/// Draw a board using the given pieces, whether or not it could come up in a game
+pub fn render_board(board: &[Option<Player>; 9], pieces: &Pieces) -> String {
+    let cells: Vec<&str> = board.iter().map(|&square| pieces.get(square)).collect();
+    render_grid(&cells, 3, pieces.cell_width())
+}
+
+/// Draw a board with squares three lines tall and seven columns wide, for small children and
+/// big screens
+pub fn render_big_board(board: &[Option<Player>; 9], pieces: &Pieces) -> String {
+    const WIDTH: usize = 7;
+    let separator = vec!["-".repeat(WIDTH); 3].join("+");
+    let blank = vec![" ".repeat(WIDTH); 3].join("|");
+    let mut s = String::new();
+    for (i, row) in board.chunks(3).enumerate() {
+        if i != 0 {
+            s.push_str(&separator);
+            s.push('\n');
+        }
+        let cells: Vec<String> = row
+            .iter()
+            .map(|&square| {
+                let piece = pieces.get(square);
+                let padding = WIDTH.saturating_sub(piece.width());
+                let left = padding / 2;
+                format!("{}{piece}{}", " ".repeat(left), " ".repeat(padding - left))
+            })
+            .collect();
+        s.push_str(&format!("{blank}\n{}\n{blank}\n", cells.join("|")));
+    }
+    s
+}
+
+/// Describe a board in words, a row to a line, for screen readers that stumble over the drawn
+/// grid
+pub fn describe_board(board: &[Option<Player>; 9]) -> String {
+    let mut s = String::new();
+    for (name, row) in ["Top", "Middle", "Bottom"].iter().zip(board.chunks(3)) {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|square| square.map_or("empty".to_string(), |player| player.to_string()))
+            .collect();
+        s.push_str(&format!("{name} row: {}.\n", cells.join(", ")));
+    }
+    s
+}
+
+/// Draw any grid of cells row by row, `width` cells to a row, each padded to `cell_width` columns
+pub fn render_grid(cells: &[&str], width: usize, cell_width: usize) -> String {
+    let separator = vec!["-".repeat(cell_width + 2); width].join("|");
+
+    let mut s = String::new();
+    for (i, row) in cells.chunks(width).enumerate() {
+        if i != 0 {
+            s.push_str(&separator);
+            s.push('\n');
+        }
+        let row: Vec<String> = row
+            .iter()
+            .map(|cell| {
+                // Pad by display width rather than char count so wide emoji line up
+                let padding = cell_width.saturating_sub(cell.width());
+                format!(" {cell}{} ", " ".repeat(padding))
+            })
+            .collect();
+        s.push_str(&row.join("|"));
+        s.push('\n');
+    }
+    s
+}
+
+impl GameState {
+    /// Draw the board using the given pieces
+    pub fn render(&self, pieces: &Pieces) -> String {
+        render_board(self.board(), pieces)
+    }
+}
+
+impl Display for GameState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.render(&Pieces::default()))
+    }
+}
+
+/// Lay out several blocks of text, such as rendered boards, next to each other
+pub fn side_by_side(blocks: &[String], gap: usize) -> String {
+    let columns: Vec<Vec<&str>> = blocks.iter().map(|b| b.lines().collect()).collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .map(|lines| lines.iter().map(|l| l.width()).max().unwrap_or(0))
+        .collect();
+    let height = columns.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut s = String::new();
+    for row in 0..height {
+        let mut line = String::new();
+        for (lines, width) in columns.iter().zip(&widths) {
+            let cell = lines.get(row).copied().unwrap_or("");
+            line.push_str(cell);
+            line.push_str(&" ".repeat(width - cell.width() + gap));
+        }
+        s.push_str(line.trim_end());
+        s.push('\n');
+    }
+    s
+}
+
+/// Get a compact summary of a finished game for sharing, with an emoji board like Wordle results
+pub fn share_text(state: &GameState, status: GameStatus) -> String {
+    let human = state.computer_player.opponent();
+    let moves = state.board().iter().filter(|s| s.is_some()).count();
+    let result = match status {
+        GameStatus::Won(winner) if winner == human => "I beat",
+        GameStatus::Won(_) => "I lost to",
+        GameStatus::Tie | GameStatus::InProgress => "I tied",
+    };
+
+    let mut text = format!("Tic-Tac-Toe: {result} the computer as {human} in {moves} moves\n");
+    for row in state.board().chunks(3) {
+        for square in row {
+            text.push(match square {
+                Some(Player::X) => '❌',
+                Some(Player::O) => '⭕',
+                None => '⬜',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
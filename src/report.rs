@@ -0,0 +1,277 @@
+//! Machine-readable reports printed by the `--json` options
+//!
+//! These types define the JSON schemas and are kept stable: fields may be added, but existing
+//! fields won't be renamed, removed, or change meaning. Squares are always numbered 0-8 from the
+//! top left, row by row, and players are the strings `"X"` and `"O"`.
+
+use crate::ai::{perfect_play_winner, Difficulty, GameResult};
+use crate::csv::{self, Filter};
+use crate::game::{GameState, GameStatus, Player};
+use crate::stats::StatsEntry;
+use crate::tournament::{Standings, Tournament};
+use serde::Serialize;
+
+/// A snapshot of the board
+///
+/// ```json
+/// {"board": ["X", null, null, null, "O", null, null, null, null],
+///  "next_player": "X", "status": "in_progress", "notation": "x../.o./... x"}
+/// ```
+///
+/// `status` is one of `"in_progress"`, `"tie"`, or `"won"`, in which case `winner` holds the
+/// winning player.
+#[derive(Debug, Serialize)]
+pub struct PositionReport {
+    /// The mark on each square, or null for an empty square
+    pub board: [Option<Player>; 9],
+    /// The player whose turn it is
+    pub next_player: Player,
+    #[serde(flatten)]
+    pub status: GameStatus,
+    /// The position in the notation of [`crate::position`]
+    pub notation: String,
+}
+
+impl From<&GameState> for PositionReport {
+    fn from(state: &GameState) -> Self {
+        Self {
+            board: *state.board(),
+            next_player: state.next_player(),
+            status: state.status(),
+            notation: state.to_position_string(),
+        }
+    }
+}
+
+/// The output of `play --json`: the moves of a non-interactive game and where it ended up
+#[derive(Debug, Serialize)]
+pub struct PlayReport {
+    /// The side the human played
+    pub human_player: Player,
+    /// The side the computer played
+    pub computer_player: Player,
+    /// Every square played by either side, in order
+    pub moves: Vec<usize>,
+    /// The position after the last move
+    pub position: PositionReport,
+}
+
+/// The output of `analyze --json`: the perfect-play evaluation of a position
+#[derive(Debug, Serialize)]
+pub struct AnalysisReport {
+    pub position: PositionReport,
+    /// The player who wins with best play from both sides, or null if it is a tie
+    pub winner_with_best_play: Option<Player>,
+    /// The squares that achieve that result for the player to move, empty if the game is over
+    pub best_moves: Vec<usize>,
+}
+
+impl From<&GameState> for AnalysisReport {
+    fn from(state: &GameState) -> Self {
+        let mut state = state.clone();
+        state.computer_player = state.next_player();
+        let best_moves = if state.status() == GameStatus::InProgress {
+            state
+                .get_best_computer_moves()
+                .iter()
+                .map(|m| m.square)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            position: PositionReport::from(&state),
+            winner_with_best_play: perfect_play_winner(&state),
+            best_moves,
+        }
+    }
+}
+
+/// The output of `tournament --json`: where each entrant finished and how each pairing went
+///
+/// ```json
+/// {"games": 1000, "seed": 7,
+///  "standings": [{"entrant": 0, "blunder_chance": 0.0, "points": 1520.5, "games": 2000}, ...],
+///  "pairings": [{"x": 0, "o": 1, "x_wins": 112, "o_wins": 0, "ties": 888}, ...]}
+/// ```
+///
+/// Entrants are numbered by their place in the `--entrants` list.
+#[derive(Debug, Serialize)]
+pub struct TournamentReport {
+    /// Games played by each pairing
+    pub games: usize,
+    pub seed: u64,
+    /// The entrants from most points to fewest
+    pub standings: Vec<EntrantReport>,
+    /// Every pairing of two entrants, once with each of them as X
+    pub pairings: Vec<PairingReport>,
+}
+
+/// One entrant's place in a [`TournamentReport`]
+#[derive(Debug, Serialize)]
+pub struct EntrantReport {
+    pub entrant: usize,
+    /// How often the entrant blunders, between 0 and 1
+    pub blunder_chance: f64,
+    /// One for each win and a half for each tie
+    pub points: f64,
+    /// Games the entrant played, as X and as O
+    pub games: usize,
+}
+
+/// The games between two entrants in a [`TournamentReport`] with one of them as X
+#[derive(Debug, Serialize)]
+pub struct PairingReport {
+    /// The entrant playing X
+    pub x: usize,
+    /// The entrant playing O
+    pub o: usize,
+    pub x_wins: u32,
+    pub o_wins: u32,
+    pub ties: u32,
+}
+
+impl TournamentReport {
+    pub fn new(tournament: &Tournament, standings: &Standings) -> Self {
+        let games_each = 2 * tournament.games * (standings.entrants.len() - 1);
+        Self {
+            games: tournament.games,
+            seed: tournament.seed,
+            standings: standings
+                .ranking()
+                .into_iter()
+                .map(|entrant| EntrantReport {
+                    entrant,
+                    blunder_chance: standings.entrants[entrant],
+                    points: standings.points(entrant),
+                    games: games_each,
+                })
+                .collect(),
+            pairings: standings
+                .pairings
+                .iter()
+                .map(|pairing| PairingReport {
+                    x: pairing.x,
+                    o: pairing.o,
+                    x_wins: pairing.results.x_wins,
+                    o_wins: pairing.results.o_wins,
+                    ties: pairing.results.ties,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The output of `stats export --json` and `history export --json`: the recorded games the
+/// filters let through
+///
+/// ```json
+/// {"games": [{"game": 1, "finished_at": 1717178649, "finished": "2024-05-31T18:04:09Z",
+///   "difficulty": "hard", "human_player": "X", "result": "tie", "moves": 9,
+///   "swapped": false, "set_up": false}, ...]}
+/// ```
+///
+/// Games are numbered as in the CSV exports. `history export` also gives each game's `history`,
+/// one entry for each move.
+#[derive(Debug, Serialize)]
+pub struct StatsReport {
+    pub games: Vec<GameReport>,
+}
+
+/// One recorded game in a [`StatsReport`]
+#[derive(Debug, Serialize)]
+pub struct GameReport {
+    /// The game's place among every recorded game, counting from 1 before filtering
+    pub game: usize,
+    /// When the game finished, in seconds since the Unix epoch
+    pub finished_at: u64,
+    /// The same time in ISO 8601, in UTC
+    pub finished: String,
+    pub difficulty: Difficulty,
+    /// How often the computer was set to blunder, for adaptive and easy games
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blunder_chance: Option<f64>,
+    pub human_player: Player,
+    /// The result for the human, `"win"`, `"loss"` or `"tie"`
+    pub result: GameResult,
+    /// How many moves were played
+    pub moves: usize,
+    /// Whether the sides were swapped under the pie rule
+    pub swapped: bool,
+    /// Set to `"X resigned"`, `"O resigned"` or `"draw agreed"` if the game didn't end on the
+    /// board
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ending: Option<String>,
+    /// Whether the game started from a set-up position instead of the empty board
+    pub set_up: bool,
+    /// Every move, for `history export`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<MoveReport>>,
+}
+
+/// One move of a game in a [`StatsReport`]
+#[derive(Debug, Serialize)]
+pub struct MoveReport {
+    pub square: usize,
+    /// The mark placed
+    pub player: Player,
+    /// `"human"` or `"computer"`
+    pub by: &'static str,
+    /// How long the move took, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub think_time_ms: Option<u64>,
+    /// The result for `player` with best play after the move
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evaluation: Option<GameResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<String>,
+    /// A judgement of the move, such as `"??"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glyph: Option<String>,
+}
+
+impl StatsReport {
+    /// The games in `entries` that `filter` lets through, with their moves if `history` is set
+    pub fn new(entries: &[StatsEntry], filter: &Filter, history: bool) -> crate::Result<Self> {
+        let games = csv::numbered(entries, filter)
+            .map(|(game, entry)| {
+                let history = history
+                    .then(|| -> crate::Result<Vec<MoveReport>> {
+                        let positions = entry.game.positions()?;
+                        Ok(entry
+                            .game
+                            .moves
+                            .iter()
+                            .zip(&positions)
+                            .enumerate()
+                            .map(|(m, (played, before))| MoveReport {
+                                square: played.square,
+                                player: before.next_player(),
+                                by: csv::mover(entry, m, before.next_player()),
+                                think_time_ms: played.think_time,
+                                evaluation: played.evaluation,
+                                annotation: played.annotation.clone(),
+                                glyph: played.glyph.map(|glyph| glyph.to_string()),
+                            })
+                            .collect())
+                    })
+                    .transpose()?;
+                Ok(GameReport {
+                    game,
+                    finished_at: entry.finished_at,
+                    finished: csv::timestamp(entry.finished_at),
+                    difficulty: entry.difficulty,
+                    blunder_chance: entry.blunder_chance,
+                    human_player: entry.human_player,
+                    result: entry.result,
+                    moves: entry.game.moves.len(),
+                    swapped: entry.game.swapped,
+                    ending: csv::ending(entry),
+                    set_up: entry.game.start.is_some(),
+                    history,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(Self { games })
+    }
+}
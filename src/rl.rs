@@ -0,0 +1,165 @@
+//! A reinforcement-learning opponent that learns tic-tac-toe from self-play with tabular
+//! Q-learning, as a contrast to the exact minimax search in [`crate::ai`]
+
+use crate::error::Error;
+use crate::game::{GameState, GameStatus, Player};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+#[cfg(feature = "storage")]
+use std::path::PathBuf;
+
+/// Settings for a training run
+#[derive(Clone, Debug)]
+pub struct TrainingOptions {
+    /// Number of self-play games
+    pub episodes: u32,
+    /// Learning rate
+    pub alpha: f64,
+    /// Chance of exploring a random move instead of the best known one
+    pub epsilon: f64,
+    /// Seed for the random number generator, for reproducible tables
+    pub seed: Option<u64>,
+}
+
+impl Default for TrainingOptions {
+    fn default() -> Self {
+        Self {
+            episodes: 200_000,
+            alpha: 0.5,
+            epsilon: 0.5,
+            seed: None,
+        }
+    }
+}
+
+/// Learned move values for every position seen in training, from the point of view of the side
+/// to move: 1 for a win, 0 for a tie and -1 for a loss
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QTable {
+    values: BTreeMap<u32, [f64; 9]>,
+}
+
+impl QTable {
+    /// The table location, e.g. `~/.local/share/simple-tic-tac-toe/rl.json` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("simple-tic-tac-toe").join("rl.json"))
+    }
+
+    /// Learn a table by playing games against itself
+    pub fn train(options: &TrainingOptions) -> Self {
+        let mut rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut table = Self::default();
+
+        for _ in 0..options.episodes {
+            let mut state = GameState::new(Player::O);
+            while state.status() == GameStatus::InProgress {
+                let square = if rng.gen_bool(options.epsilon) {
+                    let mut moves = state.legal_moves();
+                    let skip = rng.gen_range(0..moves.len());
+                    moves.nth(skip).expect("the game is in progress")
+                } else {
+                    table.best_move(&state)
+                };
+
+                let next = state.with_move(square);
+                let target = match next.status() {
+                    GameStatus::Won(_) => 1.0,
+                    GameStatus::Tie => 0.0,
+                    // The opponent moves next, so their best value is our loss
+                    GameStatus::InProgress => -table.best_value(&next),
+                };
+                let value = &mut table.values.entry(state.board_code()).or_default()[square];
+                *value += options.alpha * (target - *value);
+                state = next;
+            }
+        }
+        table
+    }
+
+    /// Get the move with the highest learned value, taking the first square on ties
+    pub fn best_move(&self, state: &GameState) -> usize {
+        let values = self.values.get(&state.board_code());
+        state
+            .legal_moves()
+            .map(|square| (square, values.map_or(0.0, |v| v[square])))
+            .fold(
+                None,
+                |best: Option<(usize, f64)>, (square, value)| match best {
+                    Some((_, best_value)) if best_value >= value => best,
+                    _ => Some((square, value)),
+                },
+            )
+            .map(|(square, _)| square)
+            .expect("best_move called on a finished game")
+    }
+
+    /// Number of positions the table has values for
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Read a table written by `save`
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(Error::io("read", path))?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::parse_with(format!("{} is not a Q-table", path.display()), e))
+    }
+
+    /// Write the table to a JSON file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+        }
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json).map_err(Error::io("write", path))
+    }
+
+    fn best_value(&self, state: &GameState) -> f64 {
+        let values = self.values.get(&state.board_code());
+        state
+            .legal_moves()
+            .map(|square| values.map_or(0.0, |v| v[square]))
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+/// Tally of games from one side's point of view
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Tally {
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+}
+
+/// Play the table against minimax, with the table as `side`, and count its results
+pub fn compare_with_minimax(table: &QTable, side: Player, games: u32) -> Tally {
+    let mut tally = Tally::default();
+    for _ in 0..games {
+        let mut state = GameState::new(side.opponent());
+        while state.status() == GameStatus::InProgress {
+            let square = if state.next_player() == side {
+                table.best_move(&state)
+            } else {
+                state.get_random_computer_move().square
+            };
+            state.apply_move(square);
+        }
+        match state.status() {
+            GameStatus::Won(winner) if winner == side => tally.wins += 1,
+            GameStatus::Won(_) => tally.losses += 1,
+            _ => tally.ties += 1,
+        }
+    }
+    tally
+}
@@ -0,0 +1,174 @@
+//! Games saved by name in one directory, along with autosaves of the last few games played, so
+//! games can be picked from a list instead of found by path
+//!
+//! Each slot is a game file named after the slot. Autosaves are the slots `autosave-1`, the most
+//! recent game, to `autosave-N`, with the oldest dropped as each new game comes in.
+
+use crate::csv::timestamp;
+use crate::error::Error;
+use crate::game::GameStatus;
+use crate::record::GameRecord;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const AUTOSAVE: &str = "autosave";
+
+/// A saved game in a [`SaveDir`]
+#[derive(Clone, Debug)]
+pub struct SaveSlot {
+    pub name: String,
+    pub path: PathBuf,
+    /// When the slot was last written, in seconds since the Unix epoch
+    pub saved_at: u64,
+    pub game: GameRecord,
+}
+
+impl SaveSlot {
+    /// Whether the slot is one of the autosaves rather than saved by name
+    pub fn is_autosave(&self) -> bool {
+        autosave_number(&self.name).is_some()
+    }
+
+    /// A line describing the game, like `autosave-1: X won, 2024-05-31T18:04:09Z, against
+    /// computer, hard, 7 moves`
+    pub fn preview(&self) -> String {
+        let result = match self.game.status() {
+            Ok(GameStatus::Won(winner)) => format!("{winner} won"),
+            Ok(GameStatus::Tie) => "tie".to_string(),
+            Ok(GameStatus::InProgress) => "unfinished".to_string(),
+            Err(_) => "unreadable moves".to_string(),
+        };
+        let mut parts = vec![result, timestamp(self.saved_at)];
+        if let Some(opponent) = &self.game.opponent {
+            parts.push(format!("against {opponent}"));
+        }
+        parts.push(format!("{} moves", self.game.moves.len()));
+        format!("{}: {}", self.name, parts.join(", "))
+    }
+}
+
+/// The directory of save slots
+pub struct SaveDir {
+    dir: PathBuf,
+}
+
+impl SaveDir {
+    /// The save directory location, e.g. `~/.local/share/simple-tic-tac-toe/saves` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("simple-tic-tac-toe").join("saves"))
+    }
+
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// The file for a slot. Names are letters, digits, `-` and `_`, so they can't reach outside
+    /// the directory
+    pub fn path(&self, name: &str) -> crate::Result<PathBuf> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(Error::parse(format!(
+                "`{name}` is not a save name, use letters, digits, `-` and `_`"
+            )));
+        }
+        Ok(self.dir.join(format!("{name}.json")))
+    }
+
+    /// Save a game under `name`, replacing any game already there
+    pub fn save(&self, name: &str, game: &GameRecord) -> crate::Result<PathBuf> {
+        let path = self.path(name)?;
+        std::fs::create_dir_all(&self.dir).map_err(Error::io("create", &self.dir))?;
+        game.save(&path)?;
+        Ok(path)
+    }
+
+    pub fn load(&self, name: &str) -> crate::Result<GameRecord> {
+        GameRecord::load(&self.path(name)?)
+    }
+
+    /// Save a game as `autosave-1`, moving the autosaves already there down one and dropping
+    /// any past `keep`. Nothing is saved if `keep` is 0
+    pub fn autosave(&self, game: &GameRecord, keep: usize) -> crate::Result<Option<PathBuf>> {
+        if keep == 0 {
+            return Ok(None);
+        }
+        // The oldest goes, along with any left over from when more were kept
+        for n in keep.. {
+            let path = self.autosave_path(n)?;
+            if !path.exists() {
+                break;
+            }
+            std::fs::remove_file(&path).map_err(Error::io("remove", &path))?;
+        }
+        for n in (1..keep).rev() {
+            let path = self.autosave_path(n)?;
+            if path.exists() {
+                let next = self.autosave_path(n + 1)?;
+                std::fs::rename(&path, &next).map_err(Error::io("rename", &path))?;
+            }
+        }
+        self.save(&format!("{AUTOSAVE}-1"), game).map(Some)
+    }
+
+    fn autosave_path(&self, n: usize) -> crate::Result<PathBuf> {
+        self.path(&format!("{AUTOSAVE}-{n}"))
+    }
+
+    /// Every saved game, most recently saved first. Files that aren't saved games are skipped
+    pub fn slots(&self) -> crate::Result<Vec<SaveSlot>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io("read", &self.dir)(e)),
+        };
+        let mut slots = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(Error::io("read", &self.dir))?.path();
+            let Some(name) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".json"))
+            else {
+                continue;
+            };
+            let game = match GameRecord::load(&path) {
+                Ok(game) => game,
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "skipping save");
+                    continue;
+                }
+            };
+            let saved_at = std::fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .map_err(Error::io("read", &path))?
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs());
+            slots.push(SaveSlot {
+                name: name.to_string(),
+                path,
+                saved_at,
+                game,
+            });
+        }
+        // Autosaves in order on ties, since they're often written within the same second
+        slots.sort_by(|a, b| {
+            b.saved_at.cmp(&a.saved_at).then_with(|| {
+                autosave_number(&a.name)
+                    .cmp(&autosave_number(&b.name))
+                    .then_with(|| a.name.cmp(&b.name))
+            })
+        });
+        Ok(slots)
+    }
+}
+
+/// The `N` of `autosave-N`
+fn autosave_number(name: &str) -> Option<usize> {
+    name.strip_prefix(AUTOSAVE)?.strip_prefix('-')?.parse().ok()
+}
@@ -0,0 +1,1220 @@
+//! A server for games between two people, many at once, over a newline-delimited JSON protocol
+//!
+//! Each line from a client is one [`Request`] and each line back is one [`Event`], tagged by a
+//! `"type"` field like the engine [`protocol`](crate::protocol). A game is known by a short code
+//! that the player who created it passes on to their opponent. Joining hands out a token for the
+//! seat, which the player sends with each move and uses to rejoin the game from a later
+//! connection, so games can be played over days like correspondence games. A game nobody has
+//! sat in for [`SEATS_KEPT_FOR`] is dropped, as is a finished game once nobody is looking at it.
+//!
+//! ```text
+//! > {"type": "create", "player": "X"}
+//! < {"type": "joined", "code": "KQXW", "token": "9f86d081884c7d65", "player": "X"}
+//! < {"type": "state", "code": "KQXW", "board": [null, ...], "next_player": "X", ...}
+//! > {"type": "move", "code": "KQXW", "token": "9f86d081884c7d65", "square": 4}
+//! ```
+//!
+//! A connection hears about each move in every game it joined, rejoined or is watching, so one
+//! client can follow many games at once. Anyone with the code can watch a game without a seat.
+//! Clients can start with the [`handshake`](crate::handshake), where watching is the
+//! `spectating` capability.
+//!
+//! The server keeps the only position that counts, so a client can't move out of turn, on a
+//! taken square or for the other side. A connection that sends [`MAX_FOULS`] such requests is
+//! closed. The config file's [`[server]`](ServerConfig) table limits how many games there are,
+//! how many each connection plays, how many connections come from one address and how fast
+//! requests are read.
+//!
+//! Games can also be the experimental [`quantum`](crate::quantum) variant, where instead of
+//! moving in turn both players `commit` to a square and then `reveal` it. Games are kept in
+//! memory, so they're gone when the server stops.
+
+use crate::config::ServerConfig;
+use crate::error::{Error, ProtocolError};
+use crate::game::{GameState, GameStatus, Player};
+use crate::handshake::{Capability, Hello, Support, Welcome};
+use crate::quantum::{self, QuantumGame};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The letters game codes are made of, leaving out ones easily misread
+const CODE_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+/// How many random codes to try for a new game before giving up on finding a free one
+const CODE_TRIES: usize = 100;
+
+/// How long a game is kept after the last time either player was connected to it or used their
+/// seat
+pub const SEATS_KEPT_FOR: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The protocol versions and capabilities the server has
+pub const SUPPORT: Support = Support {
+    oldest: 1,
+    newest: 1,
+    capabilities: &[Capability::Spectating],
+    variants: &["standard", "quantum"],
+};
+
+/// A request from a client
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Agree on a version and capabilities, answered with `welcome`
+    Hello(Hello),
+    /// Start a game and take the `player` seat, X if not given. A `quantum` game is the
+    /// [`quantum`](crate::quantum) variant, played with commits and reveals instead of moves
+    Create {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        player: Option<Player>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        quantum: bool,
+    },
+    /// Take the open seat in a game
+    Join { code: String },
+    /// Come back to a seat taken earlier, to hear about the game again
+    Rejoin { code: String, token: String },
+    /// Play a move from the seat the token is for
+    Move {
+        code: String,
+        token: String,
+        square: usize,
+    },
+    /// Choose a square in a quantum game without showing it yet, with the [`commitment`](
+    /// quantum::commitment) to it
+    Commit {
+        code: String,
+        token: String,
+        commitment: String,
+    },
+    /// Show the square committed to, once both sides have committed. A reveal that doesn't match
+    /// the commitment, or a square that can't be chosen, loses the game
+    Reveal {
+        code: String,
+        token: String,
+        square: usize,
+        nonce: String,
+    },
+    /// Give up the game
+    Resign { code: String, token: String },
+    /// Hear about a game's moves without playing in it
+    Watch { code: String },
+}
+
+/// Something sent to a client
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The answer to a hello
+    Welcome(Welcome),
+    /// The client has a seat in a game, and the token to send with its moves
+    Joined {
+        code: String,
+        token: String,
+        player: Player,
+    },
+    /// A game's position, sent after every change to everyone in the game
+    State(GameUpdate),
+    /// A request couldn't be carried out, and nothing changed
+    Error {
+        /// The game the request was for, if it named one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<String>,
+        message: String,
+    },
+}
+
+/// Where a game stands
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameUpdate {
+    pub code: String,
+    pub board: [Option<Player>; 9],
+    /// Always X in quantum games, where both sides move at once
+    pub next_player: Player,
+    #[serde(flatten)]
+    pub status: GameStatus,
+    /// The square the last move was played on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_move: Option<usize>,
+    /// Set if the game was won by the other side resigning
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resigned: Option<Player>,
+    /// Set while the game is waiting for a second player
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub waiting: bool,
+    /// Set for quantum games
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantum: Option<QuantumUpdate>,
+}
+
+/// Where the pick being made in a quantum game stands
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuantumUpdate {
+    /// The squares lost to collisions
+    pub lost: Vec<usize>,
+    /// The sides that have committed to a square for this pick
+    pub committed: Vec<Player>,
+    /// The sides that have revealed their square, which stays hidden until both have
+    pub revealed: Vec<Player>,
+    /// The squares X and O chose on the last pick
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_picks: Option<[usize; 2]>,
+}
+
+impl GameUpdate {
+    /// The result, counting resignations
+    pub fn result(&self) -> GameStatus {
+        match self.resigned {
+            Some(player) => GameStatus::Won(player.opponent()),
+            None => self.status,
+        }
+    }
+
+    /// Whether `player` has to move, or in a quantum game commit or reveal
+    pub fn is_turn_of(&self, player: Player) -> bool {
+        if self.result() != GameStatus::InProgress || self.waiting {
+            return false;
+        }
+        match &self.quantum {
+            Some(pick) => {
+                !pick.committed.contains(&player)
+                    || (pick.committed.len() == 2 && !pick.revealed.contains(&player))
+            }
+            None => self.next_player == player,
+        }
+    }
+}
+
+enum Rules {
+    Standard(GameState),
+    Quantum(QuantumTable),
+}
+
+/// A quantum game and the pick being made, X first
+#[derive(Default)]
+struct QuantumTable {
+    game: QuantumGame,
+    commitments: [Option<String>; 2],
+    reveals: [Option<usize>; 2],
+    last_picks: Option<[usize; 2]>,
+}
+
+impl QuantumTable {
+    fn update(&self) -> QuantumUpdate {
+        let sides = |taken: &[bool; 2]| {
+            [Player::X, Player::O]
+                .into_iter()
+                .zip(taken)
+                .filter_map(|(player, &taken)| taken.then_some(player))
+                .collect()
+        };
+        QuantumUpdate {
+            lost: (0..9).filter(|&square| self.game.is_lost(square)).collect(),
+            committed: sides(&self.commitments.each_ref().map(Option::is_some)),
+            revealed: sides(&self.reveals.map(|reveal| reveal.is_some())),
+            last_picks: self.last_picks,
+        }
+    }
+}
+
+struct Table {
+    rules: Rules,
+    /// The token for each seat, X first, once it's taken
+    tokens: [Option<String>; 2],
+    last_move: Option<usize>,
+    resigned: Option<Player>,
+    /// Every connection that has joined or is watching, to tell about each change
+    connections: Vec<usize>,
+    /// The connections that took or came back to a seat and are still open
+    players: Vec<usize>,
+    /// When a seat was last taken or used
+    touched: Instant,
+}
+
+impl Table {
+    fn update(&self, code: &str) -> GameUpdate {
+        let (board, next_player, quantum) = match &self.rules {
+            Rules::Standard(state) => (*state.board(), state.next_player(), None),
+            Rules::Quantum(table) => (*table.game.board(), Player::X, Some(table.update())),
+        };
+        GameUpdate {
+            code: code.to_string(),
+            board,
+            next_player,
+            status: self.status(),
+            last_move: self.last_move,
+            resigned: self.resigned,
+            waiting: self.is_waiting(),
+            quantum,
+        }
+    }
+
+    fn status(&self) -> GameStatus {
+        match &self.rules {
+            Rules::Standard(state) => state.status(),
+            Rules::Quantum(table) => table.game.status(),
+        }
+    }
+
+    fn is_waiting(&self) -> bool {
+        self.tokens.iter().any(Option::is_none)
+    }
+
+    fn seat(&self, token: &str) -> Option<Player> {
+        [Player::X, Player::O]
+            .into_iter()
+            .zip(&self.tokens)
+            .find_map(|(player, seat)| (seat.as_deref() == Some(token)).then_some(player))
+    }
+
+    fn is_over(&self) -> bool {
+        self.resigned.is_some() || self.status() != GameStatus::InProgress
+    }
+
+    /// Whether the game is worth keeping at `now`: a finished game while someone is looking at
+    /// it, and any other while one of its players is connected or could still come back
+    fn is_kept(&self, now: Instant) -> bool {
+        if self.is_over() {
+            !self.connections.is_empty()
+        } else {
+            !self.players.is_empty() || now.duration_since(self.touched) < SEATS_KEPT_FOR
+        }
+    }
+
+    /// Tell `connection` about each change as one of the players
+    fn sit(&mut self, connection: usize) {
+        for list in [&mut self.connections, &mut self.players] {
+            if !list.contains(&connection) {
+                list.push(connection);
+            }
+        }
+        self.touched = Instant::now();
+    }
+}
+
+/// How many fouls a connection gets before the server stops listening to it
+pub const MAX_FOULS: u32 = 5;
+
+/// Why a request was turned down
+enum Refusal {
+    /// Something a fair client can run into, like a game that's gone
+    Declined(String),
+    /// Something only a broken or cheating client sends, like a move out of turn or a made-up
+    /// token
+    Foul(String),
+    /// A foul that loses the game, with the events telling everyone
+    Forfeit(String, Vec<(usize, Event)>),
+}
+
+impl From<String> for Refusal {
+    fn from(message: String) -> Self {
+        Refusal::Declined(message)
+    }
+}
+
+/// Every game on the server. Connections are numbered by whoever runs the server, and each
+/// request gives back the events to send and which connection each is for
+///
+/// Clients aren't trusted: every move is checked against the server's own position, and each
+/// move out of turn, on a taken square or with someone else's seat is logged as a foul against
+/// the connection
+///
+/// ```
+/// use simple_tic_tac_toe::server::{Event, Lobby, Request};
+///
+/// let mut lobby = Lobby::new();
+/// let joined = |events: Vec<(usize, Event)>| match &events[0].1 {
+///     Event::Joined { code, token, .. } => (code.clone(), token.clone()),
+///     _ => panic!("expected a seat"),
+/// };
+/// let (code, x) = joined(lobby.handle(0, Request::Create { player: None, quantum: false }));
+/// let (_, o) = joined(lobby.handle(1, Request::Join { code: code.clone() }));
+/// let play = |token: &str, square| Request::Move { code: code.clone(), token: token.into(), square };
+///
+/// // O tries to move first, then X plays and O tries to take the same square
+/// assert!(matches!(lobby.handle(1, play(&o, 0))[..], [(1, Event::Error { .. })]));
+/// assert_eq!(lobby.handle(0, play(&x, 4)).len(), 2);
+/// assert!(matches!(lobby.handle(1, play(&o, 4))[..], [(1, Event::Error { .. })]));
+/// // A guessed token, and a square off the board
+/// assert!(matches!(lobby.handle(1, play("0000", 0))[..], [(1, Event::Error { .. })]));
+/// assert!(matches!(lobby.handle(1, play(&o, 9))[..], [(1, Event::Error { .. })]));
+/// assert_eq!(lobby.fouls(1), 4);
+/// assert_eq!(lobby.fouls(0), 0);
+///
+/// // Nothing got through, so O's fair move is still there to play
+/// assert_eq!(lobby.handle(1, play(&o, 0)).len(), 2);
+/// ```
+#[derive(Default)]
+pub struct Lobby {
+    tables: HashMap<String, Table>,
+    /// What each connection that said hello agreed to
+    agreed: HashMap<usize, Welcome>,
+    /// The fouls on each connection that has any
+    fouls: HashMap<usize, u32>,
+    limits: ServerConfig,
+}
+
+impl Lobby {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A lobby keeping to the game limits in `config`
+    pub fn with_config(config: ServerConfig) -> Self {
+        Self {
+            limits: config,
+            ..Self::default()
+        }
+    }
+
+    /// Carry out a request from `connection`
+    pub fn handle(&mut self, connection: usize, mut request: Request) -> Vec<(usize, Event)> {
+        let code = match &mut request {
+            Request::Hello(_) | Request::Create { .. } => None,
+            Request::Join { code }
+            | Request::Rejoin { code, .. }
+            | Request::Move { code, .. }
+            | Request::Commit { code, .. }
+            | Request::Reveal { code, .. }
+            | Request::Resign { code, .. }
+            | Request::Watch { code } => {
+                // Codes are easier to pass on by voice without caring about case
+                *code = code.to_ascii_uppercase();
+                Some(code.clone())
+            }
+        };
+        let result = match request {
+            Request::Hello(hello) => self.hello(connection, &hello).map_err(Refusal::from),
+            Request::Create { player, quantum } => self
+                .create(connection, player.unwrap_or(Player::X), quantum)
+                .map_err(Refusal::from),
+            Request::Join { code } => self.join(connection, &code).map_err(Refusal::from),
+            Request::Rejoin { code, token } => self.rejoin(connection, &code, &token),
+            Request::Move {
+                code,
+                token,
+                square,
+            } => self.play_move(&code, &token, square),
+            Request::Commit {
+                code,
+                token,
+                commitment,
+            } => self.commit(&code, &token, commitment),
+            Request::Reveal {
+                code,
+                token,
+                square,
+                nonce,
+            } => self.reveal(connection, &code, &token, square, &nonce),
+            Request::Resign { code, token } => self.resign(&code, &token),
+            Request::Watch { code } => self.watch(connection, &code).map_err(Refusal::from),
+        };
+        let (message, events, foul) = match result {
+            Ok(events) => return events,
+            Err(Refusal::Declined(message)) => (message, Vec::new(), false),
+            Err(Refusal::Foul(message)) => (message, Vec::new(), true),
+            Err(Refusal::Forfeit(message, events)) => (message, events, true),
+        };
+        if foul {
+            let fouls = self.fouls.entry(connection).or_default();
+            *fouls += 1;
+            tracing::warn!(connection, game = code, fouls = *fouls, message, "foul");
+        }
+        let mut answer = vec![(connection, Event::Error { code, message })];
+        answer.extend(events);
+        answer
+    }
+
+    /// How many requests from `connection` were ones no fair client would send
+    pub fn fouls(&self, connection: usize) -> u32 {
+        self.fouls.get(&connection).copied().unwrap_or(0)
+    }
+
+    /// Forget a connection that has closed
+    pub fn disconnect(&mut self, connection: usize) {
+        self.agreed.remove(&connection);
+        self.fouls.remove(&connection);
+        for table in self.tables.values_mut() {
+            table.connections.retain(|&c| c != connection);
+            table.players.retain(|&c| c != connection);
+        }
+        self.sweep(Instant::now());
+    }
+
+    /// Drop the games not worth keeping at `now`
+    fn sweep(&mut self, now: Instant) {
+        self.tables.retain(|_, table| table.is_kept(now));
+    }
+
+    /// How many games `connection` holds a seat in
+    fn games_of(&self, connection: usize) -> usize {
+        self.tables
+            .values()
+            .filter(|table| table.players.contains(&connection))
+            .count()
+    }
+
+    /// Turn the request down if taking another seat would put `connection` over the limit
+    fn check_games(&self, connection: usize) -> Result<(), String> {
+        let most = self.limits.games_per_connection;
+        if self.games_of(connection) >= most {
+            return Err(format!(
+                "you're already playing {most} games, the most at once"
+            ));
+        }
+        Ok(())
+    }
+
+    fn hello(&mut self, connection: usize, hello: &Hello) -> Result<Vec<(usize, Event)>, String> {
+        let welcome = SUPPORT.negotiate(hello).map_err(|e| e.to_string())?;
+        self.agreed.insert(connection, welcome.clone());
+        Ok(vec![(connection, Event::Welcome(welcome))])
+    }
+
+    /// Turn the request down if `connection` said hello without `capability`
+    fn require(&self, connection: usize, capability: Capability) -> Result<(), String> {
+        match self.agreed.get(&connection) {
+            Some(agreed) if !agreed.has(capability) => {
+                Err(ProtocolError::NotAgreed(capability).to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn create(
+        &mut self,
+        connection: usize,
+        player: Player,
+        quantum: bool,
+    ) -> Result<Vec<(usize, Event)>, String> {
+        self.check_games(connection)?;
+        self.sweep(Instant::now());
+        if self.tables.len() >= self.limits.max_games {
+            return Err("the server is full, try again later".to_string());
+        }
+        let mut rng = rand::thread_rng();
+        let code = (0..CODE_TRIES)
+            .map(|_| -> String {
+                (0..4)
+                    .map(|_| CODE_LETTERS[rng.gen_range(0..CODE_LETTERS.len())] as char)
+                    .collect()
+            })
+            .find(|code| !self.tables.contains_key(code))
+            .ok_or("the server has no game codes free, try again later")?;
+        let token = new_token();
+        let mut tokens = [None, None];
+        tokens[seat_index(player)] = Some(token.clone());
+        let rules = if quantum {
+            Rules::Quantum(QuantumTable::default())
+        } else {
+            Rules::Standard(GameState::new(Player::O))
+        };
+        let table = Table {
+            rules,
+            tokens,
+            last_move: None,
+            resigned: None,
+            connections: vec![connection],
+            players: vec![connection],
+            touched: Instant::now(),
+        };
+        let update = table.update(&code);
+        self.tables.insert(code.clone(), table);
+        Ok(vec![
+            (
+                connection,
+                Event::Joined {
+                    code,
+                    token,
+                    player,
+                },
+            ),
+            (connection, Event::State(update)),
+        ])
+    }
+
+    fn join(&mut self, connection: usize, code: &str) -> Result<Vec<(usize, Event)>, String> {
+        self.check_games(connection)?;
+        let table = self.table(code)?;
+        let Some(index) = table.tokens.iter().position(Option::is_none) else {
+            return Err(format!("game {code} already has two players"));
+        };
+        let token = new_token();
+        table.tokens[index] = Some(token.clone());
+        let player = [Player::X, Player::O][index];
+        table.sit(connection);
+        let mut events = vec![(
+            connection,
+            Event::Joined {
+                code: code.to_string(),
+                token,
+                player,
+            },
+        )];
+        events.extend(broadcast(table, code));
+        Ok(events)
+    }
+
+    fn rejoin(
+        &mut self,
+        connection: usize,
+        code: &str,
+        token: &str,
+    ) -> Result<Vec<(usize, Event)>, Refusal> {
+        let table = self.table(code)?;
+        let player = seat(table, code, token)?;
+        table.sit(connection);
+        Ok(vec![
+            (
+                connection,
+                Event::Joined {
+                    code: code.to_string(),
+                    token: token.to_string(),
+                    player,
+                },
+            ),
+            (connection, Event::State(table.update(code))),
+        ])
+    }
+
+    fn watch(&mut self, connection: usize, code: &str) -> Result<Vec<(usize, Event)>, String> {
+        self.require(connection, Capability::Spectating)?;
+        let table = self.table(code)?;
+        if !table.connections.contains(&connection) {
+            table.connections.push(connection);
+        }
+        Ok(vec![(connection, Event::State(table.update(code)))])
+    }
+
+    fn play_move(
+        &mut self,
+        code: &str,
+        token: &str,
+        square: usize,
+    ) -> Result<Vec<(usize, Event)>, Refusal> {
+        let table = self.table(code)?;
+        let player = seat(table, code, token)?;
+        if table.is_over() {
+            // The other side may have just resigned, so this can happen to anyone
+            return Err(format!("game {code} is over").into());
+        }
+        if table.is_waiting() {
+            return Err(waiting(code));
+        }
+        let Rules::Standard(state) = &mut table.rules else {
+            return Err(Refusal::Foul(format!(
+                "game {code} is a quantum game, so squares are committed to and revealed"
+            )));
+        };
+        if state.next_player() != player {
+            return Err(Refusal::Foul(format!(
+                "it's {}'s move in game {code}",
+                player.opponent()
+            )));
+        }
+        state
+            .try_move(square)
+            .map_err(|e| Refusal::Foul(e.to_string()))?;
+        table.last_move = Some(square);
+        Ok(broadcast(table, code))
+    }
+
+    fn commit(
+        &mut self,
+        code: &str,
+        token: &str,
+        commitment: String,
+    ) -> Result<Vec<(usize, Event)>, Refusal> {
+        let table = self.table(code)?;
+        let player = seat(table, code, token)?;
+        let pick = quantum_pick(table, code)?;
+        let side = &mut pick.commitments[seat_index(player)];
+        if side.is_some() {
+            return Err(Refusal::Foul(format!(
+                "you've already committed to a square in game {code}"
+            )));
+        }
+        if commitment.len() != 64 || !commitment.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Refusal::Foul(
+                "a commitment is a SHA-256 hash in hex".to_string(),
+            ));
+        }
+        *side = Some(commitment.to_ascii_lowercase());
+        Ok(broadcast(table, code))
+    }
+
+    fn reveal(
+        &mut self,
+        connection: usize,
+        code: &str,
+        token: &str,
+        square: usize,
+        nonce: &str,
+    ) -> Result<Vec<(usize, Event)>, Refusal> {
+        let table = self.table(code)?;
+        let player = seat(table, code, token)?;
+        let pick = quantum_pick(table, code)?;
+        let index = seat_index(player);
+        let [Some(x), Some(o)] = &pick.commitments else {
+            return Err(Refusal::Foul(format!(
+                "both sides commit before revealing in game {code}"
+            )));
+        };
+        if pick.reveals[index].is_some() {
+            return Err(Refusal::Foul(format!(
+                "you've already revealed your square in game {code}"
+            )));
+        }
+        let committed = if player == Player::X { x } else { o };
+        let cheated = if quantum::commitment(square, nonce) != *committed {
+            Some("the square and nonce don't match the commitment".to_string())
+        } else if !pick.game.open_squares().contains(&square) {
+            Some(format!("square {square} can't be chosen"))
+        } else {
+            None
+        };
+        if let Some(message) = cheated {
+            tracing::warn!(connection, game = code, "forfeited on a bad reveal");
+            table.resigned = Some(player);
+            return Err(Refusal::Forfeit(
+                format!("{message}, so game {code} is lost"),
+                broadcast(table, code),
+            ));
+        }
+        pick.reveals[index] = Some(square);
+        if let [Some(x), Some(o)] = pick.reveals {
+            pick.game
+                .pick(x, o)
+                .expect("both squares were checked to be open");
+            pick.last_picks = Some([x, o]);
+            pick.commitments = [None, None];
+            pick.reveals = [None, None];
+        }
+        Ok(broadcast(table, code))
+    }
+
+    fn resign(&mut self, code: &str, token: &str) -> Result<Vec<(usize, Event)>, Refusal> {
+        let table = self.table(code)?;
+        let player = seat(table, code, token)?;
+        if table.is_over() {
+            return Err(format!("game {code} is over").into());
+        }
+        table.resigned = Some(player);
+        Ok(broadcast(table, code))
+    }
+
+    fn table(&mut self, code: &str) -> Result<&mut Table, String> {
+        self.tables
+            .get_mut(code)
+            .ok_or_else(|| format!("there is no game {code}"))
+    }
+}
+
+/// The pick being made in a quantum game that's being played
+fn quantum_pick<'a>(table: &'a mut Table, code: &str) -> Result<&'a mut QuantumTable, Refusal> {
+    if table.is_over() {
+        return Err(format!("game {code} is over").into());
+    }
+    if table.is_waiting() {
+        return Err(waiting(code));
+    }
+    match &mut table.rules {
+        Rules::Quantum(pick) => Ok(pick),
+        Rules::Standard(_) => Err(Refusal::Foul(format!(
+            "game {code} isn't a quantum game, so moves are played as they are"
+        ))),
+    }
+}
+
+fn waiting(code: &str) -> Refusal {
+    Refusal::Foul(format!("game {code} is still waiting for a second player"))
+}
+
+/// The seat `token` is for, which it's a foul to get wrong since tokens are only handed out
+fn seat(table: &mut Table, code: &str, token: &str) -> Result<Player, Refusal> {
+    let player = table
+        .seat(token)
+        .ok_or_else(|| Refusal::Foul(format!("that token isn't for a seat in game {code}")))?;
+    table.touched = Instant::now();
+    Ok(player)
+}
+
+/// The game's state for everyone in it
+fn broadcast(table: &Table, code: &str) -> Vec<(usize, Event)> {
+    let update = table.update(code);
+    table
+        .connections
+        .iter()
+        .map(|&connection| (connection, Event::State(update.clone())))
+        .collect()
+}
+
+fn seat_index(player: Player) -> usize {
+    match player {
+        Player::X => 0,
+        Player::O => 1,
+    }
+}
+
+fn new_token() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// The lobby and a way to reach each open connection
+#[derive(Default)]
+struct Shared {
+    lobby: Lobby,
+    senders: HashMap<usize, Sender<Event>>,
+    next_connection: usize,
+    /// How many connections are open from each address
+    addresses: HashMap<IpAddr, usize>,
+}
+
+/// Spaces out the requests read from a connection once it has used up a burst of them
+struct Throttle {
+    per_second: f64,
+    /// How many requests can be read right away, up to a second's worth
+    allowance: f64,
+    last: Instant,
+}
+
+impl Throttle {
+    fn new(per_second: u32) -> Self {
+        Self {
+            per_second: per_second.into(),
+            allowance: per_second.into(),
+            last: Instant::now(),
+        }
+    }
+
+    /// Wait until another request can be read
+    fn wait(&mut self) {
+        let now = Instant::now();
+        self.allowance = (self.allowance
+            + now.duration_since(self.last).as_secs_f64() * self.per_second)
+            .min(self.per_second);
+        self.last = now;
+        if self.allowance < 1.0 {
+            std::thread::sleep(Duration::from_secs_f64(
+                (1.0 - self.allowance) / self.per_second,
+            ));
+            self.allowance = 1.0;
+            self.last = Instant::now();
+        }
+        self.allowance -= 1.0;
+    }
+}
+
+/// Accept connections on `address`, e.g. `0.0.0.0:4040`, and serve games to them within the
+/// limits in `config` until the listener fails
+pub fn serve(address: &str, config: &ServerConfig) -> crate::Result<()> {
+    let listener = TcpListener::bind(address).map_err(Error::network("listen on", address))?;
+    tracing::info!(address, "serving games");
+    let shared = Arc::new(Mutex::new(Shared {
+        lobby: Lobby::with_config(config.clone()),
+        ..Shared::default()
+    }));
+    for stream in listener.incoming() {
+        let mut stream = stream.map_err(Error::network("accept connections on", address))?;
+        let Ok(peer) = stream.peer_addr() else {
+            continue;
+        };
+        {
+            let mut shared = shared.lock().expect("no thread panics holding the lock");
+            let open = shared.addresses.entry(peer.ip()).or_default();
+            if *open >= config.connections_per_ip {
+                drop(shared);
+                tracing::warn!(peer = %peer, "too many connections from one address");
+                let refusal = Event::Error {
+                    code: None,
+                    message: "too many connections from your address".to_string(),
+                };
+                let line = serde_json::to_string(&refusal).expect("events serialize") + "\n";
+                let _ = stream.write_all(line.as_bytes());
+                continue;
+            }
+            *open += 1;
+        }
+        let shared = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            tracing::info!(peer = %peer, "connected");
+            if let Err(e) = serve_connection(stream, &shared) {
+                tracing::debug!(peer = %peer, error = %e, "connection failed");
+            }
+            tracing::info!(peer = %peer, "disconnected");
+            let mut shared = shared.lock().expect("no thread panics holding the lock");
+            if let Some(open) = shared.addresses.get_mut(&peer.ip()) {
+                *open -= 1;
+                if *open == 0 {
+                    shared.addresses.remove(&peer.ip());
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+fn serve_connection(stream: TcpStream, shared: &Mutex<Shared>) -> std::io::Result<()> {
+    let (sender, receiver) = mpsc::channel::<Event>();
+    let (connection, mut throttle) = {
+        let mut shared = shared.lock().expect("no thread panics holding the lock");
+        let connection = shared.next_connection;
+        shared.next_connection += 1;
+        shared.senders.insert(connection, sender);
+        let throttle = Throttle::new(shared.lobby.limits.requests_per_second);
+        (connection, throttle)
+    };
+    let mut writer = stream.try_clone()?;
+    let writing = std::thread::spawn(move || {
+        for event in receiver {
+            let line = serde_json::to_string(&event).expect("events serialize") + "\n";
+            if writer.write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let result = BufReader::new(stream).lines().try_for_each(|line| {
+        let line = line?;
+        throttle.wait();
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let mut shared = shared.lock().expect("no thread panics holding the lock");
+        let events = match serde_json::from_str(&line) {
+            Ok(request) => shared.lobby.handle(connection, request),
+            Err(e) => vec![(
+                connection,
+                Event::Error {
+                    code: None,
+                    message: format!("invalid request: {e}"),
+                },
+            )],
+        };
+        for (to, event) in events {
+            if let Some(sender) = shared.senders.get(&to) {
+                // A connection that's gone hears nothing, which is what it'd get anyway
+                let _ = sender.send(event);
+            }
+        }
+        if shared.lobby.fouls(connection) >= MAX_FOULS {
+            if let Some(sender) = shared.senders.get(&connection) {
+                let _ = sender.send(Event::Error {
+                    code: None,
+                    message: "too many invalid requests, closing the connection".to_string(),
+                });
+            }
+            return Err(std::io::Error::other("too many fouls"));
+        }
+        Ok(())
+    });
+
+    let mut shared = shared.lock().expect("no thread panics holding the lock");
+    shared.senders.remove(&connection);
+    shared.lobby.disconnect(connection);
+    drop(shared);
+    let _ = writing.join();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(lobby: &mut Lobby, connection: usize) -> (String, String) {
+        seat(lobby.handle(
+            connection,
+            Request::Create {
+                player: None,
+                quantum: false,
+            },
+        ))
+    }
+
+    fn seat(events: Vec<(usize, Event)>) -> (String, String) {
+        match &events[0].1 {
+            Event::Joined { code, token, .. } => (code.clone(), token.clone()),
+            other => panic!("expected a seat, got {other:?}"),
+        }
+    }
+
+    /// A quantum game with X on connection 0 and O on connection 1, and the code and tokens
+    fn quantum_game(lobby: &mut Lobby) -> (String, String, String) {
+        let (code, x) = seat(lobby.handle(
+            0,
+            Request::Create {
+                player: None,
+                quantum: true,
+            },
+        ));
+        let (_, o) = seat(lobby.handle(1, Request::Join { code: code.clone() }));
+        (code, x, o)
+    }
+
+    fn commit(code: &str, token: &str, square: usize) -> Request {
+        Request::Commit {
+            code: code.to_string(),
+            token: token.to_string(),
+            commitment: quantum::commitment(square, "salt"),
+        }
+    }
+
+    fn reveal(code: &str, token: &str, square: usize, nonce: &str) -> Request {
+        Request::Reveal {
+            code: code.to_string(),
+            token: token.to_string(),
+            square,
+            nonce: nonce.to_string(),
+        }
+    }
+
+    /// The last state sent in `events`
+    fn last_state(events: &[(usize, Event)]) -> &GameUpdate {
+        events
+            .iter()
+            .rev()
+            .find_map(|(_, event)| match event {
+                Event::State(update) => Some(update),
+                _ => None,
+            })
+            .expect("a state was sent")
+    }
+
+    fn is_error(events: &[(usize, Event)], connection: usize) -> bool {
+        matches!(events[..], [(c, Event::Error { .. })] if c == connection)
+    }
+
+    #[test]
+    fn fouls_add_up_to_the_limit_and_fair_mistakes_dont_count() {
+        let mut lobby = Lobby::new();
+        let (code, _) = create(&mut lobby, 0);
+        // A game that isn't there could have just been dropped, so it's no foul
+        assert!(is_error(
+            &lobby.handle(
+                1,
+                Request::Join {
+                    code: "ZZZZ".into()
+                }
+            ),
+            1
+        ));
+        assert_eq!(lobby.fouls(1), 0);
+        for fouls in 1..=MAX_FOULS {
+            let guess = Request::Move {
+                code: code.clone(),
+                token: format!("{fouls:016x}"),
+                square: 0,
+            };
+            assert!(is_error(&lobby.handle(1, guess), 1));
+            assert_eq!(lobby.fouls(1), fouls);
+        }
+        assert_eq!(lobby.fouls(0), 0);
+        lobby.disconnect(1);
+        assert_eq!(lobby.fouls(1), 0);
+    }
+
+    #[test]
+    fn a_reveal_that_doesnt_match_the_commitment_loses() {
+        let mut lobby = Lobby::new();
+        let (code, x, o) = quantum_game(&mut lobby);
+        lobby.handle(0, commit(&code, &x, 4));
+        lobby.handle(1, commit(&code, &o, 0));
+        let events = lobby.handle(0, reveal(&code, &x, 4, "pepper"));
+        assert!(matches!(events[0], (0, Event::Error { .. })));
+        assert_eq!(last_state(&events).resigned, Some(Player::X));
+        assert_eq!(last_state(&events).result(), GameStatus::Won(Player::O));
+        assert_eq!(lobby.fouls(0), 1);
+        // The game is over, so even a fair reveal from O gets nowhere
+        assert!(is_error(&lobby.handle(1, reveal(&code, &o, 0, "salt")), 1));
+    }
+
+    #[test]
+    fn revealing_a_square_that_cant_be_chosen_loses() {
+        let mut lobby = Lobby::new();
+        let (code, x, o) = quantum_game(&mut lobby);
+        for (token, connection, square) in [(&x, 0, 0), (&o, 1, 1)] {
+            lobby.handle(connection, commit(&code, token, square));
+        }
+        for (token, connection, square) in [(&x, 0, 0), (&o, 1, 1)] {
+            lobby.handle(connection, reveal(&code, token, square, "salt"));
+        }
+        // O commits honestly to the square X already has
+        lobby.handle(0, commit(&code, &x, 4));
+        lobby.handle(1, commit(&code, &o, 0));
+        let events = lobby.handle(1, reveal(&code, &o, 0, "salt"));
+        assert_eq!(last_state(&events).resigned, Some(Player::O));
+        assert_eq!(lobby.fouls(1), 1);
+    }
+
+    #[test]
+    fn a_second_commit_is_a_foul_and_keeps_the_first() {
+        let mut lobby = Lobby::new();
+        let (code, x, o) = quantum_game(&mut lobby);
+        lobby.handle(0, commit(&code, &x, 4));
+        assert!(is_error(&lobby.handle(0, commit(&code, &x, 8)), 0));
+        assert_eq!(lobby.fouls(0), 1);
+        lobby.handle(1, commit(&code, &o, 0));
+        lobby.handle(0, reveal(&code, &x, 4, "salt"));
+        let events = lobby.handle(1, reveal(&code, &o, 0, "salt"));
+        let update = last_state(&events);
+        assert_eq!(update.board[4], Some(Player::X));
+        assert_eq!(update.resigned, None);
+    }
+
+    #[test]
+    fn moves_are_a_foul_in_quantum_games() {
+        let mut lobby = Lobby::new();
+        let (code, x, _) = quantum_game(&mut lobby);
+        let play = Request::Move {
+            code: code.clone(),
+            token: x,
+            square: 4,
+        };
+        assert!(is_error(&lobby.handle(0, play), 0));
+        assert_eq!(lobby.fouls(0), 1);
+        let watch = lobby.handle(2, Request::Watch { code });
+        assert_eq!(last_state(&watch).board, [None; 9]);
+    }
+
+    #[test]
+    fn the_connection_is_closed_after_too_many_fouls() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let shared = Mutex::new(Shared::default());
+            let (stream, _) = listener.accept().unwrap();
+            serve_connection(stream, &shared)
+        });
+        let mut client = TcpStream::connect(address).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut lines = BufReader::new(client.try_clone().unwrap()).lines();
+        let mut send = |request: &Request| {
+            let line = serde_json::to_string(request).unwrap() + "\n";
+            client.write_all(line.as_bytes())
+        };
+        send(&Request::Create {
+            player: None,
+            quantum: false,
+        })
+        .unwrap();
+        let joined: Event = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        let Event::Joined { code, .. } = joined else {
+            panic!("expected a seat, got {joined:?}");
+        };
+        for _ in 0..MAX_FOULS {
+            let guess = Request::Move {
+                code: code.clone(),
+                token: "0000".into(),
+                square: 0,
+            };
+            send(&guess).unwrap();
+        }
+        let lines: Vec<String> = lines
+            .map(|line| line.expect("the server closes the connection instead of hanging"))
+            .collect();
+        assert!(server.join().unwrap().is_err());
+        assert!(lines.last().unwrap().contains("too many invalid requests"));
+    }
+
+    #[test]
+    fn joining_your_own_game_sends_each_state_once() {
+        let mut lobby = Lobby::new();
+        let (code, _) = create(&mut lobby, 0);
+        let events = lobby.handle(0, Request::Join { code });
+        let states = events
+            .iter()
+            .filter(|(_, event)| matches!(event, Event::State(_)))
+            .count();
+        assert_eq!(states, 1);
+    }
+
+    #[test]
+    fn a_connection_can_only_hold_so_many_games() {
+        let mut lobby = Lobby::new();
+        for _ in 0..ServerConfig::default().games_per_connection {
+            create(&mut lobby, 0);
+        }
+        let create = Request::Create {
+            player: None,
+            quantum: false,
+        };
+        assert!(matches!(
+            lobby.handle(0, create.clone())[..],
+            [(0, Event::Error { .. })]
+        ));
+        assert_eq!(lobby.fouls(0), 0);
+        // Anyone else can still start one
+        assert!(matches!(lobby.handle(1, create)[0].1, Event::Joined { .. }));
+    }
+
+    #[test]
+    fn a_full_server_turns_new_games_away() {
+        let mut lobby = Lobby::with_config(ServerConfig {
+            max_games: 2,
+            ..ServerConfig::default()
+        });
+        create(&mut lobby, 0);
+        create(&mut lobby, 1);
+        let create = Request::Create {
+            player: None,
+            quantum: false,
+        };
+        assert!(matches!(
+            lobby.handle(2, create)[..],
+            [(2, Event::Error { .. })]
+        ));
+    }
+
+    #[test]
+    fn requests_past_the_burst_are_spaced_out() {
+        let mut throttle = Throttle::new(100);
+        let start = Instant::now();
+        for _ in 0..110 {
+            throttle.wait();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn games_are_dropped_once_nobody_holds_a_seat() {
+        let mut lobby = Lobby::new();
+        let (waiting, _) = create(&mut lobby, 0);
+        let (resigned, token) = create(&mut lobby, 0);
+        lobby.handle(
+            1,
+            Request::Join {
+                code: resigned.clone(),
+            },
+        );
+        lobby.handle(
+            0,
+            Request::Resign {
+                code: resigned.clone(),
+                token,
+            },
+        );
+        lobby.disconnect(0);
+        lobby.disconnect(1);
+
+        // The open game waits for its opponent, but the finished one has nobody left to tell
+        assert!(lobby.tables.contains_key(&waiting));
+        assert!(!lobby.tables.contains_key(&resigned));
+        lobby.sweep(Instant::now() + SEATS_KEPT_FOR);
+        assert!(lobby.tables.is_empty());
+    }
+
+    #[test]
+    fn connected_players_keep_their_game() {
+        let mut lobby = Lobby::new();
+        let (code, _) = create(&mut lobby, 0);
+        lobby.sweep(Instant::now() + SEATS_KEPT_FOR);
+        assert!(lobby.tables.contains_key(&code));
+    }
+}
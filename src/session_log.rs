@@ -0,0 +1,78 @@
+//! Everything needed to re-run an interactive session exactly, so a reported bug in the computer's
+//! play can be reproduced from a file the user sends in
+
+use crate::ai::Decision;
+use crate::config::Config;
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// One answer the human gave to a prompt
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Answer {
+    /// An option picked from a list, by its position and with its label for reading the file
+    Select {
+        prompt: String,
+        index: usize,
+        label: String,
+    },
+    /// A yes or no question
+    Confirm { prompt: String, value: bool },
+}
+
+impl Answer {
+    /// The question that was asked
+    pub fn prompt(&self) -> &str {
+        match self {
+            Answer::Select { prompt, .. } | Answer::Confirm { prompt, .. } => prompt,
+        }
+    }
+}
+
+/// A recorded session, in the order things happened
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionLog {
+    /// The version of the program that made the recording
+    pub version: String,
+    /// Seed for every random choice the program made
+    pub seed: u64,
+    /// The command line, without the option that asked for the recording
+    pub args: Vec<String>,
+    /// The settings that were in effect, so the replay doesn't depend on the local config file
+    pub config: Config,
+    /// The adaptive computer's blunder chance, which otherwise depends on the local stats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blunder_chance: Option<f64>,
+    pub answers: Vec<Answer>,
+    /// Every move the computer decided on
+    pub decisions: Vec<Decision>,
+}
+
+impl SessionLog {
+    pub fn new(seed: u64, args: Vec<String>, config: Config) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            args,
+            config,
+            blunder_chance: None,
+            answers: Vec::new(),
+            decisions: Vec::new(),
+        }
+    }
+
+    /// Read a recording written by `save`
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(Error::io("read", path))?;
+        serde_json::from_str(&json).map_err(|e| {
+            Error::parse_with(format!("{} is not a session recording", path.display()), e)
+        })
+    }
+
+    /// Write the recording to a JSON file
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).map_err(Error::io("write", path))
+    }
+}
@@ -0,0 +1,186 @@
+//! Slippery tic-tac-toe, a party variant where a mark sometimes slides off the square it was
+//! aimed at and lands on a random empty square next to it, diagonals included
+//!
+//! A mark with nowhere to slide lands where it was aimed. With luck in every move there is no
+//! perfect play, only the best odds, so the computer plays by expectiminimax: it averages over
+//! where each mark might land rather than assuming it lands where it was aimed. For the same
+//! reason the game doesn't implement [`Game`](crate::games::Game), whose moves always do what
+//! they say.
+
+use crate::error::{Error, MoveError};
+use crate::game::{GameStatus, Player, LINES};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Expected values this close together count as equally good
+const EPSILON: f64 = 1e-9;
+
+/// A game of slippery tic-tac-toe
+#[derive(Clone, Debug)]
+pub struct SlipperyGame {
+    board: [Option<Player>; 9],
+    pub next_player: Player,
+    slip_chance: f64,
+}
+
+impl SlipperyGame {
+    /// A game where each mark slips with the given chance, between 0 and 1
+    pub fn new(slip_chance: f64) -> crate::Result<Self> {
+        if !(0.0..=1.0).contains(&slip_chance) {
+            return Err(Error::Config(format!(
+                "the slip chance must be between 0 and 1, got {slip_chance}"
+            )));
+        }
+        Ok(Self {
+            board: [None; 9],
+            next_player: Player::X,
+            slip_chance,
+        })
+    }
+
+    pub fn board(&self) -> &[Option<Player>; 9] {
+        &self.board
+    }
+
+    pub fn slip_chance(&self) -> f64 {
+        self.slip_chance
+    }
+
+    pub fn status(&self) -> GameStatus {
+        status(&self.board)
+    }
+
+    pub fn open_squares(&self) -> Vec<usize> {
+        (0..9)
+            .filter(|&square| self.board[square].is_none())
+            .collect()
+    }
+
+    /// Where a mark aimed at `square` can land, with the chance of each
+    pub fn landings(&self, square: usize) -> Vec<(usize, f64)> {
+        landings(&self.board, square, self.slip_chance)
+    }
+
+    /// Aim a mark at a square for the side to move, returning the square it landed on
+    pub fn play(&mut self, square: usize, rng: &mut impl Rng) -> Result<usize, MoveError> {
+        if square >= 9 {
+            return Err(MoveError::OffBoard(square));
+        }
+        if self.status() != GameStatus::InProgress {
+            return Err(MoveError::GameOver(square));
+        }
+        if self.board[square].is_some() {
+            return Err(MoveError::Taken(square));
+        }
+        let landings = self.landings(square);
+        let &(landed, _) = landings
+            .choose_weighted(rng, |&(_, chance)| chance)
+            .expect("a mark always lands somewhere");
+        self.board[landed] = Some(self.next_player);
+        self.next_player = self.next_player.opponent();
+        Ok(landed)
+    }
+}
+
+fn status(board: &[Option<Player>; 9]) -> GameStatus {
+    let winner = LINES.iter().find_map(|&[a, b, c]| {
+        board[a].filter(|&player| board[b] == Some(player) && board[c] == Some(player))
+    });
+    match winner {
+        Some(player) => GameStatus::Won(player),
+        None if board.iter().all(Option::is_some) => GameStatus::Tie,
+        None => GameStatus::InProgress,
+    }
+}
+
+fn landings(board: &[Option<Player>; 9], square: usize, slip_chance: f64) -> Vec<(usize, f64)> {
+    let neighbours: Vec<usize> = (0..9)
+        .filter(|&other| {
+            other != square
+                && board[other].is_none()
+                && (other / 3).abs_diff(square / 3) <= 1
+                && (other % 3).abs_diff(square % 3) <= 1
+        })
+        .collect();
+    if neighbours.is_empty() || slip_chance == 0.0 {
+        return vec![(square, 1.0)];
+    }
+    let slip = slip_chance / neighbours.len() as f64;
+    let mut landings = vec![(square, 1.0 - slip_chance)];
+    landings.extend(neighbours.into_iter().map(|other| (other, slip)));
+    landings
+}
+
+/// The computer player for one game's slip chance, remembering the positions it has worked out
+#[derive(Debug)]
+pub struct Search {
+    slip_chance: f64,
+    /// Expected results for the side to move, by board. The side to move follows from the board
+    /// since every move puts down exactly one mark
+    table: HashMap<[Option<Player>; 9], f64>,
+}
+
+impl Search {
+    pub fn new(game: &SlipperyGame) -> Self {
+        Self {
+            slip_chance: game.slip_chance,
+            table: HashMap::new(),
+        }
+    }
+
+    /// Every open square with the expected result of aiming at it, from 1 for a sure win to -1
+    /// for a sure loss
+    pub fn evaluate(&mut self, game: &SlipperyGame) -> Vec<(usize, f64)> {
+        game.open_squares()
+            .into_iter()
+            .map(|square| (square, self.aim(&game.board, game.next_player, square)))
+            .collect()
+    }
+
+    /// Pick one of the squares with the best expected result
+    pub fn decide(&mut self, game: &SlipperyGame, rng: &mut impl Rng) -> Option<usize> {
+        let scored = self.evaluate(game);
+        let best = scored
+            .iter()
+            .map(|&(_, value)| value)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let best_squares: Vec<usize> = scored
+            .into_iter()
+            .filter(|&(_, value)| value >= best - EPSILON)
+            .map(|(square, _)| square)
+            .collect();
+        best_squares.choose(rng).copied()
+    }
+
+    /// The expected result for `player` of aiming at a square: a chance node over where it lands
+    fn aim(&mut self, board: &[Option<Player>; 9], player: Player, square: usize) -> f64 {
+        landings(board, square, self.slip_chance)
+            .into_iter()
+            .map(|(landed, chance)| {
+                let mut after = *board;
+                after[landed] = Some(player);
+                chance * -self.value(&after, player.opponent())
+            })
+            .sum()
+    }
+
+    /// The expected result for the side to move with the best aim from here on
+    fn value(&mut self, board: &[Option<Player>; 9], to_move: Player) -> f64 {
+        match status(board) {
+            GameStatus::Won(winner) if winner == to_move => return 1.0,
+            GameStatus::Won(_) => return -1.0,
+            GameStatus::Tie => return 0.0,
+            GameStatus::InProgress => {}
+        }
+        if let Some(&value) = self.table.get(board) {
+            return value;
+        }
+        let value = (0..9)
+            .filter(|&square| board[square].is_none())
+            .map(|square| self.aim(board, to_move, square))
+            .fold(f64::NEG_INFINITY, f64::max);
+        self.table.insert(*board, value);
+        value
+    }
+}
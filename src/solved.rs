@@ -0,0 +1,121 @@
+//! Results with best play for every 3x3 position, solved at compile time so the default computer
+//! player only has to look its moves up
+
+use crate::ai::GameResult;
+use crate::game::GameState;
+
+/// Number of possible board codes, 3^9
+const CODES: usize = 19683;
+const POWERS_OF_THREE: [usize; 9] = [1, 3, 9, 27, 81, 243, 729, 2187, 6561];
+
+/// Result for the side to move, indexed by board code as in the tablebase: 0 for positions that
+/// can't come up, then 1, 2 and 3 for a loss, tie or win
+#[allow(long_running_const_eval)]
+static RESULTS: [u8; CODES] = solve();
+
+/// Look up the result with best play for the side to move, or None if the position can't come up
+pub fn result(state: &GameState) -> Option<GameResult> {
+    match RESULTS[state.board_code() as usize] {
+        1 => Some(GameResult::Loss),
+        2 => Some(GameResult::Tie),
+        3 => Some(GameResult::Win),
+        _ => None,
+    }
+}
+
+/// Solve every position, going from full boards back to the empty one so each position's
+/// children are already solved
+const fn solve() -> [u8; CODES] {
+    let mut results = [0; CODES];
+    let mut pieces = 10;
+    while pieces > 0 {
+        pieces -= 1;
+        let mut code = 0;
+        while code < CODES {
+            results[code] = solve_position(&results, code, pieces);
+            code += 1;
+        }
+    }
+    results
+}
+
+/// Solve one position if it has the given number of pieces, using the results of its children
+const fn solve_position(results: &[u8; CODES], code: usize, pieces: usize) -> u8 {
+    let mut board = [0; 9];
+    let (mut x, mut o) = (0, 0);
+    let mut i = 0;
+    while i < 9 {
+        board[i] = (code / POWERS_OF_THREE[i] % 3) as u8;
+        match board[i] {
+            1 => x += 1,
+            2 => o += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    if x + o != pieces || !(x == o || x == o + 1) {
+        return results[code];
+    }
+
+    let x_won = has_line(&board, 1);
+    let o_won = has_line(&board, 2);
+    // X moves whenever the counts are level
+    let to_move = if x == o { 1 } else { 2 };
+    if (x_won && to_move == 1) || (o_won && to_move == 2) {
+        // Only the side that just moved can have a line
+        return 0;
+    }
+    if x_won || o_won {
+        return 1;
+    }
+    if pieces == 9 {
+        return 2;
+    }
+
+    let mut best = 0;
+    let mut square = 0;
+    while square < 9 {
+        if board[square] == 0 {
+            let child = results[code + POWERS_OF_THREE[square] * to_move as usize];
+            // Reverse the child's result, which is for the other side
+            if child != 0 && 4 - child > best {
+                best = 4 - child;
+            }
+        }
+        square += 1;
+    }
+    best
+}
+
+const fn has_line(board: &[u8; 9], piece: u8) -> bool {
+    let mut i = 0;
+    while i < crate::game::LINES.len() {
+        let [a, b, c] = crate::game::LINES[i];
+        if board[a] == piece && board[b] == piece && board[c] == piece {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{minimax, SearchStats};
+
+    #[test]
+    fn solved_results_match_the_search() {
+        let mut stats = SearchStats::default();
+        for state in GameState::reachable_positions() {
+            // The search is from the computer's side and the table is from the side to move's
+            let searched = minimax(&state, &mut stats);
+            let searched = if state.next_player() == state.computer_player {
+                searched
+            } else {
+                searched.reversed()
+            };
+            assert_eq!(result(&state), Some(searched), "{:?}", state.board());
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! SOS, the pencil and paper game where players take turns writing an S or an O on a grid and
+//! score a point for every S-O-S they complete, in any direction
+//!
+//! Both letters belong to both players. Completing an SOS earns another turn, and once the grid
+//! is full the higher score wins. Points still to come don't depend on how the score got where it
+//! is, so the search scores positions by the points each side can still make from there.
+
+use crate::error::{Error, MoveError};
+use crate::game::{GameStatus, Player};
+use crate::games::Game;
+use crate::render::render_grid;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Letter {
+    S,
+    O,
+}
+
+impl Display for Letter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Letter::S => write!(f, "S"),
+            Letter::O => write!(f, "O"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SosMove {
+    pub square: usize,
+    pub letter: Letter,
+}
+
+/// A game of SOS on a square grid
+#[derive(Clone, Debug)]
+pub struct SosGame {
+    size: usize,
+    cells: Vec<Option<Letter>>,
+    /// Points so far, X first
+    scores: [u32; 2],
+    pub next_player: Player,
+}
+
+impl SosGame {
+    pub fn new(size: usize) -> crate::Result<Self> {
+        if size < 3 {
+            return Err(Error::Config(format!(
+                "SOS needs a board of at least 3x3, not {size}x{size}"
+            )));
+        }
+        Ok(Self {
+            size,
+            cells: vec![None; size * size],
+            scores: [0, 0],
+            next_player: Player::X,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn score(&self, player: Player) -> u32 {
+        self.scores[index(player)]
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.cells.iter().any(Option::is_none) {
+            return GameStatus::InProgress;
+        }
+        match self.scores[0].cmp(&self.scores[1]) {
+            std::cmp::Ordering::Greater => GameStatus::Won(Player::X),
+            std::cmp::Ordering::Less => GameStatus::Won(Player::O),
+            std::cmp::Ordering::Equal => GameStatus::Tie,
+        }
+    }
+
+    pub fn open_squares(&self) -> Vec<usize> {
+        (0..self.cells.len())
+            .filter(|&square| self.cells[square].is_none())
+            .collect()
+    }
+
+    pub fn legal_moves(&self) -> Vec<SosMove> {
+        self.open_squares()
+            .into_iter()
+            .flat_map(|square| {
+                [Letter::S, Letter::O]
+                    .into_iter()
+                    .map(move |letter| SosMove { square, letter })
+            })
+            .collect()
+    }
+
+    /// Write a letter for the side to move, returning how many SOSes it completed. The side to
+    /// move only changes when that's none
+    pub fn play(&mut self, m: SosMove) -> Result<u32, MoveError> {
+        if m.square >= self.cells.len() {
+            return Err(MoveError::OffBoard(m.square));
+        }
+        if self.cells[m.square].is_some() {
+            return Err(MoveError::Occupied {
+                row: m.square / self.size,
+                column: m.square % self.size,
+            });
+        }
+        let made = self.place(m);
+        self.scores[index(self.next_player)] += made;
+        if made == 0 {
+            self.next_player = self.next_player.opponent();
+        }
+        Ok(made)
+    }
+
+    /// Write a letter without scoring it or passing the turn, returning the SOSes it completed
+    fn place(&mut self, m: SosMove) -> u32 {
+        self.cells[m.square] = Some(m.letter);
+        let size = self.size as isize;
+        let (row, column) = (
+            (m.square / self.size) as isize,
+            (m.square % self.size) as isize,
+        );
+        let letter_at = |r: isize, c: isize| {
+            ((0..size).contains(&r) && (0..size).contains(&c))
+                .then(|| self.cells[(r * size + c) as usize])
+                .flatten()
+        };
+        let mut made = 0;
+        for (dr, dc) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+            // The new letter can be any of the three in the line
+            for offset in 0..3 {
+                let (r, c) = (row - dr * offset, column - dc * offset);
+                if letter_at(r, c) == Some(Letter::S)
+                    && letter_at(r + dr, c + dc) == Some(Letter::O)
+                    && letter_at(r + 2 * dr, c + 2 * dc) == Some(Letter::S)
+                {
+                    made += 1;
+                }
+            }
+        }
+        made
+    }
+}
+
+impl Display for SosGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let letters: Vec<String> = self
+            .cells
+            .iter()
+            .map(|cell| cell.map_or(".".to_string(), |letter| letter.to_string()))
+            .collect();
+        let cells: Vec<&str> = letters.iter().map(String::as_str).collect();
+        writeln!(f, "{}", render_grid(&cells, self.size, 1))?;
+        write!(f, "X {} - O {}", self.scores[0], self.scores[1])
+    }
+}
+
+fn index(player: Player) -> usize {
+    match player {
+        Player::X => 0,
+        Player::O => 1,
+    }
+}
+
+/// A search that looks a few letters ahead and scores by points, remembering what it has seen
+#[derive(Debug)]
+pub struct Search {
+    /// How many letters ahead to look, at least one. Boards with that many squares or fewer left
+    /// are solved
+    depth: usize,
+    /// Values by board and depth, which are the same whichever side is to move
+    table: HashMap<(Vec<Option<Letter>>, usize), i32>,
+}
+
+impl Search {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth: depth.max(1),
+            table: HashMap::new(),
+        }
+    }
+
+    /// Pick one of the moves that gets the most points more than the opponent
+    pub fn decide(&mut self, game: &SosGame, rng: &mut impl Rng) -> Option<SosMove> {
+        let mut game = game.clone();
+        let scored: Vec<(SosMove, i32)> = game
+            .legal_moves()
+            .into_iter()
+            .map(|m| (m, self.value_of(&mut game, m, self.depth)))
+            .collect();
+        let best = scored.iter().map(|&(_, value)| value).max()?;
+        let best_moves: Vec<SosMove> = scored
+            .into_iter()
+            .filter(|&(_, value)| value == best)
+            .map(|(m, _)| m)
+            .collect();
+        best_moves.choose(rng).copied()
+    }
+
+    /// The points the side to move gets from a move onwards, less the opponent's
+    fn value_of(&mut self, game: &mut SosGame, m: SosMove, depth: usize) -> i32 {
+        let player = game.next_player;
+        let made = game.place(m) as i32;
+        let value = if made > 0 {
+            made + self.negamax(game, depth - 1)
+        } else {
+            game.next_player = player.opponent();
+            let value = -self.negamax(game, depth - 1);
+            game.next_player = player;
+            value
+        };
+        game.cells[m.square] = None;
+        value
+    }
+
+    /// The points the side to move can still make, less the opponent's, looking `depth` letters
+    /// ahead
+    fn negamax(&mut self, game: &mut SosGame, depth: usize) -> i32 {
+        let depth = depth.min(game.cells.iter().filter(|cell| cell.is_none()).count());
+        if depth == 0 {
+            return 0;
+        }
+        let key = (game.cells.clone(), depth);
+        if let Some(&value) = self.table.get(&key) {
+            return value;
+        }
+        let value = game
+            .legal_moves()
+            .into_iter()
+            .map(|m| self.value_of(game, m, depth))
+            .max()
+            .expect("a board that isn't full has a move");
+        self.table.insert(key, value);
+        value
+    }
+}
+
+impl Game for SosGame {
+    type Move = SosMove;
+
+    fn next_player(&self) -> Player {
+        self.next_player
+    }
+
+    fn legal_moves(&self) -> Vec<SosMove> {
+        if self.status() == GameStatus::InProgress {
+            SosGame::legal_moves(self)
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn play(&mut self, m: SosMove) -> Result<(), MoveError> {
+        SosGame::play(self, m).map(|_| ())
+    }
+
+    fn status(&self) -> GameStatus {
+        SosGame::status(self)
+    }
+
+    fn describe(&self, m: SosMove) -> String {
+        format!(
+            "{} at row {}, column {}",
+            m.letter,
+            m.square / self.size + 1,
+            m.square % self.size + 1
+        )
+    }
+
+    /// The points the side to move is ahead by
+    fn evaluate(&self) -> i32 {
+        let player = self.next_player;
+        self.score(player) as i32 - self.score(player.opponent()) as i32
+    }
+}
@@ -0,0 +1,167 @@
+use crate::ai::{perfect_play_winner, Difficulty, GameResult};
+use crate::error::Error;
+use crate::game::{GameState, Player};
+use crate::record::GameRecord;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of recent adaptive games the adaptive difficulty looks at
+pub const ADAPTIVE_WINDOW: usize = 10;
+/// Blunder chance for the first adaptive game
+const INITIAL_BLUNDER_CHANCE: f64 = 0.3;
+/// How far the blunder chance moves for each unit of difference from the target win rate
+const ADAPTIVE_STEP: f64 = 0.2;
+
+/// One finished game as kept in the stats store
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatsEntry {
+    /// When the game finished, in seconds since the Unix epoch
+    pub finished_at: u64,
+    pub human_player: Player,
+    /// The result for the human
+    pub result: GameResult,
+    pub difficulty: Difficulty,
+    /// How often the computer was set to blunder, for adaptive and easy games
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blunder_chance: Option<f64>,
+    pub game: GameRecord,
+}
+
+impl StatsEntry {
+    /// Make an entry for a game that just finished
+    pub fn new(
+        game: GameRecord,
+        human_player: Player,
+        difficulty: Difficulty,
+        blunder_chance: Option<f64>,
+    ) -> crate::Result<Self> {
+        let Some(result) = GameResult::for_player(game.status()?, human_player) else {
+            return Err(Error::GameNotFinished);
+        };
+        let finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Ok(Self {
+            finished_at,
+            human_player,
+            result,
+            difficulty,
+            blunder_chance,
+            game,
+        })
+    }
+}
+
+/// The local record of finished games, stored as one JSON entry per line
+pub struct Stats {
+    path: PathBuf,
+}
+
+impl Stats {
+    /// The stats file location, e.g. `~/.local/share/simple-tic-tac-toe/stats.jsonl` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("simple-tic-tac-toe").join("stats.jsonl"))
+    }
+
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Read every recorded game, oldest first
+    pub fn entries(&self) -> crate::Result<Vec<StatsEntry>> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Error::io("read", &self.path)(e)),
+        };
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    let message = format!("bad entry on line {} of {}", i + 1, self.path.display());
+                    Error::parse_with(message, e)
+                })
+            })
+            .collect()
+    }
+
+    /// Add a game to the end of the store, creating it if needed
+    pub fn append(&self, entry: &StatsEntry) -> crate::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(Error::io("open", &self.path))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?).map_err(Error::io("write", &self.path))
+    }
+}
+
+/// Work out how often the adaptive computer should blunder in the next game
+///
+/// The chance used in the last adaptive game is nudged down when the human has been winning more
+/// than `target_win_rate` of the recent adaptive games, so the computer blunders less, and up
+/// when they have been winning less.
+pub fn adaptive_blunder_chance(entries: &[StatsEntry], target_win_rate: f64) -> f64 {
+    let recent: Vec<&StatsEntry> = entries
+        .iter()
+        .rev()
+        .filter(|e| e.difficulty == Difficulty::Adaptive)
+        .take(ADAPTIVE_WINDOW)
+        .collect();
+    let Some(last) = recent.first() else {
+        return INITIAL_BLUNDER_CHANCE;
+    };
+    let previous = last.blunder_chance.unwrap_or(INITIAL_BLUNDER_CHANCE);
+    let wins = recent
+        .iter()
+        .filter(|e| e.result == GameResult::Win)
+        .count();
+    let win_rate = wins as f64 / recent.len() as f64;
+    (previous + ADAPTIVE_STEP * (target_win_rate - win_rate)).clamp(0.0, 1.0)
+}
+
+/// How often the human played a worse move than the best when there was one, over every game in
+/// the history they didn't swap sides in, or None if they never had the chance
+///
+/// This is the same measure as the computer's blunder chance, so self-play with it stands in for
+/// the human.
+pub fn blunder_rate(entries: &[StatsEntry]) -> crate::Result<Option<f64>> {
+    let result = |state: &GameState, player: Player| match perfect_play_winner(state) {
+        Some(winner) if winner == player => GameResult::Win,
+        Some(_) => GameResult::Loss,
+        None => GameResult::Tie,
+    };
+    let (mut chances, mut blunders) = (0, 0);
+    for entry in entries.iter().filter(|e| !e.game.swapped) {
+        let positions = entry.game.positions()?;
+        for (before, after) in positions.iter().zip(&positions[1..]) {
+            let player = before.next_player();
+            if player != entry.human_player {
+                continue;
+            }
+            let results: Vec<GameResult> = before
+                .legal_moves()
+                .map(|square| result(&before.with_move(square), player))
+                .collect();
+            let (Some(&best), Some(&worst)) = (results.iter().max(), results.iter().min()) else {
+                continue;
+            };
+            if worst < best {
+                chances += 1;
+                if result(after, player) < best {
+                    blunders += 1;
+                }
+            }
+        }
+    }
+    Ok((chances > 0).then(|| f64::from(blunders) / f64::from(chances)))
+}
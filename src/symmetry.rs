@@ -0,0 +1,116 @@
+//! The eight rotations and reflections of the board, which turn a position into others that play
+//! exactly the same, for merging positions that only differ by how the board is turned
+//!
+//! Squares are numbered 0-8 from the top left as everywhere else. A [`Symmetry`] moves squares
+//! and positions alike, so a move found in the canonical form of a position can be carried back
+//! to the original with the inverse.
+
+use crate::game::GameState;
+
+/// Where a square ends up when the board is turned a quarter clockwise
+pub fn rotate_square(square: usize) -> usize {
+    let (row, column) = (square / 3, square % 3);
+    column * 3 + (2 - row)
+}
+
+/// Where a square ends up when the board is flipped left to right
+pub fn mirror_square(square: usize) -> usize {
+    let (row, column) = (square / 3, square % 3);
+    row * 3 + (2 - column)
+}
+
+/// A rotation or reflection of the board: flipped left to right if `mirrored`, then turned
+/// `quarter_turns` quarters clockwise
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Symmetry {
+    /// 0 to 3
+    pub quarter_turns: u8,
+    pub mirrored: bool,
+}
+
+impl Symmetry {
+    /// Every symmetry of the board, leaving it as it is first
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::turns(0, false),
+        Symmetry::turns(1, false),
+        Symmetry::turns(2, false),
+        Symmetry::turns(3, false),
+        Symmetry::turns(0, true),
+        Symmetry::turns(1, true),
+        Symmetry::turns(2, true),
+        Symmetry::turns(3, true),
+    ];
+
+    const fn turns(quarter_turns: u8, mirrored: bool) -> Self {
+        Self {
+            quarter_turns,
+            mirrored,
+        }
+    }
+
+    /// Where a square ends up
+    pub fn apply(self, square: usize) -> usize {
+        let square = if self.mirrored {
+            mirror_square(square)
+        } else {
+            square
+        };
+        (0..self.quarter_turns % 4).fold(square, |square, _| rotate_square(square))
+    }
+
+    /// The symmetry that puts the board back
+    pub fn inverse(self) -> Self {
+        if self.mirrored {
+            // Flipping then turning is itself a flip, which undoes itself
+            self
+        } else {
+            Self::turns((4 - self.quarter_turns % 4) % 4, false)
+        }
+    }
+}
+
+impl GameState {
+    /// The position with the board turned or flipped by `symmetry`
+    pub fn transform(&self, symmetry: Symmetry) -> Self {
+        let mut board = [None; 9];
+        for (square, &mark) in self.board().iter().enumerate() {
+            board[symmetry.apply(square)] = mark;
+        }
+        self.with_board(board)
+    }
+
+    /// The position with the board turned a quarter clockwise
+    pub fn rotate(&self) -> Self {
+        self.transform(Symmetry::turns(1, false))
+    }
+
+    /// The position with the board flipped left to right
+    pub fn mirror(&self) -> Self {
+        self.transform(Symmetry::turns(0, true))
+    }
+
+    /// The one position every rotation and reflection of this one shares, along with the
+    /// symmetry that turns this position into it
+    ///
+    /// ```
+    /// use simple_tic_tac_toe::GameState;
+    /// use simple_tic_tac_toe::Player;
+    ///
+    /// // Every corner opening is the same opening
+    /// let top_left = GameState::from_moves(Player::O, &[0]).unwrap();
+    /// let bottom_right = GameState::from_moves(Player::O, &[8]).unwrap();
+    /// let (canonical, symmetry) = bottom_right.canonicalize();
+    /// assert_eq!(canonical.board(), top_left.canonicalize().0.board());
+    ///
+    /// // Squares carried over to the canonical board and back
+    /// assert_eq!(canonical.board()[symmetry.apply(8)], Some(Player::X));
+    /// assert_eq!(symmetry.inverse().apply(symmetry.apply(8)), 8);
+    /// ```
+    pub fn canonicalize(&self) -> (Self, Symmetry) {
+        Symmetry::ALL
+            .iter()
+            .map(|&symmetry| (self.transform(symmetry), symmetry))
+            .min_by_key(|(state, _)| state.board_code())
+            .expect("there are symmetries")
+    }
+}
@@ -0,0 +1,136 @@
+//! An exact win/tie/loss table for every reachable position, solved backwards from the finished
+//! games so the search can look positions up instead of searching them
+//!
+//! On disk the table is the bytes `TTTB`, a format version byte, and then one byte per board,
+//! indexed by reading the board as a base-3 number with the top left square lowest, 1 for X and 2
+//! for O. Each byte is 0 for unreachable positions, then 1, 2 and 3 for a loss, tie or win for the
+//! side to move.
+
+use crate::ai::GameResult;
+use crate::error::Error;
+use crate::game::GameState;
+use std::path::Path;
+#[cfg(feature = "storage")]
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+const MAGIC: &[u8; 4] = b"TTTB";
+const VERSION: u8 = 1;
+/// Number of possible board codes, 3^9
+const CODES: usize = 19683;
+
+static INSTALLED: OnceLock<Tablebase> = OnceLock::new();
+
+/// Results with best play for the side to move in every reachable position
+pub struct Tablebase {
+    values: Vec<u8>,
+}
+
+impl Tablebase {
+    /// The table location, e.g. `~/.local/share/simple-tic-tac-toe/tablebase.bin` on Linux
+    #[cfg(feature = "storage")]
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("simple-tic-tac-toe").join("tablebase.bin"))
+    }
+
+    /// Solve every reachable position, starting from the full boards and working back
+    pub fn build() -> Self {
+        // Positions grouped by how many pieces are on the board
+        let mut layers: Vec<Vec<GameState>> = vec![Vec::new(); 10];
+        for state in GameState::reachable_positions() {
+            layers[state.board().iter().flatten().count()].push(state);
+        }
+
+        let mut values = vec![0; CODES];
+        for layer in layers.iter().rev() {
+            for state in layer {
+                let result = if state.winner().is_some() {
+                    // The last move won, so the side to move has lost
+                    GameResult::Loss
+                } else {
+                    state
+                        .legal_moves()
+                        .filter_map(|square| {
+                            decode(values[state.with_move(square).board_code() as usize])
+                        })
+                        .map(GameResult::reversed)
+                        .max()
+                        .unwrap_or(GameResult::Tie)
+                };
+                values[state.board_code() as usize] = encode(result);
+            }
+        }
+        Self { values }
+    }
+
+    /// Look up the result with best play for the side to move
+    pub fn probe(&self, state: &GameState) -> Option<GameResult> {
+        decode(self.values[state.board_code() as usize])
+    }
+
+    /// Number of positions in the table
+    pub fn len(&self) -> usize {
+        self.values.iter().filter(|&&v| v != 0).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Read a table written by `save`
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let data = std::fs::read(path).map_err(Error::io("read", path))?;
+        match data.split_at_checked(MAGIC.len() + 1) {
+            Some((header, values))
+                if header[..4] == *MAGIC && header[4] == VERSION && values.len() == CODES =>
+            {
+                Ok(Self {
+                    values: values.to_vec(),
+                })
+            }
+            _ => Err(Error::parse(format!(
+                "{} is not a tablebase, rebuild it with `tablebase build`",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Write the table to a file, creating its directory if needed
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::io("create", dir))?;
+        }
+        let mut data = MAGIC.to_vec();
+        data.push(VERSION);
+        data.extend_from_slice(&self.values);
+        std::fs::write(path, data).map_err(Error::io("write", path))
+    }
+}
+
+/// Make a tablebase available to the search, which probes it before searching any position.
+/// Only the first table installed is used
+pub fn install(tablebase: Tablebase) {
+    let _ = INSTALLED.set(tablebase);
+}
+
+/// Get the tablebase the search is using, if one was installed
+pub fn installed() -> Option<&'static Tablebase> {
+    INSTALLED.get()
+}
+
+fn encode(result: GameResult) -> u8 {
+    match result {
+        GameResult::Loss => 1,
+        GameResult::Tie => 2,
+        GameResult::Win => 3,
+    }
+}
+
+fn decode(value: u8) -> Option<GameResult> {
+    match value {
+        1 => Some(GameResult::Loss),
+        2 => Some(GameResult::Tie),
+        3 => Some(GameResult::Win),
+        _ => None,
+    }
+}
@@ -0,0 +1,194 @@
+//! Games over a plain TCP connection, one per connection, playable from `nc` or `telnet`
+//!
+//! Everything is plain lines of text: the server draws the board with the chat coordinates and
+//! reads a square like `b2` from each line the player sends.
+
+use crate::chat::{coordinate, parse_coordinate, render_for_chat};
+use crate::error::Error;
+use crate::game::{GameState, GameStatus, Player};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+/// How long a connection may go without sending anything before it's closed
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// The longest line read from a player, far more than any answer needs. A peer sending more
+/// without a newline is hung up on rather than buffered without end
+const MAX_LINE: u64 = 1024;
+
+/// Accept connections on `address`, e.g. `0.0.0.0:2323`, and play a game on each in its own thread
+pub fn serve(address: &str) -> crate::Result<()> {
+    let listener = TcpListener::bind(address).map_err(Error::network("listen on", address))?;
+    tracing::info!(address, "listening");
+    for stream in listener.incoming() {
+        let stream = stream.map_err(Error::network("accept connections on", address))?;
+        std::thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map(|a| a.to_string())
+                .unwrap_or_default();
+            tracing::info!(peer, "connected");
+            if let Err(e) = play_connection(stream) {
+                tracing::debug!(peer, error = %e, "connection failed");
+            }
+            tracing::info!(peer, "disconnected");
+        });
+    }
+    Ok(())
+}
+
+fn play_connection(stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+    let mut connection = Connection {
+        reader: BufReader::new(stream.try_clone()?),
+        writer: stream,
+    };
+    connection
+        .say("Welcome to tic-tac-toe! Squares are named a1 (top left) to c3 (bottom right).")?;
+    loop {
+        let Some(side) = connection.ask("Play as X or O? [x] ")? else {
+            return Ok(());
+        };
+        let human = match side.as_str() {
+            "" => Player::X,
+            side => match side.parse::<Player>() {
+                Ok(player) => player,
+                Err(e) => {
+                    connection.say(&e.to_string())?;
+                    continue;
+                }
+            },
+        };
+        if !play_game(&mut connection, human)? {
+            return Ok(());
+        }
+        match connection.ask("Play again? [y/n] ")?.as_deref() {
+            Some("y" | "yes") => {}
+            _ => return connection.say("Thanks for playing!"),
+        }
+    }
+}
+
+/// Play one game, returning false if the player hung up
+fn play_game(connection: &mut Connection, human: Player) -> io::Result<bool> {
+    let mut game = GameState::new(human.opponent());
+    loop {
+        if game.next_player() == game.computer_player && game.status() == GameStatus::InProgress {
+            let square = game.decide_computer_move().chosen;
+            game.apply_move(square);
+            connection.say(&format!("The computer played {}.", coordinate(square)))?;
+        }
+        for line in render_for_chat(&game) {
+            connection.say(&line)?;
+        }
+        let result = match game.status() {
+            GameStatus::InProgress => None,
+            GameStatus::Won(winner) if winner == game.computer_player => Some("The computer wins."),
+            GameStatus::Won(_) => Some("You win!"),
+            GameStatus::Tie => Some("It's a tie."),
+        };
+        if let Some(result) = result {
+            connection.say(result)?;
+            return Ok(true);
+        }
+
+        loop {
+            let Some(answer) = connection.ask(&format!("Your move as {human}: "))? else {
+                return Ok(false);
+            };
+            match parse_coordinate(&answer).map(|square| game.try_move(square)) {
+                Some(Ok(())) => break,
+                Some(Err(e)) => connection.say(&e.to_string())?,
+                None => connection.say("Type a square from a1 to c3, e.g. b2")?,
+            }
+        }
+    }
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn say(&mut self, line: &str) -> io::Result<()> {
+        write!(self.writer, "{line}\r\n")
+    }
+
+    /// Prompt for a line, returning None once the player hangs up or sends an overlong line
+    fn ask(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        write!(self.writer, "{prompt}")?;
+        let mut line = Vec::new();
+        let read = (&mut self.reader)
+            .take(MAX_LINE)
+            .read_until(b'\n', &mut line)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read as u64 == MAX_LINE && !line.ends_with(b"\n") {
+            self.say("That line is too long, goodbye.")?;
+            return Ok(None);
+        }
+        // Drop telnet option negotiation and anything else that isn't text
+        let line: String = String::from_utf8_lossy(&line)
+            .chars()
+            .filter(|c| !c.is_control() && *c != char::REPLACEMENT_CHARACTER)
+            .collect();
+        Ok(Some(line.trim().to_lowercase()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    /// Read from the server until one of `prompts`, returning everything up to it
+    fn read_until_prompt(stream: &mut TcpStream, prompts: &[&str]) -> String {
+        let mut text = String::new();
+        let mut buffer = [0; 1024];
+        while !prompts.iter().any(|prompt| text.ends_with(prompt)) {
+            let n = stream.read(&mut buffer).unwrap();
+            assert!(n > 0, "the server hung up after {text:?}");
+            text.push_str(&String::from_utf8_lossy(&buffer[..n]));
+        }
+        text
+    }
+
+    #[test]
+    fn a_tie_as_x_ends_the_game() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            play_connection(stream)
+        });
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .unwrap();
+        read_until_prompt(&mut stream, &["Play as X or O? [x] "]);
+        stream.write_all(b"x\r\n").unwrap();
+
+        // Both sides playing perfectly always ties, with X filling the last square
+        let mut game = GameState::new(Player::X);
+        let text = loop {
+            let text = read_until_prompt(&mut stream, &[": ", "[y/n] "]);
+            if let Some(played) = text.split("The computer played ").nth(1) {
+                game.apply_move(parse_coordinate(&played[..2]).unwrap());
+            }
+            if text.ends_with("[y/n] ") {
+                break text;
+            }
+            let square = game.get_best_computer_moves()[0].square;
+            game.apply_move(square);
+            write!(stream, "{}\r\n", coordinate(square)).unwrap();
+        };
+        assert_eq!(game.status(), GameStatus::Tie);
+        assert!(text.contains("It's a tie."), "{text}");
+
+        stream.write_all(b"n\r\n").unwrap();
+        read_until_prompt(&mut stream, &["Thanks for playing!\r\n"]);
+        server.join().unwrap().unwrap();
+    }
+}
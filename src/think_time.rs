@@ -0,0 +1,45 @@
+//! How long the computer seems to think before a move, for players who find an instant reply
+//! unnatural
+//!
+//! The pause follows how hard the choice looks from the evaluation: a move with no alternative,
+//! or the one move that stands out from the rest, comes quickly, and a choice between many moves
+//! that don't all play out the same takes longest.
+
+use crate::ai::Decision;
+use rand::Rng;
+use std::time::Duration;
+
+/// The shortest and longest the computer takes over a move
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ThinkTime {
+    pub fastest: Duration,
+    pub slowest: Duration,
+}
+
+impl ThinkTime {
+    /// How long to take over a decision, with a little randomness so the same kind of position
+    /// doesn't always take the same time
+    pub fn pause(&self, decision: &Decision, rng: &mut impl Rng) -> Duration {
+        let range = self.slowest.saturating_sub(self.fastest);
+        let effort = (effort(decision) * rng.gen_range(0.8..1.2)).min(1.0);
+        self.fastest + range.mul_f64(effort)
+    }
+}
+
+/// How hard a decision looks, from 0 for a move with no alternative to 1 for the hardest
+pub fn effort(decision: &Decision) -> f64 {
+    let candidates = &decision.candidates;
+    let Some(best) = candidates.iter().map(|c| c.result).max() else {
+        return 0.0;
+    };
+    if candidates.len() == 1 {
+        return 0.0;
+    }
+    let best_moves = candidates.iter().filter(|c| c.result == best).count();
+    if best_moves == 1 {
+        // A win to take or a loss to block, which stands out without much thought
+        return 0.2;
+    }
+    let worse_share = (candidates.len() - best_moves) as f64 / candidates.len() as f64;
+    candidates.len() as f64 / 9.0 * (0.4 + 0.6 * worse_share)
+}
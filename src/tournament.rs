@@ -0,0 +1,178 @@
+//! Tournaments between computer players of different strengths, and batches of self-play games,
+//! played across as many threads as there are cores
+//!
+//! Every game gets its own random number generator, seeded from the run's seed and the game's
+//! number, and results are collected by game number. So a run with the same seed gives the same
+//! games and the same totals whichever thread plays each game and whenever it finishes.
+
+use crate::explorer::Results;
+use crate::game::{GameState, GameStatus, Player};
+use crate::record::GameRecord;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// The generator for one game of a run, different for every game and every seed
+pub fn game_rng(seed: u64, game: usize) -> StdRng {
+    // Spread the game numbers out so nearby seeds don't share games
+    StdRng::seed_from_u64(seed ^ (game as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+/// Play `games` games across `threads` threads, counting each finished game in `progress`, and
+/// return what `play` gave for each in game order. `play` is given the game's number and its
+/// generator
+pub fn run_games<T: Send>(
+    games: usize,
+    seed: u64,
+    threads: usize,
+    progress: &AtomicUsize,
+    play: impl Fn(usize, &mut StdRng) -> T + Sync,
+) -> Vec<T> {
+    let next = AtomicUsize::new(0);
+    let finished = Mutex::new(Vec::with_capacity(games));
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, games.max(1)) {
+            scope.spawn(|| {
+                let mut played = Vec::new();
+                loop {
+                    let game = next.fetch_add(1, Ordering::Relaxed);
+                    if game >= games {
+                        break;
+                    }
+                    played.push((game, play(game, &mut game_rng(seed, game))));
+                    progress.fetch_add(1, Ordering::Relaxed);
+                }
+                finished
+                    .lock()
+                    .expect("no thread panics holding the lock")
+                    .extend(played);
+            });
+        }
+    });
+    let mut finished = finished
+        .into_inner()
+        .expect("no thread panics holding the lock");
+    finished.sort_unstable_by_key(|&(game, _)| game);
+    finished.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Play a game between two computer players, X blundering with one chance and O with the other
+pub fn play_game(x_blunder_chance: f64, o_blunder_chance: f64, rng: &mut impl Rng) -> GameRecord {
+    let mut state = GameState::new(Player::O);
+    let mut record = GameRecord::new(Player::O, Vec::new());
+    while state.status() == GameStatus::InProgress {
+        state.computer_player = state.next_player();
+        let blunder_chance = match state.next_player() {
+            Player::X => x_blunder_chance,
+            Player::O => o_blunder_chance,
+        };
+        let square = state
+            .decide_computer_move_with_rng(blunder_chance, rng)
+            .chosen;
+        state.apply_move(square);
+        record.push(square, None);
+    }
+    record
+}
+
+/// The games between two entrants with one of them as X
+#[derive(Copy, Clone, Debug)]
+pub struct Pairing {
+    /// The entrant playing X, by position in the entrant list
+    pub x: usize,
+    pub o: usize,
+    pub results: Results,
+}
+
+/// A round robin where every entrant plays every other the same number of games as X and as O
+#[derive(Clone, Debug)]
+pub struct Tournament {
+    /// Each entrant's blunder chance
+    pub entrants: Vec<f64>,
+    /// Games for each pairing, so each entrant plays twice this against each other one
+    pub games: usize,
+    pub seed: u64,
+    pub threads: usize,
+}
+
+impl Tournament {
+    /// Every pairing of two different entrants, in the order their games are numbered
+    fn pairings(&self) -> Vec<(usize, usize)> {
+        let n = self.entrants.len();
+        (0..n)
+            .flat_map(|x| (0..n).filter(move |&o| o != x).map(move |o| (x, o)))
+            .collect()
+    }
+
+    /// How many games the tournament plays in all
+    pub fn total_games(&self) -> usize {
+        self.pairings().len() * self.games
+    }
+
+    /// Play every game, counting each finished one in `progress`
+    pub fn run(&self, progress: &AtomicUsize) -> Standings {
+        let pairings = self.pairings();
+        let statuses = run_games(
+            self.total_games(),
+            self.seed,
+            self.threads,
+            progress,
+            |game, rng| {
+                let (x, o) = pairings[game / self.games];
+                play_game(self.entrants[x], self.entrants[o], rng)
+                    .status()
+                    .expect("self-play games only have legal moves")
+            },
+        );
+        let mut standings = Standings {
+            entrants: self.entrants.clone(),
+            pairings: pairings
+                .into_iter()
+                .map(|(x, o)| Pairing {
+                    x,
+                    o,
+                    results: Results::default(),
+                })
+                .collect(),
+        };
+        for (game, status) in statuses.into_iter().enumerate() {
+            standings.pairings[game / self.games].results.add(status);
+        }
+        standings
+    }
+}
+
+/// How a tournament's games ended
+#[derive(Clone, Debug)]
+pub struct Standings {
+    pub entrants: Vec<f64>,
+    pub pairings: Vec<Pairing>,
+}
+
+impl Standings {
+    /// An entrant's points, one for each win and a half for each tie
+    pub fn points(&self, entrant: usize) -> f64 {
+        self.pairings
+            .iter()
+            .map(|pairing| {
+                let results = pairing.results;
+                let ties = f64::from(results.ties) / 2.0;
+                if pairing.x == entrant {
+                    f64::from(results.x_wins) + ties
+                } else if pairing.o == entrant {
+                    f64::from(results.o_wins) + ties
+                } else {
+                    0.0
+                }
+            })
+            .sum()
+    }
+
+    /// The entrants from most points to fewest, earlier entrants first on equal points
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut ranking: Vec<usize> = (0..self.entrants.len()).collect();
+        ranking.sort_by(|&a, &b| self.points(b).total_cmp(&self.points(a)));
+        ranking
+    }
+}
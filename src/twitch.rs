@@ -0,0 +1,100 @@
+//! "Chat plays" games, where a stream's chat votes on the human side's moves
+//!
+//! Twitch chat is IRC, so the connection itself is an [`IrcClient`](crate::irc::IrcClient). This
+//! module counts the votes and writes the overlay file a streaming program can show.
+
+use crate::chat::{coordinate, parse_coordinate, render_for_chat};
+use crate::error::Error;
+use crate::game::GameState;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Twitch's IRC server, for clients without TLS
+pub const TWITCH_SERVER: &str = "irc.chat.twitch.tv";
+
+/// The votes cast in one voting window, one per user
+#[derive(Clone, Debug, Default)]
+pub struct Votes {
+    by_user: HashMap<String, usize>,
+}
+
+impl Votes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a chat message as a vote if it names an open square, replacing the user's earlier
+    /// vote. Returns the square voted for
+    pub fn cast(&mut self, state: &GameState, user: &str, message: &str) -> Option<usize> {
+        let square = parse_coordinate(message.trim())?;
+        if state.board()[square].is_some() {
+            return None;
+        }
+        self.by_user.insert(user.to_lowercase(), square);
+        Some(square)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_user.is_empty()
+    }
+
+    /// Each square voted for with its number of votes, most votes first
+    pub fn tally(&self) -> Vec<(usize, usize)> {
+        let mut counts = [0; 9];
+        for &square in self.by_user.values() {
+            counts[square] += 1;
+        }
+        let mut tally: Vec<(usize, usize)> = counts
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        tally.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        tally
+    }
+
+    /// The square with the most votes, with ties broken at random
+    pub fn winner(&self, rng: &mut impl Rng) -> Option<usize> {
+        let tally = self.tally();
+        let most = tally.first()?.1;
+        let tied: Vec<usize> = tally
+            .iter()
+            .filter(|&&(_, count)| count == most)
+            .map(|&(square, _)| square)
+            .collect();
+        tied.choose(rng).copied()
+    }
+
+    /// The tally on one line, e.g. `b2: 3, a1: 1`
+    pub fn summary(&self) -> String {
+        let tally: Vec<String> = self
+            .tally()
+            .into_iter()
+            .map(|(square, count)| format!("{}: {count}", coordinate(square)))
+            .collect();
+        if tally.is_empty() {
+            "no votes yet".to_string()
+        } else {
+            tally.join(", ")
+        }
+    }
+}
+
+/// Write the board, a status line, and the vote tally to a text file for a stream overlay. The
+/// file is replaced in one step so the overlay never shows a half-written board
+pub fn write_overlay(
+    path: &Path,
+    state: &GameState,
+    status: &str,
+    votes: &Votes,
+) -> crate::Result<()> {
+    let mut lines = render_for_chat(state);
+    lines.push(String::new());
+    lines.push(status.to_string());
+    lines.push(format!("Votes: {}", votes.summary()));
+    let partial = path.with_extension("tmp");
+    std::fs::write(&partial, lines.join("\n") + "\n").map_err(Error::io("write", &partial))?;
+    std::fs::rename(&partial, path).map_err(Error::io("write", path))
+}
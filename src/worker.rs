@@ -0,0 +1,218 @@
+//! Running the search on a worker thread, so front-ends can keep drawing and handle input while
+//! the computer thinks
+
+use crate::ai::{minimax, CancelToken, Candidate, Decision, SearchStats};
+use crate::game::GameState;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+/// Search for the computer's move on a worker thread, resolving to None if `cancel` is cancelled
+/// first or there's no move to make. Dropping the future before it finishes also cancels the search. This works with any
+/// async runtime, as the search runs on its own thread rather than on the executor
+pub async fn best_move(state: GameState, cancel: CancelToken) -> Option<Decision> {
+    SearchTask {
+        handle: SearchHandle::spawn(state, cancel, SearchStats::default()),
+    }
+    .await
+}
+
+#[derive(Default)]
+struct Shared {
+    /// Candidates searched so far, in the order of the legal moves
+    evaluated: Vec<Candidate>,
+    /// The search's state, handed back by the worker when it stops so the search can resume
+    stats: Option<SearchStats>,
+    /// Set once every candidate has been searched
+    decision: Option<Decision>,
+    /// Time spent searching over every run, in microseconds
+    elapsed_us: u64,
+    /// Whether a worker thread is still running
+    running: bool,
+    waker: Option<Waker>,
+}
+
+/// A search for the computer's move running on a worker thread, which can be stopped at any time
+/// to take the best move found so far and resumed later without losing its work
+pub struct SearchHandle {
+    state: GameState,
+    shared: Arc<Mutex<Shared>>,
+    cancel: CancelToken,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SearchHandle {
+    /// Start searching the position on a new thread
+    pub fn start(state: GameState) -> Self {
+        Self::spawn(state, CancelToken::new(), SearchStats::default())
+    }
+
+    fn spawn(state: GameState, cancel: CancelToken, stats: SearchStats) -> Self {
+        let mut handle = Self {
+            state,
+            shared: Arc::new(Mutex::new(Shared::default())),
+            cancel,
+            thread: None,
+        };
+        handle.run(stats);
+        handle
+    }
+
+    fn run(&mut self, mut stats: SearchStats) {
+        stats.set_cancel(self.cancel.clone());
+        self.shared.lock().unwrap().running = true;
+        let state = self.state.clone();
+        let shared = Arc::clone(&self.shared);
+
+        self.thread = Some(std::thread::spawn(move || {
+            let start = Instant::now();
+            let squares: Vec<usize> = state.legal_moves().collect();
+            loop {
+                let done = shared.lock().unwrap().evaluated.len();
+                let Some(&square) = squares.get(done) else {
+                    break;
+                };
+                let result = minimax(&state.with_move(square), &mut stats);
+                if stats.cancelled() {
+                    break;
+                }
+                shared
+                    .lock()
+                    .unwrap()
+                    .evaluated
+                    .push(Candidate { square, result });
+            }
+
+            let mut shared = shared.lock().unwrap();
+            shared.elapsed_us += start.elapsed().as_micros() as u64;
+            // A game that's over has no move to decide on
+            if !stats.cancelled() && !squares.is_empty() {
+                let candidates = shared.evaluated.clone();
+                let elapsed_us = shared.elapsed_us;
+                shared.decision = Some(state.choose_move(
+                    candidates,
+                    &stats,
+                    elapsed_us,
+                    0.0,
+                    &mut rand::thread_rng(),
+                ));
+            }
+            shared.stats = Some(stats);
+            shared.running = false;
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }));
+    }
+
+    /// Stop thinking, waiting only for the worker to notice, which it does at the next position
+    pub fn stop(&mut self) {
+        self.cancel.cancel();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    /// Carry on with a stopped search, keeping the moves already searched and the transposition
+    /// table. Does nothing if the search is still running or already finished
+    pub fn resume(&mut self) {
+        let stats = {
+            let mut shared = self.shared.lock().unwrap();
+            if shared.running || shared.decision.is_some() {
+                return;
+            }
+            shared.stats.take().unwrap_or_default()
+        };
+        self.cancel = CancelToken::new();
+        self.run(stats);
+    }
+
+    /// The best of the moves searched so far, if any have been
+    pub fn best_move_so_far(&self) -> Option<usize> {
+        let shared = self.shared.lock().unwrap();
+        let best = shared.evaluated.iter().map(|c| c.result).max()?;
+        shared
+            .evaluated
+            .iter()
+            .find(|c| c.result == best)
+            .map(|c| c.square)
+    }
+
+    /// Whether every move has been searched
+    pub fn is_finished(&self) -> bool {
+        self.shared.lock().unwrap().decision.is_some()
+    }
+
+    /// Wait for the worker to stop, returning the decision if the search finished with a move to
+    /// make
+    pub fn wait(mut self) -> Option<Decision> {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.shared.lock().unwrap().decision.take()
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        // Nobody can read the result any more, so stop the worker if it's still going
+        if self.shared.lock().unwrap().running {
+            self.cancel.cancel();
+        }
+    }
+}
+
+/// Future for `best_move`, woken by the worker when it stops
+struct SearchTask {
+    handle: SearchHandle,
+}
+
+impl Future for SearchTask {
+    type Output = Option<Decision>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.handle.shared.lock().unwrap();
+        if shared.running {
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        } else {
+            Poll::Ready(shared.decision.take())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Player;
+
+    #[test]
+    fn a_full_board_has_no_decision() {
+        let full = [0, 4, 8, 2, 6, 3, 5, 7, 1]
+            .into_iter()
+            .fold(GameState::new(Player::O), |state, square| {
+                state.with_move(square)
+            });
+        assert!(full.legal_moves().next().is_none());
+        let mut handle = SearchHandle::start(full.clone());
+        handle.stop();
+        handle.resume();
+        assert_eq!(handle.best_move_so_far(), None);
+        assert!(handle.wait().is_none());
+
+        // The future resolves to None too, instead of finding the lock poisoned
+        let mut task = std::pin::pin!(best_move(full, CancelToken::new()));
+        let waker = Waker::noop();
+        let mut context = Context::from_waker(waker);
+        loop {
+            if let Poll::Ready(decision) = task.as_mut().poll(&mut context) {
+                assert!(decision.is_none());
+                break;
+            }
+            std::thread::yield_now();
+        }
+    }
+}